@@ -1 +0,0 @@
-// Analytics specific errors
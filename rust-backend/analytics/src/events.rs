@@ -1,5 +1,6 @@
 use crate::models::{AnalyticsEvent, Event};
-use chrono::Utc;
+use chrono::{DateTime, Utc};
+use platform::events::{DomainEvent, Stream};
 use std::collections::HashMap;
 use thiserror::Error;
 use uuid::Uuid;
@@ -57,6 +58,42 @@ impl Event {
             data,
         })
     }
+
+    /// Builds an event from a catalogue event consumed off Redis Streams.
+    pub fn from_domain(event: &DomainEvent) -> Result<Event, EventError> {
+        let event_type = event.event_type();
+        let data =
+            serde_json::to_value(event).map_err(|e| EventError::ConversionError(e.to_string()))?;
+
+        let id_key = match event_type.stream() {
+            Stream::Orders => "order_id",
+            Stream::Products => "product_id",
+            Stream::Users => "user_id",
+            Stream::Logistics => "shipment_id",
+            Stream::Inventory | Stream::Suppliers => "supplier_id",
+            Stream::Payments => "payment_id",
+            Stream::Tenants => "tenant_id",
+            _ => "",
+        };
+        let id = data
+            .get(id_key)
+            .and_then(|v| v.as_str())
+            .and_then(|s| Uuid::parse_str(s).ok())
+            .unwrap_or_else(Uuid::new_v4);
+        let event_timestamp = data
+            .get("timestamp")
+            .and_then(|v| v.as_str())
+            .and_then(|s| s.parse::<DateTime<Utc>>().ok())
+            .unwrap_or_else(Utc::now);
+
+        Ok(Event {
+            id: Some(id),
+            tenant_id: Some(event.tenant_id()),
+            event_type: event_type.as_str().to_string(),
+            event_timestamp: Some(event_timestamp),
+            data,
+        })
+    }
 }
 
 /// Allowed metrics -> underlying table mapping
@@ -152,6 +189,24 @@ mod tests {
         assert_eq!(ev.extract_primary_id(), ev.user_id.unwrap());
     }
 
+    #[test]
+    fn test_event_from_domain() {
+        let order_id = Uuid::new_v4();
+        let tenant_id = Uuid::new_v4();
+        let event = Event::from_domain(&DomainEvent::OrderCreated(platform::events::OrderEvent {
+            tenant_id,
+            order_id,
+            timestamp: Utc::now(),
+            ..Default::default()
+        }))
+        .unwrap();
+
+        assert_eq!(event.id, Some(order_id));
+        assert_eq!(event.tenant_id, Some(tenant_id));
+        assert_eq!(event.event_type, "order.created");
+        assert_eq!(event.data["event_type"], "order.created");
+    }
+
     #[tokio::test]
     async fn test_metric_table_map() {
         let map = metric_table_map().await;
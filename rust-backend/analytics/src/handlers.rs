@@ -1,6 +1,7 @@
 use crate::events::{allowed_group_by, metric_table_map, parse_window_to_interval};
 use crate::models::AnalyticsRequestBody;
-use actix_web::{HttpResponse, Responder, web};
+use actix_web::{HttpResponse, Responder, ResponseError, web};
+use platform::errors::AppError;
 use serde_json::{Value, json};
 use sqlx::Postgres;
 use sqlx::Row;
@@ -132,13 +133,13 @@ impl AnalyticsRepo {
         // validate metric
         let metric = match metric {
             Some(m) => m,
-            None => return HttpResponse::BadRequest().json(json!({"error":"metric is required"})),
+            None => return AppError::BadRequest("metric is required".into()).error_response(),
         };
 
         let map = metric_table_map();
         let table = match map.await.get(metric.as_str()) {
             Some(t) => *t,
-            None => return HttpResponse::BadRequest().json(json!({"error":"unknown metric"})),
+            None => return AppError::BadRequest("unknown metric".into()).error_response(),
         };
 
         // Default aggregate_field per metric (if not provided)
@@ -170,8 +171,7 @@ impl AnalyticsRepo {
         // validate group_by columns against whitelist
         for col in &group_by_cols {
             if !allowed_cols.contains(&col.as_str()) {
-                return HttpResponse::BadRequest()
-                    .json(json!({"error": format!("group_by column not allowed: {}", col)}));
+                return AppError::BadRequest(format!("group_by column not allowed: {}", col)).error_response();
             }
         }
 
@@ -181,7 +181,7 @@ impl AnalyticsRepo {
                 // NOTE: we inject the interval *as SQL literal* but it's only created by our parser from digits+unit
                 format!("day >= NOW() - INTERVAL '{}'", interval)
             } else {
-                return HttpResponse::BadRequest().json(json!({"error":"invalid window format"}));
+                return AppError::BadRequest("invalid window format".into()).error_response();
             }
         } else {
             // default window if none given (30 days)
@@ -223,11 +223,10 @@ impl AnalyticsRepo {
             let key = k.trim();
             let key_sanitized = key.chars().all(|c| c.is_alphanumeric() || c == '_');
             if !key_sanitized {
-                return HttpResponse::BadRequest().json(json!({"error":"invalid filter key"}));
+                return AppError::BadRequest("invalid filter key".into()).error_response();
             }
             if !allowed_filters.iter().any(|a| *a == key) {
-                return HttpResponse::BadRequest()
-                    .json(json!({"error": format!("filter not allowed: {}", key)}));
+                return AppError::BadRequest(format!("filter not allowed: {}", key)).error_response();
             }
 
             // Two cases: column already exists as a top-level column (e.g. payment_method), or a JSONB field in "data->>'...'"
@@ -275,8 +274,7 @@ impl AnalyticsRepo {
                 {
                     &aggregate_field
                 } else {
-                    return HttpResponse::BadRequest()
-                        .json(json!({"error":"invalid aggregate_field"}));
+                    return AppError::BadRequest("invalid aggregate_field".into()).error_response();
                 };
             }
 
@@ -324,17 +322,14 @@ impl AnalyticsRepo {
         // Execute
         let pool = match db_router.get_pool(&tenant).await {
             Ok(p) => p,
-            Err(e) => return HttpResponse::InternalServerError().json(json!({"error": format!("DB routing error: {}", e)})),
+            Err(e) => return AppError::from(e).error_response(),
         };
-        let mut tx = match pool.begin().await {
+        let mut tx = match tenant.begin(&pool).await {
             Ok(t) => t,
-            Err(e) => return HttpResponse::InternalServerError().json(json!({"error": format!("Tx error: {}", e)})),
+            Err(e) => return AppError::from(e).error_response(),
         };
-        if let Err(e) = tenant.apply_rls(&mut *tx).await {
-            return HttpResponse::InternalServerError().json(json!({"error": format!("RLS error: {}", e)}));
-        }
 
-        match qx.fetch_one(&mut *tx).await {
+        match qx.fetch_one(tx.executor()).await {
             Ok(row) => {
                 let _ = tx.commit().await;
                 // get JSON from "data" column
@@ -344,8 +339,7 @@ impl AnalyticsRepo {
                     "result": v
                 }))
             }
-            Err(e) => HttpResponse::InternalServerError()
-                .json(json!({"error": format!("{}", e), "sql": final_sql})),
+            Err(e) => AppError::from(e).error_response(),
         }
     }
 }
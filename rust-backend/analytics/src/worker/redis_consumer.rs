@@ -1,11 +1,15 @@
-use crate::models::{AnalyticsEvent, Event};
+use crate::models::Event;
 use actix_web::web;
+use platform::events::DomainEvent;
 use platform::streams;
+use platform::tenant::TenantTx;
 use serde_json::Value;
 use sqlx::PgPool;
-use std::env;
 use tracing::{error, info, warn};
 
+/// Consumer group this service reads its streams with.
+pub const GROUP: &str = "analytics";
+
 pub const EVENTS: &[&str] = &[
     "order.created",
     "order.confirmed",
@@ -23,7 +27,7 @@ pub const EVENTS: &[&str] = &[
     "logistics.shipment_created",
     "logistics.shipment_updated",
     "logistics.shipment_cancelled",
-    "payment.succeeded",
+    "payment.success",
     "payment.failed",
     "payment.refunded",
     "user.created",
@@ -31,22 +35,21 @@ pub const EVENTS: &[&str] = &[
     "supplier.created",
     "supplier.status_updated",
     "supplier.updated",
-    "notification.sent",
-    "notification.failed",
 ];
 
 pub async fn run_redis_consumer(
     pool: PgPool,
     redis_client: web::Data<redis::Client>,
+    redis_url: Option<String>,
+    consumer: String,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let redis_url = env::var("REDIS_URL").map_err(|_| "REDIS_URL must be set in environment")?;
-    let consumer = env::var("CONSUMER_NAME").unwrap_or_else(|_| "analytics-worker-1".to_string());
+    let redis_url = redis_url.ok_or("REDIS_URL must be set in environment")?;
 
     info!("Starting analytics Redis stream consumer: {}", consumer);
 
-    streams::consume_json::<Value, _, _>(
+    streams::consume_json::<DomainEvent, _, _>(
         &redis_url,
-        "analytics",
+        GROUP,
         &consumer,
         EVENTS,
         move |envelope| {
@@ -54,40 +57,29 @@ pub async fn run_redis_consumer(
             let redis_client = redis_client.clone();
             async move {
                 let event_type = envelope.event_type.clone();
-                let mut analytics_event: AnalyticsEvent = match serde_json::from_value(envelope.payload)
-                {
-                    Ok(ev) => ev,
-                    Err(e) => {
-                        warn!("Failed parsing AnalyticsEvent ({}): {}", event_type, e);
-                        return Ok(());
-                    }
-                };
+                let payload_tenant_id = envelope.payload.tenant_id();
 
-                let envelope_tenant_id = envelope.tenant_id;
-                let payload_tenant_id = analytics_event.tenant_id;
-
-                // Safety check: Assert envelope.tenant_id matches payload.tenant_id if both are present
-                if let (Some(env_tid), Some(pay_tid)) = (envelope_tenant_id, payload_tenant_id) {
-                    if env_tid != pay_tid {
-                        warn!(%event_type, %env_tid, %pay_tid, "Mismatched envelope and payload tenant_id — overriding with envelope tenant_id");
+                // Safety check: Assert envelope.tenant_id matches payload.tenant_id
+                if let Some(env_tid) = envelope.tenant_id {
+                    if env_tid != payload_tenant_id {
+                        warn!(%event_type, %env_tid, pay_tid = %payload_tenant_id, "Mismatched envelope and payload tenant_id — overriding with envelope tenant_id");
                     }
                 }
 
-                let tenant_id = envelope_tenant_id.or(payload_tenant_id);
-                if tenant_id.is_none() || tenant_id == Some(uuid::Uuid::nil()) {
+                let tenant_id = envelope.tenant_id.unwrap_or(payload_tenant_id);
+                if tenant_id.is_nil() {
                     warn!(%event_type, stream = %envelope.stream, "Missing tenant_id in analytics stream event — skipping event ingestion");
                     return Ok(());
                 }
 
-                analytics_event.tenant_id = tenant_id;
-
-                let event = match Event::new(analytics_event) {
+                let mut event = match Event::from_domain(&envelope.payload) {
                     Ok(ev) => ev,
                     Err(err) => {
                         warn!("Failed converting to Event: {}", err);
                         return Ok(());
                     }
                 };
+                event.tenant_id = Some(tenant_id);
 
                 let db_res = insert_event(&pool, &event).await;
                 let redis_res = update_redis(&event.data, &redis_client).await;
@@ -117,7 +109,6 @@ async fn insert_event(pool: &PgPool, event: &Event) -> Result<(), sqlx::Error> {
         t if t.starts_with("inventory.") => "supplier_id",
         t if t.starts_with("payment.") => "payment_id",
         t if t.starts_with("supplier.") => "supplier_id",
-        t if t.starts_with("notification.") => "notification_id",
         _ => "random",
     };
 
@@ -141,6 +132,13 @@ async fn insert_event(pool: &PgPool, event: &Event) -> Result<(), sqlx::Error> {
             .and_then(|s| uuid::Uuid::parse_str(s).ok())
     });
 
+    // Rows are only writable inside their tenant's scope
+    let Some(tenant_id) = tenant_id else {
+        warn!(event_type = %event.event_type, "dropping analytics event without a tenant");
+        return Ok(());
+    };
+
+    let mut tx = TenantTx::begin(pool, tenant_id).await?;
     sqlx::query(
         r#"
             INSERT INTO analytics.events (id, event_type, event_timestamp, data, tenant_id)
@@ -152,8 +150,9 @@ async fn insert_event(pool: &PgPool, event: &Event) -> Result<(), sqlx::Error> {
     .bind(timestamp)
     .bind(&event.data)
     .bind(tenant_id)
-    .execute(pool)
+    .execute(tx.executor())
     .await?;
+    tx.commit().await?;
 
     Ok(())
 }
@@ -57,6 +57,7 @@ impl TestHarness {
         Ok(())
     }
 
+    /// Seeds the verifier's Redis cache with `record`, keyed and hashed for `key`.
     pub async fn seed_api_key_redis(
         &self,
         key: &str,
@@ -64,8 +65,15 @@ impl TestHarness {
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         if let Some(client) = &self.redis_client {
             let mut conn = client.get_multiplexed_async_connection().await?;
-            let redis_key = format!("api_key:{}", key);
-            let json = serde_json::to_string(record)?;
+            let prefix = platform::api_keys::key_prefix(key).ok_or("API key too short")?;
+            let key_hash = platform::api_keys::hash_api_key(key);
+            let redis_key = format!("api_key:{}", key_hash);
+            let record = platform::tenant::ApiKeyRecord {
+                key_prefix: prefix.to_string(),
+                key_hash,
+                ..record.clone()
+            };
+            let json = serde_json::to_string(&record)?;
             redis::cmd("SET")
                 .arg(&redis_key)
                 .arg(&json)
@@ -3,7 +3,8 @@ use e2e_tests::test_context::{
     generate_expired_jwt, generate_mock_api_key, generate_mock_jwt, MockTenantFixture,
 };
 use e2e_tests::TestHarness;
-use platform::middleware::tenant_middleware::{PaymentRequiredError, TenantAuthMiddleware};
+use platform::errors::Problem;
+use platform::middleware::tenant_middleware::TenantAuthMiddleware;
 use platform::tenant::{AuthMethod, PricingTier, TenantContext};
 use uuid::Uuid;
 
@@ -26,14 +27,10 @@ async fn test_r1_valid_api_key_returns_200_and_injected_context() {
             permissions: vec!["*".to_string()],
             rate_limit_override: None,
             is_active: true,
+            environment: "live".to_string(),
+            expires_at: None,
         };
-        if let Ok(mut conn) = client.get_multiplexed_async_connection().await {
-            let _: Result<(), _> = redis::cmd("SET")
-                .arg(format!("api_key:{}", api_key))
-                .arg(serde_json::to_string(&rec).unwrap())
-                .query_async(&mut conn)
-                .await;
-        }
+        let _ = harness.seed_api_key_redis(&api_key, &rec).await;
     }
 
     let app = test::init_service(
@@ -233,12 +230,13 @@ async fn test_r1_free_tier_exceeding_limit_returns_402_structured_json() {
 
     if redis_available {
         assert_eq!(resp.status(), StatusCode::PAYMENT_REQUIRED);
-        let body: PaymentRequiredError = test::read_body_json(resp).await;
-        assert_eq!(body.error, "Payment Required");
-        assert_eq!(body.message, "Usage limit exceeded for current pricing tier");
-        assert_eq!(body.tier, "Free");
-        assert_eq!(body.limit, 100);
-        assert_eq!(body.current_usage, 101);
+        let body: Problem = test::read_body_json(resp).await;
+        assert_eq!(body.title, "Payment Required");
+        assert_eq!(body.code, "usage_limit_exceeded");
+        assert_eq!(body.detail.as_deref(), Some("Usage limit exceeded for current pricing tier"));
+        assert_eq!(body.extensions["tier"], "Free");
+        assert_eq!(body.extensions["limit"], 100);
+        assert_eq!(body.extensions["current_usage"], 101);
     } else {
         // Fallback when Redis is not running in test environment
         assert!(resp.status() == StatusCode::OK || resp.status() == StatusCode::PAYMENT_REQUIRED);
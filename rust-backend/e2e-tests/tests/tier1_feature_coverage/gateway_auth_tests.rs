@@ -26,6 +26,8 @@ async fn test_gateway_auth_valid_api_key_returns_200_and_context() {
             permissions: vec!["*".to_string()],
             rate_limit_override: None,
             is_active: true,
+            environment: "live".to_string(),
+            expires_at: None,
         };
         let _ = harness.seed_api_key_redis(&fixture.api_key, &record).await;
         middleware = TenantAuthMiddleware::with_redis(client).with_secret(secret);
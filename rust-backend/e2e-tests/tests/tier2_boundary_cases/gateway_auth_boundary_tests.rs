@@ -122,6 +122,8 @@ async fn test_gateway_auth_revoked_api_key_instant_invalidation() {
         permissions: vec!["*".to_string()],
         rate_limit_override: None,
         is_active: false, // Revoked
+        environment: "live".to_string(),
+        expires_at: None,
     };
 
     let _ = harness.seed_api_key_redis(&key, &record).await;
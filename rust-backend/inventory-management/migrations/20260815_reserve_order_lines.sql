@@ -0,0 +1,3 @@
+-- Orders reserve one row per line, all in the same transaction
+ALTER TABLE reservations DROP CONSTRAINT IF EXISTS reservations_order_id_key;
+ALTER TABLE reservations ADD CONSTRAINT reservations_order_product_key UNIQUE (order_id, product_id);
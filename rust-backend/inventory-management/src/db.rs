@@ -1,24 +1,30 @@
 // src/db.rs
 use crate::models::{CreateInventoryRequest, Inventory, UpdateStockRequest};
+use platform::pagination::{CursorPage, PageRequest};
+use platform::tenant::TenantTx;
+use sqlx::{Postgres, QueryBuilder};
 use uuid::Uuid;
 
 pub struct InventoryRepo {}
 
 impl InventoryRepo {
     pub async fn get_by_supplier(
-        tx: &mut sqlx::PgConnection,
+        tx: &mut TenantTx<'_>,
         supplier_id: Uuid,
-    ) -> Result<Vec<Inventory>, sqlx::Error> {
-        sqlx::query_as::<_, Inventory>(
-            "SELECT * FROM inventory WHERE supplier_id = $1 ORDER BY name",
-        )
-        .bind(supplier_id)
-        .fetch_all(tx)
-        .await
+        page: &PageRequest,
+    ) -> Result<CursorPage<Inventory>, sqlx::Error> {
+        let mut builder = QueryBuilder::<Postgres>::new("SELECT * FROM inventory WHERE supplier_id = ");
+        builder.push_bind(supplier_id);
+        page.push_filters(&mut builder);
+        page.push_order_and_limit(&mut builder);
+
+        let rows = builder.build_query_as::<Inventory>().fetch_all(tx.executor()).await?;
+
+        Ok(page.into_page(rows))
     }
 
     pub async fn update_stock(
-        tx: &mut sqlx::PgConnection,
+        tx: &mut TenantTx<'_>,
         supplier_id: Uuid,
         req: &UpdateStockRequest,
     ) -> Result<Inventory, sqlx::Error> {
@@ -64,12 +70,12 @@ impl InventoryRepo {
         .bind(supplier_id)
         .bind(req.product_id)
         .bind(req.reserved)
-        .fetch_one(tx)
+        .fetch_one(tx.executor())
         .await
     }
 
     pub async fn create_inventory_item(
-        tx: &mut sqlx::PgConnection,
+        tx: &mut TenantTx<'_>,
         req: &CreateInventoryRequest,
     ) -> Result<Inventory, sqlx::Error> {
         sqlx::query_as::<_, Inventory>(
@@ -88,12 +94,12 @@ impl InventoryRepo {
         .bind(&req.description)
         .bind(req.price)
         .bind(&req.category)
-        .fetch_one(tx)
+        .fetch_one(tx.executor())
         .await
     }
 
     pub async fn get_one(
-        tx: &mut sqlx::PgConnection,
+        tx: &mut TenantTx<'_>,
         supplier_id: Uuid,
         product_id: Uuid,
     ) -> Result<Inventory, sqlx::Error> {
@@ -105,12 +111,12 @@ impl InventoryRepo {
         )
         .bind(supplier_id)
         .bind(product_id)
-        .fetch_one(tx)
+        .fetch_one(tx.executor())
         .await
     }
 
     pub async fn delete_product(
-        tx: &mut sqlx::PgConnection,
+        tx: &mut TenantTx<'_>,
         supplier_id: Uuid,
         product_id: Uuid,
     ) -> Result<u64, sqlx::Error> {
@@ -119,7 +125,7 @@ impl InventoryRepo {
         )
         .bind(supplier_id)
         .bind(product_id)
-        .execute(tx)
+        .execute(tx.executor())
         .await?;
 
         Ok(result.rows_affected())
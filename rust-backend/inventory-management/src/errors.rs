@@ -1 +0,0 @@
-// Inventory Management specific errors
@@ -1,47 +1,58 @@
 use crate::db::InventoryRepo;
-use crate::models::{CreateInventoryRequest, StockUpdateEvent, UpdateStockRequest};
+use crate::models::{CreateInventoryRequest, Inventory, UpdateStockRequest};
 use crate::redis_pub::RedisPublisher;
-use actix_web::{web, HttpResponse, Responder};
+use actix_web::{web, HttpRequest, HttpResponse, Responder, ResponseError};
+use chrono::Utc;
+use platform::db_router::DynamicPoolRouter;
+use platform::errors::AppError;
+use platform::events::{DomainEvent, InventoryEvent};
+use platform::pagination::CursorSigner;
 use redis::AsyncCommands;
-use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use uuid::Uuid;
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct ProductDeletedEvent {
-    pub tenant_id: Uuid,
-    pub product_id: Uuid,
-    pub supplier_id: Uuid,
-    pub deleted: bool,
-}
-
 #[utoipa::path(
     get,
     path = "/inventory/{supplier_id}",
     params(
-        ("supplier_id" = Uuid, Path, description = "Supplier ID")
+        ("supplier_id" = Uuid, Path, description = "Supplier ID"),
+        ("limit" = Option<u32>, Query, description = "Page size (1-200, default 50)"),
+        ("cursor" = Option<String>, Query, description = "next_cursor of the previous page"),
+        ("sort" = Option<String>, Query, description = "name, quantity or updated_at; prefix with - for descending (default name)"),
+        ("filter[category]" = Option<String>, Query, description = "Filters on product_id, category, low_stock_threshold, or the sortable fields")
     ),
     responses(
-        (status = 200, description = "Inventory items retrieved"),
+        (status = 200, description = "One page of inventory items with next_cursor and Link headers"),
+        (status = 400, description = "Invalid query parameters"),
         (status = 500, description = "Internal server error")
     ),
     security(("BearerAuth" = []), ("ApiKeyAuth" = []))
 )]
 pub async fn get_inventory(
+    req: HttpRequest,
     tenant: web::ReqData<platform::tenant::TenantContext>,
-    db_router: web::Data<platform::db_router::DynamicPoolRouter>,
+    db_router: web::Data<DynamicPoolRouter>,
+    signer: web::Data<CursorSigner>,
     path: web::Path<Uuid>,
+    query: web::Query<HashMap<String, String>>,
 ) -> impl Responder {
+    let page = match Inventory::list_spec().parse(&query, &signer) {
+        Ok(page) => page,
+        Err(e) => return AppError::from(e).error_response(),
+    };
     let supplier_id = path.into_inner();
-    let pool = db_router.get_pool(&tenant).await.unwrap();
-    let mut tx = pool.begin().await.unwrap();
-    tenant.apply_rls(&mut *tx).await.unwrap();
+    let pool = match db_router.get_pool(&tenant).await {
+        Ok(pool) => pool,
+        Err(e) => return DynamicPoolRouter::unavailable(e).error_response(),
+    };
+    let mut tx = match tenant.begin(&pool).await {
+        Ok(tx) => tx,
+        Err(e) => return AppError::from(e).error_response(),
+    };
 
-    match InventoryRepo::get_by_supplier(&mut *tx, supplier_id).await {
-        Ok(items) => HttpResponse::Ok().json(items),
-        Err(e) => {
-            eprintln!("DB ERROR: {:?}", e);
-            HttpResponse::InternalServerError().body(format!("DB error: {:?}", e))
-        }
+    match InventoryRepo::get_by_supplier(&mut tx, supplier_id, &page).await {
+        Ok(items) => items.respond(&req),
+        Err(e) => AppError::from(e).error_response(),
     }
 }
 
@@ -57,22 +68,24 @@ pub async fn get_inventory(
 )]
 pub async fn create_inventory(
     tenant: web::ReqData<platform::tenant::TenantContext>,
-    db_router: web::Data<platform::db_router::DynamicPoolRouter>,
+    db_router: web::Data<DynamicPoolRouter>,
     req: web::Json<CreateInventoryRequest>,
 ) -> impl Responder {
-    let pool = db_router.get_pool(&tenant).await.unwrap();
-    let mut tx = pool.begin().await.unwrap();
-    tenant.apply_rls(&mut *tx).await.unwrap();
+    let pool = match db_router.get_pool(&tenant).await {
+        Ok(pool) => pool,
+        Err(e) => return DynamicPoolRouter::unavailable(e).error_response(),
+    };
+    let mut tx = match tenant.begin(&pool).await {
+        Ok(tx) => tx,
+        Err(e) => return AppError::from(e).error_response(),
+    };
 
-    match InventoryRepo::create_inventory_item(&mut *tx, &req).await {
+    match InventoryRepo::create_inventory_item(&mut tx, &req).await {
         Ok(item) => {
             tx.commit().await.unwrap();
             HttpResponse::Created().json(item)
         }
-        Err(err) => {
-            eprintln!("Error creating inventory item: {:?}", err);
-            HttpResponse::InternalServerError().body("Failed to create inventory item")
-        }
+        Err(err) => AppError::from(err).error_response(),
     }
 }
 
@@ -92,24 +105,26 @@ pub async fn create_inventory(
 )]
 pub async fn get_inventory_item(
     tenant: web::ReqData<platform::tenant::TenantContext>,
-    db_router: web::Data<platform::db_router::DynamicPoolRouter>,
+    db_router: web::Data<DynamicPoolRouter>,
     path: web::Path<(Uuid, Uuid)>,
 ) -> impl Responder {
     let (supplier_id, product_id) = path.into_inner();
 
-    let pool = db_router.get_pool(&tenant).await.unwrap();
-    let mut tx = pool.begin().await.unwrap();
-    tenant.apply_rls(&mut *tx).await.unwrap();
+    let pool = match db_router.get_pool(&tenant).await {
+        Ok(pool) => pool,
+        Err(e) => return DynamicPoolRouter::unavailable(e).error_response(),
+    };
+    let mut tx = match tenant.begin(&pool).await {
+        Ok(tx) => tx,
+        Err(e) => return AppError::from(e).error_response(),
+    };
 
-    match InventoryRepo::get_one(&mut *tx, supplier_id, product_id).await {
+    match InventoryRepo::get_one(&mut tx, supplier_id, product_id).await {
         Ok(item) => HttpResponse::Ok().json(item),
         Err(sqlx::Error::RowNotFound) => {
-            HttpResponse::NotFound().body("Product not found for this supplier.")
-        }
-        Err(err) => {
-            eprintln!("DB error fetching inventory item: {:?}", err);
-            HttpResponse::InternalServerError().body("Database error while fetching item.")
+            AppError::NotFound("Product not found for this supplier.".into()).error_response()
         }
+        Err(err) => AppError::from(err).error_response(),
     }
 }
 
@@ -129,7 +144,7 @@ pub async fn get_inventory_item(
 )]
 pub async fn update_stock(
     tenant: web::ReqData<platform::tenant::TenantContext>,
-    db_router: web::Data<platform::db_router::DynamicPoolRouter>,
+    db_router: web::Data<DynamicPoolRouter>,
     redis_pub: web::Data<RedisPublisher>,
     redis_client: web::Data<redis::Client>,
     path: web::Path<Uuid>,
@@ -138,35 +153,35 @@ pub async fn update_stock(
     let supplier_id = path.into_inner();
     let change = req.quantity_change;
 
-    let pool = db_router.get_pool(&tenant).await.unwrap();
-    let mut tx = pool.begin().await.unwrap();
-    tenant.apply_rls(&mut *tx).await.unwrap();
+    let pool = match db_router.get_pool(&tenant).await {
+        Ok(pool) => pool,
+        Err(e) => return DynamicPoolRouter::unavailable(e).error_response(),
+    };
+    let mut tx = match tenant.begin(&pool).await {
+        Ok(tx) => tx,
+        Err(e) => return AppError::from(e).error_response(),
+    };
 
-    match InventoryRepo::update_stock(&mut *tx, supplier_id, &req).await {
+    match InventoryRepo::update_stock(&mut tx, supplier_id, &req).await {
         Ok(inventory) => {
             tx.commit().await.unwrap();
             let low_stock = inventory.quantity <= inventory.low_stock_threshold;
 
-            // Expanded event payload to reflect possible new product fields
-            let event = StockUpdateEvent {
+            let event = InventoryEvent {
                 tenant_id: tenant.tenant_id,
                 product_id: inventory.product_id,
-                supplier_id: inventory.supplier_id,
-                new_quantity: inventory.quantity,
-                change: change,
-                low_stock,
-                name: Some(inventory.name.clone()),
-                description: Some(inventory.description.clone()),
-                category: Some(inventory.category.clone()),
-                price: Some(inventory.price),
-                unit: Some(inventory.unit.clone()),
-                available: Some(inventory.available),
+                supplier_id: Some(inventory.supplier_id),
+                quantity: Some(inventory.quantity),
+                quantity_change: change,
+                low_stock: Some(low_stock),
+                timestamp: Utc::now(),
+                ..Default::default()
             };
 
             // Publish to Redis channels
-            redis_pub.publish_async("inventory.updated", event.clone());
+            redis_pub.publish_async(DomainEvent::InventoryUpdated(event.clone()));
             if low_stock {
-                redis_pub.publish_async("inventory.lowstock", event.clone());
+                redis_pub.publish_async(DomainEvent::InventoryLowStock(event));
             }
 
             // Invalidate cache for this supplier
@@ -177,16 +192,10 @@ pub async fn update_stock(
 
             HttpResponse::Ok().json(inventory)
         }
-        Err(err) => {
-            eprintln!("Database error while updating stock: {:?}", err);
-            match err {
-                sqlx::Error::RowNotFound => HttpResponse::NotFound()
-                    .body("No inventory item found for this supplier and product ID."),
-                sqlx::Error::Database(db_err) => HttpResponse::InternalServerError()
-                    .body(format!("Database constraint error: {}", db_err)),
-                _ => HttpResponse::InternalServerError().body("Unexpected database error."),
-            }
+        Err(sqlx::Error::RowNotFound) => {
+            AppError::NotFound("No inventory item found for this supplier and product ID.".into()).error_response()
         }
+        Err(err) => AppError::from(err).error_response(),
     }
 }
 
@@ -206,30 +215,37 @@ pub async fn update_stock(
 )]
 pub async fn delete_product(
     tenant: web::ReqData<platform::tenant::TenantContext>,
-    db_router: web::Data<platform::db_router::DynamicPoolRouter>,
+    db_router: web::Data<DynamicPoolRouter>,
     redis_pub: web::Data<RedisPublisher>,
     redis_client: web::Data<redis::Client>,
     path: web::Path<(Uuid, Uuid)>, // supplier_id and product_id
 ) -> impl Responder {
     let (supplier_id, product_id) = path.into_inner();
 
-    let pool = db_router.get_pool(&tenant).await.unwrap();
-    let mut tx = pool.begin().await.unwrap();
-    tenant.apply_rls(&mut *tx).await.unwrap();
+    let pool = match db_router.get_pool(&tenant).await {
+        Ok(pool) => pool,
+        Err(e) => return DynamicPoolRouter::unavailable(e).error_response(),
+    };
+    let mut tx = match tenant.begin(&pool).await {
+        Ok(tx) => tx,
+        Err(e) => return AppError::from(e).error_response(),
+    };
 
-    match InventoryRepo::delete_product(&mut *tx, supplier_id, product_id).await {
+    match InventoryRepo::delete_product(&mut tx, supplier_id, product_id).await {
         Ok(rows_affected) if rows_affected > 0 => {
             tx.commit().await.unwrap();
             // Publish deletion event
-            let event = ProductDeletedEvent {
+            let event = InventoryEvent {
                 tenant_id: tenant.tenant_id,
                 product_id,
-                supplier_id,
-                deleted: true,
+                supplier_id: Some(supplier_id),
+                quantity: Some(0),
+                timestamp: Utc::now(),
+                ..Default::default()
             };
 
             redis_pub
-                .publish::<ProductDeletedEvent>("inventory.deleted", &event)
+                .publish(&DomainEvent::InventoryDeleted(event))
                 .await
                 .unwrap();
 
@@ -240,8 +256,8 @@ pub async fn delete_product(
 
             HttpResponse::Ok().body("Product deleted successfully")
         }
-        Ok(_) => HttpResponse::NotFound().body("Product not found"),
-        Err(_) => HttpResponse::InternalServerError().body("Failed to delete product"),
+        Ok(_) => AppError::NotFound("Product not found".into()).error_response(),
+        Err(e) => AppError::from(e).error_response(),
     }
 }
 
@@ -9,16 +9,22 @@ mod worker;
 use crate::redis_pub::RedisPublisher;
 use crate::redis_sub::listen_to_redis_events;
 use actix_web::{web, App, HttpServer};
-use dotenvy::dotenv;
 use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
+use platform::config::{Schema, ServiceConfig};
+use platform::health::{self, Readiness};
 use platform::{metrics, observability};
 use platform::db_router::DynamicPoolRouter;
+use platform::middleware::request_id::RequestId;
 use platform::middleware::tenant_middleware::TenantAuthMiddleware;
+use platform::middleware::{JwksVerifier, MetricsMiddleware, RateLimiter, RequireScope};
+use platform::pagination::CursorSigner;
+use platform::scopes;
+use platform::supervisor::Supervisor;
+use platform::tenant_directory::TenantDirectory;
 use redis::Client;
 use sqlx::postgres::PgPoolOptions;
-use std::env;
-use tokio::spawn;
+use sqlx::migrate::Migrator;
 
 use crate::worker::reservation_worker;
 
@@ -55,104 +61,160 @@ pub async fn metrics_api_doc() {}
 )]
 pub struct ApiDoc;
 
+static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    dotenv().ok();
+    let config = ServiceConfig::load(
+        Schema::service("inventory-management", 3006)
+            .with_consumer("inventory-1")
+            .with_jwt()
+            .with_pagination(),
+    );
     observability::init_observability("inventory-management");
     metrics::init_metrics("inventory-management");
+    config.settings.log();
 
-    let db_url = env::var("DATABASE_URL").expect("DATABASE_URL not set");
-    let redis_url = env::var("REDIS_URL");
-    let port = env::var("SERVICE_PORT").unwrap_or_else(|_| "3006".into());
+    let db_url = &config.database_url;
+    let redis_url = config.redis_url.clone();
+    let port = config.service_port;
 
     // println!("Connecting to DB: {}", db_url);
     let pool = PgPoolOptions::new()
         .max_connections(5)
-        .connect(&db_url)
+        .connect(db_url)
         .await
         .expect("postgres");
-    if let Err(e) = sqlx::migrate!("./migrations").run(&pool).await {
+    if let Err(e) = MIGRATOR.run(&pool).await {
         eprintln!("❌ Migration failed: {:?}", e);
         std::process::exit(1);
     }
 
-    let db_router = web::Data::new(DynamicPoolRouter::new(pool.clone()));
-
     let redis_client = web::Data::new(
         redis_url
             .as_ref()
             .map(|url| Client::open(url.as_str()))
-            .unwrap_or_else(|_| {
+            .unwrap_or_else(|| {
                 eprintln!("⚠️ REDIS_URL not set — using noop client.");
                 Ok(Client::open("redis://localhost:6379").unwrap())
             })
             .unwrap(),
     );
 
+    // Stream events and the reservation sweep carry only a tenant id, so they resolve
+    // dedicated databases through the tenant directory
+    let tenants = TenantDirectory::from_env().with_redis(redis_client.get_ref().clone());
+    let db_router = web::Data::new(
+        DynamicPoolRouter::new(pool.clone())
+            .with_migrator(&MIGRATOR)
+            .with_tenant_directory(tenants.clone()),
+    );
+    db_router.spawn_eviction();
+
     let redis_pub = match redis_url.clone() {
-        Ok(ref url) => match RedisPublisher::new(url).await {
+        Some(ref url) => match RedisPublisher::new(url).await {
             Ok(pubw) => web::Data::new(pubw),
             Err(e) => {
                 eprintln!("⚠️ Failed to connect to Redis: {:?} ⚠️ Continuing without Redis publishing capabilities...", e);
                 web::Data::new(RedisPublisher::new_noop())
             }
         },
-        Err(e) => {
-            eprintln!(
-                "⚠️ No REDIS_URL configured — using no-op publisher: {:?}",
-                e
-            );
+        None => {
+            eprintln!("⚠️ No REDIS_URL configured — using no-op publisher");
             web::Data::new(RedisPublisher::new_noop())
         }
     };
 
-    reservation_worker::start_reservation_expiration_worker(pool.clone(), redis_pub.clone()).await;
+    let supervisor = Supervisor::new("inventory-management").with_deadline(config.shutdown_timeout);
+    let (worker_router, worker_pub) = (db_router.clone(), redis_pub.clone());
+    supervisor.spawn(reservation_worker::HEARTBEAT, move || {
+        reservation_worker::run_reservation_expiration_worker(worker_router.clone(), tenants.clone(), worker_pub.clone())
+    });
+
+    let mut readiness = Readiness::new("inventory-management")
+        .with_postgres(pool.clone())
+        .with_heartbeat(reservation_worker::HEARTBEAT)
+        .with_supervisor(supervisor.clone());
+    if redis_url.is_some() {
+        let max_lag = config.settings.integer("STREAM_MAX_LAG").unwrap_or_default() as u64;
+        readiness = readiness
+            .with_redis(redis_client.get_ref().clone())
+            .with_consumer(redis_client.get_ref().clone(), redis_sub::GROUP, redis_sub::EVENTS, max_lag);
+    }
+    let readiness = web::Data::new(readiness);
 
-    // spawn Redis listener in background
-    let pool_clone = pool.clone();
+    // Supervised Redis listener
+    let router_clone = db_router.clone();
     let redis_pub_clone = redis_pub.clone();
+    let consumer = config.settings.text("CONSUMER_NAME").to_string();
 
-    // Since we removed InventoryRepo instantiation, we need to pass db_router to listen_to_redis_events?
-    // Wait, listen_to_redis_events in redis_sub.rs expects repo_clone. Let's look at redis_sub.rs later and pass db_router instead or just empty InventoryRepo.
     let repo_clone = web::Data::new(db::InventoryRepo {});
 
-    spawn(async move {
-        let _ = listen_to_redis_events(pool_clone, repo_clone, redis_pub_clone).await;
-    });
+    if redis_url.is_some() {
+        supervisor.spawn("stream-consumer", move || {
+            listen_to_redis_events(
+                router_clone.clone(),
+                repo_clone.clone(),
+                redis_pub_clone.clone(),
+                redis_url.clone(),
+                consumer.clone(),
+            )
+        });
+    }
 
     tracing::info!("Inventory Service listening on 0.0.0.0:{}", port);
 
-    HttpServer::new(move || {
-        let tenant_middleware = TenantAuthMiddleware::with_redis(redis_client.get_ref().clone());
+    let jwt_verifier = JwksVerifier::from_env().expect("JWT verification keys must be configured (JWKS_URL)");
+    let cursor_signer = web::Data::new(
+        CursorSigner::from_env().expect("PAGINATION_CURSOR_SECRET must be set"),
+    );
+
+    let server = HttpServer::new(move || {
+        let tenant_middleware = TenantAuthMiddleware::with_redis(redis_client.get_ref().clone()).with_jwt_verifier(jwt_verifier.clone());
         App::new()
-            .service(
-                SwaggerUi::new("/swagger-ui/{_:.*}")
-                    .url("/api-docs/openapi.json", ApiDoc::openapi())
-            )
-            .wrap(tenant_middleware)
+            .wrap(MetricsMiddleware)
+            .wrap(RequestId)
             .app_data(db_router.clone())
             .app_data(redis_pub.clone())
             .app_data(redis_client.clone())
-            .route("/metrics", web::get().to(metrics::metrics_handler))
-            .route("/inventory", web::post().to(handlers::create_inventory))
-            .route(
-                "/inventory/{supplier_id}/{product_id}",
-                web::get().to(handlers::get_inventory_item),
-            )
-            .route(
-                "/inventory/{supplier_id}",
-                web::get().to(handlers::get_inventory),
-            )
-            .route(
-                "/inventory/{supplier_id}/update",
-                web::post().to(handlers::update_stock),
-            )
-            .route(
-                "/inventory/{supplier_id}/{product_id}",
-                web::delete().to(handlers::delete_product),
+            .app_data(cursor_signer.clone())
+            .app_data(readiness.clone())
+            .route("/health/live", web::get().to(health::health_check))
+            .route("/health/ready", web::get().to(health::readiness))
+            .service(
+                web::scope("")
+                    .wrap(RequireScope::resource(scopes::INVENTORY))
+                    .wrap(RateLimiter::new(redis_client.get_ref().clone()))
+                    .wrap(tenant_middleware)
+                    .service(
+                        SwaggerUi::new("/swagger-ui/{_:.*}")
+                            .url("/api-docs/openapi.json", ApiDoc::openapi())
+                    )
+                    .route("/metrics", web::get().to(metrics::metrics_handler))
+                    .route("/health/db", web::get().to(health::tenant_db_health))
+                    .route("/inventory", web::post().to(handlers::create_inventory))
+                    .route(
+                        "/inventory/{supplier_id}/{product_id}",
+                        web::get().to(handlers::get_inventory_item),
+                    )
+                    .route(
+                        "/inventory/{supplier_id}",
+                        web::get().to(handlers::get_inventory),
+                    )
+                    .route(
+                        "/inventory/{supplier_id}/update",
+                        web::post().to(handlers::update_stock),
+                    )
+                    .route(
+                        "/inventory/{supplier_id}/{product_id}",
+                        web::delete().to(handlers::delete_product),
+                    ),
             )
     })
     .bind(format!("0.0.0.0:{}", port))?
-    .run()
-    .await
+    .disable_signals()
+    .shutdown_timeout(supervisor.deadline().as_secs())
+    .run();
+
+    supervisor.serve(server).await
 }
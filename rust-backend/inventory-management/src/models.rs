@@ -1,4 +1,5 @@
 use chrono::{DateTime, Utc};
+use platform::pagination::{keyset_timestamp, FieldKind, Keyset, ListSpec, SortDirection};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use uuid::Uuid;
@@ -20,6 +21,34 @@ pub struct Inventory {
     pub updated_at: DateTime<Utc>,
 }
 
+impl Inventory {
+    /// Sort and filter whitelist of `GET /inventory/{supplier_id}`.
+    pub fn list_spec() -> ListSpec {
+        ListSpec::new("name", SortDirection::Asc)
+            .sortable("name", FieldKind::Text)
+            .sortable("quantity", FieldKind::Integer)
+            .sortable("updated_at", FieldKind::Timestamp)
+            .filterable("product_id", FieldKind::Uuid)
+            .filterable("category", FieldKind::Text)
+            .filterable("low_stock_threshold", FieldKind::Integer)
+    }
+}
+
+impl Keyset for Inventory {
+    fn keyset_id(&self) -> Uuid {
+        self.id
+    }
+
+    fn keyset_value(&self, field: &str) -> Option<String> {
+        match field {
+            "name" => Some(self.name.clone()),
+            "quantity" => Some(self.quantity.to_string()),
+            "updated_at" => Some(keyset_timestamp(&self.updated_at)),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Default, utoipa::ToSchema)]
 pub struct UpdateStockRequest {
     pub product_id: Uuid,
@@ -35,22 +64,6 @@ pub struct UpdateStockRequest {
     pub reserved: Option<i32>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct StockUpdateEvent {
-    pub tenant_id: Uuid,
-    pub product_id: Uuid,
-    pub supplier_id: Uuid,
-    pub new_quantity: i32,
-    pub change: Option<i32>,
-    pub low_stock: bool,
-    pub name: Option<String>,
-    pub description: Option<serde_json::Value>,
-    pub category: Option<String>,
-    pub price: Option<f64>,
-    pub unit: Option<String>,
-    pub available: Option<bool>,
-}
-
 #[derive(Debug, Deserialize, utoipa::ToSchema)]
 pub struct CreateInventoryRequest {
     pub supplier_id: Uuid,
@@ -64,33 +77,10 @@ pub struct CreateInventoryRequest {
     pub unit: String,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize, Default)]
-#[serde(default)]
-pub struct ProductEvent {
-    pub tenant_id: Option<Uuid>,
-    pub event_type: String,
-    pub product_id: Uuid,
-    pub supplier_id: Uuid,
-    pub name: Option<String>,
-    pub description: Option<serde_json::Value>,
-    pub price: Option<f64>,
-    pub category: Option<String>,
-    pub low_stock_threshold: Option<i32>,
-    pub unit: Option<String>,
-    pub quantity_change: Option<i32>,
-    pub available: Option<bool>,
-    // Order-related
-    pub order_id: Option<Uuid>,
-    pub quantity: Option<i32>,
-    pub reservation_id: Option<Uuid>,
-    pub order_timestamp: Option<DateTime<Utc>>,
-    pub expires_at: Option<DateTime<Utc>>,
-    pub user_id: Option<Uuid>, // pub status: OrderStatus,
-}
-
 #[derive(Debug, sqlx::FromRow)]
 pub struct ExpiredReservationRow {
     pub reservation_id: Uuid,
+    pub tenant_id: Uuid,
     pub order_id: Uuid,
     pub product_id: Uuid,
     pub qty: i32,
@@ -100,6 +90,7 @@ pub struct ExpiredReservationRow {
 #[derive(Debug, sqlx::FromRow)]
 pub struct ReservationRow {
     pub reservation_id: Uuid,
+    pub product_id: Uuid,
     pub qty: i32,
     pub released: bool,
     pub user_id: Uuid,
@@ -115,12 +106,4 @@ mod tests {
         assert_eq!(req.quantity, None);
         assert_eq!(req.quantity_change, None);
     }
-
-    #[test]
-    fn test_product_event_default() {
-        let event = ProductEvent::default();
-        assert_eq!(event.event_type, "");
-        assert_eq!(event.name, None);
-        assert_eq!(event.quantity_change, None);
-    }
 }
@@ -2,13 +2,12 @@
 // Consumes product/order/payment workflow events from Redis Streams.
 
 use actix_web::web::Data;
+use platform::db_router::DynamicPoolRouter;
 use platform::{metrics, streams};
-use sqlx::PgPool;
-use std::env;
 
 use crate::db::InventoryRepo;
-use crate::models::ProductEvent;
 use crate::redis_pub::RedisPublisher;
+use platform::events::{DomainEvent, OrderEvent, PaymentEvent};
 
 mod events;
 use events::{
@@ -16,7 +15,10 @@ use events::{
     release_stock_from_order, reserve_stock_from_order, update_product_from_event,
 };
 
-const EVENTS: &[&str] = &[
+/// Consumer group this service reads its streams with.
+pub const GROUP: &str = "inventory-management";
+
+pub const EVENTS: &[&str] = &[
     "product.created",
     "product.updated",
     "product.deleted",
@@ -30,34 +32,36 @@ const EVENTS: &[&str] = &[
 ];
 
 pub async fn listen_to_redis_events(
-    pool: PgPool,
+    db_router: Data<DynamicPoolRouter>,
     repo: Data<InventoryRepo>,
     redis_pub: Data<RedisPublisher>,
+    redis_url: Option<String>,
+    consumer: String,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let redis_url = env::var("REDIS_URL").map_err(|_| "REDIS_URL must be set in environment")?;
-    let consumer = env::var("CONSUMER_NAME").unwrap_or_else(|_| "inventory-1".to_string());
+    let redis_url = redis_url.ok_or("REDIS_URL must be set in environment")?;
 
-    streams::consume_json::<ProductEvent, _, _>(
+    streams::consume_json::<DomainEvent, _, _>(
         &redis_url,
-        "inventory-management",
+        GROUP,
         &consumer,
         EVENTS,
         move |envelope| {
-            let pool = pool.clone();
+            let db_router = db_router.clone();
             let repo = repo.clone();
             let redis_pub = redis_pub.clone();
             async move {
                 let event_type = envelope.event_type.clone();
                 let event = envelope.payload;
 
-                let tenant_id = envelope.tenant_id.or(event.tenant_id);
+                let payload_tenant_id = Some(event.tenant_id());
+                let tenant_id = envelope.tenant_id.or(payload_tenant_id);
                 if tenant_id.is_none() || tenant_id == Some(uuid::Uuid::nil()) {
                     tracing::warn!(%event_type, stream = %envelope.stream, "Missing tenant_id in stream event — skipping business logic");
                     metrics::inc_event("inventory-management", &envelope.stream, &event_type, "tenant_mismatch");
                     return Ok(());
                 }
 
-                if let (Some(env_tid), Some(pay_tid)) = (envelope.tenant_id, event.tenant_id) {
+                if let (Some(env_tid), Some(pay_tid)) = (envelope.tenant_id, payload_tenant_id) {
                     if env_tid != pay_tid {
                         tracing::warn!(%event_type, ?env_tid, ?pay_tid, "Tenant ID mismatch between stream envelope and payload — skipping business logic");
                         metrics::inc_event("inventory-management", &envelope.stream, &event_type, "tenant_mismatch");
@@ -65,26 +69,29 @@ pub async fn listen_to_redis_events(
                     }
                 }
 
-                let result: Result<(), Box<dyn std::error::Error + Send + Sync>> = match event_type.as_str() {
-                    "product.created" => create_product_from_event(&pool, event).await,
-                    "product.updated" => update_product_from_event(&pool, event).await,
-                    "product.deleted" => delete_product_from_event(&pool, event).await,
-                    "order.created" => reserve_stock_from_order(&pool, redis_pub, event).await,
-                    "order.cancelled" | "order.failed" | "inventory.release_command" => {
+                let kind = event.event_type();
+                let pool = match db_router.get_tenant_pool(event.tenant_id()).await {
+                    Ok(pool) => pool,
+                    Err(e) => {
+                        metrics::inc_event("inventory-management", &envelope.stream, &event_type, "error");
+                        return Err(e.into());
+                    }
+                };
+                let result: Result<(), Box<dyn std::error::Error + Send + Sync>> = match event {
+                    DomainEvent::ProductCreated(event) => create_product_from_event(&pool, kind, event).await,
+                    DomainEvent::ProductUpdated(event) => update_product_from_event(&pool, kind, event).await,
+                    DomainEvent::ProductDeleted(event) => delete_product_from_event(&pool, kind, event).await,
+                    DomainEvent::OrderCreated(event) => reserve_stock_from_order(&pool, redis_pub, event).await,
+                    DomainEvent::OrderCancelled(event)
+                    | DomainEvent::OrderFailed(event)
+                    | DomainEvent::InventoryReleaseCommand(event) => {
                         release_stock_from_order(&pool, redis_pub, event).await
                     }
-                    "payment.success" => {
-                        finalize_order_after_payment(
-                            &pool,
-                            redis_pub,
-                            repo,
-                            event.supplier_id,
-                            event,
-                        )
-                        .await
+                    DomainEvent::PaymentSuccess(event) => {
+                        finalize_order_after_payment(&pool, redis_pub, repo, event).await
                     }
-                    "payment.failed" | "payment.cancelled" => {
-                        release_stock_from_order(&pool, redis_pub, event).await
+                    DomainEvent::PaymentFailed(event) | DomainEvent::PaymentCancelled(event) => {
+                        release_stock_from_order(&pool, redis_pub, release_for_payment(event)).await
                     }
                     _ => Ok(()),
                 };
@@ -105,3 +112,17 @@ pub async fn listen_to_redis_events(
     )
     .await
 }
+
+/// Stock held for a payment that did not go through is released like a cancelled order.
+fn release_for_payment(event: PaymentEvent) -> OrderEvent {
+    OrderEvent {
+        tenant_id: event.tenant_id,
+        order_id: event.order_id,
+        product_id: event.product_id,
+        supplier_id: event.supplier_id,
+        user_id: event.user_id,
+        quantity: Some(event.quantity),
+        timestamp: event.timestamp,
+        ..Default::default()
+    }
+}
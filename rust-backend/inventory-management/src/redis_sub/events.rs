@@ -1,20 +1,20 @@
-use crate::models::{ProductEvent, UpdateStockRequest, ExpiredReservationRow, ReservationRow, CreateInventoryRequest};
+use crate::models::{UpdateStockRequest, ExpiredReservationRow, ReservationRow, CreateInventoryRequest};
 use crate::redis_pub::RedisPublisher;
 use crate::db::InventoryRepo;
 use actix_web::web;
 use chrono::{Duration, Utc};
-use platform::tenant::{AuthMethod, PricingTier, TenantContext};
+use platform::events::{DomainEvent, EventType, InventoryEvent, OrderEvent, OrderLineItem, PaymentEvent, ProductEvent};
+use platform::tenant::TenantTx;
 use sqlx::PgPool;
+use std::collections::{BTreeMap, HashMap};
 use uuid::Uuid;
 
 pub async fn create_product_from_event(
     pool: &PgPool,
+    event_type: EventType,
     event: ProductEvent,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let tenant_id = event.tenant_id.unwrap_or(event.supplier_id);
-    let ctx = TenantContext::new(tenant_id, event.user_id, PricingTier::Free, vec![], AuthMethod::ApiKey);
-    let mut tx = pool.begin().await?;
-    ctx.apply_rls(&mut *tx).await?;
+    let mut tx = TenantTx::begin(pool, event.tenant_id).await?;
 
     let req = CreateInventoryRequest {
         supplier_id: event.supplier_id,
@@ -28,10 +28,10 @@ pub async fn create_product_from_event(
         unit: event.unit.unwrap_or_else(|| "unit".to_string()),
     };
 
-    match InventoryRepo::create_inventory_item(&mut *tx, &req).await {
+    match InventoryRepo::create_inventory_item(&mut tx, &req).await {
         Ok(_) => {
             tx.commit().await?;
-            println!("✅({}) Created product {:?} via Repo", event.event_type, req.name);
+            println!("✅({}) Created product {:?} via Repo", event_type, req.name);
         }
         Err(e) => eprintln!("❌ Failed to create product: {:?}", e),
     }
@@ -40,12 +40,10 @@ pub async fn create_product_from_event(
 
 pub async fn update_product_from_event(
     pool: &PgPool,
+    event_type: EventType,
     event: ProductEvent,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let tenant_id = event.tenant_id.unwrap_or(event.supplier_id);
-    let ctx = TenantContext::new(tenant_id, event.user_id, PricingTier::Free, vec![], AuthMethod::ApiKey);
-    let mut tx = pool.begin().await?;
-    ctx.apply_rls(&mut *tx).await?;
+    let mut tx = TenantTx::begin(pool, event.tenant_id).await?;
 
     let req = UpdateStockRequest {
         product_id: event.product_id,
@@ -61,10 +59,10 @@ pub async fn update_product_from_event(
         reserved: None,
     };
 
-    match InventoryRepo::update_stock(&mut *tx, event.supplier_id, &req).await {
+    match InventoryRepo::update_stock(&mut tx, event.supplier_id, &req).await {
         Ok(_) => {
             tx.commit().await?;
-            println!("🔁({}) Updated product {:?} via Repo", event.event_type, req.name);
+            println!("🔁({}) Updated product {:?} via Repo", event_type, req.name);
         }
         Err(e) => eprintln!("❌ Failed to update product: {:?}", e),
     }
@@ -73,17 +71,15 @@ pub async fn update_product_from_event(
 
 pub async fn delete_product_from_event(
     pool: &PgPool,
+    event_type: EventType,
     event: ProductEvent,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let tenant_id = event.tenant_id.unwrap_or(event.supplier_id);
-    let ctx = TenantContext::new(tenant_id, event.user_id, PricingTier::Free, vec![], AuthMethod::ApiKey);
-    let mut tx = pool.begin().await?;
-    ctx.apply_rls(&mut *tx).await?;
+    let mut tx = TenantTx::begin(pool, event.tenant_id).await?;
 
-    match InventoryRepo::delete_product(&mut *tx, event.supplier_id, event.product_id).await {
+    match InventoryRepo::delete_product(&mut tx, event.supplier_id, event.product_id).await {
         Ok(_) => {
             tx.commit().await?;
-            println!("🗑️({}) Deleted product {} via Repo", event.event_type, event.product_id);
+            println!("🗑️({}) Deleted product {} via Repo", event_type, event.product_id);
         }
         Err(e) => eprintln!("❌ Failed to delete product: {:?}", e),
     }
@@ -93,24 +89,21 @@ pub async fn delete_product_from_event(
 pub async fn reserve_stock_from_order(
     pool: &PgPool,
     redis_pub: web::Data<RedisPublisher>,
-    event: ProductEvent,
+    event: OrderEvent,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let tenant_id = event.tenant_id.unwrap_or(event.supplier_id);
-    let ctx = TenantContext::new(tenant_id, event.user_id, PricingTier::Free, vec![], AuthMethod::ApiKey);
 
-    let mut tx_expired = pool.begin().await?;
-    ctx.apply_rls(&mut *tx_expired).await?;
+    let mut tx_expired = TenantTx::begin(pool, event.tenant_id).await?;
 
     let expired_reservations = sqlx::query_as::<_, ExpiredReservationRow>(
         r#"
-            SELECT reservation_id, order_id, product_id, qty, user_id
+            SELECT reservation_id, tenant_id, order_id, product_id, qty, user_id
             FROM reservations
             WHERE expires_at <= NOW()
             AND released = false
             FOR UPDATE
         "#
     )
-    .fetch_all(&mut *tx_expired)
+    .fetch_all(tx_expired.executor())
     .await?;
 
     // Process each expired reservation
@@ -124,7 +117,7 @@ pub async fn reserve_stock_from_order(
         )
         .bind(r.qty)
         .bind(r.product_id)
-        .execute(&mut *tx_expired)
+        .execute(tx_expired.executor())
         .await?;
 
         sqlx::query(
@@ -135,22 +128,21 @@ pub async fn reserve_stock_from_order(
             "#,
         )
         .bind(r.reservation_id)
-        .execute(&mut *tx_expired)
+        .execute(tx_expired.executor())
         .await?;
 
-        let cancel_event = ProductEvent {
-            tenant_id: event.tenant_id.or(Some(event.supplier_id)),
-            event_type: "inventory.expired".into(),
+        let cancel_event = InventoryEvent {
+            tenant_id: event.tenant_id,
             product_id: r.product_id,
             order_id: Some(r.order_id),
             quantity: Some(r.qty),
             user_id: Some(r.user_id),
             reservation_id: Some(r.reservation_id),
-            order_timestamp: Some(Utc::now()),
+            timestamp: Utc::now(),
             ..Default::default()
         };
 
-        redis_pub.publish_async("inventory.expired", cancel_event);
+        redis_pub.publish_async(DomainEvent::InventoryExpired(cancel_event));
 
         println!(
             "Expired reservation {} for order {} was released. its status is 'expired'",
@@ -159,22 +151,24 @@ pub async fn reserve_stock_from_order(
     }
     tx_expired.commit().await?;
 
-    let product_id = event.product_id;
-    let order_id = event.order_id.ok_or("Missing order_id")?;
-    let qty_requested = event.quantity.ok_or("Missing quantity")?;
-    let user_id = event.user_id.ok_or("Missing user_id")?;
+    let order_id = event.order_id;
+    let user_id = event.user_id;
+    let requested = requested_by_product(&event.line_items());
+    if requested.is_empty() {
+        return Err("Order has no lines to reserve".into());
+    }
+    let product_ids: Vec<Uuid> = requested.keys().copied().collect();
+    let first_product = product_ids[0];
+    let total_qty: i32 = requested.values().sum();
 
     // adjust timing, configurable to add flexibility for when the customer is able to pay
     let expires_at = Utc::now() + Duration::seconds(2 * 24 * 60 * 60);
 
-    // Atomically check & reserve stock
-    let mut tx = pool.begin().await?;
-    ctx.apply_rls(&mut *tx).await?;
+    // Atomically check & reserve stock for every line
+    let mut tx = TenantTx::begin(pool, event.tenant_id).await?;
 
-    // let existing: Option<(Uuid, i32)> = //
-
-    // ensure reservation for this order doesn't already exist (idempotency)
-    if let Ok(Some((reservation_id, qty))) = sqlx::query_as::<_, (Uuid, i32)>(
+    // ensure reservations for this order don't already exist (idempotency)
+    let existing = sqlx::query_as::<_, (Uuid, i32)>(
         r#"
             SELECT reservation_id, qty
             FROM reservations
@@ -182,211 +176,226 @@ pub async fn reserve_stock_from_order(
         "#,
     )
     .bind(order_id)
-    .fetch_optional(&mut *tx)
-    .await
-    {
+    .fetch_all(tx.executor())
+    .await?;
+
+    if let Some((reservation_id, _)) = existing.first() {
         tx.commit().await?;
-        let success_event = ProductEvent {
-            tenant_id: event.tenant_id.or(Some(event.supplier_id)),
-            event_type: "inventory.reserved".into(),
-            product_id: product_id,
+        let success_event = InventoryEvent {
+            tenant_id: event.tenant_id,
+            product_id: first_product,
+            supplier_id: Some(event.supplier_id),
             order_id: Some(order_id),
-            quantity: Some(qty),
+            quantity: Some(existing.iter().map(|(_, qty)| qty).sum()),
             user_id: Some(user_id),
+            amount: event.total_cents,
             expires_at: Some(expires_at),
-            reservation_id: Some(reservation_id),
-            order_timestamp: Some(Utc::now()),
+            reservation_id: Some(*reservation_id),
+            timestamp: Utc::now(),
             ..Default::default()
         };
 
-        redis_pub.publish_async("inventory.reserved", success_event);
+        redis_pub.publish_async(DomainEvent::InventoryReserved(success_event));
         return Ok(());
     }
 
-    // get quantity as well as reserved, to compare them to see if the requested quantity is less than what is avaialable
-    let (qty, reserved) = sqlx::query_as::<_, (i32, i32)>(
+    // Rows are locked in product order so concurrent multi-line orders cannot deadlock
+    let stock: HashMap<Uuid, (i32, i32)> = sqlx::query_as::<_, (Uuid, i32, i32)>(
         r#"
-            SELECT quantity, reserved
+            SELECT product_id, quantity, reserved
             FROM inventory
-            WHERE product_id = $1 FOR UPDATE
+            WHERE product_id = ANY($1)
+            ORDER BY product_id
+            FOR UPDATE
         "#,
     )
-    .bind(product_id)
-    .fetch_one(&mut *tx)
-    .await?;
-
-    // find out whether the requested quantity is even available to prevent overselling
-    let available = qty - reserved;
-
-    if available < qty_requested {
+    .bind(&product_ids)
+    .fetch_all(tx.executor())
+    .await?
+    .into_iter()
+    .map(|(product_id, quantity, reserved)| (product_id, (quantity, reserved)))
+    .collect();
+
+    // All lines are reserved or none are, so a short line rejects the whole order
+    if let Some((product_id, qty_requested)) = first_shortage(&requested, &stock) {
         tx.rollback().await?;
         // Publish REJECTED
-        let reject_event = ProductEvent {
-            tenant_id: event.tenant_id.or(Some(event.supplier_id)),
-            event_type: "inventory.rejected".into(),
-            product_id: product_id,
+        let reject_event = InventoryEvent {
+            tenant_id: event.tenant_id,
+            product_id,
+            supplier_id: Some(event.supplier_id),
             order_id: Some(order_id),
             quantity: Some(qty_requested),
             user_id: Some(user_id),
-            order_timestamp: Some(Utc::now()),
+            timestamp: Utc::now(),
             ..Default::default()
         };
 
-        redis_pub.publish_async("inventory.rejected", reject_event);
+        redis_pub.publish_async(DomainEvent::InventoryRejected(reject_event));
         return Ok(());
     }
 
-    // Reserve stock
-    sqlx::query(
-        r#"
-            UPDATE inventory
-            SET reserved = reserved + $1
-            WHERE product_id = $2
-        "#,
-    )
-    .bind(qty_requested)
-    .bind(product_id)
-    .execute(&mut *tx)
-    .await?;
+    let mut first_reservation = None;
+    for (&product_id, &qty_requested) in &requested {
+        sqlx::query(
+            r#"
+                UPDATE inventory
+                SET reserved = reserved + $1
+                WHERE product_id = $2
+            "#,
+        )
+        .bind(qty_requested)
+        .bind(product_id)
+        .execute(tx.executor())
+        .await?;
 
-    // insert reservation row (idempotency + expiry)
-    let reservation_id = Uuid::new_v4();
-    // let user_id = Uuid::new_v4();
-    // let expires_at = Utc::now() + Duration::seconds(reservation_ttl_secs);
-    sqlx::query(
-        r#"
-            INSERT INTO reservations (reservation_id, order_id, product_id, qty, user_id, expires_at, created_at, released)
-            VALUES ($1, $2, $3, $4, $5, $6, now(), false)
-        "#,
-    )
-    .bind(reservation_id)
-    .bind(order_id)
-    .bind(product_id)
-    .bind(qty_requested)
-    .bind(user_id)
-    .bind(expires_at)
-    .execute(&mut *tx)
-    .await?;
+        // insert reservation row (idempotency + expiry)
+        let reservation_id = Uuid::new_v4();
+        first_reservation.get_or_insert(reservation_id);
+        sqlx::query(
+            r#"
+                INSERT INTO reservations (reservation_id, tenant_id, order_id, product_id, qty, user_id, expires_at, created_at, released)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, now(), false)
+            "#,
+        )
+        .bind(reservation_id)
+        .bind(event.tenant_id)
+        .bind(order_id)
+        .bind(product_id)
+        .bind(qty_requested)
+        .bind(user_id)
+        .bind(expires_at)
+        .execute(tx.executor())
+        .await?;
+    }
 
     tx.commit().await?;
 
     // Publish success
-    let success_event = ProductEvent {
-        tenant_id: event.tenant_id.or(Some(event.supplier_id)),
-        event_type: "inventory.reserved".into(),
-        product_id: product_id,
+    let success_event = InventoryEvent {
+        tenant_id: event.tenant_id,
+        product_id: first_product,
+        supplier_id: Some(event.supplier_id),
         order_id: Some(order_id),
-        quantity: Some(qty_requested),
+        quantity: Some(total_qty),
+        amount: event.total_cents,
         expires_at: Some(expires_at),
         user_id: Some(user_id),
-        reservation_id: Some(reservation_id),
-        order_timestamp: Some(Utc::now()),
+        reservation_id: first_reservation,
+        timestamp: Utc::now(),
         ..Default::default()
     };
 
-    redis_pub.publish_async("inventory.reserved", success_event);
+    redis_pub.publish_async(DomainEvent::InventoryReserved(success_event));
 
-    println!("Stock Reserved for order {}", order_id);
+    println!("Stock Reserved for order {} ({} products)", order_id, requested.len());
 
     Ok(())
 }
 
+/// Quantity requested per product, merging lines that repeat a product.
+fn requested_by_product(lines: &[OrderLineItem]) -> BTreeMap<Uuid, i32> {
+    let mut requested = BTreeMap::new();
+    for line in lines {
+        *requested.entry(line.product_id).or_insert(0) += line.quantity;
+    }
+    requested
+}
+
+/// The first product whose available stock (`quantity - reserved`) cannot cover the
+/// request, including products with no inventory row.
+fn first_shortage(requested: &BTreeMap<Uuid, i32>, stock: &HashMap<Uuid, (i32, i32)>) -> Option<(Uuid, i32)> {
+    requested
+        .iter()
+        .find(|(product_id, &qty)| {
+            stock
+                .get(product_id)
+                .is_none_or(|&(quantity, reserved)| quantity - reserved < qty)
+        })
+        .map(|(&product_id, &qty)| (product_id, qty))
+}
+
 pub async fn release_stock_from_order(
     pool: &PgPool,
     redis_pub: web::Data<RedisPublisher>,
-    event: ProductEvent,
+    event: OrderEvent,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let product_id = event.product_id;
-    let order_id = event.order_id.unwrap_or(Uuid::new_v4());
-    let qty = event.quantity.unwrap_or(0);
+    let order_id = event.order_id;
 
-    let tenant_id = event.tenant_id.unwrap_or(event.supplier_id);
-    let ctx = TenantContext::new(tenant_id, event.user_id, PricingTier::Free, vec![], AuthMethod::ApiKey);
-    let mut tx = pool.begin().await?;
-    ctx.apply_rls(&mut *tx).await?;
+    let mut tx = TenantTx::begin(pool, event.tenant_id).await?;
 
-    // Check reservation exists and amount is ok
-    let res_row = sqlx::query_as::<_, ReservationRow>(
+    // Every line of the order that is still held
+    let rows = sqlx::query_as::<_, ReservationRow>(
         r#"
-            SELECT reservation_id, qty, released, user_id
+            SELECT reservation_id, product_id, qty, released, user_id
             FROM reservations
-            WHERE order_id = $1 FOR UPDATE
+            WHERE order_id = $1 AND released = false
+            ORDER BY product_id
+            FOR UPDATE
         "#,
     )
     .bind(order_id)
-    .fetch_optional(&mut *tx)
+    .fetch_all(tx.executor())
     .await?;
 
-    if res_row.is_none() {
-        // nothing to release; idempotent success
+    if rows.is_empty() {
+        // nothing to release (never reserved, already released or expired); idempotent success
         tx.rollback().await?;
         return Ok(());
     }
 
-    let reservation_id: Uuid = res_row.as_ref().unwrap().reservation_id;
-    let reserved_qty: i32 = res_row.as_ref().unwrap().qty;
-    let released_flag: bool = res_row.as_ref().unwrap().released;
-    let user_id: Uuid = res_row.as_ref().unwrap().user_id;
-    let expires_at = Utc::now() + Duration::seconds(2 * 24 * 60 * 60);
-
-    if released_flag {
-        tx.rollback().await?;
-        return Ok(()); // already released//expired
-    }
-
-    if qty > reserved_qty {
-        tx.rollback().await?;
-        return Err("release amount greater than reserved amount".into());
-    }
-
-    // decrement reserved safely
-    let res = sqlx::query(
-        r#"
-            UPDATE inventory
-            SET reserved = reserved - $1
-            WHERE product_id = $2
-            AND reserved >= $1
-        "#,
-    )
-    .bind(qty)
-    .bind(product_id)
-    .execute(&mut *tx)
-    .await?;
+    for row in &rows {
+        // decrement reserved safely
+        let res = sqlx::query(
+            r#"
+                UPDATE inventory
+                SET reserved = reserved - $1
+                WHERE product_id = $2
+                AND reserved >= $1
+            "#,
+        )
+        .bind(row.qty)
+        .bind(row.product_id)
+        .execute(tx.executor())
+        .await?;
 
-    if res.rows_affected() == 0 {
-        tx.rollback().await?;
-        return Err("failed to update reserved (insufficient reserved)".into());
+        if res.rows_affected() == 0 {
+            tx.rollback().await?;
+            return Err("failed to update reserved (insufficient reserved)".into());
+        }
     }
 
-    // mark reservation as released
+    // mark the order's reservations as released
     sqlx::query(
         r#"
             UPDATE reservations
             SET released = true
-            WHERE reservation_id = $1
+            WHERE order_id = $1 AND released = false
         "#,
     )
-    .bind(reservation_id)
-    .execute(&mut *tx)
+    .bind(order_id)
+    .execute(tx.executor())
     .await?;
 
     tx.commit().await?;
 
+    let expires_at = Utc::now() + Duration::seconds(2 * 24 * 60 * 60);
+
     // publish event AFTER commit
-    let release_event = ProductEvent {
-        tenant_id: event.tenant_id.or(Some(event.supplier_id)),
-        event_type: "inventory.released".into(),
-        product_id: product_id,
+    let release_event = InventoryEvent {
+        tenant_id: event.tenant_id,
+        product_id: rows[0].product_id,
+        supplier_id: Some(event.supplier_id),
         order_id: Some(order_id),
-        quantity: Some(qty),
-        user_id: Some(user_id),
+        quantity: Some(rows.iter().map(|row| row.qty).sum()),
+        user_id: Some(rows[0].user_id),
         expires_at: Some(expires_at),
-        reservation_id: Some(reservation_id),
-        order_timestamp: Some(Utc::now()),
+        reservation_id: Some(rows[0].reservation_id),
+        timestamp: Utc::now(),
         ..Default::default()
     };
 
-    redis_pub.publish_async("inventory.released", release_event);
+    redis_pub.publish_async(DomainEvent::InventoryReleased(release_event));
 
     Ok(())
 }
@@ -395,117 +404,109 @@ pub async fn finalize_order_after_payment(
     pool: &PgPool,
     redis_pub: web::Data<RedisPublisher>,
     _repo: web::Data<InventoryRepo>,
-    supplier_id: Uuid,
-    event: ProductEvent,
+    event: PaymentEvent,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let order_id = event.order_id.ok_or("missing order_id")?;
-    let qty = event.quantity.unwrap_or(0);
-    let product_id = event.product_id;
+    let order_id = event.order_id;
 
-    let tenant_id = event.tenant_id.unwrap_or(supplier_id);
-    let ctx = TenantContext::new(tenant_id, event.user_id, PricingTier::Free, vec![], AuthMethod::ApiKey);
-    let mut tx = pool.begin().await?;
-    ctx.apply_rls(&mut *tx).await?;
+    let mut tx = TenantTx::begin(pool, event.tenant_id).await?;
 
-    // Fetch reservation
-    let res_row = sqlx::query_as::<_, ReservationRow>(
+    // Fetch every line's reservation
+    let rows = sqlx::query_as::<_, ReservationRow>(
         r#"
-        SELECT reservation_id, qty, released, user_id
+        SELECT reservation_id, product_id, qty, released, user_id
         FROM reservations
         WHERE order_id = $1
+        ORDER BY product_id
         FOR UPDATE
         "#,
     )
     .bind(order_id)
-    .fetch_optional(&mut *tx)
+    .fetch_all(tx.executor())
     .await?;
 
-    let row = match res_row {
-        Some(r) => r,
-        None => {
-            tx.rollback().await?;
-            return Err("No reservation found for order".into());
-        }
-    };
+    if rows.is_empty() {
+        tx.rollback().await?;
+        return Err("No reservation found for order".into());
+    }
 
-    if row.released {
+    if rows.iter().any(|row| row.released) {
         tx.rollback().await?;
         return Err("Reservation already released (expired)".into());
     }
 
-    if qty > row.qty {
+    let reserved_qty: i32 = rows.iter().map(|row| row.qty).sum();
+    if event.quantity > reserved_qty {
         tx.rollback().await?;
         return Err("Payment quantity exceeds reserved".into());
     }
 
-    let reservation_id = row.reservation_id;
-    let user_id = row.user_id;
-
-    // Now mark reservation consumed
+    // Now mark the reservations consumed
     sqlx::query(
         r#"
             UPDATE reservations
                 SET released = TRUE
-            WHERE reservation_id = $1
-        "#,
-    )
-    .bind(reservation_id)
-    .execute(&mut *tx)
-    .await?;
-
-    let row: (i32, i32) = sqlx::query_as(
-        r#"
-            UPDATE inventory
-            SET quantity = quantity - $1,
-                reserved = reserved - $1
-            WHERE product_id = $2
-            RETURNING quantity, low_stock_threshold
+            WHERE order_id = $1
         "#,
     )
-    .bind(qty)
-    .bind(product_id)
-    .fetch_one(&mut *tx)
+    .bind(order_id)
+    .execute(tx.executor())
     .await?;
 
-    let (current_qty, low_stock_threshold) = row;
+    // Stock leaves the warehouse for every line
+    let mut updates = Vec::with_capacity(rows.len());
+    for row in &rows {
+        let (current_qty, low_stock_threshold): (i32, i32) = sqlx::query_as(
+            r#"
+                UPDATE inventory
+                SET quantity = quantity - $1,
+                    reserved = reserved - $1
+                WHERE product_id = $2
+                RETURNING quantity, low_stock_threshold
+            "#,
+        )
+        .bind(row.qty)
+        .bind(row.product_id)
+        .fetch_one(tx.executor())
+        .await?;
+        updates.push((row, current_qty, low_stock_threshold));
+    }
 
     tx.commit().await?;
 
     let expires_at = Utc::now() + Duration::seconds(2 * 24 * 60 * 60);
 
-    let finalised_event = ProductEvent {
-        tenant_id: event.tenant_id.or(Some(supplier_id)),
-        event_type: "inventory.finalized".into(),
-        product_id,
+    let finalised_event = InventoryEvent {
+        tenant_id: event.tenant_id,
+        product_id: rows[0].product_id,
+        supplier_id: Some(event.supplier_id),
         order_id: Some(order_id),
-        quantity: Some(qty),
-        user_id: Some(user_id),
+        quantity: Some(reserved_qty),
+        user_id: Some(rows[0].user_id),
         expires_at: Some(expires_at),
-        reservation_id: Some(reservation_id),
-        order_timestamp: Some(Utc::now()),
+        reservation_id: Some(rows[0].reservation_id),
+        timestamp: Utc::now(),
         ..Default::default()
     };
 
-    redis_pub.publish_async("inventory.finalized", finalised_event);
+    redis_pub.publish_async(DomainEvent::InventoryFinalized(finalised_event));
 
-    let updated_event = ProductEvent {
-        tenant_id: event.tenant_id.or(Some(supplier_id)),
-        event_type: "inventory.updated".into(),
-        product_id,
-        quantity: Some(current_qty),
-        ..Default::default()
-    };
-    redis_pub.publish_async("inventory.updated", updated_event);
-
-    if current_qty <= low_stock_threshold {
-        let lowstock_event = ProductEvent {
-            tenant_id: event.tenant_id.or(Some(supplier_id)),
-            event_type: "inventory.lowstock".into(),
-            product_id,
+    for (row, current_qty, low_stock_threshold) in updates {
+        let low_stock = current_qty <= low_stock_threshold;
+        let updated_event = InventoryEvent {
+            tenant_id: event.tenant_id,
+            product_id: row.product_id,
+            supplier_id: Some(event.supplier_id),
             quantity: Some(current_qty),
+            quantity_change: Some(-row.qty),
+            low_stock: Some(low_stock),
+            timestamp: Utc::now(),
             ..Default::default()
         };
-        redis_pub.publish_async("inventory.lowstock", lowstock_event);
+        redis_pub.publish_async(DomainEvent::InventoryUpdated(updated_event.clone()));
+
+        if low_stock {
+            redis_pub.publish_async(DomainEvent::InventoryLowStock(updated_event));
+        }
     }
 
     Ok(())
@@ -523,4 +524,22 @@ mod tests {
         // INSERT INTO reservations
         assert!(true);
     }
+
+    #[test]
+    fn test_order_lines_are_reserved_all_or_nothing() {
+        let (bolts, nuts) = (Uuid::new_v4(), Uuid::new_v4());
+        let line = |product_id, quantity| OrderLineItem { product_id, quantity, ..Default::default() };
+        let requested = requested_by_product(&[line(bolts, 2), line(nuts, 5), line(bolts, 3)]);
+        assert_eq!(requested[&bolts], 5);
+
+        let mut stock = HashMap::from([(bolts, (10, 5)), (nuts, (5, 0))]);
+        assert_eq!(first_shortage(&requested, &stock), None);
+
+        stock.insert(bolts, (10, 6));
+        assert_eq!(first_shortage(&requested, &stock), Some((bolts, 5)));
+
+        stock.insert(bolts, (10, 0));
+        stock.remove(&nuts);
+        assert_eq!(first_shortage(&requested, &stock), Some((nuts, 5)));
+    }
 }
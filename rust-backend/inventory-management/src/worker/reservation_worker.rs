@@ -2,41 +2,87 @@ use crate::models::ExpiredReservationRow;
 use crate::redis_pub::RedisPublisher;
 use actix_web::web::Data;
 use chrono::Utc;
-use serde_json::json;
-use sqlx::PgPool;
+use platform::events::{DomainEvent, InventoryEvent};
+use platform::db_router::DynamicPoolRouter;
+use platform::health::Heartbeat;
+use platform::supervisor::{self, TaskError};
+use platform::tenant::TenantTx;
+use platform::tenant_directory::TenantDirectory;
 use tokio::time::{interval, Duration};
+use uuid::Uuid;
 
-pub async fn start_reservation_expiration_worker(pool: PgPool, redis_pub: Data<RedisPublisher>) {
-    tokio::spawn(async move {
-        let mut interval_timer = interval(Duration::from_secs(2 * 24 * 60 * 60));
+pub const HEARTBEAT: &str = "reservation-expiration";
+const PERIOD: Duration = Duration::from_secs(2 * 24 * 60 * 60);
 
-        loop {
-            interval_timer.tick().await;
+/// Releases expired reservations every `PERIOD`, until the supervisor shuts down.
+pub async fn run_reservation_expiration_worker(
+    db_router: Data<DynamicPoolRouter>,
+    tenants: TenantDirectory,
+    redis_pub: Data<RedisPublisher>,
+) -> Result<(), TaskError> {
+    let heartbeat = Heartbeat::register(HEARTBEAT, PERIOD);
+    let mut interval_timer = interval(PERIOD);
 
-            match clean_expired_reservations(&pool, &redis_pub).await {
-                Ok(_) => println!("Expired reservation cleanup complete"),
-                Err(e) => eprintln!("Failed to clean expired reservations: {:?}", e),
+    loop {
+        tokio::select! {
+            _ = interval_timer.tick() => {}
+            _ = supervisor::shutdown_requested() => return Ok(()),
+        }
+        heartbeat.beat();
+
+        let tenant_ids = match tenants.tenant_ids().await {
+            Ok(ids) => ids,
+            Err(e) => {
+                eprintln!("Failed to list tenants for reservation cleanup: {:?}", e);
+                continue;
+            }
+        };
+        for tenant_id in tenant_ids {
+            match clean_expired_reservations(&db_router, tenant_id, &redis_pub).await {
+                Ok(_) => println!("Expired reservation cleanup complete for tenant {}", tenant_id),
+                Err(e) => eprintln!("Failed to clean expired reservations for tenant {}: {:?}", tenant_id, e),
             }
         }
-    });
+    }
 }
 
 async fn clean_expired_reservations(
-    pool: &PgPool,
+    db_router: &DynamicPoolRouter,
+    tenant_id: Uuid,
     redis_pub: &RedisPublisher,
 ) -> Result<(), sqlx::Error> {
+    let pool = db_router.get_tenant_pool(tenant_id).await?;
+    let mut tx = TenantTx::begin(&pool, tenant_id).await?;
     let expired = sqlx::query_as::<_, ExpiredReservationRow>(
         r#"
-            SELECT reservation_id, order_id, product_id, qty, user_id
+            SELECT reservation_id, tenant_id, order_id, product_id, qty, user_id
             FROM reservations
             WHERE expires_at < NOW() AND released = false
         "#
     )
-    .fetch_all(pool)
+    .fetch_all(tx.executor())
     .await?;
+    tx.commit().await?;
 
     for res in expired {
-        // Release stock
+        // Mark the reservation expired and give its stock back together, so a crash
+        // cannot release one without the other
+        let mut tx = TenantTx::begin(&pool, tenant_id).await?;
+        let released = sqlx::query(
+            r#"
+                UPDATE reservations
+                SET released = true
+                WHERE reservation_id = $1 AND released = false
+            "#, // released = true basically means status = "expired"
+        )
+        .bind(res.reservation_id)
+        .execute(tx.executor())
+        .await?;
+        if released.rows_affected() == 0 {
+            // Released or consumed since the scan
+            continue;
+        }
+
         sqlx::query(
             r#"
                 UPDATE inventory
@@ -46,34 +92,23 @@ async fn clean_expired_reservations(
         )
         .bind(res.qty)
         .bind(res.product_id)
-        .execute(pool)
-        .await?;
-
-        // Mark reservation as expired
-        sqlx::query(
-            r#"
-                UPDATE reservations
-                SET released = true
-                WHERE reservation_id = $1
-            "#, // released = true basically means status = "expired"
-        )
-        .bind(res.reservation_id)
-        .execute(pool)
+        .execute(tx.executor())
         .await?;
+        tx.commit().await?;
 
         // Publish event to order service
-        let event = json!({
-            "event_type": "inventory.reservation_expired",
-            "id": res.order_id,
-            "user_id": res.user_id,
-            "product_id": res.product_id,
-            "new_status": "failed".to_string(), // failed aka expired
-            "timestamp": Utc::now().timestamp_millis(),
+        let event = DomainEvent::InventoryReservationExpired(InventoryEvent {
+            tenant_id: res.tenant_id,
+            product_id: res.product_id,
+            order_id: Some(res.order_id),
+            reservation_id: Some(res.reservation_id),
+            user_id: Some(res.user_id),
+            quantity: Some(res.qty),
+            timestamp: Utc::now(),
+            ..Default::default()
         });
 
-        if let Err(e) = redis_pub
-            .publish("inventory.reservation_expired", &event)
-            .await
+        if let Err(e) = redis_pub.publish(&event).await
         {
             eprintln!("Redis publish error: {:?}", e)
         };
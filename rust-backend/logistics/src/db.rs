@@ -1,7 +1,10 @@
 use crate::models::{
-    CreateShipmentRequest, ListShipmentQuery, Shipment, ShipmentStatus, UpdateShipmentStatusRequest,
+    CreateShipmentRequest, Shipment, ShipmentStatus, UpdateShipmentStatusRequest,
 };
 use chrono::Utc;
+use platform::pagination::{CursorPage, PageRequest};
+use platform::tenant::TenantTx;
+use sqlx::{Postgres, QueryBuilder};
 use uuid::Uuid;
 
 #[derive(Clone, Default)]
@@ -16,7 +19,7 @@ impl LogisticsRepo {
     /// Creates a shipment and publishes logistics.shipment_created.
     pub async fn create_shipment(
         &self,
-        conn: &mut sqlx::PgConnection,
+        tx: &mut TenantTx<'_>,
         tenant_id: Uuid,
         req: &CreateShipmentRequest,
     ) -> Result<Shipment, sqlx::Error> {
@@ -40,66 +43,55 @@ impl LogisticsRepo {
         .bind(req.product_id)
         .bind(tracking_number)
         .bind(&req.notes)
-        .fetch_one(conn)
+        .fetch_one(tx.executor())
         .await
     }
 
     /// Returns shipment details by id.
     pub async fn get_shipment(
         &self,
-        conn: &mut sqlx::PgConnection,
+        tx: &mut TenantTx<'_>,
         shipment_id: Uuid,
     ) -> Result<Shipment, sqlx::Error> {
         sqlx::query_as::<_, Shipment>("SELECT * FROM shipments WHERE id = $1")
             .bind(shipment_id)
-            .fetch_one(conn)
+            .fetch_one(tx.executor())
             .await
     }
 
     /// Returns one shipment by order id.
     pub async fn get_by_order_id(
         &self,
-        conn: &mut sqlx::PgConnection,
+        tx: &mut TenantTx<'_>,
         order_id: Uuid,
     ) -> Result<Shipment, sqlx::Error> {
         sqlx::query_as::<_, Shipment>("SELECT * FROM shipments WHERE order_id = $1")
             .bind(order_id)
-            .fetch_one(conn)
+            .fetch_one(tx.executor())
             .await
     }
 
-    /// Returns supplier shipments using filter and pagination query fields.
+    /// Returns one page of a supplier's shipments.
     pub async fn list_supplier_shipments(
         &self,
-        conn: &mut sqlx::PgConnection,
+        tx: &mut TenantTx<'_>,
         supplier_id: Uuid,
-        query: &ListShipmentQuery,
-    ) -> Result<Vec<Shipment>, sqlx::Error> {
-        let limit = query.limit.unwrap_or(50).clamp(1, 200);
-        let offset = query.offset.unwrap_or(0).max(0);
+        page: &PageRequest,
+    ) -> Result<CursorPage<Shipment>, sqlx::Error> {
+        let mut builder = QueryBuilder::<Postgres>::new("SELECT * FROM shipments WHERE supplier_id = ");
+        builder.push_bind(supplier_id);
+        page.push_filters(&mut builder);
+        page.push_order_and_limit(&mut builder);
 
-        sqlx::query_as::<_, Shipment>(
-            r#"
-            SELECT *
-            FROM shipments
-            WHERE supplier_id = $1
-              AND ($2::shipment_status IS NULL OR status = $2)
-            ORDER BY created_at DESC
-            LIMIT $3 OFFSET $4
-            "#,
-        )
-        .bind(supplier_id)
-        .bind(query.status.as_ref())
-        .bind(limit)
-        .bind(offset)
-        .fetch_all(conn)
-        .await
+        let rows = builder.build_query_as::<Shipment>().fetch_all(tx.executor()).await?;
+
+        Ok(page.into_page(rows))
     }
 
     /// Updates shipment status and publishes logistics.shipment_updated.
     pub async fn update_status(
         &self,
-        conn: &mut sqlx::PgConnection,
+        tx: &mut TenantTx<'_>,
         shipment_id: Uuid,
         req: &UpdateShipmentStatusRequest,
     ) -> Result<Shipment, sqlx::Error> {
@@ -138,7 +130,7 @@ impl LogisticsRepo {
         .bind(dispatched_at)
         .bind(delivered_at)
         .bind(shipment_id)
-        .fetch_optional(conn)
+        .fetch_optional(tx.executor())
         .await?;
 
         match res {
@@ -150,7 +142,7 @@ impl LogisticsRepo {
     /// Cancels the shipment for an order when cancellation is allowed.
     pub async fn cancel_by_order_id(
         &self,
-        conn: &mut sqlx::PgConnection,
+        tx: &mut TenantTx<'_>,
         order_id: Uuid,
     ) -> Result<Shipment, sqlx::Error> {
         sqlx::query_as::<_, Shipment>(
@@ -163,7 +155,7 @@ impl LogisticsRepo {
             "#,
         )
         .bind(order_id)
-        .fetch_one(conn)
+        .fetch_one(tx.executor())
         .await
     }
 }
@@ -180,7 +172,7 @@ mod tests {
         let repo = LogisticsRepo::new();
         let tenant_id = Uuid::new_v4();
         let order_id = Uuid::new_v4();
-        let mut tx = pool.begin().await.unwrap();
+        let mut tx = TenantTx::begin(&pool, tenant_id).await.unwrap();
 
         let req = CreateShipmentRequest {
             order_id,
@@ -191,7 +183,7 @@ mod tests {
         };
 
         // 1. Create a shipment
-        let shipment = repo.create_shipment(&mut *tx, tenant_id, &req).await.unwrap();
+        let shipment = repo.create_shipment(&mut tx, tenant_id, &req).await.unwrap();
         assert_eq!(shipment.status, ShipmentStatus::Pending);
 
         // 2. Invalid transition (Pending -> Delivered)
@@ -199,7 +191,7 @@ mod tests {
             status: ShipmentStatus::Delivered,
             notes: None,
         };
-        let result = repo.update_status(&mut *tx, shipment.id, &invalid_update).await;
+        let result = repo.update_status(&mut tx, shipment.id, &invalid_update).await;
         
         // Because of the WHERE clause in update_status, 0 rows are updated, returning RowNotFound.
         assert!(matches!(result, Err(sqlx::Error::RowNotFound)));
@@ -209,7 +201,7 @@ mod tests {
             status: ShipmentStatus::Intransit,
             notes: None,
         };
-        let updated = repo.update_status(&mut *tx, shipment.id, &valid_update).await.unwrap();
+        let updated = repo.update_status(&mut tx, shipment.id, &valid_update).await.unwrap();
         assert_eq!(updated.status, ShipmentStatus::Intransit);
     }
 }
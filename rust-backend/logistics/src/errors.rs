@@ -1 +0,0 @@
-// Logistics specific errors
@@ -1,10 +1,13 @@
-use actix_web::{web, HttpResponse, Responder};
-use chrono::Utc;
+use actix_web::{web, HttpRequest, HttpResponse, Responder, ResponseError};
+use platform::errors::AppError;
+use platform::events::DomainEvent;
+use platform::pagination::CursorSigner;
+use std::collections::HashMap;
 use uuid::Uuid;
 
 use crate::db::LogisticsRepo;
 use crate::models::{
-    CreateShipmentRequest, ListShipmentQuery, LogisticsEvent, Shipment, UpdateShipmentStatusRequest,
+    CreateShipmentRequest, Shipment, UpdateShipmentStatusRequest,
 };
 use crate::publisher::RedisPublisher;
 use crate::rabbit_pub::RabbitPublisher;
@@ -26,41 +29,27 @@ pub async fn create_shipment(
 ) -> impl Responder {
     let pool = match db_router.get_pool(&tenant).await {
         Ok(p) => p,
-        Err(e) => return HttpResponse::InternalServerError().body(format!("db pool error: {e}")),
+        Err(e) => return AppError::from(e).error_response(),
     };
-    let mut tx = match pool.begin().await {
+    let mut tx = match tenant.begin(&pool).await {
         Ok(t) => t,
-        Err(e) => return HttpResponse::InternalServerError().body(format!("tx begin error: {e}")),
+        Err(e) => return AppError::from(e).error_response(),
     };
-    if let Err(e) = tenant.apply_rls(&mut *tx).await {
-        return HttpResponse::InternalServerError().body(format!("rls error: {e}"));
-    }
 
-    match repo.create_shipment(&mut *tx, tenant.tenant_id, &req).await {
+    match repo.create_shipment(&mut tx, tenant.tenant_id, &req).await {
         Ok(shipment) => {
             if let Err(e) = tx.commit().await {
-                return HttpResponse::InternalServerError().body(format!("tx commit error: {e}"));
+                return AppError::from(e).error_response();
             }
-            let event = LogisticsEvent {
-                tenant_id: tenant.tenant_id,
-                event_type: "logistics.shipment_created".into(),
-                shipment_id: shipment.id,
-                order_id: shipment.order_id,
-                user_id: shipment.user_id,
-                supplier_id: shipment.supplier_id,
-                product_id: shipment.product_id,
-                status: shipment.status.clone(),
-                tracking_number: shipment.tracking_number.clone(),
-                timestamp: Utc::now(),
-            };
+            let event = DomainEvent::ShipmentCreated(shipment.event());
 
-            redis_pub.publish_async("logistics.shipment_created", event.clone());
-            rabbit_pub.publish_async(event.clone());
+            redis_pub.publish_async(event.clone());
+            rabbit_pub.publish_async(event);
 
             HttpResponse::Created().json(shipment)
         }
         Err(e) => {
-            HttpResponse::InternalServerError().body(format!("failed to create shipment: {e}"))
+            AppError::from(e).error_response()
         }
     }
 }
@@ -80,61 +69,70 @@ pub async fn get_shipment(
 ) -> impl Responder {
     let pool = match db_router.get_pool(&tenant).await {
         Ok(p) => p,
-        Err(e) => return HttpResponse::InternalServerError().body(format!("db pool error: {e}")),
+        Err(e) => return AppError::from(e).error_response(),
     };
-    let mut tx = match pool.begin().await {
+    let mut tx = match tenant.begin(&pool).await {
         Ok(t) => t,
-        Err(e) => return HttpResponse::InternalServerError().body(format!("tx begin error: {e}")),
+        Err(e) => return AppError::from(e).error_response(),
     };
-    if let Err(e) = tenant.apply_rls(&mut *tx).await {
-        return HttpResponse::InternalServerError().body(format!("rls error: {e}"));
-    }
 
-    match repo.get_shipment(&mut *tx, path.into_inner()).await {
+    match repo.get_shipment(&mut tx, path.into_inner()).await {
         Ok(shipment) => {
             let _ = tx.commit().await;
             HttpResponse::Ok().json(shipment)
         }
-        Err(sqlx::Error::RowNotFound) => HttpResponse::NotFound().body("shipment not found"),
-        Err(e) => HttpResponse::InternalServerError().body(format!("db error: {e}")),
+        Err(sqlx::Error::RowNotFound) => AppError::NotFound("shipment not found".into()).error_response(),
+        Err(e) => AppError::from(e).error_response(),
     }
 }
 
-/// Returns supplier shipments using filter and pagination query fields.
+/// Returns one page of supplier shipments, sorted and filtered by the shared grammar.
 #[utoipa::path(
     get,
     path = "/shipments/supplier/{supplier_id}",
-    params(("supplier_id" = Uuid, Path, description = "Supplier ID"), ListShipmentQuery),
-    responses((status = 200, description = "Shipments found", body = Vec<Shipment>))
+    params(
+        ("supplier_id" = Uuid, Path, description = "Supplier ID"),
+        ("limit" = Option<u32>, Query, description = "Page size (1-200, default 50)"),
+        ("cursor" = Option<String>, Query, description = "next_cursor of the previous page"),
+        ("sort" = Option<String>, Query, description = "created_at or updated_at; prefix with - for descending (default -created_at)"),
+        ("filter[status]" = Option<String>, Query, description = "Filters on status, order_id, user_id, product_id or tracking_number, e.g. filter[status][in]=pending,intransit")
+    ),
+    responses(
+        (status = 200, description = "One page of shipments with next_cursor and Link headers"),
+        (status = 400, description = "Invalid query parameters")
+    )
 )]
 pub async fn list_supplier_shipments(
+    req: HttpRequest,
     tenant: web::ReqData<platform::tenant::TenantContext>,
     db_router: web::Data<platform::db_router::DynamicPoolRouter>,
     repo: web::Data<LogisticsRepo>,
+    signer: web::Data<CursorSigner>,
     path: web::Path<Uuid>,
-    query: web::Query<ListShipmentQuery>,
+    query: web::Query<HashMap<String, String>>,
 ) -> impl Responder {
+    let page = match Shipment::list_spec().parse(&query, &signer) {
+        Ok(page) => page,
+        Err(e) => return AppError::from(e).error_response(),
+    };
     let pool = match db_router.get_pool(&tenant).await {
         Ok(p) => p,
-        Err(e) => return HttpResponse::InternalServerError().body(format!("db pool error: {e}")),
+        Err(e) => return AppError::from(e).error_response(),
     };
-    let mut tx = match pool.begin().await {
+    let mut tx = match tenant.begin(&pool).await {
         Ok(t) => t,
-        Err(e) => return HttpResponse::InternalServerError().body(format!("tx begin error: {e}")),
+        Err(e) => return AppError::from(e).error_response(),
     };
-    if let Err(e) = tenant.apply_rls(&mut *tx).await {
-        return HttpResponse::InternalServerError().body(format!("rls error: {e}"));
-    }
 
     match repo
-        .list_supplier_shipments(&mut *tx, path.into_inner(), &query.into_inner())
+        .list_supplier_shipments(&mut tx, path.into_inner(), &page)
         .await
     {
         Ok(shipments) => {
             let _ = tx.commit().await;
-            HttpResponse::Ok().json(shipments)
+            shipments.respond(&req)
         }
-        Err(e) => HttpResponse::InternalServerError().body(format!("db error: {e}")),
+        Err(e) => AppError::from(e).error_response(),
     }
 }
 
@@ -157,47 +155,33 @@ pub async fn update_status(
 ) -> impl Responder {
     let pool = match db_router.get_pool(&tenant).await {
         Ok(p) => p,
-        Err(e) => return HttpResponse::InternalServerError().body(format!("db pool error: {e}")),
+        Err(e) => return AppError::from(e).error_response(),
     };
-    let mut tx = match pool.begin().await {
+    let mut tx = match tenant.begin(&pool).await {
         Ok(t) => t,
-        Err(e) => return HttpResponse::InternalServerError().body(format!("tx begin error: {e}")),
+        Err(e) => return AppError::from(e).error_response(),
     };
-    if let Err(e) = tenant.apply_rls(&mut *tx).await {
-        return HttpResponse::InternalServerError().body(format!("rls error: {e}"));
-    }
 
-    match repo.update_status(&mut *tx, path.into_inner(), &req).await {
+    match repo.update_status(&mut tx, path.into_inner(), &req).await {
         Ok(shipment) => {
             if let Err(e) = tx.commit().await {
-                return HttpResponse::InternalServerError().body(format!("tx commit error: {e}"));
+                return AppError::from(e).error_response();
             }
-            let event = LogisticsEvent {
-                tenant_id: tenant.tenant_id,
-                event_type: "logistics.shipment_updated".into(),
-                shipment_id: shipment.id,
-                order_id: shipment.order_id,
-                user_id: shipment.user_id,
-                supplier_id: shipment.supplier_id,
-                product_id: shipment.product_id,
-                status: shipment.status.clone(),
-                tracking_number: shipment.tracking_number.clone(),
-                timestamp: Utc::now(),
-            };
+            let event = DomainEvent::ShipmentUpdated(shipment.event());
 
-            redis_pub.publish_async("logistics.shipment_updated", event.clone());
-            rabbit_pub.publish_async(event.clone());
+            redis_pub.publish_async(event.clone());
+            rabbit_pub.publish_async(event);
 
             HttpResponse::Ok().json(shipment)
         }
         Err(sqlx::Error::Protocol(message))
             if message.to_string().contains("invalid status transition") =>
         {
-            HttpResponse::BadRequest().body(message.to_string())
+            AppError::BadRequest(message.to_string()).error_response()
         }
-        Err(sqlx::Error::RowNotFound) => HttpResponse::NotFound().body("shipment not found"),
+        Err(sqlx::Error::RowNotFound) => AppError::NotFound("shipment not found".into()).error_response(),
         Err(e) => {
-            HttpResponse::InternalServerError().body(format!("failed to update shipment: {e}"))
+            AppError::from(e).error_response()
         }
     }
 }
@@ -219,44 +203,30 @@ pub async fn cancel_shipment_by_order(
 ) -> impl Responder {
     let pool = match db_router.get_pool(&tenant).await {
         Ok(p) => p,
-        Err(e) => return HttpResponse::InternalServerError().body(format!("db pool error: {e}")),
+        Err(e) => return AppError::from(e).error_response(),
     };
-    let mut tx = match pool.begin().await {
+    let mut tx = match tenant.begin(&pool).await {
         Ok(t) => t,
-        Err(e) => return HttpResponse::InternalServerError().body(format!("tx begin error: {e}")),
+        Err(e) => return AppError::from(e).error_response(),
     };
-    if let Err(e) = tenant.apply_rls(&mut *tx).await {
-        return HttpResponse::InternalServerError().body(format!("rls error: {e}"));
-    }
 
-    match repo.cancel_by_order_id(&mut *tx, path.into_inner()).await {
+    match repo.cancel_by_order_id(&mut tx, path.into_inner()).await {
         Ok(shipment) => {
             if let Err(e) = tx.commit().await {
-                return HttpResponse::InternalServerError().body(format!("tx commit error: {e}"));
+                return AppError::from(e).error_response();
             }
-            let event = LogisticsEvent {
-                tenant_id: tenant.tenant_id,
-                event_type: "logistics.shipment_cancelled".into(),
-                shipment_id: shipment.id,
-                order_id: shipment.order_id,
-                user_id: shipment.user_id,
-                supplier_id: shipment.supplier_id,
-                product_id: shipment.product_id,
-                status: shipment.status.clone(),
-                tracking_number: shipment.tracking_number.clone(),
-                timestamp: Utc::now(),
-            };
+            let event = DomainEvent::ShipmentCancelled(shipment.event());
 
-            redis_pub.publish_async("logistics.shipment_cancelled", event.clone());
-            rabbit_pub.publish_async(event.clone());
+            redis_pub.publish_async(event.clone());
+            rabbit_pub.publish_async(event);
 
             HttpResponse::Ok().json(shipment)
         }
         Err(sqlx::Error::RowNotFound) => {
-            HttpResponse::NotFound().body("active shipment for order not found")
+            AppError::NotFound("active shipment for order not found".into()).error_response()
         }
         Err(e) => {
-            HttpResponse::InternalServerError().body(format!("failed to cancel shipment: {e}"))
+            AppError::from(e).error_response()
         }
     }
 }
@@ -1,4 +1,6 @@
 use chrono::{DateTime, Utc};
+use platform::events::ShipmentEvent;
+use platform::pagination::{keyset_timestamp, FieldKind, Keyset, ListSpec, SortDirection};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use uuid::Uuid;
@@ -26,6 +28,15 @@ impl ShipmentStatus {
             _ => false,
         }
     }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ShipmentStatus::Pending => "pending",
+            ShipmentStatus::Intransit => "intransit",
+            ShipmentStatus::Delivered => "delivered",
+            ShipmentStatus::Cancelled => "cancelled",
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
@@ -45,6 +56,49 @@ pub struct Shipment {
     pub delivered_at: Option<DateTime<Utc>>,
 }
 
+impl Shipment {
+    /// Catalogue payload carried by the `logistics.shipment_*` events.
+    pub fn event(&self) -> ShipmentEvent {
+        ShipmentEvent {
+            tenant_id: self.tenant_id,
+            shipment_id: self.id,
+            order_id: self.order_id,
+            user_id: self.user_id,
+            supplier_id: self.supplier_id,
+            product_id: self.product_id,
+            status: self.status.as_str().to_string(),
+            tracking_number: self.tracking_number.clone(),
+            timestamp: Utc::now(),
+        }
+    }
+
+    /// Sort and filter whitelist of the shipment list endpoints.
+    pub fn list_spec() -> ListSpec {
+        ListSpec::new("created_at", SortDirection::Desc)
+            .sortable("created_at", FieldKind::Timestamp)
+            .sortable("updated_at", FieldKind::Timestamp)
+            .filterable("status", FieldKind::Enum)
+            .filterable("order_id", FieldKind::Uuid)
+            .filterable("user_id", FieldKind::Uuid)
+            .filterable("product_id", FieldKind::Uuid)
+            .filterable("tracking_number", FieldKind::Text)
+    }
+}
+
+impl Keyset for Shipment {
+    fn keyset_id(&self) -> Uuid {
+        self.id
+    }
+
+    fn keyset_value(&self, field: &str) -> Option<String> {
+        match field {
+            "created_at" => Some(keyset_timestamp(&self.created_at)),
+            "updated_at" => Some(keyset_timestamp(&self.updated_at)),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct CreateShipmentRequest {
     pub order_id: Uuid,
@@ -60,38 +114,6 @@ pub struct UpdateShipmentStatusRequest {
     pub notes: Option<String>,
 }
 
-#[derive(Debug, Deserialize, utoipa::IntoParams)]
-pub struct ListShipmentQuery {
-    pub status: Option<ShipmentStatus>,
-    pub limit: Option<i64>,
-    pub offset: Option<i64>,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct LogisticsEvent {
-    pub tenant_id: Uuid,
-    pub event_type: String,
-    pub shipment_id: Uuid,
-    pub order_id: Uuid,
-    pub user_id: Uuid,
-    pub supplier_id: Uuid,
-    pub product_id: Uuid,
-    pub status: ShipmentStatus,
-    pub tracking_number: String,
-    pub timestamp: DateTime<Utc>,
-}
-
-#[derive(Debug, Clone, Deserialize)]
-pub struct IncomingOrderEvent {
-    pub tenant_id: Option<Uuid>,
-    #[allow(dead_code)] // deserialized from stream; reserved for future event routing
-    pub event_type: String,
-    pub order_id: Option<Uuid>,
-    pub user_id: Option<Uuid>,
-    pub supplier_id: Uuid,
-    pub product_id: Uuid,
-}
-
 #[cfg(test)]
 mod tests {
     use super::ShipmentStatus;
@@ -122,4 +144,16 @@ mod tests {
         assert!(!ShipmentStatus::Cancelled.can_transition_to(&ShipmentStatus::Intransit));
         assert!(!ShipmentStatus::Cancelled.can_transition_to(&ShipmentStatus::Delivered));
     }
+
+    #[test]
+    fn event_status_matches_wire_format() {
+        for status in [
+            ShipmentStatus::Pending,
+            ShipmentStatus::Intransit,
+            ShipmentStatus::Delivered,
+            ShipmentStatus::Cancelled,
+        ] {
+            assert_eq!(serde_json::to_value(&status).unwrap(), status.as_str());
+        }
+    }
 }
@@ -1,9 +1,9 @@
-use crate::models::LogisticsEvent;
 use lapin::{
     options::{BasicPublishOptions, ConfirmSelectOptions, ExchangeDeclareOptions},
     types::FieldTable,
     BasicProperties, Connection, ConnectionProperties,
 };
+use platform::events::DomainEvent;
 use tokio::time::{timeout, Duration};
 use tracing::warn;
 
@@ -33,19 +33,19 @@ impl RabbitPublisher {
     }
 
     /// Publishes logistics events to the analytics topic exchange.
-    pub async fn publish_event(&self, event: &LogisticsEvent) -> Result<(), lapin::Error> {
+    pub async fn publish_event(&self, event: &DomainEvent) -> Result<(), lapin::Error> {
         let future = async {
             let payload = serde_json::to_vec(event)
                 .map_err(|e| lapin::Error::from(std::io::Error::other(e.to_string())))?;
             let mut headers = FieldTable::default();
             headers.insert(
                 "x-tenant-id".into(),
-                lapin::types::AMQPValue::LongString(event.tenant_id.to_string().into()),
+                lapin::types::AMQPValue::LongString(event.tenant_id().to_string().into()),
             );
             let confirm = self.channel
                 .basic_publish(
                     "analytics_events_topic",
-                    &event.event_type,
+                    event.event_type().as_str(),
                     BasicPublishOptions::default(),
                     &payload,
                     BasicProperties::default().with_delivery_mode(2).with_headers(headers),
@@ -65,7 +65,7 @@ impl RabbitPublisher {
     }
 
     /// Publishes in detached mode to keep request paths non-blocking.
-    pub fn publish_async(&self, event: LogisticsEvent) {
+    pub fn publish_async(&self, event: DomainEvent) {
         let this = self.clone();
         tokio::spawn(async move {
             if let Err(err) = this.publish_event(&event).await {
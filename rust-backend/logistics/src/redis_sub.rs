@@ -1,17 +1,19 @@
 use actix_web::web::Data;
-use chrono::Utc;
 use platform::db_router::DynamicPoolRouter;
-use platform::tenant::{AuthMethod, PricingTier, TenantContext};
+use platform::events::DomainEvent;
+use platform::tenant::TenantTx;
 use platform::{metrics, streams};
-use std::env;
 use uuid::Uuid;
 
 use crate::db::LogisticsRepo;
-use crate::models::{CreateShipmentRequest, IncomingOrderEvent, LogisticsEvent, ShipmentStatus};
+use crate::models::CreateShipmentRequest;
 use crate::publisher::RedisPublisher;
 use crate::rabbit_pub::RabbitPublisher;
 
-const EVENTS: &[&str] = &[
+/// Consumer group this service reads its streams with.
+pub const GROUP: &str = "logistics";
+
+pub const EVENTS: &[&str] = &[
     "inventory.finalized",
     "order.cancelled",
     "logistics.shipment_preparation_command",
@@ -23,13 +25,14 @@ pub async fn listen_to_redis_events(
     repo: Data<LogisticsRepo>,
     redis_pub: Data<RedisPublisher>,
     rabbit_pub: Data<RabbitPublisher>,
+    redis_url: Option<String>,
+    consumer: String,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let redis_url = env::var("REDIS_URL").map_err(|_| "REDIS_URL must be set in environment")?;
-    let consumer = env::var("CONSUMER_NAME").unwrap_or_else(|_| "logistics-1".to_string());
+    let redis_url = redis_url.ok_or("REDIS_URL must be set in environment")?;
 
-    streams::consume_json::<IncomingOrderEvent, _, _>(
+    streams::consume_json::<DomainEvent, _, _>(
         &redis_url,
-        "logistics",
+        GROUP,
         &consumer,
         EVENTS,
         move |envelope| {
@@ -41,33 +44,22 @@ pub async fn listen_to_redis_events(
                 let event_type = envelope.event_type.clone();
                 let event = envelope.payload;
 
-                let tenant_id = envelope.tenant_id.or(event.tenant_id);
-                if tenant_id.is_none() || tenant_id == Some(uuid::Uuid::nil()) {
+                let tenant_id = event.tenant_id();
+                if tenant_id.is_nil() {
                     tracing::warn!(%event_type, stream = %envelope.stream, "Missing tenant_id in stream event — skipping business logic");
                     metrics::inc_event("logistics", &envelope.stream, &event_type, "tenant_mismatch");
                     return Ok(());
                 }
 
-                if let (Some(env_tid), Some(pay_tid)) = (envelope.tenant_id, event.tenant_id) {
-                    if env_tid != pay_tid {
-                        tracing::warn!(%event_type, ?env_tid, ?pay_tid, "Tenant ID mismatch between envelope and payload — skipping business logic");
+                if let Some(env_tid) = envelope.tenant_id {
+                    if env_tid != tenant_id {
+                        tracing::warn!(%event_type, ?env_tid, pay_tid = ?tenant_id, "Tenant ID mismatch between envelope and payload — skipping business logic");
                         metrics::inc_event("logistics", &envelope.stream, &event_type, "tenant_mismatch");
                         return Ok(());
                     }
                 }
 
-                let tenant_id = tenant_id.unwrap();
-
-                let result = handle_event(
-                    &db_router,
-                    &repo,
-                    &redis_pub,
-                    &rabbit_pub,
-                    &event_type,
-                    tenant_id,
-                    event,
-                )
-                .await;
+                let result = handle_event(&db_router, &repo, &redis_pub, &rabbit_pub, event).await;
                 metrics::inc_event(
                     "logistics",
                     &envelope.stream,
@@ -85,96 +77,74 @@ pub async fn listen_to_redis_events(
     .await
 }
 
+/// Side effect a consumed event asks of logistics.
+enum ShipmentAction {
+    Create(CreateShipmentRequest),
+    Cancel { order_id: Uuid },
+}
+
 async fn handle_event(
     db_router: &Data<DynamicPoolRouter>,
     repo: &Data<LogisticsRepo>,
     redis_pub: &Data<RedisPublisher>,
     rabbit_pub: &Data<RabbitPublisher>,
-    event_type: &str,
-    tenant_id: Uuid,
-    event: IncomingOrderEvent,
+    event: DomainEvent,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let tenant_ctx = TenantContext::new(
-        tenant_id,
-        event.user_id,
-        PricingTier::Free,
-        vec![],
-        AuthMethod::ApiKey,
-    );
-
-    let pool = db_router.get_pool(&tenant_ctx).await?;
-    let mut tx = pool.begin().await?;
-    tenant_ctx.apply_rls(&mut *tx).await?;
-
-    match event_type {
-        "inventory.finalized" | "logistics.shipment_preparation_command" => {
-            let Some(order_id) = event.order_id else {
+    let tenant_id = event.tenant_id();
+    let action = match event {
+        DomainEvent::InventoryFinalized(e) => {
+            let (Some(order_id), Some(user_id), Some(supplier_id)) = (e.order_id, e.user_id, e.supplier_id) else {
                 return Ok(());
             };
-            let Some(user_id) = event.user_id else {
-                return Ok(());
-            };
-
-            match repo.get_by_order_id(&mut *tx, order_id).await {
+            ShipmentAction::Create(CreateShipmentRequest {
+                order_id,
+                user_id,
+                supplier_id,
+                product_id: e.product_id,
+                notes: Some("Created after payment finalization".to_string()),
+            })
+        }
+        DomainEvent::ShipmentPreparationCommand(e) => ShipmentAction::Create(CreateShipmentRequest {
+            order_id: e.order_id,
+            user_id: e.user_id,
+            supplier_id: e.supplier_id,
+            product_id: e.product_id,
+            notes: Some("Created after payment finalization".to_string()),
+        }),
+        DomainEvent::OrderCancelled(e) => ShipmentAction::Cancel { order_id: e.order_id },
+        _ => return Ok(()),
+    };
+
+    let pool = db_router.get_tenant_pool(tenant_id).await?;
+    let mut tx = TenantTx::begin(&pool, tenant_id).await?;
+
+    match action {
+        ShipmentAction::Create(req) => {
+            match repo.get_by_order_id(&mut tx, req.order_id).await {
                 Ok(_) => return Ok(()),
                 Err(sqlx::Error::RowNotFound) => {}
                 Err(e) => return Err(Box::new(e)),
             }
 
-            let req = CreateShipmentRequest {
-                order_id,
-                user_id,
-                supplier_id: event.supplier_id,
-                product_id: event.product_id,
-                notes: Some("Created after payment finalization".to_string()),
-            };
-            let shipment = repo.create_shipment(&mut *tx, tenant_id, &req).await?;
+            let shipment = repo.create_shipment(&mut tx, tenant_id, &req).await?;
             tx.commit().await?;
 
-            let outbound = LogisticsEvent {
-                tenant_id,
-                event_type: "logistics.shipment_created".to_string(),
-                shipment_id: shipment.id,
-                order_id: shipment.order_id,
-                user_id: shipment.user_id,
-                supplier_id: shipment.supplier_id,
-                product_id: shipment.product_id,
-                status: shipment.status,
-                tracking_number: shipment.tracking_number,
-                timestamp: Utc::now(),
-            };
-            redis_pub
-                .publish("logistics.shipment_created", &outbound)
-                .await?;
+            let outbound = DomainEvent::ShipmentCreated(shipment.event());
+            redis_pub.publish(&outbound).await?;
             rabbit_pub.publish_async(outbound);
         }
-        "order.cancelled" => {
-            let Some(order_id) = event.order_id else {
-                return Ok(());
-            };
-            let shipment = match repo.cancel_by_order_id(&mut *tx, order_id).await {
+        ShipmentAction::Cancel { order_id } => {
+            let shipment = match repo.cancel_by_order_id(&mut tx, order_id).await {
                 Ok(shipment) => shipment,
                 Err(sqlx::Error::RowNotFound) => return Ok(()),
                 Err(e) => return Err(Box::new(e)),
             };
             tx.commit().await?;
 
-            let outbound = LogisticsEvent {
-                tenant_id,
-                event_type: "logistics.shipment_cancelled".to_string(),
-                shipment_id: shipment.id,
-                order_id: shipment.order_id,
-                user_id: shipment.user_id,
-                supplier_id: shipment.supplier_id,
-                product_id: shipment.product_id,
-                status: ShipmentStatus::Cancelled,
-                tracking_number: shipment.tracking_number,
-                timestamp: Utc::now(),
-            };
-            redis_pub.publish_async("logistics.shipment_cancelled", outbound.clone());
+            let outbound = DomainEvent::ShipmentCancelled(shipment.event());
+            redis_pub.publish_async(outbound.clone());
             rabbit_pub.publish_async(outbound);
         }
-        _ => {}
     }
 
     Ok(())
@@ -1,8 +1,10 @@
 use crate::models::{
-    CreateNotificationRequest, ListNotificationsQuery, Notification, NotificationChannel,
+    CreateNotificationRequest, Notification, NotificationChannel,
     NotificationDevice, RegisterDeviceRequest, UpdatePreferencesRequest, UserPreference,
 };
 use chrono::Utc;
+use platform::pagination::{CursorPage, PageRequest};
+use platform::tenant::TenantTx;
 use sqlx::{Postgres, QueryBuilder};
 use uuid::Uuid;
 
@@ -16,12 +18,12 @@ impl NotificationRepo {
     }
 
     pub async fn create(
-        conn: &mut sqlx::PgConnection,
+        tx: &mut TenantTx<'_>,
         tenant_id: Uuid,
         req: &CreateNotificationRequest,
     ) -> Result<Notification, sqlx::Error> {
         if let Some(user_id) = req.user_id {
-            if let Ok(prefs) = Self::get_preferences(&mut *conn, tenant_id, user_id).await {
+            if let Ok(prefs) = Self::get_preferences(tx, tenant_id, user_id).await {
                 let is_enabled = match req.channel {
                     NotificationChannel::Email => prefs.email_enabled,
                     NotificationChannel::Sms => prefs.sms_enabled,
@@ -73,58 +75,39 @@ impl NotificationRepo {
         .bind(&req.subject)
         .bind(&req.body)
         .bind(req.payload.as_ref())
-        .fetch_one(&mut *conn)
+        .fetch_one(tx.executor())
         .await
     }
 
     pub async fn list(
-        conn: &mut sqlx::PgConnection,
+        tx: &mut TenantTx<'_>,
         tenant_id: Uuid,
-        query: &ListNotificationsQuery,
-    ) -> Result<Vec<Notification>, sqlx::Error> {
-        let limit = query.limit.unwrap_or(50).clamp(1, 200);
-        let offset = query.offset.unwrap_or(0).max(0);
-
+        page: &PageRequest,
+    ) -> Result<CursorPage<Notification>, sqlx::Error> {
         let mut builder = QueryBuilder::<Postgres>::new("SELECT * FROM notifications WHERE tenant_id = ");
         builder.push_bind(tenant_id);
+        page.push_filters(&mut builder);
+        page.push_order_and_limit(&mut builder);
 
-        if let Some(user_id) = query.user_id {
-            builder.push(" AND user_id = ");
-            builder.push_bind(user_id);
-        }
-
-        if let Some(supplier_id) = query.supplier_id {
-            builder.push(" AND supplier_id = ");
-            builder.push_bind(supplier_id);
-        }
-
-        if let Some(status) = &query.status {
-            builder.push(" AND status = ");
-            builder.push_bind(status);
-        }
-
-        builder.push(" ORDER BY created_at DESC LIMIT ");
-        builder.push_bind(limit);
-        builder.push(" OFFSET ");
-        builder.push_bind(offset);
+        let rows = builder.build_query_as().fetch_all(tx.executor()).await?;
 
-        builder.build_query_as().fetch_all(&mut *conn).await
+        Ok(page.into_page(rows))
     }
 
     pub async fn get(
-        conn: &mut sqlx::PgConnection,
+        tx: &mut TenantTx<'_>,
         tenant_id: Uuid,
         id: Uuid,
     ) -> Result<Notification, sqlx::Error> {
         sqlx::query_as::<_, Notification>("SELECT * FROM notifications WHERE id = $1 AND tenant_id = $2")
             .bind(id)
             .bind(tenant_id)
-            .fetch_one(&mut *conn)
+            .fetch_one(tx.executor())
             .await
     }
 
     pub async fn mark_sent(
-        conn: &mut sqlx::PgConnection,
+        tx: &mut TenantTx<'_>,
         id: Uuid,
     ) -> Result<Notification, sqlx::Error> {
         sqlx::query_as::<_, Notification>(
@@ -136,12 +119,12 @@ impl NotificationRepo {
             "#,
         )
         .bind(id)
-        .fetch_one(&mut *conn)
+        .fetch_one(tx.executor())
         .await
     }
 
     pub async fn mark_failed(
-        conn: &mut sqlx::PgConnection,
+        tx: &mut TenantTx<'_>,
         id: Uuid,
         error: &str,
     ) -> Result<Notification, sqlx::Error> {
@@ -155,12 +138,12 @@ impl NotificationRepo {
         )
         .bind(id)
         .bind(error)
-        .fetch_one(&mut *conn)
+        .fetch_one(tx.executor())
         .await
     }
 
     pub async fn mark_read(
-        conn: &mut sqlx::PgConnection,
+        tx: &mut TenantTx<'_>,
         tenant_id: Uuid,
         id: Uuid,
     ) -> Result<Notification, sqlx::Error> {
@@ -175,12 +158,12 @@ impl NotificationRepo {
         .bind(id)
         .bind(Utc::now())
         .bind(tenant_id)
-        .fetch_one(&mut *conn)
+        .fetch_one(tx.executor())
         .await
     }
 
     pub async fn pending_batch(
-        conn: &mut sqlx::PgConnection,
+        tx: &mut TenantTx<'_>,
         limit: i64,
     ) -> Result<Vec<Notification>, sqlx::Error> {
         sqlx::query_as::<_, Notification>(
@@ -201,12 +184,12 @@ impl NotificationRepo {
             "#,
         )
         .bind(limit.clamp(1, 100))
-        .fetch_all(&mut *conn)
+        .fetch_all(tx.executor())
         .await
     }
 
     pub async fn register_device(
-        conn: &mut sqlx::PgConnection,
+        tx: &mut TenantTx<'_>,
         tenant_id: Uuid,
         req: &RegisterDeviceRequest,
     ) -> Result<NotificationDevice, sqlx::Error> {
@@ -236,12 +219,12 @@ impl NotificationRepo {
         .bind(&req.provider)
         .bind(&req.device_id)
         .bind(&req.app_version)
-        .fetch_one(&mut *conn)
+        .fetch_one(tx.executor())
         .await
     }
 
     pub async fn list_user_devices(
-        conn: &mut sqlx::PgConnection,
+        tx: &mut TenantTx<'_>,
         tenant_id: Uuid,
         user_id: Uuid,
     ) -> Result<Vec<NotificationDevice>, sqlx::Error> {
@@ -255,12 +238,12 @@ impl NotificationRepo {
         )
         .bind(tenant_id)
         .bind(user_id)
-        .fetch_all(&mut *conn)
+        .fetch_all(tx.executor())
         .await
     }
 
     pub async fn disable_device(
-        conn: &mut sqlx::PgConnection,
+        tx: &mut TenantTx<'_>,
         tenant_id: Uuid,
         id: Uuid,
     ) -> Result<NotificationDevice, sqlx::Error> {
@@ -274,12 +257,12 @@ impl NotificationRepo {
         )
         .bind(id)
         .bind(tenant_id)
-        .fetch_one(&mut *conn)
+        .fetch_one(tx.executor())
         .await
     }
 
     pub async fn get_preferences(
-        conn: &mut sqlx::PgConnection,
+        tx: &mut TenantTx<'_>,
         tenant_id: Uuid,
         user_id: Uuid,
     ) -> Result<UserPreference, sqlx::Error> {
@@ -290,7 +273,7 @@ impl NotificationRepo {
         )
         .bind(tenant_id)
         .bind(user_id)
-        .fetch_optional(&mut *conn)
+        .fetch_optional(tx.executor())
         .await
         .map(|opt| {
             opt.unwrap_or_else(|| UserPreference {
@@ -307,7 +290,7 @@ impl NotificationRepo {
     }
 
     pub async fn update_preferences(
-        conn: &mut sqlx::PgConnection,
+        tx: &mut TenantTx<'_>,
         tenant_id: Uuid,
         user_id: Uuid,
         req: &UpdatePreferencesRequest,
@@ -332,7 +315,7 @@ impl NotificationRepo {
         .bind(req.sms_enabled)
         .bind(req.push_enabled)
         .bind(req.in_app_enabled)
-        .fetch_one(&mut *conn)
+        .fetch_one(tx.executor())
         .await
     }
 }
@@ -356,8 +339,8 @@ mod tests {
             push_enabled: Some(true),
             in_app_enabled: Some(true),
         };
-        let mut conn = pool.acquire().await.unwrap();
-        let _ = NotificationRepo::update_preferences(&mut *conn, tenant_id, user_id, &update_req).await;
+        let mut tx = TenantTx::begin(&pool, tenant_id).await.unwrap();
+        let _ = NotificationRepo::update_preferences(&mut tx, tenant_id, user_id, &update_req).await;
 
         // 2. Try to create Email notification
         let req = CreateNotificationRequest {
@@ -373,7 +356,7 @@ mod tests {
             payload: None,
         };
 
-        let result = NotificationRepo::create(&mut *conn, tenant_id, &req).await;
+        let result = NotificationRepo::create(&mut tx, tenant_id, &req).await;
 
         // 3. Verify it was rejected
         assert!(result.is_err());
@@ -1 +0,0 @@
-// Notifications specific errors
@@ -1,9 +1,12 @@
-use actix_web::{web, HttpResponse, Responder};
+use actix_web::{web, HttpRequest, HttpResponse, Responder, ResponseError};
+use platform::errors::AppError;
+use platform::pagination::CursorSigner;
+use std::collections::HashMap;
 use uuid::Uuid;
 
 use crate::db::NotificationRepo;
 use crate::models::{
-    CreateNotificationRequest, ListNotificationsQuery, Notification, NotificationChannel, NotificationDevice, RegisterDeviceRequest, UpdatePreferencesRequest, UserPreference,
+    CreateNotificationRequest, Notification, NotificationChannel, NotificationDevice, RegisterDeviceRequest, UpdatePreferencesRequest, UserPreference,
 };
 use crate::provider::NotificationProvider;
 
@@ -22,25 +25,21 @@ pub async fn create_notification(
     let request = req.into_inner();
     let pool = match db_router.get_pool(&tenant).await {
         Ok(pool) => pool,
-        Err(e) => return HttpResponse::InternalServerError().body(format!("db pool error: {e}")),
+        Err(e) => return AppError::from(e).error_response(),
     };
-    let mut tx = match pool.begin().await {
+    let mut tx = match tenant.begin(&pool).await {
         Ok(tx) => tx,
-        Err(e) => return HttpResponse::InternalServerError().body(format!("db error: {e}")),
+        Err(e) => return AppError::from(e).error_response(),
     };
-    if let Err(e) = tenant.apply_rls(&mut *tx).await {
-        return HttpResponse::InternalServerError().body(format!("rls error: {e}"));
-    }
 
     if request.channel == NotificationChannel::Push && request.recipient.is_none() {
         let Some(user_id) = request.user_id else {
-            return HttpResponse::BadRequest()
-                .body("push notifications require either recipient push token or user_id");
+            return AppError::BadRequest("push notifications require either recipient push token or user_id".into()).error_response();
         };
 
-        let devices = match NotificationRepo::list_user_devices(&mut *tx, tenant.tenant_id, user_id).await {
+        let devices = match NotificationRepo::list_user_devices(&mut tx, tenant.tenant_id, user_id).await {
             Ok(devices) => devices,
-            Err(e) => return HttpResponse::InternalServerError().body(format!("db error: {e}")),
+            Err(e) => return AppError::from(e).error_response(),
         };
 
         if devices.is_empty() {
@@ -68,49 +67,48 @@ pub async fn create_notification(
             }
             device_request.payload = Some(payload);
 
-            match NotificationRepo::create(&mut *tx, tenant.tenant_id, &device_request).await {
+            match NotificationRepo::create(&mut tx, tenant.tenant_id, &device_request).await {
                 Ok(notification) => {
                     let id = notification.id;
                     let delivered = match provider.send(&notification).await {
-                        Ok(()) => NotificationRepo::mark_sent(&mut *tx, id).await,
-                        Err(error) => NotificationRepo::mark_failed(&mut *tx, id, &error).await,
+                        Ok(()) => NotificationRepo::mark_sent(&mut tx, id).await,
+                        Err(error) => NotificationRepo::mark_failed(&mut tx, id, &error).await,
                     };
 
                     match delivered {
                         Ok(notification) => sent.push(notification),
                         Err(e) => {
-                            return HttpResponse::InternalServerError()
-                                .body(format!("db error: {e}"));
+                            return AppError::from(e).error_response();
                         }
                     }
                 }
                 Err(e) => {
-                    return HttpResponse::InternalServerError().body(format!("db error: {e}"))
+                    return AppError::from(e).error_response()
                 }
             }
         }
 
         if let Err(e) = tx.commit().await {
-            return HttpResponse::InternalServerError().body(format!("db commit error: {e}"));
+            return AppError::from(e).error_response();
         }
         return HttpResponse::Created().json(sent);
     }
 
-    match NotificationRepo::create(&mut *tx, tenant.tenant_id, &request).await {
+    match NotificationRepo::create(&mut tx, tenant.tenant_id, &request).await {
         Ok(notification) => {
             let id = notification.id;
             let res = match provider.send(&notification).await {
-                Ok(()) => match NotificationRepo::mark_sent(&mut *tx, id).await {
+                Ok(()) => match NotificationRepo::mark_sent(&mut tx, id).await {
                     Ok(sent) => HttpResponse::Created().json(sent),
-                    Err(e) => HttpResponse::InternalServerError().body(format!("db error: {e}")),
+                    Err(e) => AppError::from(e).error_response(),
                 },
-                Err(error) => match NotificationRepo::mark_failed(&mut *tx, id, &error).await {
+                Err(error) => match NotificationRepo::mark_failed(&mut tx, id, &error).await {
                     Ok(failed) => HttpResponse::Accepted().json(failed),
-                    Err(e) => HttpResponse::InternalServerError().body(format!("db error: {e}")),
+                    Err(e) => AppError::from(e).error_response(),
                 },
             };
             if let Err(e) = tx.commit().await {
-                return HttpResponse::InternalServerError().body(format!("db commit error: {e}"));
+                return AppError::from(e).error_response();
             }
             res
         }
@@ -120,36 +118,47 @@ pub async fn create_notification(
                 "message": msg
             }))
         }
-        Err(e) => HttpResponse::InternalServerError().body(format!("db error: {e}")),
+        Err(e) => AppError::from(e).error_response(),
     }
 }
 
 #[utoipa::path(
     get,
     path = "/notifications",
-    params(ListNotificationsQuery),
-    responses((status = 200, description = "Notifications listed", body = Vec<Notification>))
+    params(
+        ("limit" = Option<u32>, Query, description = "Page size (1-200, default 50)"),
+        ("cursor" = Option<String>, Query, description = "next_cursor of the previous page"),
+        ("sort" = Option<String>, Query, description = "created_at or updated_at; prefix with - for descending (default -created_at)"),
+        ("filter[status]" = Option<String>, Query, description = "Filters on user_id, supplier_id, order_id, status, channel, priority or event_type")
+    ),
+    responses(
+        (status = 200, description = "One page of notifications with next_cursor and Link headers"),
+        (status = 400, description = "Invalid query parameters")
+    )
 )]
 pub async fn list_notifications(
+    req: HttpRequest,
     tenant: web::ReqData<platform::tenant::TenantContext>,
     db_router: web::Data<platform::db_router::DynamicPoolRouter>,
-    query: web::Query<ListNotificationsQuery>,
+    signer: web::Data<CursorSigner>,
+    query: web::Query<HashMap<String, String>>,
 ) -> impl Responder {
+    let page = match Notification::list_spec().parse(&query, &signer) {
+        Ok(page) => page,
+        Err(e) => return AppError::from(e).error_response(),
+    };
     let pool = match db_router.get_pool(&tenant).await {
         Ok(pool) => pool,
-        Err(e) => return HttpResponse::InternalServerError().body(format!("db pool error: {e}")),
+        Err(e) => return AppError::from(e).error_response(),
     };
-    let mut tx = match pool.begin().await {
+    let mut tx = match tenant.begin(&pool).await {
         Ok(tx) => tx,
-        Err(e) => return HttpResponse::InternalServerError().body(format!("db error: {e}")),
+        Err(e) => return AppError::from(e).error_response(),
     };
-    if let Err(e) = tenant.apply_rls(&mut *tx).await {
-        return HttpResponse::InternalServerError().body(format!("rls error: {e}"));
-    }
 
-    match NotificationRepo::list(&mut *tx, tenant.tenant_id, &query.into_inner()).await {
-        Ok(notifications) => HttpResponse::Ok().json(notifications),
-        Err(e) => HttpResponse::InternalServerError().body(format!("db error: {e}")),
+    match NotificationRepo::list(&mut tx, tenant.tenant_id, &page).await {
+        Ok(notifications) => notifications.respond(&req),
+        Err(e) => AppError::from(e).error_response(),
     }
 }
 
@@ -166,20 +175,17 @@ pub async fn get_notification(
 ) -> impl Responder {
     let pool = match db_router.get_pool(&tenant).await {
         Ok(pool) => pool,
-        Err(e) => return HttpResponse::InternalServerError().body(format!("db pool error: {e}")),
+        Err(e) => return AppError::from(e).error_response(),
     };
-    let mut tx = match pool.begin().await {
+    let mut tx = match tenant.begin(&pool).await {
         Ok(tx) => tx,
-        Err(e) => return HttpResponse::InternalServerError().body(format!("db error: {e}")),
+        Err(e) => return AppError::from(e).error_response(),
     };
-    if let Err(e) = tenant.apply_rls(&mut *tx).await {
-        return HttpResponse::InternalServerError().body(format!("rls error: {e}"));
-    }
 
-    match NotificationRepo::get(&mut *tx, tenant.tenant_id, path.into_inner()).await {
+    match NotificationRepo::get(&mut tx, tenant.tenant_id, path.into_inner()).await {
         Ok(notification) => HttpResponse::Ok().json(notification),
-        Err(sqlx::Error::RowNotFound) => HttpResponse::NotFound().body("notification not found"),
-        Err(e) => HttpResponse::InternalServerError().body(format!("db error: {e}")),
+        Err(sqlx::Error::RowNotFound) => AppError::NotFound("notification not found".into()).error_response(),
+        Err(e) => AppError::from(e).error_response(),
     }
 }
 
@@ -196,23 +202,20 @@ pub async fn mark_notification_read(
 ) -> impl Responder {
     let pool = match db_router.get_pool(&tenant).await {
         Ok(pool) => pool,
-        Err(e) => return HttpResponse::InternalServerError().body(format!("db pool error: {e}")),
+        Err(e) => return AppError::from(e).error_response(),
     };
-    let mut tx = match pool.begin().await {
+    let mut tx = match tenant.begin(&pool).await {
         Ok(tx) => tx,
-        Err(e) => return HttpResponse::InternalServerError().body(format!("db error: {e}")),
+        Err(e) => return AppError::from(e).error_response(),
     };
-    if let Err(e) = tenant.apply_rls(&mut *tx).await {
-        return HttpResponse::InternalServerError().body(format!("rls error: {e}"));
-    }
 
-    match NotificationRepo::mark_read(&mut *tx, tenant.tenant_id, path.into_inner()).await {
+    match NotificationRepo::mark_read(&mut tx, tenant.tenant_id, path.into_inner()).await {
         Ok(notification) => {
             let _ = tx.commit().await;
             HttpResponse::Ok().json(notification)
         }
-        Err(sqlx::Error::RowNotFound) => HttpResponse::NotFound().body("notification not found"),
-        Err(e) => HttpResponse::InternalServerError().body(format!("db error: {e}")),
+        Err(sqlx::Error::RowNotFound) => AppError::NotFound("notification not found".into()).error_response(),
+        Err(e) => AppError::from(e).error_response(),
     }
 }
 
@@ -229,22 +232,19 @@ pub async fn register_device(
 ) -> impl Responder {
     let pool = match db_router.get_pool(&tenant).await {
         Ok(pool) => pool,
-        Err(e) => return HttpResponse::InternalServerError().body(format!("db pool error: {e}")),
+        Err(e) => return AppError::from(e).error_response(),
     };
-    let mut tx = match pool.begin().await {
+    let mut tx = match tenant.begin(&pool).await {
         Ok(tx) => tx,
-        Err(e) => return HttpResponse::InternalServerError().body(format!("db error: {e}")),
+        Err(e) => return AppError::from(e).error_response(),
     };
-    if let Err(e) = tenant.apply_rls(&mut *tx).await {
-        return HttpResponse::InternalServerError().body(format!("rls error: {e}"));
-    }
 
-    match NotificationRepo::register_device(&mut *tx, tenant.tenant_id, &req).await {
+    match NotificationRepo::register_device(&mut tx, tenant.tenant_id, &req).await {
         Ok(device) => {
             let _ = tx.commit().await;
             HttpResponse::Created().json(device)
         }
-        Err(e) => HttpResponse::InternalServerError().body(format!("db error: {e}")),
+        Err(e) => AppError::from(e).error_response(),
     }
 }
 
@@ -261,19 +261,16 @@ pub async fn list_user_devices(
 ) -> impl Responder {
     let pool = match db_router.get_pool(&tenant).await {
         Ok(pool) => pool,
-        Err(e) => return HttpResponse::InternalServerError().body(format!("db pool error: {e}")),
+        Err(e) => return AppError::from(e).error_response(),
     };
-    let mut tx = match pool.begin().await {
+    let mut tx = match tenant.begin(&pool).await {
         Ok(tx) => tx,
-        Err(e) => return HttpResponse::InternalServerError().body(format!("db error: {e}")),
+        Err(e) => return AppError::from(e).error_response(),
     };
-    if let Err(e) = tenant.apply_rls(&mut *tx).await {
-        return HttpResponse::InternalServerError().body(format!("rls error: {e}"));
-    }
 
-    match NotificationRepo::list_user_devices(&mut *tx, tenant.tenant_id, path.into_inner()).await {
+    match NotificationRepo::list_user_devices(&mut tx, tenant.tenant_id, path.into_inner()).await {
         Ok(devices) => HttpResponse::Ok().json(devices),
-        Err(e) => HttpResponse::InternalServerError().body(format!("db error: {e}")),
+        Err(e) => AppError::from(e).error_response(),
     }
 }
 
@@ -290,23 +287,20 @@ pub async fn disable_device(
 ) -> impl Responder {
     let pool = match db_router.get_pool(&tenant).await {
         Ok(pool) => pool,
-        Err(e) => return HttpResponse::InternalServerError().body(format!("db pool error: {e}")),
+        Err(e) => return AppError::from(e).error_response(),
     };
-    let mut tx = match pool.begin().await {
+    let mut tx = match tenant.begin(&pool).await {
         Ok(tx) => tx,
-        Err(e) => return HttpResponse::InternalServerError().body(format!("db error: {e}")),
+        Err(e) => return AppError::from(e).error_response(),
     };
-    if let Err(e) = tenant.apply_rls(&mut *tx).await {
-        return HttpResponse::InternalServerError().body(format!("rls error: {e}"));
-    }
 
-    match NotificationRepo::disable_device(&mut *tx, tenant.tenant_id, path.into_inner()).await {
+    match NotificationRepo::disable_device(&mut tx, tenant.tenant_id, path.into_inner()).await {
         Ok(device) => {
             let _ = tx.commit().await;
             HttpResponse::Ok().json(device)
         }
-        Err(sqlx::Error::RowNotFound) => HttpResponse::NotFound().body("device not found"),
-        Err(e) => HttpResponse::InternalServerError().body(format!("db error: {e}")),
+        Err(sqlx::Error::RowNotFound) => AppError::NotFound("device not found".into()).error_response(),
+        Err(e) => AppError::from(e).error_response(),
     }
 }
 
@@ -323,19 +317,16 @@ pub async fn get_preferences(
 ) -> impl Responder {
     let pool = match db_router.get_pool(&tenant).await {
         Ok(pool) => pool,
-        Err(e) => return HttpResponse::InternalServerError().body(format!("db pool error: {e}")),
+        Err(e) => return AppError::from(e).error_response(),
     };
-    let mut tx = match pool.begin().await {
+    let mut tx = match tenant.begin(&pool).await {
         Ok(tx) => tx,
-        Err(e) => return HttpResponse::InternalServerError().body(format!("db error: {e}")),
+        Err(e) => return AppError::from(e).error_response(),
     };
-    if let Err(e) = tenant.apply_rls(&mut *tx).await {
-        return HttpResponse::InternalServerError().body(format!("rls error: {e}"));
-    }
 
-    match NotificationRepo::get_preferences(&mut *tx, tenant.tenant_id, path.into_inner()).await {
+    match NotificationRepo::get_preferences(&mut tx, tenant.tenant_id, path.into_inner()).await {
         Ok(prefs) => HttpResponse::Ok().json(prefs),
-        Err(e) => HttpResponse::InternalServerError().body(format!("db error: {e}")),
+        Err(e) => AppError::from(e).error_response(),
     }
 }
 
@@ -354,22 +345,19 @@ pub async fn update_preferences(
 ) -> impl Responder {
     let pool = match db_router.get_pool(&tenant).await {
         Ok(pool) => pool,
-        Err(e) => return HttpResponse::InternalServerError().body(format!("db pool error: {e}")),
+        Err(e) => return AppError::from(e).error_response(),
     };
-    let mut tx = match pool.begin().await {
+    let mut tx = match tenant.begin(&pool).await {
         Ok(tx) => tx,
-        Err(e) => return HttpResponse::InternalServerError().body(format!("db error: {e}")),
+        Err(e) => return AppError::from(e).error_response(),
     };
-    if let Err(e) = tenant.apply_rls(&mut *tx).await {
-        return HttpResponse::InternalServerError().body(format!("rls error: {e}"));
-    }
 
-    match NotificationRepo::update_preferences(&mut *tx, tenant.tenant_id, path.into_inner(), &req).await {
+    match NotificationRepo::update_preferences(&mut tx, tenant.tenant_id, path.into_inner(), &req).await {
         Ok(prefs) => {
             let _ = tx.commit().await;
             HttpResponse::Ok().json(prefs)
         }
-        Err(e) => HttpResponse::InternalServerError().body(format!("db error: {e}")),
+        Err(e) => AppError::from(e).error_response(),
     }
 }
 
@@ -1,4 +1,5 @@
 use chrono::{DateTime, Utc};
+use platform::pagination::{keyset_timestamp, FieldKind, Keyset, ListSpec, SortDirection};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use sqlx::FromRow;
@@ -57,6 +58,36 @@ pub struct Notification {
     pub updated_at: DateTime<Utc>,
 }
 
+impl Notification {
+    /// Sort and filter whitelist of `GET /notifications`.
+    pub fn list_spec() -> ListSpec {
+        ListSpec::new("created_at", SortDirection::Desc)
+            .sortable("created_at", FieldKind::Timestamp)
+            .sortable("updated_at", FieldKind::Timestamp)
+            .filterable("user_id", FieldKind::Uuid)
+            .filterable("supplier_id", FieldKind::Uuid)
+            .filterable("order_id", FieldKind::Uuid)
+            .filterable("status", FieldKind::Enum)
+            .filterable("channel", FieldKind::Enum)
+            .filterable("priority", FieldKind::Enum)
+            .filterable("event_type", FieldKind::Text)
+    }
+}
+
+impl Keyset for Notification {
+    fn keyset_id(&self) -> Uuid {
+        self.id
+    }
+
+    fn keyset_value(&self, field: &str) -> Option<String> {
+        match field {
+            "created_at" => Some(keyset_timestamp(&self.created_at)),
+            "updated_at" => Some(keyset_timestamp(&self.updated_at)),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct CreateNotificationRequest {
     pub user_id: Option<Uuid>,
@@ -71,32 +102,6 @@ pub struct CreateNotificationRequest {
     pub payload: Option<Value>,
 }
 
-#[derive(Debug, Deserialize, utoipa::IntoParams)]
-pub struct ListNotificationsQuery {
-    pub user_id: Option<Uuid>,
-    pub supplier_id: Option<Uuid>,
-    pub status: Option<NotificationStatus>,
-    pub limit: Option<i64>,
-    pub offset: Option<i64>,
-}
-
-#[allow(dead_code)]
-#[derive(Debug, Clone, Deserialize)]
-pub struct DomainEvent {
-    pub tenant_id: Option<Uuid>,
-    pub event_type: Option<String>,
-    pub user_id: Option<Uuid>,
-    pub supplier_id: Option<Uuid>,
-    pub order_id: Option<Uuid>,
-    pub product_id: Option<Uuid>,
-    pub quantity: Option<i32>,
-    pub status: Option<String>,
-    pub tracking_number: Option<String>,
-    pub recipient: Option<String>,
-    #[serde(flatten)]
-    pub payload: Value,
-}
-
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::Type, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 #[sqlx(type_name = "notification_device_platform", rename_all = "lowercase")]
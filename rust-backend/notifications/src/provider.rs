@@ -1,7 +1,7 @@
 use crate::models::{Notification, NotificationChannel};
 use reqwest::header::{AUTHORIZATION, CONTENT_TYPE};
 use serde_json::json;
-use std::env;
+use platform::config::{Kind, Schema, Setting, Settings};
 
 #[derive(Clone)]
 pub struct NotificationProvider {
@@ -19,22 +19,35 @@ pub struct NotificationProvider {
 }
 
 impl NotificationProvider {
-    pub fn from_env() -> Self {
+    /// Adds the provider's settings to a service schema.
+    pub fn settings(schema: Schema) -> Schema {
+        schema
+            .with(Setting::required("NOTIFICATION_DRY_RUN", Kind::Bool).with_default("true"))
+            .with(Setting::optional("EMAIL_WEBHOOK_URL", Kind::Url))
+            .with(Setting::optional("SMS_WEBHOOK_URL", Kind::Url))
+            .with(Setting::optional("SENDGRID_API_KEY", Kind::Text).secret())
+            .with(Setting::optional("SENDGRID_FROM_EMAIL", Kind::Text))
+            .with(Setting::optional("TWILIO_ACCOUNT_SID", Kind::Text))
+            .with(Setting::optional("TWILIO_AUTH_TOKEN", Kind::Text).secret())
+            .with(Setting::optional("TWILIO_FROM_NUMBER", Kind::Text))
+            .with(Setting::required("EXPO_PUSH_URL", Kind::Url).with_default("https://exp.host/--/api/v2/push/send"))
+            .with(Setting::optional("EXPO_ACCESS_TOKEN", Kind::Text).secret())
+    }
+
+    pub fn from_settings(settings: &Settings) -> Self {
+        let owned = |key: &str| settings.get(key).map(str::to_string);
         Self {
-            dry_run: env::var("NOTIFICATION_DRY_RUN")
-                .map(|value| value != "false")
-                .unwrap_or(true),
+            dry_run: settings.flag("NOTIFICATION_DRY_RUN"),
             client: reqwest::Client::new(),
-            email_webhook_url: env_non_empty("EMAIL_WEBHOOK_URL"),
-            sms_webhook_url: env_non_empty("SMS_WEBHOOK_URL"),
-            sendgrid_api_key: env_non_empty("SENDGRID_API_KEY"),
-            sendgrid_from_email: env_non_empty("SENDGRID_FROM_EMAIL"),
-            twilio_account_sid: env_non_empty("TWILIO_ACCOUNT_SID"),
-            twilio_auth_token: env_non_empty("TWILIO_AUTH_TOKEN"),
-            twilio_from_number: env_non_empty("TWILIO_FROM_NUMBER"),
-            expo_push_url: env::var("EXPO_PUSH_URL")
-                .unwrap_or_else(|_| "https://exp.host/--/api/v2/push/send".to_string()),
-            expo_access_token: env_non_empty("EXPO_ACCESS_TOKEN"),
+            email_webhook_url: owned("EMAIL_WEBHOOK_URL"),
+            sms_webhook_url: owned("SMS_WEBHOOK_URL"),
+            sendgrid_api_key: owned("SENDGRID_API_KEY"),
+            sendgrid_from_email: owned("SENDGRID_FROM_EMAIL"),
+            twilio_account_sid: owned("TWILIO_ACCOUNT_SID"),
+            twilio_auth_token: owned("TWILIO_AUTH_TOKEN"),
+            twilio_from_number: owned("TWILIO_FROM_NUMBER"),
+            expo_push_url: settings.text("EXPO_PUSH_URL").to_string(),
+            expo_access_token: owned("EXPO_ACCESS_TOKEN"),
         }
     }
 
@@ -194,7 +207,3 @@ async fn ensure_success(response: reqwest::Response, label: &str) -> Result<(),
         .unwrap_or_else(|_| "<body unavailable>".to_string());
     Err(format!("{label} failed with {status}: {body}"))
 }
-
-fn env_non_empty(key: &str) -> Option<String> {
-    env::var(key).ok().filter(|value| !value.trim().is_empty())
-}
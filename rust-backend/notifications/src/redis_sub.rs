@@ -1,12 +1,17 @@
+use platform::events::DomainEvent;
+use platform::tenant::TenantTx;
 use platform::{metrics, streams};
-use std::env;
+use uuid::Uuid;
 
 use crate::db::NotificationRepo;
 use crate::models::{
-    CreateNotificationRequest, DomainEvent, NotificationChannel, NotificationPriority, UserPreference
+    CreateNotificationRequest, NotificationChannel, NotificationPriority, UserPreference
 };
 
-const EVENTS: &[&str] = &[
+/// Consumer group this service reads its streams with.
+pub const GROUP: &str = "notifications";
+
+pub const EVENTS: &[&str] = &[
     "order.created",
     "order.cancelled",
     "order.confirmed",
@@ -28,13 +33,14 @@ const EVENTS: &[&str] = &[
 
 pub async fn listen_to_redis_events(
     pool: sqlx::PgPool,
+    redis_url: Option<String>,
+    consumer: String,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let redis_url = env::var("REDIS_URL")?;
-    let consumer = env::var("CONSUMER_NAME").unwrap_or_else(|_| "notifications-1".to_string());
+    let redis_url = redis_url.ok_or("REDIS_URL must be set in environment")?;
 
     streams::consume_json::<DomainEvent, _, _>(
         &redis_url,
-        "notifications",
+        GROUP,
         &consumer,
         EVENTS,
         move |envelope| {
@@ -42,38 +48,33 @@ pub async fn listen_to_redis_events(
             async move {
                 let event_type = envelope.event_type.clone();
                 let event = &envelope.payload;
-                let tenant_id = envelope.tenant_id.or(event.tenant_id);
+                let tenant_id = event.tenant_id();
 
-                if tenant_id.is_none() || tenant_id == Some(uuid::Uuid::nil()) {
+                if tenant_id.is_nil() {
                     tracing::warn!(%event_type, stream = %envelope.stream, "Missing tenant_id in notification stream event — skipping notification");
                     metrics::inc_event("notifications", &envelope.stream, &event_type, "tenant_mismatch");
                     return Ok(());
                 }
 
-                if let (Some(env_tid), Some(pay_tid)) = (envelope.tenant_id, event.tenant_id) {
-                    if env_tid != pay_tid {
-                        tracing::warn!(%event_type, ?env_tid, ?pay_tid, "Tenant ID mismatch between envelope and payload — skipping notification");
+                if let Some(env_tid) = envelope.tenant_id {
+                    if env_tid != tenant_id {
+                        tracing::warn!(%event_type, ?env_tid, pay_tid = ?tenant_id, "Tenant ID mismatch between envelope and payload — skipping notification");
                         metrics::inc_event("notifications", &envelope.stream, &event_type, "tenant_mismatch");
                         return Ok(());
                     }
                 }
 
-                let tenant_id = tenant_id.unwrap();
-
-                let Some((subject, body, priority)) =
-                    notification_from_event(&envelope.event_type, &envelope.payload)
-                else {
+                let Some((subject, body, priority)) = notification_from_event(event) else {
                     return Ok(());
                 };
-                
-                let recipient = event
-                    .recipient
-                    .clone()
-                    .or_else(|| event.user_id.map(|id| format!("user:{id}")))
-                    .or_else(|| event.supplier_id.map(|id| format!("supplier:{id}")))
+
+                let (user_id, supplier_id, order_id) = event_subjects(event);
+                let recipient = user_id
+                    .map(|id| format!("user:{id}"))
+                    .or_else(|| supplier_id.map(|id| format!("supplier:{id}")))
                     .unwrap_or_else(|| "system".to_string());
 
-                let mut tx = match pool.begin().await {
+                let mut tx = match TenantTx::begin(&pool, tenant_id).await {
                     Ok(tx) => tx,
                     Err(e) => {
                         eprintln!("Failed to acquire DB transaction in redis_sub: {e}");
@@ -81,15 +82,9 @@ pub async fn listen_to_redis_events(
                     }
                 };
 
-                let tenant_setting_query = format!("SET LOCAL app.current_tenant_id = '{}'", tenant_id);
-                if let Err(e) = sqlx::query(&tenant_setting_query).execute(&mut *tx).await {
-                    eprintln!("Failed to set RLS context in redis sub: {e}");
-                    return Ok(());
-                }
-
                 // Fetch user preferences dynamically
-                let prefs = if let Some(uid) = event.user_id {
-                    NotificationRepo::get_preferences(&mut *tx, tenant_id, uid).await.unwrap_or_else(|_| UserPreference {
+                let prefs = if let Some(uid) = user_id {
+                    NotificationRepo::get_preferences(&mut tx, tenant_id, uid).await.unwrap_or_else(|_| UserPreference {
                         user_id: uid,
                         tenant_id,
                         email_enabled: true,
@@ -125,19 +120,19 @@ pub async fn listen_to_redis_events(
 
                 for channel in active_channels {
                     let req = CreateNotificationRequest {
-                        user_id: event.user_id,
-                        supplier_id: event.supplier_id,
-                        order_id: event.order_id,
+                        user_id,
+                        supplier_id,
+                        order_id,
                         event_type: Some(envelope.event_type.clone()),
                         channel,
                         priority: Some(priority.clone()),
                         recipient: Some(recipient.clone()),
                         subject: subject.clone(),
                         body: body.clone(),
-                        payload: Some(event.payload.clone()),
+                        payload: serde_json::to_value(event).ok(),
                     };
 
-                    match NotificationRepo::create(&mut *tx, tenant_id, &req).await {
+                    match NotificationRepo::create(&mut tx, tenant_id, &req).await {
                         Ok(_) => {
                             metrics::inc_event("notifications", &envelope.stream, &envelope.event_type, "ok");
                         }
@@ -157,86 +152,113 @@ pub async fn listen_to_redis_events(
     .await
 }
 
-fn notification_from_event(channel: &str, event: &DomainEvent) -> Option<(Option<String>, String, NotificationPriority)> {
-    match channel {
-        "inventory.lowstock" => Some((
+/// The (user, supplier, order) a notification is about.
+fn event_subjects(event: &DomainEvent) -> (Option<Uuid>, Option<Uuid>, Option<Uuid>) {
+    match event {
+        DomainEvent::OrderCreated(e)
+        | DomainEvent::OrderCancelled(e)
+        | DomainEvent::OrderConfirmed(e)
+        | DomainEvent::OrderFailed(e)
+        | DomainEvent::OrderDelivered(e)
+        | DomainEvent::OrderReviewRequested(e) => (Some(e.user_id), Some(e.supplier_id), Some(e.order_id)),
+        DomainEvent::InventoryLowStock(e) | DomainEvent::InventoryRejected(e) => (e.user_id, e.supplier_id, e.order_id),
+        DomainEvent::ShipmentCreated(e) | DomainEvent::ShipmentUpdated(e) | DomainEvent::ShipmentCancelled(e) => {
+            (Some(e.user_id), Some(e.supplier_id), Some(e.order_id))
+        }
+        DomainEvent::PaymentFailed(e) | DomainEvent::PaymentSuccess(e) | DomainEvent::PaymentCancelled(e) => {
+            (Some(e.user_id), Some(e.supplier_id), Some(e.order_id))
+        }
+        DomainEvent::SupplierCreated(e) | DomainEvent::SupplierStatusUpdated(e) => (Some(e.user_id), Some(e.supplier_id), None),
+        DomainEvent::UserCreated(e) => (e.user_id, None, None),
+        _ => (None, None, None),
+    }
+}
+
+fn notification_from_event(event: &DomainEvent) -> Option<(Option<String>, String, NotificationPriority)> {
+    match event {
+        DomainEvent::InventoryLowStock(e) => Some((
             Some("Inventory is running low".to_string()),
-            format!("Product {:?} is at or below its low-stock threshold.", event.product_id),
+            format!("Product {} is at or below its low-stock threshold.", e.product_id),
             NotificationPriority::High,
         )),
-        "inventory.rejected" => Some((
+        DomainEvent::InventoryRejected(e) => Some((
             Some("Order could not be reserved".to_string()),
-            format!("Order {:?} was rejected because stock was unavailable.", event.order_id),
+            format!("Order {:?} was rejected because stock was unavailable.", e.order_id),
             NotificationPriority::High,
         )),
-        "logistics.shipment_created" => Some((
+        DomainEvent::ShipmentCreated(e) => Some((
             Some("Shipment created".to_string()),
-            format!("Shipment for order {:?} was created. Tracking number: {}", event.order_id, event.tracking_number.as_deref().unwrap_or("pending")),
+            format!("Shipment for order {} was created. Tracking number: {}", e.order_id, e.tracking_number),
             NotificationPriority::Normal,
         )),
-        "logistics.shipment_updated" => Some((
+        DomainEvent::ShipmentUpdated(e) => Some((
             Some("Shipment updated".to_string()),
-            format!("Shipment for order {:?} is now {}.", event.order_id, event.status.as_deref().unwrap_or("updated")),
+            format!("Shipment for order {} is now {}.", e.order_id, e.status),
+            NotificationPriority::Normal,
+        )),
+        DomainEvent::ShipmentCancelled(e) => Some((
+            Some("Order cancelled".to_string()),
+            format!("Order {} was cancelled.", e.order_id),
             NotificationPriority::Normal,
         )),
-        "logistics.shipment_cancelled" | "order.cancelled" => Some((
+        DomainEvent::OrderCancelled(e) => Some((
             Some("Order cancelled".to_string()),
-            format!("Order {:?} was cancelled.", event.order_id),
+            format!("Order {} was cancelled.", e.order_id),
             NotificationPriority::Normal,
         )),
-        "payment.failed" => Some((
+        DomainEvent::PaymentFailed(e) => Some((
             Some("Payment failed".to_string()),
-            format!("Payment for order {:?} failed.", event.order_id),
+            format!("Payment for order {} failed.", e.order_id),
             NotificationPriority::Critical,
         )),
-        "payment.success" => Some((
+        DomainEvent::PaymentSuccess(e) => Some((
             Some("Payment successful".to_string()),
-            format!("Payment for order {:?} was successful.", event.order_id),
+            format!("Payment for order {} was successful.", e.order_id),
             NotificationPriority::Normal,
         )),
-        "payment.cancelled" => Some((
+        DomainEvent::PaymentCancelled(e) => Some((
             Some("Payment cancelled".to_string()),
-            format!("Payment for order {:?} was cancelled.", event.order_id),
+            format!("Payment for order {} was cancelled.", e.order_id),
             NotificationPriority::High,
         )),
-        "supplier.created" => Some((
+        DomainEvent::SupplierCreated(e) => Some((
             Some("Supplier onboarding started".to_string()),
-            format!("Supplier {:?} has been created and is pending review.", event.supplier_id),
+            format!("Supplier {} has been created and is pending review.", e.supplier_id),
             NotificationPriority::Normal,
         )),
-        "supplier.status_updated" => Some((
+        DomainEvent::SupplierStatusUpdated(e) => Some((
             Some("Supplier status updated".to_string()),
-            format!("Supplier {:?} status is now {}.", event.supplier_id, event.status.as_deref().unwrap_or("updated")),
+            format!("Supplier {} status is now {}.", e.supplier_id, e.status),
             NotificationPriority::Normal,
         )),
-        "user.created" => Some((
+        DomainEvent::UserCreated(_) => Some((
             Some("Welcome".to_string()),
             "Your account has been created successfully.".to_string(),
             NotificationPriority::Normal,
         )),
-        "order.created" => Some((
+        DomainEvent::OrderCreated(e) => Some((
             Some("Order received".to_string()),
-            format!("Order {:?} has been received and is pending inventory reservation.", event.order_id),
+            format!("Order {} has been received and is pending inventory reservation.", e.order_id),
             NotificationPriority::Normal,
         )),
-        "order.confirmed" => Some((
+        DomainEvent::OrderConfirmed(e) => Some((
             Some("Order Confirmed".to_string()),
-            format!("Your order {:?} has been confirmed and is being prepared for shipment.", event.order_id),
+            format!("Your order {} has been confirmed and is being prepared for shipment.", e.order_id),
             NotificationPriority::High,
         )),
-        "order.failed" => Some((
+        DomainEvent::OrderFailed(e) => Some((
             Some("Order Failed".to_string()),
-            format!("We're sorry, but your order {:?} has failed.", event.order_id),
+            format!("We're sorry, but your order {} has failed.", e.order_id),
             NotificationPriority::High,
         )),
-        "order.delivered" => Some((
+        DomainEvent::OrderDelivered(e) => Some((
             Some("Order Delivered".to_string()),
-            format!("Your order {:?} has been delivered.", event.order_id),
+            format!("Your order {} has been delivered.", e.order_id),
             NotificationPriority::Normal,
         )),
-        "order.review_requested" => Some((
+        DomainEvent::OrderReviewRequested(e) => Some((
             Some("Please review your purchase".to_string()),
-            format!("How did you like your order {:?}? Please leave a review.", event.order_id),
+            format!("How did you like your order {}? Please leave a review.", e.order_id),
             NotificationPriority::Normal,
         )),
         _ => None,
@@ -0,0 +1,63 @@
+-- Catalogue prices order-service charges, projected from product-catalog's product events
+CREATE TABLE IF NOT EXISTS catalog_products (
+    tenant_id UUID NOT NULL,
+    product_id UUID NOT NULL,
+    supplier_id UUID NOT NULL,
+    name TEXT,
+    unit_price_cents BIGINT NOT NULL DEFAULT 0,
+    available BOOLEAN NOT NULL DEFAULT TRUE,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (tenant_id, product_id)
+);
+ALTER TABLE catalog_products ENABLE ROW LEVEL SECURITY;
+ALTER TABLE catalog_products FORCE ROW LEVEL SECURITY;
+DROP POLICY IF EXISTS catalog_products_tenant_isolation_policy ON catalog_products;
+CREATE POLICY catalog_products_tenant_isolation_policy ON catalog_products
+    FOR ALL
+    USING (tenant_id = NULLIF(current_setting('app.current_tenant_id', true), '')::uuid)
+    WITH CHECK (tenant_id = NULLIF(current_setting('app.current_tenant_id', true), '')::uuid);
+
+-- One row per product in an order, priced when the order was placed; amounts in cents
+CREATE TABLE IF NOT EXISTS order_lines (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    tenant_id UUID NOT NULL,
+    order_id UUID NOT NULL REFERENCES orders(id) ON DELETE CASCADE,
+    line_number INT NOT NULL,
+    product_id UUID NOT NULL,
+    quantity INT NOT NULL CHECK (quantity > 0),
+    unit_price_cents BIGINT NOT NULL CHECK (unit_price_cents >= 0),
+    tax_cents BIGINT NOT NULL DEFAULT 0,
+    discount_cents BIGINT NOT NULL DEFAULT 0,
+    line_total_cents BIGINT NOT NULL,
+    UNIQUE (order_id, line_number)
+);
+CREATE INDEX IF NOT EXISTS idx_order_lines_tenant_product ON order_lines(tenant_id, product_id);
+ALTER TABLE order_lines ENABLE ROW LEVEL SECURITY;
+ALTER TABLE order_lines FORCE ROW LEVEL SECURITY;
+DROP POLICY IF EXISTS order_lines_tenant_isolation_policy ON order_lines;
+CREATE POLICY order_lines_tenant_isolation_policy ON order_lines
+    FOR ALL
+    USING (tenant_id = NULLIF(current_setting('app.current_tenant_id', true), '')::uuid)
+    WITH CHECK (tenant_id = NULLIF(current_setting('app.current_tenant_id', true), '')::uuid);
+
+ALTER TABLE orders
+    ADD COLUMN IF NOT EXISTS subtotal_cents BIGINT NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS tax_cents BIGINT NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS discount_cents BIGINT NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS total_cents BIGINT NOT NULL DEFAULT 0;
+
+-- product_id, qty and items are superseded by order_lines and no longer written
+ALTER TABLE orders ALTER COLUMN product_id DROP DEFAULT;
+ALTER TABLE orders ALTER COLUMN items SET DEFAULT '[]'::jsonb;
+
+-- Existing single-product orders become one-line orders without a recorded price.
+-- The owner is exempt from RLS only while it is not forced.
+ALTER TABLE orders NO FORCE ROW LEVEL SECURITY;
+ALTER TABLE order_lines NO FORCE ROW LEVEL SECURITY;
+INSERT INTO order_lines (tenant_id, order_id, line_number, product_id, quantity, unit_price_cents, line_total_cents)
+SELECT tenant_id, id, 1, product_id, qty, 0, 0
+FROM orders
+WHERE product_id IS NOT NULL AND qty > 0
+ON CONFLICT (order_id, line_number) DO NOTHING;
+ALTER TABLE orders FORCE ROW LEVEL SECURITY;
+ALTER TABLE order_lines FORCE ROW LEVEL SECURITY;
@@ -0,0 +1,8 @@
+-- Keyset pagination sorts on created_at, which must never be NULL
+UPDATE orders SET created_at = COALESCE(order_timestamp, NOW()) WHERE created_at IS NULL;
+ALTER TABLE orders ALTER COLUMN created_at SET DEFAULT NOW();
+ALTER TABLE orders ALTER COLUMN created_at SET NOT NULL;
+
+-- A buyer's orders and a supplier's open orders, newest first
+CREATE INDEX IF NOT EXISTS idx_orders_tenant_user ON orders(tenant_id, user_id, created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_orders_tenant_supplier ON orders(tenant_id, supplier_id, created_at DESC);
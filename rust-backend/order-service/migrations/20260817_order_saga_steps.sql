@@ -0,0 +1,23 @@
+-- saga_log was created but never written; it becomes the order saga's append-only step log.
+-- The latest row (highest seq) of an order is its current state.
+ALTER TABLE saga_log
+    ADD COLUMN IF NOT EXISTS seq BIGINT GENERATED ALWAYS AS IDENTITY,
+    ADD COLUMN IF NOT EXISTS tenant_id UUID NOT NULL,
+    ADD COLUMN IF NOT EXISTS step VARCHAR(50) NOT NULL,
+    -- When the saga counts as stuck if no later row arrives; NULL for states that wait indefinitely
+    ADD COLUMN IF NOT EXISTS deadline_at TIMESTAMPTZ;
+
+ALTER TABLE saga_log DROP CONSTRAINT IF EXISTS saga_log_order_id_fkey;
+ALTER TABLE saga_log
+    ADD CONSTRAINT saga_log_order_id_fkey FOREIGN KEY (order_id) REFERENCES orders(id) ON DELETE CASCADE;
+
+CREATE INDEX IF NOT EXISTS idx_saga_log_order ON saga_log(tenant_id, order_id, seq DESC);
+CREATE INDEX IF NOT EXISTS idx_saga_log_deadline ON saga_log(tenant_id, deadline_at) WHERE deadline_at IS NOT NULL;
+
+ALTER TABLE saga_log ENABLE ROW LEVEL SECURITY;
+ALTER TABLE saga_log FORCE ROW LEVEL SECURITY;
+DROP POLICY IF EXISTS saga_log_tenant_isolation_policy ON saga_log;
+CREATE POLICY saga_log_tenant_isolation_policy ON saga_log
+    FOR ALL
+    USING (tenant_id = NULLIF(current_setting('app.current_tenant_id', true), '')::uuid)
+    WITH CHECK (tenant_id = NULLIF(current_setting('app.current_tenant_id', true), '')::uuid);
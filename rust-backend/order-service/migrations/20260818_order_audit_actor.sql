@@ -0,0 +1,10 @@
+-- Every status transition appends a row recording who or what caused it.
+ALTER TABLE order_audit_logs ALTER COLUMN id SET DEFAULT gen_random_uuid();
+ALTER TABLE order_audit_logs
+    -- user, api_key, event or worker; rows written before actors were recorded are 'system'
+    ADD COLUMN IF NOT EXISTS actor_type VARCHAR(20) NOT NULL DEFAULT 'system',
+    -- User or API key id, stream entry id, or worker name
+    ADD COLUMN IF NOT EXISTS actor_id TEXT,
+    ADD COLUMN IF NOT EXISTS reason TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_order_audit_logs_history ON order_audit_logs(tenant_id, order_id, changed_at);
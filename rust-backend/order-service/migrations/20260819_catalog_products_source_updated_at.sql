@@ -0,0 +1,4 @@
+-- Timestamp of the product event last applied to each row, so out-of-order
+-- redeliveries of older events are ignored. Backfilled rows have none and accept
+-- the first event that arrives.
+ALTER TABLE catalog_products ADD COLUMN IF NOT EXISTS source_updated_at TIMESTAMPTZ;
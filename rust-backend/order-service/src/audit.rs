@@ -0,0 +1,142 @@
+// src/audit.rs
+// Records who or what moved an order between statuses, in the transaction that moved it.
+
+use platform::tenant::{AuthMethod, TenantContext, TenantTx};
+use uuid::Uuid;
+
+use crate::models::{OrderAuditLog, OrderStatus};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Actor {
+    User(Uuid),
+    ApiKey(Uuid),
+    /// A stream entry, by id.
+    Event(String),
+    /// A background worker, by heartbeat name.
+    Worker(&'static str),
+    /// A caller whose credentials name neither a user nor an API key.
+    Tenant(Uuid),
+}
+
+impl Actor {
+    pub fn from_context(ctx: &TenantContext) -> Self {
+        match (&ctx.auth_method, ctx.api_key_id, ctx.user_id) {
+            (AuthMethod::ApiKey, Some(key_id), _) => Actor::ApiKey(key_id),
+            (_, _, Some(user_id)) => Actor::User(user_id),
+            _ => Actor::Tenant(ctx.tenant_id),
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            Actor::User(_) => "user",
+            Actor::ApiKey(_) => "api_key",
+            Actor::Event(_) => "event",
+            Actor::Worker(_) => "worker",
+            Actor::Tenant(_) => "tenant",
+        }
+    }
+
+    fn id(&self) -> String {
+        match self {
+            Actor::User(id) | Actor::ApiKey(id) | Actor::Tenant(id) => id.to_string(),
+            Actor::Event(id) => id.clone(),
+            Actor::Worker(name) => name.to_string(),
+        }
+    }
+}
+
+/// Why an order's status changed, written alongside the change.
+#[derive(Debug, Clone)]
+pub struct Cause {
+    pub actor: Actor,
+    pub reason: Option<String>,
+}
+
+impl Cause {
+    pub fn new(actor: Actor) -> Self {
+        Self { actor, reason: None }
+    }
+
+    pub fn with_reason(mut self, reason: impl Into<String>) -> Self {
+        self.reason = Some(reason.into());
+        self
+    }
+}
+
+pub async fn record(
+    tx: &mut TenantTx<'_>,
+    order_id: Uuid,
+    previous: Option<&OrderStatus>,
+    new: &OrderStatus,
+    cause: &Cause,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+            INSERT INTO order_audit_logs (tenant_id, order_id, previous_status, new_status, actor_type, actor_id, reason, changed_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, clock_timestamp())
+        "#,
+    )
+    .bind(tx.tenant_id())
+    .bind(order_id)
+    .bind(previous)
+    .bind(new)
+    .bind(cause.actor.kind())
+    .bind(cause.actor.id())
+    .bind(&cause.reason)
+    .execute(tx.executor())
+    .await?;
+    Ok(())
+}
+
+/// Every recorded transition of an order, oldest first; `None` if the order does not exist.
+pub async fn history(tx: &mut TenantTx<'_>, order_id: Uuid) -> Result<Option<Vec<OrderAuditLog>>, sqlx::Error> {
+    let entries = sqlx::query_as::<_, OrderAuditLog>(
+        r#"
+            SELECT id, tenant_id, order_id, previous_status, new_status, actor_type, actor_id, reason, changed_at, metadata
+            FROM order_audit_logs
+            WHERE tenant_id = $1 AND order_id = $2
+            ORDER BY changed_at, id
+        "#,
+    )
+    .bind(tx.tenant_id())
+    .bind(order_id)
+    .fetch_all(tx.executor())
+    .await?;
+
+    if entries.is_empty() {
+        // Orders placed before transitions were audited have no rows yet
+        let exists: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM orders WHERE id = $1)")
+            .bind(order_id)
+            .fetch_one(tx.executor())
+            .await?;
+        if !exists {
+            return Ok(None);
+        }
+    }
+    Ok(Some(entries))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use platform::tenant::PricingTier;
+
+    #[test]
+    fn test_actor_from_context_prefers_api_key() {
+        let tenant_id = Uuid::new_v4();
+        let key_id = Uuid::new_v4();
+        let user_id = Uuid::new_v4();
+
+        let keyed = TenantContext::new(tenant_id, Some(user_id), PricingTier::Free, vec![], AuthMethod::ApiKey)
+            .with_api_key_id(key_id);
+        assert_eq!(Actor::from_context(&keyed), Actor::ApiKey(key_id));
+
+        let jwt = TenantContext::new(tenant_id, Some(user_id), PricingTier::Free, vec![], AuthMethod::Jwt);
+        assert_eq!(Actor::from_context(&jwt), Actor::User(user_id));
+
+        let anonymous = TenantContext::new(tenant_id, None, PricingTier::Free, vec![], AuthMethod::Jwt);
+        assert_eq!(Actor::from_context(&anonymous), Actor::Tenant(tenant_id));
+        assert_eq!(Actor::from_context(&anonymous).kind(), "tenant");
+    }
+}
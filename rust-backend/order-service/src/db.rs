@@ -1,24 +1,72 @@
-use dotenvy::dotenv;
-use sqlx::{Pool, Postgres};
-use std::env;
-
-pub async fn get_db_pool() -> Pool<Postgres> {
-    dotenv().ok();
-    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL not set");
-    sqlx::PgPool::connect(&database_url)
-        .await
-        .expect("Failed to connect to Postgres")
+use std::collections::HashMap;
+
+use crate::audit::{self, Cause};
+use crate::models::{Order, OrderLine, OrderStatus, OrderSummary};
+use platform::pagination::{CursorPage, PageRequest};
+use platform::tenant::TenantTx;
+use sqlx::{FromRow, Pool, Postgres, QueryBuilder};
+use uuid::Uuid;
+
+pub async fn get_db_pool(database_url: &str) -> Result<Pool<Postgres>, sqlx::Error> {
+    sqlx::PgPool::connect(database_url).await
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum TransitionError {
+    /// The order does not exist, or does not match the guard's user or product.
+    #[error("order not found")]
+    NotFound,
+    #[error("order is {current:?} and cannot become {requested:?}")]
+    Illegal { current: OrderStatus, requested: OrderStatus },
+    /// The guard's `expected_version` is stale.
+    #[error("order is at version {current_version}")]
+    VersionConflict { current_version: i32 },
+    #[error(transparent)]
+    Database(#[from] sqlx::Error),
+}
+
+/// Conditions a status change only applies under, beyond the order id; unset ones
+/// always hold.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OrderGuard {
+    pub user_id: Option<Uuid>,
+    /// The order must have a line for this product.
+    pub product_id: Option<Uuid>,
+    pub expected_version: Option<i32>,
 }
 
 pub async fn update_order_status_db(
-    pool: &sqlx::PgPool,
+    tx: &mut TenantTx<'_>,
     order_id: uuid::Uuid,
     new_status: crate::models::OrderStatus,
-    expected_version: Option<i32>,
+    guard: OrderGuard,
     order_timestamp: Option<chrono::DateTime<chrono::Utc>>,
     expires_at: Option<chrono::DateTime<chrono::Utc>>,
-) -> Result<crate::models::Order, sqlx::Error> {
-    sqlx::query_as::<_, crate::models::Order>(
+    cause: &Cause,
+) -> Result<Order, TransitionError> {
+    let previous = lock_for_transition(tx, order_id, &new_status).await?;
+    // The row is locked, so the version read here is the one the update would see
+    let current_version: i32 = sqlx::query_scalar(
+        r#"
+            SELECT version FROM orders
+            WHERE id = $1
+            AND ($2::uuid IS NULL OR user_id = $2)
+            AND ($3::uuid IS NULL OR EXISTS (
+                SELECT 1 FROM order_lines WHERE order_lines.order_id = orders.id AND order_lines.product_id = $3
+            ))
+        "#,
+    )
+    .bind(order_id)
+    .bind(guard.user_id)
+    .bind(guard.product_id)
+    .fetch_optional(tx.executor())
+    .await?
+    .ok_or(TransitionError::NotFound)?;
+    if guard.expected_version.is_some_and(|expected| expected != current_version) {
+        return Err(TransitionError::VersionConflict { current_version });
+    }
+
+    let order = sqlx::query_as::<_, Order>(
         r#"
             UPDATE orders
             SET
@@ -28,17 +76,6 @@ pub async fn update_order_status_db(
                 updated_at = NOW(),
                 version = version + 1
             WHERE id = $4
-            AND ($5 IS NULL OR version = $5)
-            AND (
-                ($1 = 'pending') OR
-                ($1 = 'confirmed' AND status = 'pending') OR
-                ($1 = 'failed' AND status = 'pending') OR
-                ($1 = 'cancelled' AND status != 'cancelled' AND status != 'delivered') OR
-                ($1 = 'shipped' AND (status = 'confirmed' OR status = 'processing')) OR
-                ($1 = 'delivered' AND status = 'shipped') OR
-                ($1 = 'refunded' AND status != 'refunded') OR
-                ($1 = 'processing' AND status = 'confirmed')
-            )
             RETURNING *
         "#,
     )
@@ -46,14 +83,137 @@ pub async fn update_order_status_db(
     .bind(order_timestamp)
     .bind(expires_at)
     .bind(order_id)
-    .bind(expected_version)
-    .fetch_one(pool)
+    .fetch_one(tx.executor())
+    .await?;
+    audit::record(tx, order.id, Some(&previous), &order.status, cause).await?;
+    Ok(with_lines(tx, order).await?)
+}
+
+/// Locks the order row for the rest of the transaction and returns its status once
+/// `OrderStatus::can_transition_to` allows moving it to `requested`, so the status a
+/// transition starts from is the one checked and recorded in its audit row.
+pub async fn lock_for_transition(
+    tx: &mut TenantTx<'_>,
+    order_id: Uuid,
+    requested: &OrderStatus,
+) -> Result<OrderStatus, TransitionError> {
+    let current: OrderStatus = sqlx::query_scalar("SELECT status FROM orders WHERE id = $1 FOR UPDATE")
+        .bind(order_id)
+        .fetch_optional(tx.executor())
+        .await?
+        .ok_or(TransitionError::NotFound)?;
+    if !current.can_transition_to(requested) {
+        return Err(TransitionError::Illegal { current, requested: requested.clone() });
+    }
+    Ok(current)
+}
+
+pub async fn insert_order_lines(
+    tx: &mut TenantTx<'_>,
+    order_id: Uuid,
+    lines: &[OrderLine],
+) -> Result<(), sqlx::Error> {
+    let tenant_id = tx.tenant_id();
+    for line in lines {
+        sqlx::query(
+            r#"
+                INSERT INTO order_lines (tenant_id, order_id, line_number, product_id, quantity, unit_price_cents, tax_cents, discount_cents, line_total_cents)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            "#,
+        )
+        .bind(tenant_id)
+        .bind(order_id)
+        .bind(line.line_number)
+        .bind(line.product_id)
+        .bind(line.quantity)
+        .bind(line.unit_price_cents)
+        .bind(line.tax_cents)
+        .bind(line.discount_cents)
+        .bind(line.line_total_cents)
+        .execute(tx.executor())
+        .await?;
+    }
+    Ok(())
+}
+
+pub async fn order_lines(tx: &mut TenantTx<'_>, order_id: Uuid) -> Result<Vec<OrderLine>, sqlx::Error> {
+    sqlx::query_as::<_, OrderLine>(
+        r#"
+            SELECT line_number, product_id, quantity, unit_price_cents, tax_cents, discount_cents, line_total_cents
+            FROM order_lines
+            WHERE order_id = $1
+            ORDER BY line_number
+        "#,
+    )
+    .bind(order_id)
+    .fetch_all(tx.executor())
     .await
 }
 
+/// Attaches the order's lines, which `RETURNING *` on `orders` does not include.
+pub async fn with_lines(tx: &mut TenantTx<'_>, mut order: Order) -> Result<Order, sqlx::Error> {
+    order.lines = order_lines(tx, order.id).await?;
+    Ok(order)
+}
+
+#[derive(FromRow)]
+struct ListedLine {
+    order_id: Uuid,
+    #[sqlx(flatten)]
+    line: OrderLine,
+}
+
+/// Returns one page of the tenant's orders with their lines, delivered (archived) ones included.
+pub async fn list_orders(tx: &mut TenantTx<'_>, page: &PageRequest) -> Result<CursorPage<Order>, sqlx::Error> {
+    // The explicit tenant predicate lets Postgres walk idx_orders_tenant_id in created_at order
+    let mut builder = QueryBuilder::<Postgres>::new("SELECT * FROM orders WHERE tenant_id = ");
+    builder.push_bind(tx.tenant_id());
+    page.push_filters(&mut builder);
+    page.push_order_and_limit(&mut builder);
+    let orders = builder.build_query_as::<Order>().fetch_all(tx.executor()).await?;
+
+    let mut page = page.into_page(orders);
+    let ids: Vec<Uuid> = page.data.iter().map(|order| order.id).collect();
+    let rows = sqlx::query_as::<_, ListedLine>(
+        r#"
+            SELECT order_id, line_number, product_id, quantity, unit_price_cents, tax_cents, discount_cents, line_total_cents
+            FROM order_lines
+            WHERE order_id = ANY($1)
+            ORDER BY order_id, line_number
+        "#,
+    )
+    .bind(&ids)
+    .fetch_all(tx.executor())
+    .await?;
+
+    let mut lines: HashMap<Uuid, Vec<OrderLine>> = HashMap::new();
+    for row in rows {
+        lines.entry(row.order_id).or_default().push(row.line);
+    }
+    for order in &mut page.data {
+        order.lines = lines.remove(&order.id).unwrap_or_default();
+    }
+    Ok(page)
+}
+
+/// Counts the orders matching the page's filters, across all pages, per status.
+pub async fn order_summary(tx: &mut TenantTx<'_>, page: &PageRequest) -> Result<OrderSummary, sqlx::Error> {
+    let mut builder = QueryBuilder::<Postgres>::new("SELECT status::text, COUNT(*) FROM orders WHERE tenant_id = ");
+    builder.push_bind(tx.tenant_id());
+    page.push_filter_conditions(&mut builder);
+    builder.push(" GROUP BY status");
+    let counts = builder.build_query_as::<(String, i64)>().fetch_all(tx.executor()).await?;
+
+    Ok(OrderSummary {
+        total: counts.iter().map(|(_, count)| count).sum(),
+        by_status: counts.into_iter().collect(),
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::audit::Actor;
     use sqlx::PgPool;
 
     #[sqlx::test]
@@ -63,33 +223,32 @@ mod tests {
         let order_id = uuid::Uuid::new_v4();
         let user_id = uuid::Uuid::new_v4();
         let supplier_id = uuid::Uuid::new_v4();
-        let product_id = uuid::Uuid::new_v4();
-        let items = serde_json::json!([]);
+        let tenant_id = uuid::Uuid::new_v4();
+        let mut tx = TenantTx::begin(&pool, tenant_id).await.unwrap();
 
         sqlx::query(
             r#"
-            INSERT INTO orders (id, user_id, supplier_id, product_id, items, qty, status, expires_at, order_timestamp, version)
-            VALUES ($1, $2, $3, $4, $5, $6, 'pending', NOW() + INTERVAL '1 day', NOW(), 1)
+            INSERT INTO orders (id, user_id, supplier_id, status, expires_at, order_timestamp, version, tenant_id)
+            VALUES ($1, $2, $3, 'pending', NOW() + INTERVAL '1 day', NOW(), 1, $4)
             "#
         )
         .bind(&order_id)
         .bind(&user_id)
         .bind(&supplier_id)
-        .bind(&product_id)
-        .bind(&items)
-        .bind(1)
-        .execute(&pool)
+        .bind(tenant_id)
+        .execute(tx.executor())
         .await
         .unwrap();
 
         // Test valid state transition and valid version (Pending -> Confirmed)
         let order = update_order_status_db(
-            &pool, 
+            &mut tx, 
             order_id, 
             crate::models::OrderStatus::Confirmed, 
-            Some(1), 
+            OrderGuard { expected_version: Some(1), ..Default::default() }, 
             None, 
-            None
+            None,
+            &Cause::new(Actor::Worker("test")),
         ).await.expect("Valid transition should succeed");
 
         assert_eq!(order.version, 2);
@@ -97,15 +256,45 @@ mod tests {
 
         // Test invalid version update (version is now 2, we expect 1)
         let err = update_order_status_db(
-            &pool, 
+            &mut tx, 
             order_id, 
             crate::models::OrderStatus::Shipped, 
-            Some(1), // Mismatched version
+            OrderGuard { expected_version: Some(1), ..Default::default() }, // Mismatched version
             None, 
-            None
+            None,
+            &Cause::new(Actor::Worker("test")),
         ).await.expect_err("Should fail due to version mismatch");
         
-        assert!(matches!(err, sqlx::Error::RowNotFound));
+        assert!(matches!(err, TransitionError::VersionConflict { current_version: 2 }));
+
+        // Another user's order is not found
+        let err = update_order_status_db(
+            &mut tx,
+            order_id,
+            crate::models::OrderStatus::Shipped,
+            OrderGuard { user_id: Some(uuid::Uuid::new_v4()), ..Default::default() },
+            None,
+            None,
+            &Cause::new(Actor::Worker("test")),
+        ).await.expect_err("Should fail because the order belongs to another user");
+
+        assert!(matches!(err, TransitionError::NotFound));
+
+        // Illegal transitions report the current status (Confirmed -> Delivered)
+        let err = update_order_status_db(
+            &mut tx,
+            order_id,
+            crate::models::OrderStatus::Delivered,
+            OrderGuard::default(),
+            None,
+            None,
+            &Cause::new(Actor::Worker("test")),
+        ).await.expect_err("Should fail because confirmed orders cannot be delivered");
+
+        assert!(matches!(
+            err,
+            TransitionError::Illegal { current: crate::models::OrderStatus::Confirmed, .. }
+        ));
     }
 }
 
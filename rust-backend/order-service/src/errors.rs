@@ -1 +0,0 @@
-// Order Service specific errors
@@ -1,25 +1,39 @@
-use actix_web::{web, App, HttpServer};
+use std::time::Duration;
 
+use actix_web::{http::Method, web, App, HttpServer};
+
+mod audit;
 mod db;
 mod models;
+mod pricing;
 mod redis_pub;
 mod redis_sub;
 mod routes;
+mod saga;
 mod worker;
-use tokio::spawn;
 
+use crate::worker::catalog_backfill;
 use crate::worker::order_expiration_worker as expiration_worker;
+use crate::worker::saga_timeout_worker;
 
 use crate::redis_pub::RedisPublisher;
 use redis::Client as RedisClient;
+use sqlx::migrate::Migrator;
 
-use dotenvy::dotenv;
-use platform::{metrics, observability};
-use std::env;
+use platform::config::{Kind, Schema, ServiceConfig, Setting};
+use platform::db_router::DynamicPoolRouter;
+use platform::health::{self, Readiness};
+use platform::outbox::OutboxRelay;
+use platform::streams::StreamPublisher;
+use platform::supervisor::Supervisor;
+use platform::tenant_directory::TenantDirectory;
+use platform::{metrics, observability, scopes};
 
 use crate::redis_sub::listen_to_redis_events;
 
-use platform::middleware::TenantAuthMiddleware;
+use platform::middleware::request_id::RequestId;
+use platform::middleware::{Idempotency, JwksVerifier, MetricsMiddleware, RateLimiter, RequireScope, TenantAuthMiddleware};
+use platform::pagination::CursorSigner;
 
 use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
@@ -28,7 +42,11 @@ use utoipa_swagger_ui::SwaggerUi;
 #[openapi(
     paths(
         routes::create_order,
+        routes::list_orders,
+        routes::order_summary,
         routes::get_order,
+        routes::get_order_saga,
+        routes::get_order_history,
         routes::update_status,
         routes::delete_order,
     ),
@@ -37,9 +55,15 @@ use utoipa_swagger_ui::SwaggerUi;
             models::Order,
             models::OrderAuditLog,
             models::CreateOrderRequest,
+            models::CreateOrderLine,
+            models::OrderLine,
+            models::OrderSummary,
             models::UpdateOrderStatus,
             models::OrderStatus,
-            models::OrderEvent
+            saga::SagaStatus,
+            saga::SagaLogEntry,
+            saga::SagaState,
+            saga::SagaStep
         )
     ),
     tags(
@@ -48,20 +72,35 @@ use utoipa_swagger_ui::SwaggerUi;
 )]
 struct ApiDoc;
 
+static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    dotenv().ok();
+    let config = ServiceConfig::load(
+        Schema::service("order-service", 3005)
+            .with_consumer("order-service-1")
+            .with_jwt()
+            .with_pagination()
+            .with(Setting::required("ORDER_TAX_RATE_BPS", Kind::Integer).with_default("0"))
+            .with(Setting::required("SAGA_STEP_TIMEOUT_SECS", Kind::Integer).with_default("900"))
+            .with(Setting::required("SAGA_PAYMENT_TIMEOUT_SECS", Kind::Integer).with_default("172800")),
+    );
     observability::init_observability("order-service");
     metrics::init_metrics("order-service");
+    config.settings.log();
 
-    let redis_url = env::var("REDIS_URL").ok();
-    let port = env::var("SERVICE_PORT")
-        .or_else(|_| env::var("PORT"))
-        .unwrap_or_else(|_| "3005".to_string());
+    let redis_url = config.redis_url.clone();
+    let port = config.service_port;
 
-    let pool = db::get_db_pool().await;
+    let pool = match db::get_db_pool(&config.database_url).await {
+        Ok(pool) => pool,
+        Err(e) => {
+            eprintln!("❌ Failed to connect to Postgres: {e}");
+            std::process::exit(1);
+        }
+    };
 
-    sqlx::migrate!("./migrations")
+    MIGRATOR
         .run(&pool)
         .await
         .expect("Migrations Failed");
@@ -75,6 +114,13 @@ async fn main() -> std::io::Result<()> {
         .expect("redis client"),
     );
 
+    // Requests, stream events and workers all reach tenant data through the router, so
+    // Enterprise tenants with a dedicated database are never served from the shared one
+    let db_router = DynamicPoolRouter::new(pool.clone())
+        .with_migrator(&MIGRATOR)
+        .with_tenant_directory(TenantDirectory::from_env().with_redis(redis_client.get_ref().clone()));
+    db_router.spawn_eviction();
+
     let redis_pub = match &redis_url {
         Some(url) => match RedisPublisher::new(url).await {
             Ok(pubw) => web::Data::new(pubw),
@@ -90,39 +136,116 @@ async fn main() -> std::io::Result<()> {
         }
     };
 
-    expiration_worker::start_order_expiration_worker(pool.clone(), redis_pub.get_ref().clone())
-        .await;
+    // Drain events committed alongside order writes into Redis Streams. Without a
+    // publisher the relay stays off and rows wait in the outbox.
+    let outbox_publisher = redis_url
+        .as_deref()
+        .and_then(|url| StreamPublisher::new(url).ok())
+        .unwrap_or_else(StreamPublisher::noop);
+    let supervisor = Supervisor::new("order-service").with_deadline(config.shutdown_timeout);
+    OutboxRelay::new("order-service", pool.clone(), outbox_publisher)
+        .with_router(db_router.clone())
+        .supervise(&supervisor);
+
+    let (backfill_router, backfill_tenants) = (db_router.clone(), TenantDirectory::from_env());
+    supervisor.spawn(catalog_backfill::TASK, move || {
+        catalog_backfill::run_catalog_backfill(backfill_router.clone(), backfill_tenants.clone())
+    });
+
+    let seconds = |key: &str| Duration::from_secs(config.settings.integer(key).unwrap_or_default() as u64);
+    let saga = saga::Saga::new()
+        .with_step_timeout(seconds("SAGA_STEP_TIMEOUT_SECS"))
+        .with_payment_timeout(seconds("SAGA_PAYMENT_TIMEOUT_SECS"));
+    let (worker_router, worker_tenants) = (db_router.clone(), TenantDirectory::from_env());
+    supervisor.spawn(expiration_worker::HEARTBEAT, move || {
+        expiration_worker::run_order_expiration_worker(worker_router.clone(), worker_tenants.clone(), saga)
+    });
 
-    // spawn Redis listener in background
-    let pool_clone = pool.clone();
-    spawn(async move {
-        let _ = listen_to_redis_events(pool_clone).await;
+    let (saga_router, tenants) = (db_router.clone(), TenantDirectory::from_env());
+    supervisor.spawn(saga_timeout_worker::HEARTBEAT, move || {
+        saga_timeout_worker::run_saga_timeout_worker(saga_router.clone(), tenants.clone(), saga)
     });
 
+    let mut readiness = Readiness::new("order-service")
+        .with_postgres(pool.clone())
+        .with_heartbeat(expiration_worker::HEARTBEAT)
+        .with_heartbeat(saga_timeout_worker::HEARTBEAT)
+        .with_supervisor(supervisor.clone());
+    if redis_url.is_some() {
+        let max_lag = config.settings.integer("STREAM_MAX_LAG").unwrap_or_default() as u64;
+        readiness = readiness
+            .with_redis(redis_client.get_ref().clone())
+            .with_consumer(redis_client.get_ref().clone(), redis_sub::GROUP, redis_sub::EVENTS, max_lag);
+    }
+    let readiness = web::Data::new(readiness);
+
+    // Supervised Redis listener
+    if redis_url.is_some() {
+        let router_clone = db_router.clone();
+        let consumer = config.settings.text("CONSUMER_NAME").to_string();
+        supervisor.spawn("stream-consumer", move || {
+            listen_to_redis_events(router_clone.clone(), redis_url.clone(), consumer.clone(), saga)
+        });
+    }
+
+    let pricing = web::Data::new(
+        pricing::Pricing::new().with_tax_rate_bps(config.settings.integer("ORDER_TAX_RATE_BPS").unwrap_or_default()),
+    );
+    let order_saga = web::Data::new(saga);
+    let db_router = web::Data::new(db_router);
+
     tracing::info!("Order Service listening on 0.0.0.0:{}", port);
 
-    HttpServer::new(move || {
+    let jwt_verifier = JwksVerifier::from_env().expect("JWT verification keys must be configured (JWKS_URL)");
+    let cursor_signer = web::Data::new(
+        CursorSigner::from_env().expect("PAGINATION_CURSOR_SECRET must be set"),
+    );
+
+    let server = HttpServer::new(move || {
         App::new()
-            .app_data(web::Data::new(pool.clone()))
+            .wrap(MetricsMiddleware)
+            .wrap(RequestId)
+            .app_data(db_router.clone())
             .app_data(redis_pub.clone())
             .app_data(redis_client.clone())
+            .app_data(pricing.clone())
+            .app_data(order_saga.clone())
+            .app_data(cursor_signer.clone())
+            .app_data(readiness.clone())
             .route("/metrics", web::get().to(metrics::metrics_handler))
-            .route("/health", web::get().to(|| async { actix_web::HttpResponse::Ok().body("OK") }))
+            .configure(health::configure)
             .service(
                 SwaggerUi::new("/swagger-ui/{_:.*}")
                     .url("/api-docs/openapi.json", ApiDoc::openapi())
             )
             .service(
                 web::scope("/api/v1")
-                    .wrap(TenantAuthMiddleware::with_redis(redis_client.get_ref().clone()))
+                    .wrap(Idempotency::new(redis_client.get_ref().clone()))
+                    .wrap(RequireScope::resource(scopes::ORDERS))
+                    // Creating an order prices every line and reserves stock; the summary
+                    // aggregates over every matching order
+                    .wrap(
+                        RateLimiter::new(redis_client.get_ref().clone())
+                            .with_route_cost(Method::POST, "/api/v1/orders", 5)
+                            .with_route_cost(Method::GET, "/api/v1/orders/summary", 5),
+                    )
+                    .wrap(TenantAuthMiddleware::with_redis(redis_client.get_ref().clone()).with_jwt_verifier(jwt_verifier.clone()))
+                    .route("/health/db", web::get().to(health::tenant_db_health))
                     .service(routes::create_order)
+                    .service(routes::list_orders)
+                    .service(routes::order_summary)
                     .service(routes::get_order)
+                    .service(routes::get_order_saga)
+                    .service(routes::get_order_history)
                     .service(routes::update_status)
                     .service(routes::delete_order)
             )
     })
     .bind(format!("0.0.0.0:{}", port))?
-    .run()
-    .await
+    .disable_signals()
+    .shutdown_timeout(supervisor.deadline().as_secs())
+    .run();
+
+    supervisor.serve(server).await
 }
 
@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json;
@@ -6,21 +8,109 @@ use uuid::Uuid;
 
 use utoipa::ToSchema;
 
+use platform::events::{OrderEvent, OrderLineItem};
+use platform::pagination::{keyset_timestamp, FieldKind, Keyset, ListSpec, SortDirection};
+
+/// An order and its totals; amounts are in cents. `lines` is loaded separately from
+/// `order_lines`.
 #[derive(Serialize, Deserialize, Debug, FromRow, ToSchema)]
 pub struct Order {
     pub id: Uuid,
     pub tenant_id: Uuid,
-    pub product_id: Uuid,
     pub user_id: Uuid,
     pub supplier_id: Uuid,
-    pub items: serde_json::Value,
-    pub qty: Option<i32>,
     pub status: OrderStatus,
+    pub subtotal_cents: i64,
+    pub tax_cents: i64,
+    pub discount_cents: i64,
+    pub total_cents: i64,
+    pub created_at: DateTime<Utc>,
     pub updated_at: Option<DateTime<Utc>>,
     pub expires_at: DateTime<Utc>,
     pub order_timestamp: DateTime<Utc>,
     pub deleted_at: Option<DateTime<Utc>>,
     pub version: i32,
+    #[sqlx(skip)]
+    #[serde(default)]
+    pub lines: Vec<OrderLine>,
+}
+
+impl Order {
+    /// Lifecycle event for this order; `product_id` and `quantity` repeat the first line.
+    pub fn event(&self) -> OrderEvent {
+        let first = self.lines.first();
+        OrderEvent {
+            tenant_id: self.tenant_id,
+            order_id: self.id,
+            product_id: first.map(|line| line.product_id).unwrap_or_default(),
+            supplier_id: self.supplier_id,
+            user_id: self.user_id,
+            quantity: first.map(|line| line.quantity),
+            lines: self.lines.iter().map(OrderLine::item).collect(),
+            total_cents: Some(self.total_cents),
+            timestamp: Utc::now(),
+            ..Default::default()
+        }
+    }
+
+    /// Sort and filter whitelist of `GET /orders` and `GET /orders/summary`.
+    pub fn list_spec() -> ListSpec {
+        ListSpec::new("created_at", SortDirection::Desc)
+            .sortable("created_at", FieldKind::Timestamp)
+            .sortable("total_cents", FieldKind::Integer)
+            .filterable("status", FieldKind::Enum)
+            .filterable("user_id", FieldKind::Uuid)
+            .filterable("supplier_id", FieldKind::Uuid)
+            .filterable_through(
+                "product_id",
+                FieldKind::Uuid,
+                "SELECT 1 FROM order_lines WHERE order_lines.order_id = orders.id",
+            )
+    }
+}
+
+impl Keyset for Order {
+    fn keyset_id(&self) -> Uuid {
+        self.id
+    }
+
+    fn keyset_value(&self, field: &str) -> Option<String> {
+        match field {
+            "created_at" => Some(keyset_timestamp(&self.created_at)),
+            "total_cents" => Some(self.total_cents.to_string()),
+            _ => None,
+        }
+    }
+}
+
+/// Number of matching orders in each status.
+#[derive(Serialize, Debug, Default, PartialEq, ToSchema)]
+pub struct OrderSummary {
+    pub total: i64,
+    pub by_status: BTreeMap<String, i64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, FromRow, ToSchema)]
+pub struct OrderLine {
+    pub line_number: i32,
+    pub product_id: Uuid,
+    pub quantity: i32,
+    pub unit_price_cents: i64,
+    pub tax_cents: i64,
+    pub discount_cents: i64,
+    pub line_total_cents: i64,
+}
+
+impl OrderLine {
+    pub fn item(&self) -> OrderLineItem {
+        OrderLineItem {
+            product_id: self.product_id,
+            quantity: self.quantity,
+            unit_price_cents: self.unit_price_cents,
+            tax_cents: self.tax_cents,
+            discount_cents: self.discount_cents,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, FromRow, ToSchema)]
@@ -30,19 +120,29 @@ pub struct OrderAuditLog {
     pub order_id: Uuid,
     pub previous_status: Option<String>,
     pub new_status: String,
+    /// `user`, `api_key`, `event`, `worker` or `tenant`; `system` for rows from before actors were recorded.
+    pub actor_type: String,
+    pub actor_id: Option<String>,
+    pub reason: Option<String>,
     pub changed_at: DateTime<Utc>,
     pub metadata: Option<serde_json::Value>,
 }
 
-// items is basically the name of whatever you ordered
+/// Prices are not accepted from the client; they are read from the catalog. Orders
+/// always start out pending, so there is no status either.
 #[derive(Serialize, Deserialize, ToSchema)]
 pub struct CreateOrderRequest {
     pub user_id: Uuid,
     pub supplier_id: Uuid,
+    pub lines: Vec<CreateOrderLine>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct CreateOrderLine {
     pub product_id: Uuid,
-    pub qty: i32,
-    pub status: Option<OrderStatus>,
-    pub items: serde_json::Value,
+    pub quantity: i32,
+    /// Discount in basis points of the line price; requires the `products:write` scope.
+    pub discount_bps: Option<i64>,
 }
 
 #[derive(Serialize, Deserialize, ToSchema)]
@@ -55,6 +155,8 @@ pub struct UpdateOrderStatus {
     pub expires_at: Option<DateTime<Utc>>,
     pub order_timestamp: Option<DateTime<Utc>>,
     pub expected_version: Option<i32>,
+    /// Recorded in the order's history with the transition.
+    pub reason: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::Type, PartialEq, Eq, ToSchema)]
@@ -71,34 +173,52 @@ pub enum OrderStatus {
     Refunded,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize, Default, ToSchema)]
-#[serde(default)]
-pub struct OrderEvent {
-    pub tenant_id: Option<Uuid>,
-    pub event_type: String,
-    pub product_id: Uuid,
-    pub supplier_id: Uuid,
-    pub name: Option<String>,
-    pub description: Option<String>,
-    pub price: Option<f64>,
-    pub category: Option<String>,
-    pub low_stock_threshold: Option<i32>,
-    pub unit: Option<String>,
-    pub quantity_change: Option<i32>,
-    pub available: Option<bool>,
-    // Order-related
-    pub order_id: Option<Uuid>,
-    pub quantity: Option<i32>,
-    pub reservation_id: Option<Uuid>,
-    pub timestamp: DateTime<Utc>,
-    pub order_timestamp: Option<DateTime<Utc>>,
-    pub expires_at: DateTime<Utc>,
-    pub user_id: Option<Uuid>,
-    pub status: Option<OrderStatus>,
-    
-    // Command routing fields
-    pub notification_channel: Option<String>,
-    pub refund_amount: Option<f64>,
+impl OrderStatus {
+    pub const ALL: [OrderStatus; 8] = [
+        OrderStatus::Pending,
+        OrderStatus::Processing,
+        OrderStatus::Confirmed,
+        OrderStatus::Shipped,
+        OrderStatus::Delivered,
+        OrderStatus::Cancelled,
+        OrderStatus::Failed,
+        OrderStatus::Refunded,
+    ];
+
+    /// Checks whether an order may move from this status to `next`. Every status change,
+    /// from a handler, an event or a worker, is checked here; staying in the same status
+    /// is not a transition.
+    pub fn can_transition_to(&self, next: &OrderStatus) -> bool {
+        use OrderStatus::*;
+        matches!(
+            (self, next),
+            (Pending, Confirmed | Failed | Cancelled | Refunded)
+                | (Confirmed, Processing | Shipped | Cancelled | Refunded)
+                | (Processing, Shipped | Cancelled | Refunded)
+                | (Shipped, Delivered | Cancelled | Refunded)
+                | (Delivered | Cancelled | Failed, Refunded)
+                // Failed and refunded orders can still be closed as cancelled
+                | (Failed | Refunded, Cancelled)
+                // Any order can be reset to pending, e.g. to retry a failed checkout
+                | (Confirmed | Processing | Shipped | Delivered | Cancelled | Failed | Refunded, Pending)
+        )
+    }
+
+    /// Statuses this one may move to, in declaration order.
+    pub fn next_statuses(&self) -> Vec<OrderStatus> {
+        Self::ALL.into_iter().filter(|next| self.can_transition_to(next)).collect()
+    }
+
+    /// The order status a logistics shipment status implies; `None` while the shipment
+    /// has not left, which changes nothing on the order.
+    pub fn for_shipment_status(status: &str) -> Option<OrderStatus> {
+        match status {
+            "intransit" => Some(OrderStatus::Shipped),
+            "delivered" => Some(OrderStatus::Delivered),
+            "cancelled" => Some(OrderStatus::Cancelled),
+            _ => None,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -113,5 +233,111 @@ mod tests {
         let status: OrderStatus = serde_json::from_str("\"shipped\"").unwrap();
         assert_eq!(status, OrderStatus::Shipped);
     }
+
+    #[test]
+    fn test_order_transitions_follow_the_lifecycle() {
+        use OrderStatus::*;
+        let allowed = [
+            (Pending, Confirmed),
+            (Pending, Failed),
+            (Pending, Cancelled),
+            (Pending, Refunded),
+            (Confirmed, Processing),
+            (Confirmed, Shipped),
+            (Confirmed, Cancelled),
+            (Confirmed, Refunded),
+            (Processing, Shipped),
+            (Processing, Cancelled),
+            (Processing, Refunded),
+            (Shipped, Delivered),
+            (Shipped, Cancelled),
+            (Shipped, Refunded),
+            (Delivered, Refunded),
+            (Cancelled, Refunded),
+            (Failed, Refunded),
+            (Failed, Cancelled),
+            (Refunded, Cancelled),
+        ];
+        for from in OrderStatus::ALL {
+            for to in OrderStatus::ALL {
+                let reset = to == Pending && from != Pending;
+                assert_eq!(
+                    from.can_transition_to(&to),
+                    reset || allowed.contains(&(from.clone(), to.clone())),
+                    "{from:?} -> {to:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_order_transition_properties_hold_for_all_pairs() {
+        let mut backwards = Vec::new();
+        let resettable = |status: &OrderStatus| *status != OrderStatus::Pending;
+        for from in OrderStatus::ALL {
+            // No status transitions to itself, and every other status can be reset to pending
+            assert!(!from.can_transition_to(&from), "{from:?} -> {from:?}");
+            assert_eq!(from.can_transition_to(&OrderStatus::Pending), resettable(&from));
+            assert_eq!(
+                from.next_statuses(),
+                OrderStatus::ALL.into_iter().filter(|to| from.can_transition_to(to)).collect::<Vec<_>>()
+            );
+            for to in OrderStatus::ALL {
+                if resettable(&from) && resettable(&to) && from.can_transition_to(&to) && to.can_transition_to(&from) {
+                    backwards.push((from.clone(), to.clone()));
+                }
+            }
+        }
+        // Apart from resets, the only way back is cancelling a refunded order
+        assert_eq!(
+            backwards,
+            vec![(OrderStatus::Cancelled, OrderStatus::Refunded), (OrderStatus::Refunded, OrderStatus::Cancelled)]
+        );
+
+        // Every status is reachable from pending, and none is a dead end
+        let mut reached = vec![OrderStatus::Pending];
+        let mut i = 0;
+        while i < reached.len() {
+            for next in reached[i].next_statuses() {
+                if !reached.contains(&next) {
+                    reached.push(next);
+                }
+            }
+            i += 1;
+        }
+        assert_eq!(reached.len(), OrderStatus::ALL.len());
+        assert!(OrderStatus::ALL.iter().all(|s| !s.next_statuses().is_empty()));
+    }
+
+    #[test]
+    fn test_shipment_statuses_map_to_order_statuses() {
+        assert_eq!(OrderStatus::for_shipment_status("pending"), None);
+        assert_eq!(OrderStatus::for_shipment_status("intransit"), Some(OrderStatus::Shipped));
+        assert_eq!(OrderStatus::for_shipment_status("delivered"), Some(OrderStatus::Delivered));
+        assert_eq!(OrderStatus::for_shipment_status("cancelled"), Some(OrderStatus::Cancelled));
+    }
+
+    #[test]
+    fn test_order_list_filters_products_through_lines() {
+        use platform::pagination::CursorSigner;
+        use sqlx::{Postgres, QueryBuilder};
+
+        let product_id = Uuid::new_v4().to_string();
+        let query = [
+            ("filter[product_id]".to_string(), product_id),
+            ("filter[created_at][gte]".to_string(), "2026-01-01T00:00:00Z".to_string()),
+        ]
+        .into_iter()
+        .collect();
+        let page = Order::list_spec().parse(&query, &CursorSigner::new("secret")).unwrap();
+
+        let mut builder = QueryBuilder::<Postgres>::new("SELECT * FROM orders WHERE TRUE");
+        page.push_filters(&mut builder);
+        assert_eq!(
+            builder.sql(),
+            "SELECT * FROM orders WHERE TRUE AND created_at >= $1::timestamptz AND EXISTS \
+             (SELECT 1 FROM order_lines WHERE order_lines.order_id = orders.id AND product_id = $2::uuid)"
+        );
+    }
 }
 
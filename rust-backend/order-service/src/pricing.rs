@@ -0,0 +1,301 @@
+// src/pricing.rs
+// Prices order lines server-side from the catalog projection kept by redis_sub.
+
+use std::collections::HashMap;
+
+use platform::errors::AppError;
+use platform::events::ProductEvent;
+use platform::tenant::TenantTx;
+use sqlx::FromRow;
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::models::{CreateOrderLine, OrderLine};
+
+const BPS: i64 = 10_000;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum PricingError {
+    #[error("an order needs at least one line")]
+    NoLines,
+    #[error("line {0}: quantity must be positive")]
+    InvalidQuantity(i32),
+    #[error("line {0}: discount must be between 0 and 10000 basis points")]
+    InvalidDiscount(i32),
+    #[error("product {0} is not in the catalog")]
+    UnknownProduct(Uuid),
+    #[error("product {0} is not available")]
+    Unavailable(Uuid),
+    #[error("product {0} is not sold by this supplier")]
+    WrongSupplier(Uuid),
+}
+
+impl From<PricingError> for AppError {
+    fn from(e: PricingError) -> Self {
+        AppError::Unprocessable(e.to_string())
+    }
+}
+
+/// A product as order-service last saw it on `stream:products`.
+#[derive(Debug, Clone, FromRow)]
+pub struct CatalogProduct {
+    pub product_id: Uuid,
+    pub supplier_id: Uuid,
+    pub unit_price_cents: i64,
+    pub available: bool,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct PricedOrder {
+    pub lines: Vec<OrderLine>,
+    pub subtotal_cents: i64,
+    pub tax_cents: i64,
+    pub discount_cents: i64,
+    pub total_cents: i64,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Pricing {
+    tax_rate_bps: i64,
+}
+
+impl Pricing {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Tax charged on each line after its discount, in basis points (`ORDER_TAX_RATE_BPS`).
+    pub fn with_tax_rate_bps(mut self, bps: i64) -> Self {
+        self.tax_rate_bps = bps;
+        self
+    }
+
+    /// Prices `lines` for an order from `supplier_id`. Every product must be in the
+    /// catalog, available and sold by that supplier.
+    pub fn price(
+        &self,
+        supplier_id: Uuid,
+        lines: &[CreateOrderLine],
+        catalog: &HashMap<Uuid, CatalogProduct>,
+    ) -> Result<PricedOrder, PricingError> {
+        if lines.is_empty() {
+            return Err(PricingError::NoLines);
+        }
+
+        let mut priced = Vec::with_capacity(lines.len());
+        for (line_number, line) in (1..).zip(lines) {
+            if line.quantity <= 0 {
+                return Err(PricingError::InvalidQuantity(line_number));
+            }
+            let discount_bps = line.discount_bps.unwrap_or(0);
+            if !(0..=BPS).contains(&discount_bps) {
+                return Err(PricingError::InvalidDiscount(line_number));
+            }
+            let product = catalog.get(&line.product_id).ok_or(PricingError::UnknownProduct(line.product_id))?;
+            if product.supplier_id != supplier_id {
+                return Err(PricingError::WrongSupplier(line.product_id));
+            }
+            if !product.available {
+                return Err(PricingError::Unavailable(line.product_id));
+            }
+
+            let gross = product.unit_price_cents * i64::from(line.quantity);
+            let discount_cents = apply_bps(gross, discount_bps);
+            let tax_cents = apply_bps(gross - discount_cents, self.tax_rate_bps);
+            priced.push(OrderLine {
+                line_number,
+                product_id: line.product_id,
+                quantity: line.quantity,
+                unit_price_cents: product.unit_price_cents,
+                tax_cents,
+                discount_cents,
+                line_total_cents: gross - discount_cents + tax_cents,
+            });
+        }
+
+        Ok(PricedOrder {
+            subtotal_cents: priced.iter().map(|l| l.unit_price_cents * i64::from(l.quantity)).sum(),
+            tax_cents: priced.iter().map(|l| l.tax_cents).sum(),
+            discount_cents: priced.iter().map(|l| l.discount_cents).sum(),
+            total_cents: priced.iter().map(|l| l.line_total_cents).sum(),
+            lines: priced,
+        })
+    }
+}
+
+/// `amount * bps / 10000`, rounded half up to the cent.
+fn apply_bps(amount: i64, bps: i64) -> i64 {
+    (amount * bps + BPS / 2) / BPS
+}
+
+pub fn price_to_cents(price: f64) -> i64 {
+    (price * 100.0).round() as i64
+}
+
+pub async fn load_catalog(
+    tx: &mut TenantTx<'_>,
+    product_ids: &[Uuid],
+) -> Result<HashMap<Uuid, CatalogProduct>, sqlx::Error> {
+    let rows = sqlx::query_as::<_, CatalogProduct>(
+        r#"
+            SELECT product_id, supplier_id, unit_price_cents, available
+            FROM catalog_products
+            WHERE product_id = ANY($1)
+        "#,
+    )
+    .bind(product_ids)
+    .fetch_all(tx.executor())
+    .await?;
+    Ok(rows.into_iter().map(|p| (p.product_id, p)).collect())
+}
+
+/// Applies a `product.created` or `product.updated` event to the projection. Fields
+/// the event leaves out keep their previous values. A product is only projected once an
+/// event carries its price, and an event older than the one last applied is ignored so
+/// a late redelivery cannot roll the price back.
+pub async fn upsert_catalog_product(tx: &mut TenantTx<'_>, event: &ProductEvent) -> Result<(), sqlx::Error> {
+    let query = match event.price {
+        Some(_) => sqlx::query(
+            r#"
+                INSERT INTO catalog_products (tenant_id, product_id, supplier_id, name, unit_price_cents, available, source_updated_at, updated_at)
+                VALUES ($1, $2, $3, $4, $5, COALESCE($6, TRUE), $7, NOW())
+                ON CONFLICT (tenant_id, product_id) DO UPDATE SET
+                    supplier_id = EXCLUDED.supplier_id,
+                    name = COALESCE($4, catalog_products.name),
+                    unit_price_cents = $5,
+                    available = COALESCE($6, catalog_products.available),
+                    source_updated_at = $7,
+                    updated_at = NOW()
+                WHERE catalog_products.source_updated_at IS NULL OR catalog_products.source_updated_at <= $7
+            "#,
+        ),
+        // Without a price there is nothing to charge, so an unknown product stays unknown
+        None => sqlx::query(
+            r#"
+                UPDATE catalog_products SET
+                    supplier_id = $3,
+                    name = COALESCE($4, name),
+                    available = COALESCE($6, available),
+                    source_updated_at = $7,
+                    updated_at = NOW()
+                WHERE tenant_id = $1 AND product_id = $2
+                  AND (source_updated_at IS NULL OR source_updated_at <= $7)
+            "#,
+        ),
+    };
+    query
+        .bind(event.tenant_id)
+        .bind(event.product_id)
+        .bind(event.supplier_id)
+        .bind(&event.name)
+        .bind(event.price.map(price_to_cents))
+        .bind(event.available)
+        .bind(event.timestamp)
+        .execute(tx.executor())
+        .await?;
+    Ok(())
+}
+
+pub async fn delete_catalog_product(tx: &mut TenantTx<'_>, product_id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM catalog_products WHERE product_id = $1")
+        .bind(product_id)
+        .execute(tx.executor())
+        .await?;
+    Ok(())
+}
+
+/// Seeds the projection from product-catalog's `products` table, which shares the tenant's
+/// database, for products created before order-service consumed `stream:products`. Rows
+/// already projected from events win, so running it again changes nothing.
+pub async fn backfill_catalog(tx: &mut TenantTx<'_>) -> Result<u64, sqlx::Error> {
+    let has_products: bool = sqlx::query_scalar("SELECT to_regclass('products') IS NOT NULL")
+        .fetch_one(tx.executor())
+        .await?;
+    if !has_products {
+        return Ok(0);
+    }
+    let result = sqlx::query(
+        r#"
+            INSERT INTO catalog_products (tenant_id, product_id, supplier_id, name, unit_price_cents, available, updated_at)
+            SELECT tenant_id, product_id, supplier_id, name, ROUND(price * 100)::BIGINT, available, NOW()
+            FROM products
+            WHERE deleted_at IS NULL
+            ON CONFLICT (tenant_id, product_id) DO NOTHING
+        "#,
+    )
+    .execute(tx.executor())
+    .await?;
+    Ok(result.rows_affected())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn catalog(supplier_id: Uuid, products: &[(Uuid, i64, bool)]) -> HashMap<Uuid, CatalogProduct> {
+        products
+            .iter()
+            .map(|&(product_id, unit_price_cents, available)| {
+                (product_id, CatalogProduct { product_id, supplier_id, unit_price_cents, available })
+            })
+            .collect()
+    }
+
+    fn line(product_id: Uuid, quantity: i32, discount_bps: Option<i64>) -> CreateOrderLine {
+        CreateOrderLine { product_id, quantity, discount_bps }
+    }
+
+    #[test]
+    fn test_lines_are_priced_from_the_catalog() {
+        let supplier_id = Uuid::new_v4();
+        let (bolts, nuts) = (Uuid::new_v4(), Uuid::new_v4());
+        let catalog = catalog(supplier_id, &[(bolts, 1_999, true), (nuts, 250, true)]);
+        let pricing = Pricing::new().with_tax_rate_bps(800);
+
+        let order = pricing
+            .price(supplier_id, &[line(bolts, 3, None), line(nuts, 10, Some(1_000))], &catalog)
+            .unwrap();
+
+        assert_eq!(order.lines[0].line_number, 1);
+        assert_eq!(order.lines[0].unit_price_cents, 1_999);
+        assert_eq!(order.lines[0].tax_cents, 480); // 8% of 59.97, rounded
+        assert_eq!(order.lines[0].line_total_cents, 6_477);
+        assert_eq!(order.lines[1].discount_cents, 250);
+        assert_eq!(order.lines[1].tax_cents, 180);
+        assert_eq!(order.lines[1].line_total_cents, 2_430);
+        assert_eq!(order.subtotal_cents, 5_997 + 2_500);
+        assert_eq!(order.discount_cents, 250);
+        assert_eq!(order.tax_cents, 660);
+        assert_eq!(order.total_cents, order.subtotal_cents - order.discount_cents + order.tax_cents);
+    }
+
+    #[test]
+    fn test_invalid_lines_are_rejected() {
+        let supplier_id = Uuid::new_v4();
+        let (listed, hidden, unknown) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let catalog = catalog(supplier_id, &[(listed, 100, true), (hidden, 100, false)]);
+        let pricing = Pricing::new();
+
+        assert_eq!(pricing.price(supplier_id, &[], &catalog), Err(PricingError::NoLines));
+        assert_eq!(
+            pricing.price(supplier_id, &[line(listed, 1, None), line(listed, 0, None)], &catalog),
+            Err(PricingError::InvalidQuantity(2))
+        );
+        assert_eq!(
+            pricing.price(supplier_id, &[line(listed, 1, Some(10_001))], &catalog),
+            Err(PricingError::InvalidDiscount(1))
+        );
+        assert_eq!(
+            pricing.price(supplier_id, &[line(unknown, 1, None)], &catalog),
+            Err(PricingError::UnknownProduct(unknown))
+        );
+        assert_eq!(
+            pricing.price(supplier_id, &[line(hidden, 1, None)], &catalog),
+            Err(PricingError::Unavailable(hidden))
+        );
+        assert_eq!(
+            pricing.price(Uuid::new_v4(), &[line(listed, 1, None)], &catalog),
+            Err(PricingError::WrongSupplier(listed))
+        );
+    }
+}
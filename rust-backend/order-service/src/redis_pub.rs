@@ -1,3 +1,4 @@
+use platform::events::DomainEvent;
 use platform::streams::StreamPublisher;
 
 #[derive(Clone)]
@@ -13,22 +14,11 @@ impl RedisPublisher {
     }
 
     #[allow(dead_code)] // public API; available for callers that need an awaitable publish
-    pub async fn publish<T: serde::Serialize>(
-        &self,
-        event_type: &str,
-        message: &T,
-    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        self.publisher.publish(event_type, message).await?;
+    pub async fn publish(&self, event: &DomainEvent) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.publisher.publish_event(event).await?;
         Ok(())
     }
 
-    pub fn publish_async<T>(&self, event_type: &str, message: T)
-    where
-        T: serde::Serialize + Send + Sync + 'static,
-    {
-        self.publisher.publish_async(event_type, message);
-    }
-
     pub fn new_noop() -> Self {
         Self {
             publisher: StreamPublisher::noop(),
@@ -1,57 +1,65 @@
 // src/redis_sub.rs
-// Consumes workflow events from Redis Streams and updates order state.
-
-use crate::models::{OrderEvent, OrderStatus};
-use crate::redis_pub::RedisPublisher;
+// Consumes workflow events from Redis Streams, advances order sagas and updates order state.
+
+use crate::audit::{Actor, Cause};
+use crate::db::{OrderGuard, TransitionError};
+use crate::models::OrderStatus;
+use crate::pricing;
+use crate::saga::{Saga, SagaStep};
+use platform::db_router::DynamicPoolRouter;
+use platform::events::{DomainEvent, ShipmentEvent};
 use platform::metrics;
+use platform::outbox;
 use platform::streams;
-use serde_json::Value;
-use sqlx::PgPool;
-use std::env;
+use platform::tenant::TenantTx;
 
 mod events;
 use events::{
-    update_order_cancelled_event, update_order_confirmed_event, update_order_delivered_event,
+    follow_up_event, update_order_cancelled_event, update_order_confirmed_event, update_order_delivered_event,
     update_order_failed_event, update_order_shipped_event,
 };
 
-const EVENTS: &[&str] = &[
+/// Consumer group this service reads its streams with.
+pub const GROUP: &str = "order-service";
+
+pub const EVENTS: &[&str] = &[
     "inventory.rejected",
     "inventory.reservation_expired",
     "inventory.reserved",
     "inventory.expired",
     "inventory.released",
     "inventory.finalized",
+    "payment.success",
+    "payment.failed",
+    "payment.cancelled",
     "order.delivered",
     "logistics.shipment_created",
     "logistics.shipment_updated",
     "logistics.shipment_cancelled",
+    "product.created",
+    "product.updated",
+    "product.deleted",
 ];
 
-pub async fn listen_to_redis_events(pool: PgPool) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let redis_url = env::var("REDIS_URL").map_err(|_| "REDIS_URL must be set in environment")?;
-    let consumer = env::var("CONSUMER_NAME").unwrap_or_else(|_| "order-service-1".to_string());
-    
-    let redis_pub = RedisPublisher::new(&redis_url)
-        .await
-        .map_err(|e| format!("Failed to create RedisPublisher: {}", e))?;
+pub async fn listen_to_redis_events(
+    db_router: DynamicPoolRouter,
+    redis_url: Option<String>,
+    consumer: String,
+    saga: Saga,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let redis_url = redis_url.ok_or("REDIS_URL must be set in environment")?;
 
-    streams::consume_json::<Value, _, _>(
+    streams::consume_json::<DomainEvent, _, _>(
         &redis_url,
-        "order-service",
+        GROUP,
         &consumer,
         EVENTS,
         move |envelope| {
-            let pool = pool.clone();
-            let redis_pub = redis_pub.clone();
+            let db_router = db_router.clone();
             async move {
                 let event_type = envelope.event_type.clone();
 
-                let payload_tenant_id = envelope
-                    .payload
-                    .get("tenant_id")
-                    .and_then(|v| v.as_str())
-                    .and_then(|s| uuid::Uuid::parse_str(s).ok());
+                let payload_tenant_id = Some(envelope.payload.tenant_id());
                 let tenant_id = envelope.tenant_id.or(payload_tenant_id);
 
                 if tenant_id.is_none() || tenant_id == Some(uuid::Uuid::nil()) {
@@ -68,7 +76,8 @@ pub async fn listen_to_redis_events(pool: PgPool) -> Result<(), Box<dyn std::err
                     }
                 }
 
-                let result = handle_event(&pool, &redis_pub, &event_type, envelope.payload).await;
+                let cause = Cause::new(Actor::Event(envelope.id.clone())).with_reason(event_type.clone());
+                let result = handle_event(&db_router, &saga, &cause, envelope.payload).await;
                 metrics::inc_event(
                     "order-service",
                     &envelope.stream,
@@ -87,90 +96,74 @@ pub async fn listen_to_redis_events(pool: PgPool) -> Result<(), Box<dyn std::err
 }
 
 async fn handle_event(
-    pool: &PgPool,
-    redis_pub: &RedisPublisher,
-    event_type: &str,
-    payload: Value,
+    db_router: &DynamicPoolRouter,
+    saga: &Saga,
+    cause: &Cause,
+    event: DomainEvent,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    if event_type.starts_with("logistics.") {
-        return handle_logistics_event(pool, redis_pub, event_type, payload).await;
+    let event_type = event.event_type();
+    let tenant_id = event.tenant_id();
+    let pool = &db_router.get_tenant_pool(tenant_id).await?;
+
+    // The saga records the step and issues any compensation before the order status
+    // follows, and both commit together
+    let mut tx = TenantTx::begin(pool, tenant_id).await?;
+    if let Some((order_id, step)) = SagaStep::from_event(&event) {
+        saga.advance(&mut tx, order_id, step).await?;
     }
 
-    let event: OrderEvent = match serde_json::from_value(payload) {
-        Ok(event) => event,
-        Err(_) => return Ok(()),
-    };
-
-    match event_type {
-        "inventory.rejected" => update_order_failed_event(pool, redis_pub, event).await,
-        "inventory.reservation_expired" | "inventory.expired" | "inventory.released" => {
-            update_order_cancelled_event(pool, redis_pub, event).await
+    match event {
+        DomainEvent::InventoryRejected(event) => {
+            update_order_failed_event(&mut tx, event_type, event.order_id, cause).await?
+        }
+        DomainEvent::InventoryReservationExpired(event)
+        | DomainEvent::InventoryExpired(event)
+        | DomainEvent::InventoryReleased(event) => {
+            update_order_cancelled_event(&mut tx, event_type, event.order_id, cause).await?
+        }
+        DomainEvent::InventoryReserved(event) => {
+            update_order_confirmed_event(&mut tx, event_type, event.order_id, cause).await?
+        }
+        DomainEvent::InventoryFinalized(event) => {
+            update_order_shipped_event(&mut tx, event_type, event.order_id, cause).await?
+        }
+        DomainEvent::OrderDelivered(event) => {
+            update_order_delivered_event(&mut tx, event_type, Some(event.order_id), cause).await?
         }
-        "inventory.reserved" => update_order_confirmed_event(pool, redis_pub, event).await,
-        "inventory.finalized" => update_order_shipped_event(pool, redis_pub, event).await,
-        "order.delivered" => update_order_delivered_event(pool, redis_pub, event).await,
-        _ => Ok(()),
+        DomainEvent::ShipmentCreated(event)
+        | DomainEvent::ShipmentUpdated(event)
+        | DomainEvent::ShipmentCancelled(event) => handle_logistics_event(&mut tx, event, cause).await?,
+        // Keep the catalogue projection that order lines are priced from current
+        DomainEvent::ProductCreated(event) | DomainEvent::ProductUpdated(event) => {
+            pricing::upsert_catalog_product(&mut tx, &event).await?
+        }
+        DomainEvent::ProductDeleted(event) => pricing::delete_catalog_product(&mut tx, event.product_id).await?,
+        _ => {}
     }
+    tx.commit().await?;
+    Ok(())
 }
 
+/// Moves the order to the status its shipment implies, when the order's lifecycle allows it.
 async fn handle_logistics_event(
-    pool: &PgPool,
-    redis_pub: &RedisPublisher,
-    event_type: &str,
-    value: Value,
+    tx: &mut TenantTx<'_>,
+    event: ShipmentEvent,
+    cause: &Cause,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let order_id = value
-        .get("order_id")
-        .and_then(|v| v.as_str())
-        .and_then(|v| uuid::Uuid::parse_str(v).ok());
-
-    let Some(order_id) = order_id else {
-        return Ok(());
-    };
-
-    let status = match event_type {
-        "logistics.shipment_created" => Some(OrderStatus::Confirmed), // Optional mapping
-        "logistics.shipment_cancelled" => Some(OrderStatus::Cancelled),
-        "logistics.shipment_updated" => match value.get("status").and_then(|v| v.as_str()) {
-            Some("intransit") => Some(OrderStatus::Shipped),
-            Some("delivered") => Some(OrderStatus::Delivered),
-            Some("cancelled") => Some(OrderStatus::Cancelled),
-            _ => None,
-        },
-        _ => None,
-    };
-
-    if let Some(status) = status {
-        if let Ok(order) = crate::db::update_order_status_db(pool, order_id, status.clone(), None, None, None).await {
-            // Replicate side effects
-            if status == OrderStatus::Cancelled {
-                let cancel_event = OrderEvent {
-                    event_type: "order.cancelled".to_string(),
-                    product_id: order.product_id,
-                    supplier_id: order.supplier_id,
-                    order_id: Some(order.id),
-                    quantity: order.qty,
-                    user_id: Some(order.user_id),
-                    timestamp: order.order_timestamp,
-                    ..Default::default()
-                };
-                redis_pub.publish_async("order.cancelled", cancel_event);
-            } else if status == OrderStatus::Shipped {
-                let shipped_event = OrderEvent {
-                    event_type: "order.shipped".to_string(),
-                    product_id: order.product_id,
-                    supplier_id: order.supplier_id,
-                    order_id: Some(order.id),
-                    quantity: order.qty,
-                    user_id: Some(order.user_id),
-                    timestamp: order.order_timestamp,
-                    ..Default::default()
-                };
-                redis_pub.publish_async("order.shipped", shipped_event);
-            }
+    if let Some(status) = OrderStatus::for_shipment_status(&event.status) {
+        let order = match crate::db::update_order_status_db(tx, event.order_id, status.clone(), OrderGuard::default(), None, None, cause).await {
+            Ok(order) => order,
+            // Already moved there, e.g. by order.delivered; a repeated shipment status changes nothing
+            Err(TransitionError::Illegal { current, requested }) if current == requested => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+        // Replicate side effects
+        if status == OrderStatus::Cancelled {
+            outbox::enqueue_event(tx.executor(), &DomainEvent::OrderCancelled(follow_up_event(&order))).await?;
+        } else if status == OrderStatus::Shipped {
+            outbox::enqueue_event(tx.executor(), &DomainEvent::OrderShipped(follow_up_event(&order))).await?;
         }
     }
 
     Ok(())
 }
-
@@ -1,95 +1,91 @@
-use crate::models::OrderEvent;
-use crate::redis_pub::RedisPublisher;
-use sqlx::PgPool;
+use crate::audit::Cause;
+use crate::db::{OrderGuard, TransitionError};
+use crate::models::{Order, OrderStatus};
+use platform::events::{DomainEvent, EventType, OrderEvent};
+use platform::outbox;
+use platform::tenant::TenantTx;
+use uuid::Uuid;
+
+/// Moves the order to `status` in the consumer's transaction. Transitions the order's
+/// lifecycle no longer allows are logged and skipped; database errors are returned so the
+/// saga step recorded in the same transaction rolls back with them.
+async fn update_status(
+    tx: &mut TenantTx<'_>,
+    event_type: EventType,
+    order_id: Option<Uuid>,
+    status: OrderStatus,
+    cause: &Cause,
+) -> Result<Option<Order>, Box<dyn std::error::Error + Send + Sync>> {
+    let order_id = order_id.ok_or("No order_id found")?;
+    match crate::db::update_order_status_db(tx, order_id, status, OrderGuard::default(), None, None, cause).await {
+        Ok(order) => {
+            println!("🔁({}) Updated order {:?} via DB", event_type, order_id);
+            Ok(Some(order))
+        }
+        Err(TransitionError::Database(e)) => Err(e.into()),
+        Err(e) => {
+            eprintln!("❌ Failed to update order status: {:?}", e);
+            Ok(None)
+        }
+    }
+}
 
 pub async fn update_order_failed_event(
-    pool: &PgPool,
-    _redis_pub: &RedisPublisher, // Not emitting another event for failed here
-    event: OrderEvent,
+    tx: &mut TenantTx<'_>,
+    event_type: EventType,
+    order_id: Option<Uuid>,
+    cause: &Cause,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let order_id = event.order_id.ok_or("No order_id found")?;
-    match crate::db::update_order_status_db(pool, order_id, crate::models::OrderStatus::Failed, None, None, None).await {
-        Ok(_) => println!("🔁({}) Updated order {:?} via DB", event.event_type, order_id),
-        Err(e) => eprintln!("❌ Failed to update order status: {:?}", e),
-    }
+    // Not emitting another event for failed here
+    update_status(tx, event_type, order_id, OrderStatus::Failed, cause).await?;
     Ok(())
 }
 
 pub async fn update_order_confirmed_event(
-    pool: &PgPool,
-    _redis_pub: &RedisPublisher,
-    event: OrderEvent,
+    tx: &mut TenantTx<'_>,
+    event_type: EventType,
+    order_id: Option<Uuid>,
+    cause: &Cause,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let order_id = event.order_id.ok_or("No order_id found")?;
-    match crate::db::update_order_status_db(pool, order_id, crate::models::OrderStatus::Confirmed, None, None, None).await {
-        Ok(_) => println!("🔁({}) Updated order {:?} via DB", event.event_type, order_id),
-        Err(e) => eprintln!("❌ Failed to update order status: {:?}", e),
-    }
+    update_status(tx, event_type, order_id, OrderStatus::Confirmed, cause).await?;
     Ok(())
 }
 
 pub async fn update_order_cancelled_event(
-    pool: &PgPool,
-    redis_pub: &RedisPublisher,
-    event: OrderEvent,
+    tx: &mut TenantTx<'_>,
+    event_type: EventType,
+    order_id: Option<Uuid>,
+    cause: &Cause,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let order_id = event.order_id.ok_or("No order_id found")?;
-    match crate::db::update_order_status_db(pool, order_id, crate::models::OrderStatus::Cancelled, None, None, None).await {
-        Ok(order) => {
-            println!("🔁({}) Updated order {:?} via DB", event.event_type, order_id);
-            let cancel_event = OrderEvent {
-                event_type: "order.cancelled".to_string(),
-                product_id: order.product_id,
-                supplier_id: order.supplier_id,
-                order_id: Some(order.id),
-                quantity: order.qty,
-                user_id: Some(order.user_id),
-                timestamp: order.order_timestamp,
-                ..Default::default()
-            };
-            redis_pub.publish_async("order.cancelled", cancel_event);
-        },
-        Err(e) => eprintln!("❌ Failed to update order status: {:?}", e),
+    if let Some(order) = update_status(tx, event_type, order_id, OrderStatus::Cancelled, cause).await? {
+        outbox::enqueue_event(tx.executor(), &DomainEvent::OrderCancelled(follow_up_event(&order))).await?;
     }
     Ok(())
 }
 
 pub async fn update_order_shipped_event(
-    pool: &PgPool,
-    redis_pub: &RedisPublisher,
-    event: OrderEvent,
+    tx: &mut TenantTx<'_>,
+    event_type: EventType,
+    order_id: Option<Uuid>,
+    cause: &Cause,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let order_id = event.order_id.ok_or("No order_id found")?;
-    match crate::db::update_order_status_db(pool, order_id, crate::models::OrderStatus::Shipped, None, None, None).await {
-        Ok(order) => {
-            println!("🔁({}) Updated order {:?} via DB", event.event_type, order_id);
-            let shipped_event = OrderEvent {
-                event_type: "order.shipped".to_string(),
-                product_id: order.product_id,
-                supplier_id: order.supplier_id,
-                order_id: Some(order.id),
-                quantity: order.qty,
-                user_id: Some(order.user_id),
-                timestamp: order.order_timestamp,
-                ..Default::default()
-            };
-            redis_pub.publish_async("order.shipped", shipped_event);
-        },
-        Err(e) => eprintln!("❌ Failed to update order status: {:?}", e),
+    if let Some(order) = update_status(tx, event_type, order_id, OrderStatus::Shipped, cause).await? {
+        outbox::enqueue_event(tx.executor(), &DomainEvent::OrderShipped(follow_up_event(&order))).await?;
     }
     Ok(())
 }
 
 pub async fn update_order_delivered_event(
-    pool: &PgPool,
-    _redis_pub: &RedisPublisher,
-    event: OrderEvent,
+    tx: &mut TenantTx<'_>,
+    event_type: EventType,
+    order_id: Option<Uuid>,
+    cause: &Cause,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let order_id = event.order_id.ok_or("No order_id found")?;
-    match crate::db::update_order_status_db(pool, order_id, crate::models::OrderStatus::Delivered, None, None, None).await {
-        Ok(_) => println!("🔁({}) Updated order {:?} via DB", event.event_type, order_id),
-        Err(e) => eprintln!("❌ Failed to update order status: {:?}", e),
-    }
+    update_status(tx, event_type, order_id, OrderStatus::Delivered, cause).await?;
     Ok(())
 }
 
+/// Order snapshot republished after an event-driven status change.
+pub(super) fn follow_up_event(order: &Order) -> OrderEvent {
+    order.event()
+}
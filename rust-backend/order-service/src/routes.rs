@@ -1,13 +1,24 @@
-use actix_web::{delete, get, post, put, web, HttpResponse};
+use std::collections::HashMap;
+
+use actix_web::http::StatusCode;
+use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse, ResponseError};
 use chrono::{Duration, Utc};
-use serde_json::json;
-use sqlx::PgPool;
 use uuid::Uuid;
 
+use crate::audit::{self, Actor, Cause};
+use crate::db::{self, OrderGuard, TransitionError};
+use crate::pricing::{self, Pricing};
 use crate::redis_pub::RedisPublisher;
+use crate::saga::{self, Saga, SagaStep};
+use platform::db_router::DynamicPoolRouter;
+use platform::errors::{AppError, Problem};
+use platform::events::{DomainEvent, OrderEvent};
+use platform::outbox;
+use platform::pagination::CursorSigner;
+use platform::scopes;
 use platform::tenant::TenantContext;
 
-use crate::models::{CreateOrderRequest, Order, OrderEvent, OrderStatus, UpdateOrderStatus};
+use crate::models::{CreateOrderRequest, Order, OrderStatus, UpdateOrderStatus};
 
 #[utoipa::path(
     post,
@@ -15,6 +26,8 @@ use crate::models::{CreateOrderRequest, Order, OrderEvent, OrderStatus, UpdateOr
     request_body = CreateOrderRequest,
     responses(
         (status = 201, description = "Order created successfully", body = Order),
+        (status = 403, description = "Line discounts require the products:write scope"),
+        (status = 422, description = "Empty order, or a product that is unknown, unavailable or from another supplier"),
         (status = 500, description = "Internal server error")
     ),
     security(
@@ -25,89 +38,184 @@ use crate::models::{CreateOrderRequest, Order, OrderEvent, OrderStatus, UpdateOr
 #[post("/orders")]
 pub async fn create_order(
     tenant: web::ReqData<TenantContext>,
-    pool: web::Data<PgPool>,
-    redis_pub: web::Data<RedisPublisher>,
+    db_router: web::Data<DynamicPoolRouter>,
+    pricing: web::Data<Pricing>,
+    saga: web::Data<Saga>,
     req: web::Json<CreateOrderRequest>,
 ) -> HttpResponse {
+    // Discounts change what the supplier is paid, so only catalogue managers may set them
+    if req.lines.iter().any(|line| line.discount_bps.is_some()) {
+        if let Err(e) = tenant.require_scope(scopes::PRODUCTS_WRITE) {
+            return e.error_response();
+        }
+    }
+
     let order_id = Uuid::new_v4();
-    let status = req.status.clone().unwrap_or(OrderStatus::Pending);
     let order_timestamp = Utc::now();
 
     // adjust timing, configurable to add flexibility for when the customer is able to pay
     let expires_at = Utc::now() + Duration::seconds(2 * 24 * 60 * 60);
 
-    let mut tx = match pool.begin().await {
+    let pool = match db_router.get_pool(&tenant).await {
+        Ok(pool) => pool,
+        Err(e) => return DynamicPoolRouter::unavailable(e).error_response(),
+    };
+    let mut tx = match tenant.begin(&pool).await {
         Ok(t) => t,
-        Err(_) => return HttpResponse::InternalServerError().json(json!({"error": "Failed to start transaction"})),
+        Err(e) => return AppError::from(e).error_response(),
     };
 
-    if let Err(_) = tenant.apply_rls(&mut *tx).await {
-        return HttpResponse::InternalServerError().json(json!({"error": "Failed to apply RLS"}));
-    }
+    // Totals are computed here from the catalogue, never taken from the client
+    let product_ids: Vec<Uuid> = req.lines.iter().map(|line| line.product_id).collect();
+    let catalog = match pricing::load_catalog(&mut tx, &product_ids).await {
+        Ok(catalog) => catalog,
+        Err(e) => return AppError::from(e).error_response(),
+    };
+    let priced = match pricing.price(req.supplier_id, &req.lines, &catalog) {
+        Ok(priced) => priced,
+        Err(e) => return AppError::from(e).error_response(),
+    };
 
     let result = sqlx::query_as::<_, Order>(
         r#"
-            INSERT INTO orders (id, user_id, supplier_id, product_id, items, qty, status, expires_at, order_timestamp, version, tenant_id)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, 1, $10)
+            INSERT INTO orders (id, user_id, supplier_id, status, subtotal_cents, tax_cents, discount_cents, total_cents, expires_at, order_timestamp, version, tenant_id)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, 1, $11)
             RETURNING *
         "#
     )
     .bind(&order_id)
     .bind(req.user_id)
     .bind(req.supplier_id)
-    .bind(req.product_id)
-    .bind(&req.items)
-    .bind(req.qty)
-    .bind(status)
+    .bind(OrderStatus::Pending)
+    .bind(priced.subtotal_cents)
+    .bind(priced.tax_cents)
+    .bind(priced.discount_cents)
+    .bind(priced.total_cents)
     .bind(expires_at)
     .bind(order_timestamp)
     .bind(tenant.tenant_id)
-    .fetch_one(&mut *tx)
+    .fetch_one(tx.executor())
     .await;
 
-    let _ = tx.commit().await;
-
     match result {
-        Ok(order) => {
-            let event = OrderEvent {
-                tenant_id: Some(order.tenant_id),
-                event_type: "order.created".to_string(),
-                product_id: order.product_id,
-                supplier_id: order.supplier_id,
-
-                // Product-related fields (None since this event is order-based, their implementation is in product catalog)
-                // name: None,
-                // description: None,
-                // price: None,
-                // category: None,
-                // low_stock_threshold: None,
-                // unit: None,
-                // quantity_change: None,
-                // available: None,
-
-                // Order-related fields
-                order_id: Some(order.id),
-                quantity: order.qty,
-                reservation_id: None,
-                user_id: Some(order.user_id),
-                expires_at: order.expires_at,
-
-                // Add order_timestamp for event ordering
+        Ok(mut order) => {
+            if let Err(e) = db::insert_order_lines(&mut tx, order.id, &priced.lines).await {
+                return AppError::from(e).error_response();
+            }
+            order.lines = priced.lines;
+            if let Err(e) = saga.start(&mut tx, &order).await {
+                return AppError::from(e).error_response();
+            }
+
+            let event = DomainEvent::OrderCreated(OrderEvent {
+                expires_at: Some(order.expires_at),
+                // order_timestamp keeps event ordering aligned with the order row
                 timestamp: order.order_timestamp,
+                ..order.event()
+            });
 
-                ..Default::default()
-            };
-            redis_pub.publish_async("order.created", event.clone());
+            // Enqueued in the same transaction so the order and its event commit together
+            if let Err(e) = outbox::enqueue_event(tx.executor(), &event).await {
+                return AppError::from(e).error_response();
+            }
+            if let Err(e) = tx.commit().await {
+                return AppError::from(e).error_response();
+            }
 
             HttpResponse::Created().json(serde_json::json!({
                 "message": "Order successfully created",
                 "id": order,
             }))
         }
-        Err(err) => {
-            eprintln!("Error creating order: {}", err);
-            HttpResponse::InternalServerError().json(json!({"error": "Failed to create order"}))
+        Err(err) => AppError::from(err).error_response(),
+    }
+}
+
+/// Returns one page of the tenant's orders, sorted and filtered by the shared grammar.
+#[utoipa::path(
+    get,
+    path = "/api/v1/orders",
+    params(
+        ("limit" = Option<u32>, Query, description = "Page size (1-200, default 50)"),
+        ("cursor" = Option<String>, Query, description = "next_cursor of the previous page"),
+        ("sort" = Option<String>, Query, description = "created_at or total_cents; prefix with - for descending (default -created_at)"),
+        ("filter[status]" = Option<String>, Query, description = "Filters on status, user_id, supplier_id, product_id (any line) or created_at, e.g. filter[status][in]=pending,confirmed or filter[created_at][gte]=2026-01-01T00:00:00Z")
+    ),
+    responses(
+        (status = 200, description = "One page of orders with their lines, next_cursor and Link headers"),
+        (status = 400, description = "Invalid query parameters")
+    ),
+    security(("BearerAuth" = []), ("ApiKeyAuth" = []))
+)]
+#[get("/orders")]
+pub async fn list_orders(
+    req: HttpRequest,
+    tenant: web::ReqData<TenantContext>,
+    db_router: web::Data<DynamicPoolRouter>,
+    signer: web::Data<CursorSigner>,
+    query: web::Query<HashMap<String, String>>,
+) -> HttpResponse {
+    let page = match Order::list_spec().parse(&query, &signer) {
+        Ok(page) => page,
+        Err(e) => return AppError::from(e).error_response(),
+    };
+    let pool = match db_router.get_pool(&tenant).await {
+        Ok(pool) => pool,
+        Err(e) => return DynamicPoolRouter::unavailable(e).error_response(),
+    };
+    let mut tx = match tenant.begin(&pool).await {
+        Ok(t) => t,
+        Err(e) => return AppError::from(e).error_response(),
+    };
+
+    match db::list_orders(&mut tx, &page).await {
+        Ok(orders) => {
+            let _ = tx.commit().await;
+            orders.respond(&req)
+        }
+        Err(e) => AppError::from(e).error_response(),
+    }
+}
+
+/// Counts the orders matching the same filters as `GET /orders`, per status.
+#[utoipa::path(
+    get,
+    path = "/api/v1/orders/summary",
+    params(
+        ("filter[status]" = Option<String>, Query, description = "Same filters as GET /api/v1/orders; limit, sort and cursor do not affect the counts")
+    ),
+    responses(
+        (status = 200, description = "Order counts per status", body = crate::models::OrderSummary),
+        (status = 400, description = "Invalid query parameters")
+    ),
+    security(("BearerAuth" = []), ("ApiKeyAuth" = []))
+)]
+#[get("/orders/summary")]
+pub async fn order_summary(
+    tenant: web::ReqData<TenantContext>,
+    db_router: web::Data<DynamicPoolRouter>,
+    signer: web::Data<CursorSigner>,
+    query: web::Query<HashMap<String, String>>,
+) -> HttpResponse {
+    let page = match Order::list_spec().parse(&query, &signer) {
+        Ok(page) => page,
+        Err(e) => return AppError::from(e).error_response(),
+    };
+    let pool = match db_router.get_pool(&tenant).await {
+        Ok(pool) => pool,
+        Err(e) => return DynamicPoolRouter::unavailable(e).error_response(),
+    };
+    let mut tx = match tenant.begin(&pool).await {
+        Ok(t) => t,
+        Err(e) => return AppError::from(e).error_response(),
+    };
+
+    match db::order_summary(&mut tx, &page).await {
+        Ok(summary) => {
+            let _ = tx.commit().await;
+            HttpResponse::Ok().json(summary)
         }
+        Err(e) => AppError::from(e).error_response(),
     }
 }
 
@@ -126,20 +234,20 @@ pub async fn create_order(
 #[get("/orders/{id}")]
 pub async fn get_order(
     tenant: web::ReqData<TenantContext>,
-    pool: web::Data<PgPool>,
+    db_router: web::Data<DynamicPoolRouter>,
     path: web::Path<Uuid>
 ) -> HttpResponse {
     let order_id = path.into_inner();
     
-    let mut tx = match pool.begin().await {
+    let pool = match db_router.get_pool(&tenant).await {
+        Ok(pool) => pool,
+        Err(e) => return DynamicPoolRouter::unavailable(e).error_response(),
+    };
+    let mut tx = match tenant.begin(&pool).await {
         Ok(t) => t,
-        Err(_) => return HttpResponse::InternalServerError().json(json!({"error": "Failed to start transaction"})),
+        Err(e) => return AppError::from(e).error_response(),
     };
 
-    if let Err(_) = tenant.apply_rls(&mut *tx).await {
-        return HttpResponse::InternalServerError().json(json!({"error": "Failed to apply RLS"}));
-    }
-
     let result = sqlx::query_as::<_, Order>(
         r#"
             SELECT *
@@ -148,14 +256,95 @@ pub async fn get_order(
         "#,
     )
     .bind(order_id)
-    .fetch_one(&mut *tx)
+    .fetch_one(tx.executor())
     .await;
+    let result = match result {
+        Ok(order) => db::with_lines(&mut tx, order).await,
+        Err(e) => Err(e),
+    };
 
     let _ = tx.commit().await;
 
     match result {
         Ok(order) => HttpResponse::Ok().json(order),
-        Err(_) => HttpResponse::NotFound().json(json!({"error": "Order not found"})),
+        Err(sqlx::Error::RowNotFound) => AppError::NotFound("Order not found".into()).error_response(),
+        Err(e) => AppError::from(e).error_response(),
+    }
+}
+
+/// Shows where an order's saga stands and every step it has taken, for operators.
+#[utoipa::path(
+    get,
+    path = "/api/v1/orders/{id}/saga",
+    params(
+        ("id" = Uuid, Path, description = "Order UUID")
+    ),
+    responses(
+        (status = 200, description = "Current saga state, deadline and step history", body = crate::saga::SagaStatus),
+        (status = 404, description = "Order not found or placed before sagas were recorded")
+    ),
+    security(("BearerAuth" = []), ("ApiKeyAuth" = []))
+)]
+#[get("/orders/{id}/saga")]
+pub async fn get_order_saga(
+    tenant: web::ReqData<TenantContext>,
+    db_router: web::Data<DynamicPoolRouter>,
+    path: web::Path<Uuid>,
+) -> HttpResponse {
+    let pool = match db_router.get_pool(&tenant).await {
+        Ok(pool) => pool,
+        Err(e) => return DynamicPoolRouter::unavailable(e).error_response(),
+    };
+    let mut tx = match tenant.begin(&pool).await {
+        Ok(t) => t,
+        Err(e) => return AppError::from(e).error_response(),
+    };
+
+    let result = saga::status(&mut tx, path.into_inner()).await;
+    let _ = tx.commit().await;
+
+    match result {
+        Ok(Some(status)) => HttpResponse::Ok().json(status),
+        Ok(None) => AppError::NotFound("No saga recorded for this order".into()).error_response(),
+        Err(e) => AppError::from(e).error_response(),
+    }
+}
+
+/// Every status transition of an order, oldest first, with who or what caused it.
+#[utoipa::path(
+    get,
+    path = "/api/v1/orders/{id}/history",
+    params(
+        ("id" = Uuid, Path, description = "Order UUID")
+    ),
+    responses(
+        (status = 200, description = "Status transitions with actor and reason", body = [crate::models::OrderAuditLog]),
+        (status = 404, description = "Order not found")
+    ),
+    security(("BearerAuth" = []), ("ApiKeyAuth" = []))
+)]
+#[get("/orders/{id}/history")]
+pub async fn get_order_history(
+    tenant: web::ReqData<TenantContext>,
+    db_router: web::Data<DynamicPoolRouter>,
+    path: web::Path<Uuid>,
+) -> HttpResponse {
+    let pool = match db_router.get_pool(&tenant).await {
+        Ok(pool) => pool,
+        Err(e) => return DynamicPoolRouter::unavailable(e).error_response(),
+    };
+    let mut tx = match tenant.begin(&pool).await {
+        Ok(t) => t,
+        Err(e) => return AppError::from(e).error_response(),
+    };
+
+    let result = audit::history(&mut tx, path.into_inner()).await;
+    let _ = tx.commit().await;
+
+    match result {
+        Ok(Some(entries)) => HttpResponse::Ok().json(entries),
+        Ok(None) => AppError::NotFound("Order not found".into()).error_response(),
+        Err(e) => AppError::from(e).error_response(),
     }
 }
 
@@ -168,7 +357,9 @@ pub async fn get_order(
     request_body = UpdateOrderStatus,
     responses(
         (status = 200, description = "Order status updated", body = Order),
-        (status = 404, description = "Order not found or version mismatch"),
+        (status = 400, description = "new_status is missing"),
+        (status = 404, description = "Order not found, or not the given user's or product's"),
+        (status = 409, description = "expected_version is stale, with current_version in the body; or the order's current status cannot move to new_status, with current_status and allowed"),
         (status = 500, description = "Internal server error")
     ),
     security(("BearerAuth" = []), ("ApiKeyAuth" = []))
@@ -176,241 +367,123 @@ pub async fn get_order(
 #[put("/orders/{id}/status")]
 pub async fn update_status(
     tenant: web::ReqData<TenantContext>,
-    pool: web::Data<PgPool>,
+    db_router: web::Data<DynamicPoolRouter>,
+    saga: web::Data<Saga>,
     path: web::Path<Uuid>,
-    redis_pub: web::Data<RedisPublisher>,
     req: web::Json<UpdateOrderStatus>,
 ) -> HttpResponse {
     let order_id = path.into_inner();
-    let new_status = req.new_status.clone().unwrap_or(OrderStatus::Pending);
-    let user_id = req.user_id;
+    let Some(new_status) = req.new_status.clone() else {
+        return AppError::BadRequest("new_status is required".into()).error_response();
+    };
     let order_timestamp = req.order_timestamp.unwrap_or(Utc::now());
     let expires_at = req
         .expires_at
         .unwrap_or(Utc::now() + Duration::seconds(2 * 24 * 60 * 60));
-    let product_id = req.product_id.unwrap_or(Uuid::new_v4());
 
     // Update status and return the final updated status
-    let mut tx = match pool.begin().await {
+    let pool = match db_router.get_pool(&tenant).await {
+        Ok(pool) => pool,
+        Err(e) => return DynamicPoolRouter::unavailable(e).error_response(),
+    };
+    let mut tx = match tenant.begin(&pool).await {
         Ok(t) => t,
-        Err(_) => return HttpResponse::InternalServerError().json(json!({"error": "Failed to start transaction"})),
+        Err(e) => return AppError::from(e).error_response(),
     };
 
-    if let Err(_) = tenant.apply_rls(&mut *tx).await {
-        return HttpResponse::InternalServerError().json(json!({"error": "Failed to apply RLS"}));
+    let guard = OrderGuard {
+        user_id: req.user_id,
+        product_id: req.product_id,
+        expected_version: req.expected_version,
+    };
+    let mut cause = Cause::new(Actor::from_context(&tenant));
+    if let Some(reason) = &req.reason {
+        cause = cause.with_reason(reason.clone());
     }
-
-    let result = sqlx::query_as::<_, Order>(
-        r#"
-            UPDATE orders
-            SET
-                status = COALESCE($1, status),
-                order_timestamp = COALESCE($2, order_timestamp),
-                expires_at = COALESCE($3, expires_at),
-                updated_at = NOW(),
-                version = version + 1
-            WHERE id = $4 AND product_id = $5 AND user_id = $6
-            AND ($7 IS NULL OR version = $7)
-            AND (
-                ($1 = 'pending') OR
-                ($1 = 'confirmed' AND status = 'pending') OR
-                ($1 = 'failed' AND status = 'pending') OR
-                ($1 = 'cancelled' AND status != 'cancelled' AND status != 'delivered') OR
-                ($1 = 'shipped' AND (status = 'confirmed' OR status = 'processing')) OR
-                ($1 = 'delivered' AND status = 'shipped') OR
-                ($1 = 'refunded' AND status != 'refunded') OR
-                ($1 = 'processing' AND status = 'confirmed')
-            )
-            RETURNING *
-        "#,
+    let result = db::update_order_status_db(
+        &mut tx,
+        order_id,
+        new_status,
+        guard,
+        Some(order_timestamp),
+        Some(expires_at),
+        &cause,
     )
-    .bind(new_status)
-    .bind(order_timestamp)
-    .bind(expires_at)
-    .bind(order_id)
-    .bind(product_id)
-    .bind(user_id)
-    .bind(req.expected_version)
-    .fetch_one(&mut *tx)
     .await;
 
     match result {
         Ok(order) => {
-            // Log audit entry
-            let audit_id = Uuid::new_v4();
-            let new_status_str = serde_json::to_string(&order.status).unwrap().replace("\"", "");
-            let _ = sqlx::query(
-                "INSERT INTO order_audit_logs (id, tenant_id, order_id, previous_status, new_status, changed_at) VALUES ($1, $2, $3, $4, $5, NOW())"
-            )
-            .bind(audit_id)
-            .bind(tenant.tenant_id)
-            .bind(order.id)
-            .bind::<Option<String>>(None)
-            .bind(&new_status_str)
-            .execute(&mut *tx)
-            .await;
-            
-            let _ = tx.commit().await;
+            let base = order.event();
 
+            let mut events: Vec<DomainEvent> = Vec::new();
             match order.status {
                 OrderStatus::Failed => {
-                    let fail_event = OrderEvent {
-                        tenant_id: Some(order.tenant_id),
-                        event_type: "order.failed".to_string(),
-                        product_id: order.product_id,
-                        supplier_id: order.supplier_id,
-                        order_id: Some(order.id),
-                        quantity: order.qty,
-                        user_id: Some(order.user_id),
-                        timestamp: Utc::now(),
-                        ..Default::default()
-                    };
-                    redis_pub.publish_async("order.failed", fail_event.clone());
-                    
-                    let release_cmd = OrderEvent { event_type: "inventory.release_command".to_string(), ..fail_event.clone() };
-                    redis_pub.publish_async("inventory.release_command", release_cmd);
-                    
-                    let refund_cmd = OrderEvent { event_type: "payment.refund_command".to_string(), ..fail_event.clone() };
-                    redis_pub.publish_async("payment.refund_command", refund_cmd);
-                    
+                    // The saga issues the compensating commands
+                    if let Err(e) = saga.advance(&mut tx, order.id, SagaStep::OrderCancelled).await {
+                        return AppError::from(e).error_response();
+                    }
+                    events.push(DomainEvent::OrderFailed(base));
                     println!("Order {} failed", order.id);
                 }
 
                 OrderStatus::Confirmed => {
-                    let conf_event = OrderEvent {
-                        tenant_id: Some(order.tenant_id),
-                        event_type: "order.confirmed".to_string(),
-                        product_id: order.product_id,
-                        supplier_id: order.supplier_id,
-                        order_id: Some(order.id),
-                        quantity: order.qty,
-                        user_id: Some(order.user_id),
-                        timestamp: Utc::now(),
-                        ..Default::default()
-                    };
-                    redis_pub.publish_async("order.confirmed", conf_event.clone());
-                    
-                    let ship_cmd = OrderEvent { event_type: "logistics.shipment_preparation_command".to_string(), ..conf_event.clone() };
-                    redis_pub.publish_async("logistics.shipment_preparation_command", ship_cmd);
-                    
+                    events.push(DomainEvent::OrderConfirmed(base.clone()));
+                    events.push(DomainEvent::ShipmentPreparationCommand(base));
                     println!("Order {} confirmed", order.id);
                 }
 
                 OrderStatus::Cancelled => {
-                    let cancel_event = OrderEvent {
-                        tenant_id: Some(order.tenant_id),
-                        event_type: "order.cancelled".to_string(),
-                        product_id: order.product_id,
-                        supplier_id: order.supplier_id,
-                        order_id: Some(order.id),
-                        quantity: order.qty,
-                        user_id: Some(order.user_id),
-                        timestamp: Utc::now(),
-                        ..Default::default()
-                    };
-                    redis_pub.publish_async("order.cancelled", cancel_event.clone());
-                    
-                    let release_cmd = OrderEvent { event_type: "inventory.release_command".to_string(), ..cancel_event.clone() };
-                    redis_pub.publish_async("inventory.release_command", release_cmd);
-                    
-                    let refund_cmd = OrderEvent { event_type: "payment.refund_command".to_string(), ..cancel_event.clone() };
-                    redis_pub.publish_async("payment.refund_command", refund_cmd);
-                    
+                    if let Err(e) = saga.advance(&mut tx, order.id, SagaStep::OrderCancelled).await {
+                        return AppError::from(e).error_response();
+                    }
+                    events.push(DomainEvent::OrderCancelled(base));
                     println!("Order {} cancelled", order.id);
                 }
 
                 OrderStatus::Delivered => {
-                    let _ = sqlx::query("UPDATE orders SET deleted_at = NOW() WHERE id = $1")
+                    if let Err(e) = sqlx::query("UPDATE orders SET deleted_at = NOW() WHERE id = $1")
                         .bind(order.id)
-                        .execute(pool.get_ref())
-                        .await;
-
-                    let review_cmd = OrderEvent {
-                        tenant_id: Some(order.tenant_id),
-                        event_type: "order.review_requested".to_string(),
-                        product_id: order.product_id,
-                        supplier_id: order.supplier_id,
-                        order_id: Some(order.id),
-                        user_id: Some(order.user_id),
-                        timestamp: Utc::now(),
-                        ..Default::default()
-                    };
-                    redis_pub.publish_async("order.review_requested", review_cmd.clone());
-
-                    let del_event = OrderEvent {
-                        event_type: "order.delivered".to_string(),
-                        ..review_cmd.clone()
-                    };
-                    redis_pub.publish_async("order.delivered", del_event);
+                        .execute(tx.executor())
+                        .await
+                    {
+                        return AppError::from(e).error_response();
+                    }
+
+                    events.push(DomainEvent::OrderReviewRequested(base.clone()));
+                    events.push(DomainEvent::OrderDelivered(base));
                     println!("Order {} delivered (Soft deleted)", order.id);
                 }
 
                 OrderStatus::Pending => {
-                    let pending_event = OrderEvent {
-                        tenant_id: Some(order.tenant_id),
-                        event_type: "order.pending".to_string(),
-                        product_id: order.product_id,
-                        supplier_id: order.supplier_id,
-                        order_id: Some(order.id),
-                        user_id: Some(order.user_id),
-                        timestamp: Utc::now(),
-                        ..Default::default()
-                    };
-                    redis_pub.publish_async("order.pending", pending_event);
+                    events.push(DomainEvent::OrderPending(base));
                     println!("Order {} set to Pending", order.id);
                 }
 
                 OrderStatus::Shipped => {
-                    let shipped_event = OrderEvent {
-                        tenant_id: Some(order.tenant_id),
-                        event_type: "order.shipped".to_string(),
-                        product_id: order.product_id,
-                        supplier_id: order.supplier_id,
-                        order_id: Some(order.id),
-                        quantity: order.qty,
-                        user_id: Some(order.user_id),
-                        timestamp: Utc::now(),
-                        ..Default::default()
-                    };
-                    redis_pub.publish_async("order.shipped", shipped_event);
+                    events.push(DomainEvent::OrderShipped(base));
                     println!("Order {} shipped", order.id);
                 }
 
                 OrderStatus::Refunded => {
-                    let refunded_event = OrderEvent {
-                        tenant_id: Some(order.tenant_id),
-                        event_type: "order.refunded".to_string(),
-                        product_id: order.product_id,
-                        supplier_id: order.supplier_id,
-                        order_id: Some(order.id),
-                        quantity: order.qty,
-                        user_id: Some(order.user_id),
-                        timestamp: Utc::now(),
-                        ..Default::default()
-                    };
-                    redis_pub.publish_async("order.refunded", refunded_event);
+                    events.push(DomainEvent::OrderRefunded(base));
                     println!("Order {} refunded", order.id);
                 }
 
                 OrderStatus::Processing => {
-                    let proc_event = OrderEvent {
-                        tenant_id: Some(order.tenant_id),
-                        event_type: "order.processing".to_string(),
-                        product_id: order.product_id,
-                        supplier_id: order.supplier_id,
-                        order_id: Some(order.id),
-                        user_id: Some(order.user_id),
-                        timestamp: Utc::now(),
-                        ..Default::default()
-                    };
-                    redis_pub.publish_async("order.processing", proc_event);
+                    events.push(DomainEvent::OrderProcessing(base));
                     println!("Order {} is processing", order.id);
                 }
+            }
 
-                _ => {
-                    println!("Order {} updated to {:?}", order.id, order.status);
+            // Events are enqueued in the status-change transaction and relayed after commit
+            for event in &events {
+                if let Err(e) = outbox::enqueue_event(tx.executor(), event).await {
+                    return AppError::from(e).error_response();
                 }
             }
+            if let Err(e) = tx.commit().await {
+                return AppError::from(e).error_response();
+            }
 
             // Response
             HttpResponse::Ok().json(serde_json::json!({
@@ -418,13 +491,7 @@ pub async fn update_status(
                 "status": order
             }))
         }
-        Err(sqlx::Error::RowNotFound) => {
-            return HttpResponse::NotFound().json(serde_json::json!({"error": "Order not found"}));
-        }
-        Err(e) => {
-            return HttpResponse::InternalServerError()
-                .json(serde_json::json!({"error": format!("DB Error: {}", e)}));
-        }
+        Err(e) => transition_error_response(e),
     }
 }
 
@@ -448,24 +515,24 @@ pub async fn update_status(
 pub async fn delete_order(
     tenant: web::ReqData<TenantContext>,
     _redis_pub: web::Data<RedisPublisher>,
-    pool: web::Data<PgPool>,
+    db_router: web::Data<DynamicPoolRouter>,
     path: web::Path<(Uuid, Uuid)>,
 ) -> HttpResponse {
     let (order_id, user_id) = path.into_inner();
     
-    let mut tx = match pool.begin().await {
+    let pool = match db_router.get_pool(&tenant).await {
+        Ok(pool) => pool,
+        Err(e) => return DynamicPoolRouter::unavailable(e).error_response(),
+    };
+    let mut tx = match tenant.begin(&pool).await {
         Ok(t) => t,
-        Err(_) => return HttpResponse::InternalServerError().json(json!({"error": "Failed to start transaction"})),
+        Err(e) => return AppError::from(e).error_response(),
     };
 
-    if let Err(_) = tenant.apply_rls(&mut *tx).await {
-        return HttpResponse::InternalServerError().json(json!({"error": "Failed to apply RLS"}));
-    }
-
     let result = sqlx::query("DELETE FROM orders WHERE id = $1 AND user_id = $2")
         .bind(order_id)
         .bind(user_id)
-        .execute(&mut *tx)
+        .execute(tx.executor())
         .await;
         
     let _ = tx.commit().await;
@@ -475,30 +542,71 @@ pub async fn delete_order(
             // redis_pub.publish("order.deleted", &event).await.unwrap();
             HttpResponse::Ok().body("Order deleted successfully")
         }
-        Ok(_) => HttpResponse::NotFound().body("Not found"),
-        Err(e) => {
-            eprintln!("DB error deleting order: {:?}", e);
-            HttpResponse::InternalServerError().body("DB error")
+        Ok(_) => AppError::NotFound("Not found".into()).error_response(),
+        Err(e) => AppError::from(e).error_response(),
+    }
+}
+
+/// 409 with the order's current status and where it may go instead, for illegal transitions.
+fn transition_error_response(err: TransitionError) -> HttpResponse {
+    match err {
+        TransitionError::NotFound => AppError::NotFound("Order not found".into()).error_response(),
+        TransitionError::VersionConflict { current_version } => Problem::new(StatusCode::CONFLICT, "version_conflict")
+            .with_detail(format!("Order is at version {current_version}"))
+            .with_extension("current_version", current_version)
+            .response(),
+        TransitionError::Illegal { current, requested } => {
+            let allowed = current.next_statuses();
+            Problem::new(StatusCode::CONFLICT, "illegal_status_transition")
+                .with_detail(format!("Order is {current:?} and cannot become {requested:?}"))
+                .with_extension("current_status", current)
+                .with_extension("requested_status", requested)
+                .with_extension("allowed", allowed)
+                .response()
         }
+        TransitionError::Database(e) => AppError::from(e).error_response(),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use actix_web::{test, App};
+    use crate::models::CreateOrderLine;
+    use actix_web::{test, App, HttpMessage};
+    use platform::tenant::{AuthMethod, PricingTier, TenantTx};
     use sqlx::PgPool;
 
+    /// Lists `product_id` in the tenant's catalogue, as a `product.created` event would.
+    async fn seed_catalog(pool: &PgPool, tenant_id: Uuid, supplier_id: Uuid, product_id: Uuid) {
+        let mut tx = TenantTx::begin(pool, tenant_id).await.unwrap();
+        sqlx::query("INSERT INTO catalog_products (tenant_id, product_id, supplier_id, unit_price_cents) VALUES ($1, $2, $3, 1999)")
+            .bind(tenant_id)
+            .bind(product_id)
+            .bind(supplier_id)
+            .execute(tx.executor())
+            .await
+            .unwrap();
+        tx.commit().await.unwrap();
+    }
+
+    fn as_tenant<R: HttpMessage>(req: R, tenant_id: Uuid) -> R {
+        req.extensions_mut()
+            .insert(TenantContext::new(tenant_id, None, PricingTier::Free, vec![], AuthMethod::ApiKey));
+        req
+    }
+
     #[sqlx::test]
     #[ignore]
     async fn test_create_and_update_order_optimistic_concurrency(pool: PgPool) {
         let redis_pub = web::Data::new(RedisPublisher::new_noop());
-        let pool_data = web::Data::new(pool);
+        let db_router = web::Data::new(DynamicPoolRouter::new(pool.clone()));
         
         let mut app = test::init_service(
             App::new()
-                .app_data(pool_data.clone())
+                .app_data(db_router.clone())
                 .app_data(redis_pub.clone())
+                .app_data(web::Data::new(Pricing::new()))
+                .app_data(web::Data::new(Saga::new()))
                 .service(create_order)
                 .service(update_status)
         ).await;
@@ -506,21 +614,21 @@ mod tests {
         let user_id = Uuid::new_v4();
         let supplier_id = Uuid::new_v4();
         let product_id = Uuid::new_v4();
+        let tenant_id = Uuid::new_v4();
+        seed_catalog(&pool, tenant_id, supplier_id, product_id).await;
 
         // 1. Create order
         let create_req = CreateOrderRequest {
             user_id,
             supplier_id,
-            product_id,
-            qty: 5,
-            status: Some(OrderStatus::Pending),
-            items: json!([{"name": "test item"}]),
+            lines: vec![CreateOrderLine { product_id, quantity: 5, discount_bps: None }],
         };
 
         let req = test::TestRequest::post()
             .uri("/orders")
             .set_json(&create_req)
             .to_request();
+        let req = as_tenant(req, tenant_id);
         let resp = test::call_service(&mut app, req).await;
         assert_eq!(resp.status(), actix_web::http::StatusCode::CREATED);
         
@@ -538,12 +646,14 @@ mod tests {
             expires_at: None,
             order_timestamp: None,
             expected_version: Some(initial_version),
+            reason: None,
         };
         
         let req2 = test::TestRequest::put()
             .uri(&format!("/orders/{}/status", order_id))
             .set_json(&update_req_success)
             .to_request();
+        let req2 = as_tenant(req2, tenant_id);
         let resp2 = test::call_service(&mut app, req2).await;
         assert_eq!(resp2.status(), actix_web::http::StatusCode::OK);
         
@@ -560,26 +670,32 @@ mod tests {
             expires_at: None,
             order_timestamp: None,
             expected_version: Some(1), // old version!
+            reason: None,
         };
         
         let req3 = test::TestRequest::put()
             .uri(&format!("/orders/{}/status", order_id))
             .set_json(&update_req_fail)
             .to_request();
+        let req3 = as_tenant(req3, tenant_id);
         let resp3 = test::call_service(&mut app, req3).await;
-        assert_eq!(resp3.status(), actix_web::http::StatusCode::NOT_FOUND); // Not found because of WHERE version = $7 mismatch
+        assert_eq!(resp3.status(), actix_web::http::StatusCode::CONFLICT);
+        let body3: serde_json::Value = test::read_body_json(resp3).await;
+        assert_eq!(body3["current_version"], 2);
     }
 
     #[sqlx::test]
     #[ignore]
     async fn test_invalid_state_transition(pool: PgPool) {
         let redis_pub = web::Data::new(RedisPublisher::new_noop());
-        let pool_data = web::Data::new(pool);
+        let db_router = web::Data::new(DynamicPoolRouter::new(pool.clone()));
         
         let mut app = test::init_service(
             App::new()
-                .app_data(pool_data.clone())
+                .app_data(db_router.clone())
                 .app_data(redis_pub.clone())
+                .app_data(web::Data::new(Pricing::new()))
+                .app_data(web::Data::new(Saga::new()))
                 .service(create_order)
                 .service(update_status)
         ).await;
@@ -587,21 +703,21 @@ mod tests {
         let user_id = Uuid::new_v4();
         let supplier_id = Uuid::new_v4();
         let product_id = Uuid::new_v4();
+        let tenant_id = Uuid::new_v4();
+        seed_catalog(&pool, tenant_id, supplier_id, product_id).await;
 
         // 1. Create order (Pending)
         let create_req = CreateOrderRequest {
             user_id,
             supplier_id,
-            product_id,
-            qty: 1,
-            status: Some(OrderStatus::Pending),
-            items: json!([]),
+            lines: vec![CreateOrderLine { product_id, quantity: 1, discount_bps: None }],
         };
 
         let req = test::TestRequest::post()
             .uri("/orders")
             .set_json(&create_req)
             .to_request();
+        let req = as_tenant(req, tenant_id);
         let resp = test::call_service(&mut app, req).await;
         let body: serde_json::Value = test::read_body_json(resp).await;
         let order_id = Uuid::parse_str(body["id"]["id"].as_str().unwrap()).unwrap();
@@ -615,16 +731,20 @@ mod tests {
             expires_at: None,
             order_timestamp: None,
             expected_version: None, // Ignore version for this test
+            reason: None,
         };
         
         let req2 = test::TestRequest::put()
             .uri(&format!("/orders/{}/status", order_id))
             .set_json(&invalid_update)
             .to_request();
+        let req2 = as_tenant(req2, tenant_id);
         let resp2 = test::call_service(&mut app, req2).await;
         
-        // Should return 404 because the state transition condition in the WHERE clause fails
-        assert_eq!(resp2.status(), actix_web::http::StatusCode::NOT_FOUND); 
+        // Should return 409 with the status the order is still in
+        assert_eq!(resp2.status(), actix_web::http::StatusCode::CONFLICT);
+        let body2: serde_json::Value = test::read_body_json(resp2).await;
+        assert_eq!(body2["current_status"], "pending");
     }
 }
 
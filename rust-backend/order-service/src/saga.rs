@@ -0,0 +1,376 @@
+// src/saga.rs
+// Orchestrates an order through inventory, payment and logistics, recording each step in saga_log.
+
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use platform::events::DomainEvent;
+use platform::outbox::{self, OutboxError};
+use platform::tenant::TenantTx;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::db;
+use crate::models::Order;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type, ToSchema)]
+#[sqlx(type_name = "varchar", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum SagaState {
+    /// Waiting for inventory to reserve every line.
+    Started,
+    /// Waiting for the buyer's payment.
+    InventoryReserved,
+    /// Waiting for inventory to commit the reserved stock.
+    PaymentCaptured,
+    /// Waiting for logistics to create the shipment.
+    InventoryFinalized,
+    /// Waiting for delivery.
+    Shipping,
+    Completed,
+    /// Ended before anything had to be undone.
+    Failed,
+    /// Compensating commands were issued.
+    Compensated,
+}
+
+/// What moved a saga: an event from another service, a user action, or a timeout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type, ToSchema)]
+#[sqlx(type_name = "varchar", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum SagaStep {
+    OrderPlaced,
+    InventoryReserved,
+    InventoryRejected,
+    /// Inventory gave the stock back, including when the reservation expired.
+    InventoryReleased,
+    PaymentCaptured,
+    PaymentFailed,
+    InventoryFinalized,
+    ShipmentCreated,
+    ShipmentCancelled,
+    Delivered,
+    /// Cancelled or failed through the API, or expired unpaid.
+    OrderCancelled,
+    TimedOut,
+}
+
+impl SagaState {
+    pub fn is_terminal(self) -> bool {
+        matches!(self, SagaState::Completed | SagaState::Failed | SagaState::Compensated)
+    }
+
+    /// Position on the happy path, for the states that have one.
+    fn rank(self) -> Option<u8> {
+        match self {
+            SagaState::Started => Some(0),
+            SagaState::InventoryReserved => Some(1),
+            SagaState::PaymentCaptured => Some(2),
+            SagaState::InventoryFinalized => Some(3),
+            SagaState::Shipping => Some(4),
+            SagaState::Completed => Some(5),
+            SagaState::Failed | SagaState::Compensated => None,
+        }
+    }
+
+    /// The state `step` moves the saga to, or `None` when it does not apply.
+    ///
+    /// Happy-path steps arrive on different streams and so may be seen out of order:
+    /// a later step skips the ones not seen yet, and an earlier one arriving late is
+    /// ignored. Reservations and payments that arrive after compensation are
+    /// compensated again.
+    pub fn next(self, step: SagaStep) -> Option<SagaState> {
+        use SagaState as S;
+
+        let forward = match step {
+            SagaStep::InventoryReserved => Some(S::InventoryReserved),
+            SagaStep::PaymentCaptured => Some(S::PaymentCaptured),
+            SagaStep::InventoryFinalized => Some(S::InventoryFinalized),
+            SagaStep::ShipmentCreated => Some(S::Shipping),
+            SagaStep::Delivered => Some(S::Completed),
+            _ => None,
+        };
+        if let (Some(target), Some(from)) = (forward, self.rank()) {
+            return (target.rank() > Some(from)).then_some(target);
+        }
+
+        match (self, step) {
+            (S::Compensated, SagaStep::InventoryReserved | SagaStep::PaymentCaptured) => Some(S::Compensated),
+            (S::Started, SagaStep::InventoryRejected) => Some(S::Failed),
+            (S::InventoryReserved, SagaStep::PaymentFailed) => Some(S::Compensated),
+            (S::Started | S::InventoryReserved | S::PaymentCaptured, SagaStep::InventoryReleased) => Some(S::Compensated),
+            (S::InventoryFinalized | S::Shipping, SagaStep::ShipmentCancelled) => Some(S::Compensated),
+            (state, SagaStep::OrderCancelled | SagaStep::TimedOut) if !state.is_terminal() => Some(S::Compensated),
+            _ => None,
+        }
+    }
+}
+
+impl SagaStep {
+    /// The order and step a consumed event stands for, if it belongs to the saga.
+    pub fn from_event(event: &DomainEvent) -> Option<(Uuid, SagaStep)> {
+        let (order_id, step) = match event {
+            DomainEvent::InventoryReserved(e) => (e.order_id?, SagaStep::InventoryReserved),
+            DomainEvent::InventoryRejected(e) => (e.order_id?, SagaStep::InventoryRejected),
+            DomainEvent::InventoryReleased(e)
+            | DomainEvent::InventoryReservationExpired(e)
+            | DomainEvent::InventoryExpired(e) => (e.order_id?, SagaStep::InventoryReleased),
+            DomainEvent::InventoryFinalized(e) => (e.order_id?, SagaStep::InventoryFinalized),
+            DomainEvent::PaymentSuccess(e) => (e.order_id, SagaStep::PaymentCaptured),
+            DomainEvent::PaymentFailed(e) | DomainEvent::PaymentCancelled(e) => (e.order_id, SagaStep::PaymentFailed),
+            DomainEvent::ShipmentCreated(e) => (e.order_id, SagaStep::ShipmentCreated),
+            DomainEvent::ShipmentCancelled(e) => (e.order_id, SagaStep::ShipmentCancelled),
+            DomainEvent::ShipmentUpdated(e) => match e.status.as_str() {
+                "delivered" => (e.order_id, SagaStep::Delivered),
+                "cancelled" => (e.order_id, SagaStep::ShipmentCancelled),
+                _ => return None,
+            },
+            DomainEvent::OrderDelivered(e) => (e.order_id, SagaStep::Delivered),
+            _ => return None,
+        };
+        Some((order_id, step))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, FromRow, ToSchema)]
+pub struct SagaLogEntry {
+    pub state: SagaState,
+    pub step: SagaStep,
+    pub deadline_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// An order's saga as operators see it; `steps` is oldest first.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SagaStatus {
+    pub order_id: Uuid,
+    pub state: SagaState,
+    pub deadline_at: Option<DateTime<Utc>>,
+    /// The deadline passed and the timeout worker has not compensated the saga yet.
+    pub stuck: bool,
+    pub steps: Vec<SagaLogEntry>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Saga {
+    step_timeout: Duration,
+    payment_timeout: Duration,
+}
+
+impl Default for Saga {
+    fn default() -> Self {
+        Self {
+            step_timeout: Duration::from_secs(15 * 60),
+            payment_timeout: Duration::from_secs(2 * 24 * 60 * 60),
+        }
+    }
+}
+
+impl Saga {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How long inventory may take to answer (`SAGA_STEP_TIMEOUT_SECS`).
+    pub fn with_step_timeout(mut self, timeout: Duration) -> Self {
+        self.step_timeout = timeout;
+        self
+    }
+
+    /// How long the buyer has to pay for reserved stock (`SAGA_PAYMENT_TIMEOUT_SECS`).
+    pub fn with_payment_timeout(mut self, timeout: Duration) -> Self {
+        self.payment_timeout = timeout;
+        self
+    }
+
+    fn timeout(&self, state: SagaState) -> Option<Duration> {
+        match state {
+            SagaState::Started | SagaState::PaymentCaptured => Some(self.step_timeout),
+            SagaState::InventoryReserved => Some(self.payment_timeout),
+            // The order is paid for and its stock committed, so a slow logistics service
+            // is no reason to refund it; deliveries take as long as they take
+            SagaState::InventoryFinalized | SagaState::Shipping => None,
+            SagaState::Completed | SagaState::Failed | SagaState::Compensated => None,
+        }
+    }
+
+    /// Records the start of the saga, in the transaction that creates the order.
+    pub async fn start(&self, tx: &mut TenantTx<'_>, order: &Order) -> Result<(), sqlx::Error> {
+        self.append(tx, order.id, SagaState::Started, SagaStep::OrderPlaced).await
+    }
+
+    /// Applies `step` to the order's saga and returns the new state, or `None` when the
+    /// step did not apply. Entering `Compensated` enqueues `inventory.release_command`
+    /// and `payment.refund_command` in the same transaction; both are idempotent.
+    pub async fn advance(
+        &self,
+        tx: &mut TenantTx<'_>,
+        order_id: Uuid,
+        step: SagaStep,
+    ) -> Result<Option<SagaState>, OutboxError> {
+        // The order row serialises concurrent steps of the same saga
+        let order = sqlx::query_as::<_, Order>("SELECT * FROM orders WHERE id = $1 FOR UPDATE")
+            .bind(order_id)
+            .fetch_optional(tx.executor())
+            .await?;
+        let Some(order) = order else {
+            return Ok(None);
+        };
+
+        // Orders placed before the saga existed start from the beginning
+        let current = current_entry(tx, order_id).await?;
+        let state = current.as_ref().map_or(SagaState::Started, |entry| entry.state);
+        if step == SagaStep::TimedOut {
+            let deadline = current.and_then(|entry| entry.deadline_at);
+            if deadline.is_none_or(|at| at > Utc::now()) {
+                return Ok(None);
+            }
+        }
+        let Some(next) = state.next(step) else {
+            return Ok(None);
+        };
+
+        self.append(tx, order_id, next, step).await?;
+        if next == SagaState::Compensated {
+            let order = db::with_lines(tx, order).await?;
+            let event = order.event();
+            outbox::enqueue_event(tx.executor(), &DomainEvent::InventoryReleaseCommand(event.clone())).await?;
+            outbox::enqueue_event(tx.executor(), &DomainEvent::PaymentRefundCommand(event)).await?;
+        }
+        Ok(Some(next))
+    }
+
+    async fn append(
+        &self,
+        tx: &mut TenantTx<'_>,
+        order_id: Uuid,
+        state: SagaState,
+        step: SagaStep,
+    ) -> Result<(), sqlx::Error> {
+        let deadline_at = self
+            .timeout(state)
+            .and_then(|timeout| chrono::Duration::from_std(timeout).ok())
+            .map(|timeout| Utc::now() + timeout);
+        sqlx::query(
+            r#"
+                INSERT INTO saga_log (tenant_id, order_id, state, step, deadline_at)
+                VALUES ($1, $2, $3, $4, $5)
+            "#,
+        )
+        .bind(tx.tenant_id())
+        .bind(order_id)
+        .bind(state)
+        .bind(step)
+        .bind(deadline_at)
+        .execute(tx.executor())
+        .await?;
+        Ok(())
+    }
+}
+
+async fn current_entry(tx: &mut TenantTx<'_>, order_id: Uuid) -> Result<Option<SagaLogEntry>, sqlx::Error> {
+    sqlx::query_as::<_, SagaLogEntry>(
+        r#"
+            SELECT state, step, deadline_at, created_at
+            FROM saga_log
+            WHERE order_id = $1
+            ORDER BY seq DESC
+            LIMIT 1
+        "#,
+    )
+    .bind(order_id)
+    .fetch_optional(tx.executor())
+    .await
+}
+
+/// The order's saga, or `None` when it has no recorded steps.
+pub async fn status(tx: &mut TenantTx<'_>, order_id: Uuid) -> Result<Option<SagaStatus>, sqlx::Error> {
+    let steps = sqlx::query_as::<_, SagaLogEntry>(
+        r#"
+            SELECT state, step, deadline_at, created_at
+            FROM saga_log
+            WHERE order_id = $1
+            ORDER BY seq
+        "#,
+    )
+    .bind(order_id)
+    .fetch_all(tx.executor())
+    .await?;
+
+    Ok(steps.last().cloned().map(|current| SagaStatus {
+        order_id,
+        state: current.state,
+        deadline_at: current.deadline_at,
+        stuck: current.deadline_at.is_some_and(|at| at <= Utc::now()),
+        steps,
+    }))
+}
+
+/// Orders whose saga is still in a state past its deadline, oldest deadline first.
+pub async fn stuck_orders(tx: &mut TenantTx<'_>, limit: i64) -> Result<Vec<Uuid>, sqlx::Error> {
+    sqlx::query_scalar(
+        r#"
+            SELECT s.order_id
+            FROM saga_log s
+            WHERE s.tenant_id = $1
+            AND s.deadline_at <= NOW()
+            -- rows written before finalized stock stopped having a deadline
+            AND s.state <> 'inventory_finalized'
+            AND NOT EXISTS (SELECT 1 FROM saga_log later WHERE later.order_id = s.order_id AND later.seq > s.seq)
+            ORDER BY s.deadline_at
+            LIMIT $2
+        "#,
+    )
+    .bind(tx.tenant_id())
+    .bind(limit)
+    .fetch_all(tx.executor())
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_happy_path_tolerates_out_of_order_steps() {
+        assert_eq!(SagaState::Started.next(SagaStep::InventoryReserved), Some(SagaState::InventoryReserved));
+        assert_eq!(SagaState::InventoryReserved.next(SagaStep::PaymentCaptured), Some(SagaState::PaymentCaptured));
+        // inventory.finalized read before payment.success
+        assert_eq!(
+            SagaState::InventoryReserved.next(SagaStep::InventoryFinalized),
+            Some(SagaState::InventoryFinalized)
+        );
+        assert_eq!(SagaState::InventoryFinalized.next(SagaStep::PaymentCaptured), None);
+        assert_eq!(SagaState::InventoryFinalized.next(SagaStep::ShipmentCreated), Some(SagaState::Shipping));
+        assert_eq!(SagaState::Shipping.next(SagaStep::Delivered), Some(SagaState::Completed));
+        assert_eq!(SagaState::Completed.next(SagaStep::Delivered), None);
+    }
+
+    #[test]
+    fn test_failures_compensate_until_terminal() {
+        assert_eq!(SagaState::Started.next(SagaStep::InventoryRejected), Some(SagaState::Failed));
+        assert_eq!(SagaState::InventoryReserved.next(SagaStep::PaymentFailed), Some(SagaState::Compensated));
+        assert_eq!(SagaState::PaymentCaptured.next(SagaStep::InventoryReleased), Some(SagaState::Compensated));
+        assert_eq!(SagaState::Shipping.next(SagaStep::ShipmentCancelled), Some(SagaState::Compensated));
+        assert_eq!(SagaState::Shipping.next(SagaStep::OrderCancelled), Some(SagaState::Compensated));
+        assert_eq!(SagaState::InventoryFinalized.next(SagaStep::TimedOut), Some(SagaState::Compensated));
+
+        assert_eq!(SagaState::Completed.next(SagaStep::OrderCancelled), None);
+        assert_eq!(SagaState::Failed.next(SagaStep::TimedOut), None);
+        assert_eq!(SagaState::Compensated.next(SagaStep::InventoryReleased), None);
+        // A reservation or payment that lands after compensation is undone again
+        assert_eq!(SagaState::Compensated.next(SagaStep::PaymentCaptured), Some(SagaState::Compensated));
+    }
+
+    #[test]
+    fn test_only_waiting_states_have_deadlines() {
+        let saga = Saga::new().with_step_timeout(Duration::from_secs(60));
+        assert_eq!(saga.timeout(SagaState::Started), Some(Duration::from_secs(60)));
+        assert_eq!(saga.timeout(SagaState::InventoryReserved), Some(Duration::from_secs(2 * 24 * 60 * 60)));
+        assert_eq!(saga.timeout(SagaState::InventoryFinalized), None);
+        assert_eq!(saga.timeout(SagaState::Shipping), None);
+        assert_eq!(saga.timeout(SagaState::Compensated), None);
+    }
+}
@@ -0,0 +1,35 @@
+use crate::pricing;
+use platform::db_router::DynamicPoolRouter;
+use platform::supervisor::TaskError;
+use platform::tenant::TenantTx;
+use platform::tenant_directory::TenantDirectory;
+
+pub const TASK: &str = "catalog-backfill";
+
+/// Fills `catalog_products` for every tenant once at startup, so orders for products that
+/// predate the `stream:products` consumer are not rejected with 422. Fails, and is retried
+/// by the supervisor, until every tenant has been seeded.
+pub async fn run_catalog_backfill(db_router: DynamicPoolRouter, tenants: TenantDirectory) -> Result<(), TaskError> {
+    let mut failed = 0;
+    for tenant_id in tenants.tenant_ids().await? {
+        let seeded = async {
+            let pool = db_router.get_tenant_pool(tenant_id).await?;
+            let mut tx = TenantTx::begin(&pool, tenant_id).await?;
+            let seeded = pricing::backfill_catalog(&mut tx).await?;
+            tx.commit().await?;
+            Ok::<_, sqlx::Error>(seeded)
+        };
+        match seeded.await {
+            Ok(0) => {}
+            Ok(seeded) => tracing::info!(%tenant_id, seeded, "backfilled catalog products"),
+            Err(e) => {
+                eprintln!("catalog backfill failed for tenant {tenant_id}: {e}");
+                failed += 1;
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(format!("catalog backfill failed for {failed} tenants").into());
+    }
+    Ok(())
+}
@@ -1,2 +1,3 @@
+pub mod catalog_backfill;
 pub mod order_expiration_worker;
-
+pub mod saga_timeout_worker;
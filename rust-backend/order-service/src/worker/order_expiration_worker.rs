@@ -1,85 +1,100 @@
-use crate::models::OrderEvent;
-use crate::redis_pub::RedisPublisher;
+use crate::audit::{Actor, Cause};
+use crate::db::{self, OrderGuard, TransitionError};
+use crate::models::OrderStatus;
+use crate::saga::{Saga, SagaStep};
 use chrono::{DateTime, Utc};
-use sqlx::PgPool;
+use platform::events::{DomainEvent, OrderEvent};
+use platform::db_router::DynamicPoolRouter;
+use platform::health::Heartbeat;
+use platform::outbox;
+use platform::supervisor::{self, TaskError};
+use platform::tenant::TenantTx;
+use platform::tenant_directory::TenantDirectory;
 use uuid::Uuid;
 
-pub async fn start_order_expiration_worker(pool: PgPool, redis_pub: RedisPublisher) {
-    tokio::spawn(async move {
-        loop {
-            // Find the next expiring pending order
-            let next_expiry: Option<(Uuid, DateTime<Utc>)> = sqlx::query_as(
-                "SELECT id, expires_at FROM orders WHERE status = 'pending' AND deleted_at IS NULL ORDER BY expires_at ASC LIMIT 1"
-            )
-            .fetch_optional(&pool)
-            .await
-            .unwrap_or(None);
+pub const HEARTBEAT: &str = "order-expiration";
+/// Longest sleep between polls, so the heartbeat keeps beating while the next
+/// order is far from expiring.
+const MAX_SLEEP: std::time::Duration = std::time::Duration::from_secs(300);
 
-            if let Some((id, expires_at)) = next_expiry {
-                let now = Utc::now();
-                if expires_at <= now {
-                    // Process this expired order
-                    if let Err(e) = fail_expired_order(&pool, &redis_pub, id).await {
-                        eprintln!("Order expiration worker error for order {}: {:?}", id, e);
-                    }
-                } else {
-                    // Sleep exactly until the order expires
-                    let duration = (expires_at - now).to_std().unwrap_or(std::time::Duration::from_secs(0));
-                    tokio::time::sleep(duration).await;
-                }
-            } else {
-                // No pending orders exist, sleep for 5 minutes before checking again
-                tokio::time::sleep(std::time::Duration::from_secs(300)).await;
+/// Fails pending orders as they expire, until the supervisor shuts down.
+pub async fn run_order_expiration_worker(db_router: DynamicPoolRouter, tenants: TenantDirectory, saga: Saga) -> Result<(), TaskError> {
+    let heartbeat = Heartbeat::register(HEARTBEAT, MAX_SLEEP);
+    while !supervisor::is_shutting_down() {
+        heartbeat.beat();
+        // Orders are only visible inside their tenant's scope, so tenants are swept in turn
+        let tenant_ids = match tenants.tenant_ids().await {
+            Ok(ids) => ids,
+            Err(e) => {
+                eprintln!("Order expiration worker failed to list tenants: {:?}", e);
+                Vec::new()
             }
+        };
+
+        let mut pause = MAX_SLEEP;
+        for tenant_id in tenant_ids {
+            match expire_orders(&db_router, &saga, tenant_id).await {
+                // Sleep until the next order expires, waking up to report liveness
+                Ok(Some(expires_at)) => pause = pause.min((expires_at - Utc::now()).to_std().unwrap_or_default()),
+                Ok(None) => {}
+                Err(e) => eprintln!("Order expiration worker error for tenant {}: {:?}", tenant_id, e),
+            }
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(pause) => {}
+            _ = supervisor::shutdown_requested() => {}
         }
-    });
+    }
+    Ok(())
 }
 
-async fn fail_expired_order(pool: &PgPool, redis_pub: &RedisPublisher, order_id: Uuid) -> Result<(), sqlx::Error> {
-    let order_data: Option<(uuid::Uuid, uuid::Uuid, uuid::Uuid, chrono::DateTime<Utc>)> = sqlx::query_as(
-        "SELECT product_id, user_id, supplier_id, expires_at FROM orders WHERE id = $1 AND status = 'pending'"
-    )
-    .bind(order_id)
-    .fetch_optional(pool)
-    .await?;
-
-    if let Some((product_id, user_id, supplier_id, expires_at)) = order_data {
-        // Log the audit transition
-        let audit_id = Uuid::new_v4();
-        let _ = sqlx::query(
-            "INSERT INTO order_audit_logs (id, order_id, previous_status, new_status, changed_at) VALUES ($1, $2, $3, $4, NOW())"
+/// Fails the tenant's expired pending orders, oldest first, and returns when its next
+/// pending order expires.
+async fn expire_orders(
+    db_router: &DynamicPoolRouter,
+    saga: &Saga,
+    tenant_id: Uuid,
+) -> Result<Option<DateTime<Utc>>, Box<dyn std::error::Error + Send + Sync>> {
+    let pool = db_router.get_tenant_pool(tenant_id).await?;
+    while !supervisor::is_shutting_down() {
+        // The status change, its audit row, the saga step and the follow-up events commit together
+        let mut tx = TenantTx::begin(&pool, tenant_id).await?;
+        let next_expiry: Option<(Uuid, DateTime<Utc>)> = sqlx::query_as(
+            "SELECT id, expires_at FROM orders WHERE status = 'pending' AND deleted_at IS NULL ORDER BY expires_at ASC LIMIT 1"
         )
-        .bind(audit_id)
-        .bind(order_id)
-        .bind("pending")
-        .bind("failed")
-        .execute(pool)
+        .fetch_optional(tx.executor())
         .await?;
 
-        sqlx::query("UPDATE orders SET status = 'failed' WHERE id = $1")
-            .bind(order_id)
-            .execute(pool)
-            .await?;
-
-        let fail_event = OrderEvent {
-            tenant_id: Some(supplier_id),
-            event_type: "order.failed".to_string(),
-            order_id: Some(order_id),
-            user_id: Some(user_id),
-            product_id,
-            supplier_id,
-            timestamp: Utc::now(),
-            expires_at,
-            ..Default::default()
-        };
-        redis_pub.publish_async("order.failed", fail_event.clone());
-        
-        let release_cmd = OrderEvent { event_type: "inventory.release_command".to_string(), ..fail_event.clone() };
-        redis_pub.publish_async("inventory.release_command", release_cmd);
-        
-        let refund_cmd = OrderEvent { event_type: "payment.refund_command".to_string(), ..fail_event.clone() };
-        redis_pub.publish_async("payment.refund_command", refund_cmd);
+        match next_expiry {
+            Some((id, expires_at)) if expires_at <= Utc::now() => {
+                fail_expired_order(&mut tx, saga, id).await?;
+                tx.commit().await?;
+            }
+            next => return Ok(next.map(|(_, expires_at)| expires_at)),
+        }
     }
+    Ok(None)
+}
+
+async fn fail_expired_order(
+    tx: &mut TenantTx<'_>,
+    saga: &Saga,
+    order_id: Uuid,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let cause = Cause::new(Actor::Worker(HEARTBEAT)).with_reason("expired");
+    let order = match db::update_order_status_db(tx, order_id, OrderStatus::Failed, OrderGuard::default(), None, None, &cause).await {
+        Ok(order) => order,
+        // Confirmed or cancelled since it was selected
+        Err(TransitionError::NotFound | TransitionError::Illegal { .. } | TransitionError::VersionConflict { .. }) => return Ok(()),
+        Err(TransitionError::Database(e)) => return Err(e.into()),
+    };
+    // Compensating the saga enqueues the inventory release and payment refund commands
+    saga.advance(tx, order_id, SagaStep::OrderCancelled).await?;
 
+    let fail_event = OrderEvent {
+        expires_at: Some(order.expires_at),
+        ..order.event()
+    };
+    outbox::enqueue_event(tx.executor(), &DomainEvent::OrderFailed(fail_event)).await?;
     Ok(())
 }
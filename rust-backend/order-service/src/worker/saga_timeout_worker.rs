@@ -0,0 +1,76 @@
+use crate::audit::{Actor, Cause};
+use crate::db::{self, OrderGuard, TransitionError};
+use crate::models::OrderStatus;
+use crate::saga::{self, Saga, SagaState, SagaStep};
+use platform::db_router::DynamicPoolRouter;
+use platform::events::DomainEvent;
+use platform::health::Heartbeat;
+use platform::outbox;
+use platform::supervisor::{self, TaskError};
+use platform::tenant::TenantTx;
+use platform::tenant_directory::TenantDirectory;
+use std::time::Duration;
+use uuid::Uuid;
+
+pub const HEARTBEAT: &str = "saga-timeouts";
+const PERIOD: Duration = Duration::from_secs(30);
+const BATCH: i64 = 100;
+
+/// Compensates sagas stuck past their deadline every `PERIOD`, until the supervisor shuts down.
+pub async fn run_saga_timeout_worker(db_router: DynamicPoolRouter, tenants: TenantDirectory, saga: Saga) -> Result<(), TaskError> {
+    let heartbeat = Heartbeat::register(HEARTBEAT, PERIOD);
+    let mut interval = tokio::time::interval(PERIOD);
+
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {}
+            _ = supervisor::shutdown_requested() => return Ok(()),
+        }
+        heartbeat.beat();
+
+        let tenant_ids = match tenants.tenant_ids().await {
+            Ok(ids) => ids,
+            Err(e) => {
+                eprintln!("saga timeout worker failed to list tenants: {e}");
+                continue;
+            }
+        };
+
+        for tenant_id in tenant_ids {
+            if let Err(e) = compensate_stuck(&db_router, &saga, tenant_id).await {
+                eprintln!("saga timeout worker failed for tenant {tenant_id}: {e}");
+            }
+        }
+    }
+}
+
+async fn compensate_stuck(
+    db_router: &DynamicPoolRouter,
+    saga: &Saga,
+    tenant_id: Uuid,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let pool = &db_router.get_tenant_pool(tenant_id).await?;
+    let mut tx = TenantTx::begin(pool, tenant_id).await?;
+    let stuck = saga::stuck_orders(&mut tx, BATCH).await?;
+    tx.commit().await?;
+
+    // One transaction per order, so a failing order does not hold back the others
+    for order_id in stuck {
+        let mut tx = TenantTx::begin(pool, tenant_id).await?;
+        if saga.advance(&mut tx, order_id, SagaStep::TimedOut).await? != Some(SagaState::Compensated) {
+            continue;
+        }
+        let cause = Cause::new(Actor::Worker(HEARTBEAT)).with_reason("saga timed out");
+        match db::update_order_status_db(&mut tx, order_id, OrderStatus::Cancelled, OrderGuard::default(), None, None, &cause).await {
+            Ok(order) => {
+                outbox::enqueue_event(tx.executor(), &DomainEvent::OrderCancelled(order.event())).await?;
+            }
+            // Already cancelled or delivered; the compensation still stands
+            Err(TransitionError::NotFound | TransitionError::Illegal { .. }) => {}
+            Err(e) => return Err(e.into()),
+        }
+        tx.commit().await?;
+        tracing::warn!(%tenant_id, %order_id, "order saga timed out and was compensated");
+    }
+    Ok(())
+}
@@ -0,0 +1,6 @@
+-- Trace context captured when an event is enqueued, so the relay can publish it into
+-- the same trace and correlation chain as the request that wrote it.
+ALTER TABLE event_outbox
+    ADD COLUMN IF NOT EXISTS correlation_id UUID,
+    ADD COLUMN IF NOT EXISTS traceparent TEXT,
+    ADD COLUMN IF NOT EXISTS tracestate TEXT;
@@ -1,25 +1,15 @@
 use crate::models::{CreatePaymentIntentRequest, PaymentIntent, PaymentStatus, PaymentWebhook};
-use sqlx::PgPool;
+use platform::tenant::TenantTx;
 use uuid::Uuid;
 
-#[derive(Clone)]
-pub struct PaymentRepo {
-    pub pool: PgPool,
-}
+pub struct PaymentRepo;
 
 impl PaymentRepo {
-    pub fn new(pool: PgPool) -> Self {
-        Self { pool }
-    }
-
-    pub async fn create_intent<'a, E>(
-        executor: E,
+    pub async fn create_intent(
+        tx: &mut TenantTx<'_>,
         tenant_id: &Uuid,
         req: &CreatePaymentIntentRequest,
-    ) -> Result<PaymentIntent, sqlx::Error>
-    where
-        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
-    {
+    ) -> Result<PaymentIntent, sqlx::Error> {
         sqlx::query_as::<_, PaymentIntent>(
             r#"
             INSERT INTO payment_intents (
@@ -41,37 +31,28 @@ impl PaymentRepo {
         .bind(&req.currency)
         .bind(&req.provider)
         .bind(req.metadata.as_ref())
-        .fetch_one(executor)
+        .fetch_one(tx.executor())
         .await
     }
 
-    pub async fn get<'a, E>(executor: E, id: Uuid) -> Result<PaymentIntent, sqlx::Error>
-    where
-        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
-    {
+    pub async fn get(tx: &mut TenantTx<'_>, id: Uuid) -> Result<PaymentIntent, sqlx::Error> {
         sqlx::query_as::<_, PaymentIntent>("SELECT * FROM payment_intents WHERE id = $1")
             .bind(id)
-            .fetch_one(executor)
+            .fetch_one(tx.executor())
             .await
     }
 
-    pub async fn get_intent_by_order_id<'a, E>(executor: E, order_id: Uuid) -> Result<PaymentIntent, sqlx::Error>
-    where
-        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
-    {
+    pub async fn get_intent_by_order_id(tx: &mut TenantTx<'_>, order_id: Uuid) -> Result<PaymentIntent, sqlx::Error> {
         sqlx::query_as::<_, PaymentIntent>("SELECT * FROM payment_intents WHERE order_id = $1 ORDER BY created_at DESC LIMIT 1")
             .bind(order_id)
-            .fetch_one(executor)
+            .fetch_one(tx.executor())
             .await
     }
 
-    pub async fn apply_webhook<'a, E>(
-        executor: E,
+    pub async fn apply_webhook(
+        tx: &mut TenantTx<'_>,
         webhook: &PaymentWebhook,
-    ) -> Result<PaymentIntent, sqlx::Error>
-    where
-        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
-    {
+    ) -> Result<PaymentIntent, sqlx::Error> {
         sqlx::query_as::<_, PaymentIntent>(
             r#"
             UPDATE payment_intents
@@ -90,63 +71,51 @@ impl PaymentRepo {
         .bind(&webhook.provider_reference)
         .bind(webhook.metadata.as_ref())
         .bind(&webhook.idempotency_key)
-        .fetch_one(executor)
+        .fetch_one(tx.executor())
         .await
     }
 
-    pub async fn update_status<'a, E>(
-        executor: E,
+    pub async fn update_status(
+        tx: &mut TenantTx<'_>,
         id: Uuid,
         status: PaymentStatus,
-    ) -> Result<PaymentIntent, sqlx::Error>
-    where
-        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
-    {
+    ) -> Result<PaymentIntent, sqlx::Error> {
         sqlx::query_as::<_, PaymentIntent>(
             "UPDATE payment_intents SET status = $1, updated_at = NOW() WHERE id = $2 RETURNING *",
         )
         .bind(status)
         .bind(id)
-        .fetch_one(executor)
+        .fetch_one(tx.executor())
         .await
     }
 
-    pub async fn update_provider_reference<'a, E>(
-        executor: E,
+    pub async fn update_provider_reference(
+        tx: &mut TenantTx<'_>,
         id: Uuid,
         provider_reference: &str,
         metadata: &serde_json::Value,
-    ) -> Result<PaymentIntent, sqlx::Error>
-    where
-        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
-    {
+    ) -> Result<PaymentIntent, sqlx::Error> {
         sqlx::query_as::<_, PaymentIntent>(
             "UPDATE payment_intents SET provider_reference = $1, metadata = $2, updated_at = NOW() WHERE id = $3 RETURNING *",
         )
         .bind(provider_reference)
         .bind(metadata)
         .bind(id)
-        .fetch_one(executor)
+        .fetch_one(tx.executor())
         .await
     }
 
-    pub async fn cancel_by_order_id<'a, E>(executor: E, order_id: Uuid) -> Result<(), sqlx::Error>
-    where
-        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
-    {
-        PaymentRepo::cancel_by_order_id_returning(executor, order_id).await?;
+    pub async fn cancel_by_order_id(tx: &mut TenantTx<'_>, order_id: Uuid) -> Result<(), sqlx::Error> {
+        PaymentRepo::cancel_by_order_id_returning(tx, order_id).await?;
         Ok(())
     }
 
-    pub async fn cancel_by_order_id_returning<'a, E>(executor: E, order_id: Uuid) -> Result<PaymentIntent, sqlx::Error>
-    where
-        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
-    {
+    pub async fn cancel_by_order_id_returning(tx: &mut TenantTx<'_>, order_id: Uuid) -> Result<PaymentIntent, sqlx::Error> {
         sqlx::query_as::<_, PaymentIntent>(
             "UPDATE payment_intents SET status = 'cancelled', updated_at = NOW() WHERE order_id = $1 AND status != 'succeeded' RETURNING *"
         )
         .bind(order_id)
-        .fetch_one(executor)
+        .fetch_one(tx.executor())
         .await
     }
 }
@@ -154,12 +123,11 @@ impl PaymentRepo {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use sqlx::PgPool;
+use sqlx::PgPool;
 
     #[sqlx::test]
     #[ignore]
     async fn test_create_and_idempotency(pool: PgPool) {
-        let repo = PaymentRepo::new(pool);
         let req = CreatePaymentIntentRequest {
             idempotency_key: "test_idemp_key".to_string(),
             order_id: Uuid::new_v4(),
@@ -174,19 +142,20 @@ mod tests {
         };
 
         // First creation
-        let tenant_id = Uuid::new_v4(); let intent1 = PaymentRepo::create_intent(&repo.pool, &tenant_id, &req).await.expect("Failed to create intent");
+        let tenant_id = Uuid::new_v4();
+        let mut tx = TenantTx::begin(&pool, tenant_id).await.unwrap();
+        let intent1 = PaymentRepo::create_intent(&mut tx, &tenant_id, &req).await.expect("Failed to create intent");
         assert_eq!(intent1.idempotency_key, "test_idemp_key");
         assert_eq!(intent1.amount, 5000);
 
         // Idempotent creation (same key)
-        let intent2 = PaymentRepo::create_intent(&repo.pool, &tenant_id, &req).await.expect("Failed idempotent creation");
+        let intent2 = PaymentRepo::create_intent(&mut tx, &tenant_id, &req).await.expect("Failed idempotent creation");
         assert_eq!(intent1.id, intent2.id, "Idempotent request should return the same intent ID");
     }
 
     #[sqlx::test]
     #[ignore]
     async fn test_apply_webhook(pool: PgPool) {
-        let repo = PaymentRepo::new(pool);
         let req = CreatePaymentIntentRequest {
             idempotency_key: "webhook_test_key".to_string(),
             order_id: Uuid::new_v4(),
@@ -200,7 +169,9 @@ mod tests {
             metadata: None,
         };
 
-        let tenant_id = Uuid::new_v4(); let intent = PaymentRepo::create_intent(&repo.pool, &tenant_id, &req).await.unwrap();
+        let tenant_id = Uuid::new_v4();
+        let mut tx = TenantTx::begin(&pool, tenant_id).await.unwrap();
+        let intent = PaymentRepo::create_intent(&mut tx, &tenant_id, &req).await.unwrap();
 
         let webhook = PaymentWebhook {
             provider_reference: None,
@@ -209,7 +180,7 @@ mod tests {
             metadata: None,
         };
 
-        let updated = PaymentRepo::apply_webhook(&repo.pool, &webhook).await.expect("Failed to apply webhook");
+        let updated = PaymentRepo::apply_webhook(&mut tx, &webhook).await.expect("Failed to apply webhook");
         assert_eq!(updated.id, intent.id);
         assert!(matches!(updated.status, PaymentStatus::Succeeded));
     }
@@ -1 +0,0 @@
-// Payments specific errors
@@ -1,14 +1,22 @@
-use actix_web::{web, HttpResponse, Responder};
+use actix_web::{web, HttpResponse, Responder, ResponseError};
 use chrono::Utc;
-use platform::{streams::StreamPublisher, tenant::TenantContext, db_router::DynamicPoolRouter};
+use platform::errors::AppError;
+use platform::events::{DomainEvent, PaymentEvent};
+use platform::{outbox, tenant::{TenantContext, TenantTx}, db_router::DynamicPoolRouter};
 use uuid::Uuid;
 
 use crate::db::PaymentRepo;
 use crate::models::{
-    CreatePaymentIntentRequest, PaymentEvent, PaymentIntent, PaymentStatus, PaymentWebhook,
+    CreatePaymentIntentRequest, PaymentIntent, PaymentStatus, PaymentWebhook,
 };
 use crate::stripe::StripeClient;
 
+/// Stripe failures are logged; the client only learns the provider failed.
+fn provider_error(err: String) -> HttpResponse {
+    tracing::error!(error = %err, "payment provider request failed");
+    AppError::BadGateway("Payment provider request failed".into()).error_response()
+}
+
 #[utoipa::path(
     post,
     path = "/payments/intents",
@@ -22,28 +30,32 @@ use crate::stripe::StripeClient;
 pub async fn create_payment_intent(
     tenant: actix_web::web::ReqData<TenantContext>,
     db_router: actix_web::web::Data<DynamicPoolRouter>,
-    publisher: web::Data<StreamPublisher>,
+    stripe_client: web::Data<StripeClient>,
     mut req: web::Json<CreatePaymentIntentRequest>,
 ) -> impl Responder {
-    let stripe_client = StripeClient::new();
     let amount_cents = req.amount;
     let currency = req.currency.clone().unwrap_or_else(|| "usd".to_string());
 
     req.provider = Some("stripe".to_string());
 
-let pool = db_router.get_pool(&tenant).await.unwrap();
-    let mut tx = pool.begin().await.unwrap();
-    tenant.apply_rls(&mut *tx).await.unwrap();
+    let pool = match db_router.get_pool(&tenant).await {
+        Ok(pool) => pool,
+        Err(e) => return DynamicPoolRouter::unavailable(e).error_response(),
+    };
+    let mut tx = match tenant.begin(&pool).await {
+        Ok(tx) => tx,
+        Err(e) => return AppError::from(e).error_response(),
+    };
 
-    let intent = match PaymentRepo::create_intent(&mut *tx, &tenant.tenant_id, &req).await {
+    let intent = match PaymentRepo::create_intent(&mut tx, &tenant.tenant_id, &req).await {
         Ok(i) => i,
-        Err(e) => return HttpResponse::InternalServerError().body(format!("db error: {e}")),
+        Err(e) => return AppError::from(e).error_response(),
     };
 
     // 2. Call Stripe
-    let stripe_res = match stripe_client.create_payment_intent(amount_cents, &currency, req.metadata.clone(), &req.idempotency_key).await {
+    let stripe_res = match stripe_client.create_payment_intent(amount_cents, &currency, Some(stripe_metadata(tenant.tenant_id, req.metadata.as_ref())), &req.idempotency_key).await {
         Ok(res) => res,
-        Err(e) => return HttpResponse::InternalServerError().body(e),
+        Err(e) => return provider_error(e),
     };
 
     // 3. Update local DB with Stripe ID
@@ -53,13 +65,15 @@ let pool = db_router.get_pool(&tenant).await.unwrap();
         obj.insert("stripe_id".to_string(), serde_json::Value::String(stripe_res.id.clone()));
     }
 
-    match PaymentRepo::update_provider_reference(&mut *tx, intent.id, &stripe_res.id, &meta).await {
+    match PaymentRepo::update_provider_reference(&mut tx, intent.id, &stripe_res.id, &meta).await {
         Ok(updated_intent) => {
+            if let Err(e) = enqueue_payment_event(tx.executor(), tenant.tenant_id, DomainEvent::PaymentInitiated, &updated_intent).await {
+                return AppError::from(e).error_response();
+            }
             tx.commit().await.unwrap();
-            publish_payment_event(&publisher, tenant.tenant_id, "payment.initiated", &updated_intent);
             HttpResponse::Created().json(updated_intent)
         }
-        Err(e) => HttpResponse::InternalServerError().body(format!("db error: {e}")),
+        Err(e) => AppError::from(e).error_response(),
     }
 }
 
@@ -81,14 +95,19 @@ pub async fn get_payment_intent(
     db_router: actix_web::web::Data<DynamicPoolRouter>,
     path: web::Path<Uuid>,
 ) -> impl Responder {
-    let pool = db_router.get_pool(&tenant).await.unwrap();
-    let mut tx = pool.begin().await.unwrap();
-    tenant.apply_rls(&mut *tx).await.unwrap();
+    let pool = match db_router.get_pool(&tenant).await {
+        Ok(pool) => pool,
+        Err(e) => return DynamicPoolRouter::unavailable(e).error_response(),
+    };
+    let mut tx = match tenant.begin(&pool).await {
+        Ok(tx) => tx,
+        Err(e) => return AppError::from(e).error_response(),
+    };
 
-    match PaymentRepo::get(&mut *tx, path.into_inner()).await {
+    match PaymentRepo::get(&mut tx, path.into_inner()).await {
         Ok(intent) => { tx.commit().await.unwrap(); HttpResponse::Ok().json(intent) },
-        Err(sqlx::Error::RowNotFound) => HttpResponse::NotFound().body("payment intent not found"),
-        Err(e) => HttpResponse::InternalServerError().body(format!("db error: {e}")),
+        Err(sqlx::Error::RowNotFound) => AppError::NotFound("payment intent not found".into()).error_response(),
+        Err(e) => AppError::from(e).error_response(),
     }
 }
 
@@ -108,10 +127,9 @@ pub async fn get_payment_intent(
 pub async fn mark_payment_succeeded(
     tenant: actix_web::web::ReqData<TenantContext>,
     db_router: actix_web::web::Data<DynamicPoolRouter>,
-    publisher: web::Data<StreamPublisher>,
     path: web::Path<Uuid>,
 ) -> impl Responder {
-    update_status(tenant, db_router, publisher, path.into_inner(), PaymentStatus::Succeeded).await
+    update_status(tenant, db_router, path.into_inner(), PaymentStatus::Succeeded).await
 }
 
 #[utoipa::path(
@@ -130,10 +148,9 @@ pub async fn mark_payment_succeeded(
 pub async fn mark_payment_failed(
     tenant: actix_web::web::ReqData<TenantContext>,
     db_router: actix_web::web::Data<DynamicPoolRouter>,
-    publisher: web::Data<StreamPublisher>,
     path: web::Path<Uuid>,
 ) -> impl Responder {
-    update_status(tenant, db_router, publisher, path.into_inner(), PaymentStatus::Failed).await
+    update_status(tenant, db_router, path.into_inner(), PaymentStatus::Failed).await
 }
 
 #[utoipa::path(
@@ -150,75 +167,112 @@ pub async fn mark_payment_failed(
 )]
 pub async fn payment_webhook(
     db_router: actix_web::web::Data<DynamicPoolRouter>,
-    publisher: web::Data<StreamPublisher>,
+    stripe_client: web::Data<StripeClient>,
     req: actix_web::HttpRequest,
     body: web::Bytes,
 ) -> impl Responder {
-    let stripe_client = StripeClient::new();
     let signature = req.headers().get("Stripe-Signature").and_then(|v| v.to_str().ok()).unwrap_or("");
     let payload_str = String::from_utf8_lossy(&body);
 
     if let Err(e) = stripe_client.verify_webhook_signature(&payload_str, signature) {
-        return HttpResponse::BadRequest().body(format!("Invalid signature: {e}"));
+        return AppError::BadRequest(format!("Invalid signature: {e}")).error_response();
     }
 
     let Ok(webhook) = serde_json::from_str::<PaymentWebhook>(&payload_str) else {
-        return HttpResponse::BadRequest().body("Invalid webhook payload");
+        return AppError::BadRequest("Invalid webhook payload".into()).error_response();
     };
 
-    let pool = db_router.shared_pool();
+    // The webhook is unauthenticated; the tenant comes from the metadata stamped on the
+    // intent when it was created.
+    let Some(tenant_id) = webhook.tenant_id() else {
+        return AppError::BadRequest("Webhook metadata has no tenant_id".into()).error_response();
+    };
+    let pool = match db_router.get_tenant_pool(tenant_id).await {
+        Ok(pool) => pool,
+        Err(e) => return DynamicPoolRouter::unavailable(e).error_response(),
+    };
+    let mut tx = match TenantTx::begin(&pool, tenant_id).await {
+        Ok(tx) => tx,
+        Err(e) => return AppError::from(e).error_response(),
+    };
 
-    match PaymentRepo::apply_webhook(pool, &webhook).await {
+    match PaymentRepo::apply_webhook(&mut tx, &webhook).await {
         Ok(intent) => {
-            let event_type = event_type_for_status(&intent.status);
-            publish_payment_event(&publisher, intent.tenant_id, event_type, &intent);
+            let event = event_for_status(&intent.status);
+            if let Err(e) = enqueue_payment_event(tx.executor(), intent.tenant_id, event, &intent).await {
+                return AppError::from(e).error_response();
+            }
+            tx.commit().await.unwrap();
             HttpResponse::Ok().json(intent)
         }
-        Err(sqlx::Error::RowNotFound) => HttpResponse::NotFound().body("payment intent not found"),
-        Err(e) => HttpResponse::InternalServerError().body(format!("db error: {e}")),
+        Err(sqlx::Error::RowNotFound) => AppError::NotFound("payment intent not found".into()).error_response(),
+        Err(e) => AppError::from(e).error_response(),
     }
 }
 
 async fn update_status(
     tenant: actix_web::web::ReqData<TenantContext>,
     db_router: actix_web::web::Data<DynamicPoolRouter>,
-    publisher: web::Data<StreamPublisher>,
     id: Uuid,
     status: PaymentStatus,
 ) -> HttpResponse {
-    let pool = db_router.get_pool(&tenant).await.unwrap();
-    let mut tx = pool.begin().await.unwrap();
-    tenant.apply_rls(&mut *tx).await.unwrap();
+    let pool = match db_router.get_pool(&tenant).await {
+        Ok(pool) => pool,
+        Err(e) => return DynamicPoolRouter::unavailable(e).error_response(),
+    };
+    let mut tx = match tenant.begin(&pool).await {
+        Ok(tx) => tx,
+        Err(e) => return AppError::from(e).error_response(),
+    };
 
-    match PaymentRepo::update_status(&mut *tx, id, status).await {
+    match PaymentRepo::update_status(&mut tx, id, status).await {
         Ok(intent) => {
+            let event = event_for_status(&intent.status);
+            if let Err(e) = enqueue_payment_event(tx.executor(), tenant.tenant_id, event, &intent).await {
+                return AppError::from(e).error_response();
+            }
             tx.commit().await.unwrap();
-            let event_type = event_type_for_status(&intent.status);
-            publish_payment_event(&publisher, tenant.tenant_id, event_type, &intent);
             HttpResponse::Ok().json(intent)
         }
-        Err(sqlx::Error::RowNotFound) => HttpResponse::NotFound().body("payment intent not found"),
-        Err(e) => HttpResponse::InternalServerError().body(format!("db error: {e}")),
+        Err(sqlx::Error::RowNotFound) => AppError::NotFound("payment intent not found".into()).error_response(),
+        Err(e) => AppError::from(e).error_response(),
     }
 }
 
-fn event_type_for_status(status: &PaymentStatus) -> &'static str {
+fn event_for_status(status: &PaymentStatus) -> fn(PaymentEvent) -> DomainEvent {
     match status {
-        PaymentStatus::Succeeded => "payment.success",
-        PaymentStatus::Failed => "payment.failed",
-        PaymentStatus::Cancelled => "payment.cancelled",
-        PaymentStatus::Refunded => "payment.refunded",
-        PaymentStatus::Processing => "payment.processing",
-        PaymentStatus::RequiresPaymentMethod => "payment.initiated",
+        PaymentStatus::Succeeded => DomainEvent::PaymentSuccess,
+        PaymentStatus::Failed => DomainEvent::PaymentFailed,
+        PaymentStatus::Cancelled => DomainEvent::PaymentCancelled,
+        PaymentStatus::Refunded => DomainEvent::PaymentRefunded,
+        PaymentStatus::Processing => DomainEvent::PaymentProcessing,
+        PaymentStatus::RequiresPaymentMethod => DomainEvent::PaymentInitiated,
+    }
+}
+
+/// Metadata sent to Stripe: the caller's string fields plus `tenant_id`, which comes back
+/// on webhooks so they can be applied inside the tenant's RLS scope.
+pub(crate) fn stripe_metadata(tenant_id: Uuid, metadata: Option<&serde_json::Value>) -> serde_json::Value {
+    let mut meta = metadata.cloned().filter(|m| m.is_object()).unwrap_or_else(|| serde_json::json!({}));
+    if let Some(obj) = meta.as_object_mut() {
+        obj.insert("tenant_id".to_string(), serde_json::Value::String(tenant_id.to_string()));
     }
+    meta
 }
 
-fn publish_payment_event(publisher: &StreamPublisher, tenant_id: Uuid, event_type: &str, intent: &PaymentIntent) {
-    publisher.publish_async(
-        event_type,
-        PaymentEvent {
+async fn enqueue_payment_event<'c, E>(
+    executor: E,
+    tenant_id: Uuid,
+    event: fn(PaymentEvent) -> DomainEvent,
+    intent: &PaymentIntent,
+) -> Result<(), outbox::OutboxError>
+where
+    E: sqlx::Executor<'c, Database = sqlx::Postgres>,
+{
+    outbox::enqueue_event(
+        executor,
+        &event(PaymentEvent {
             tenant_id,
-            event_type: event_type.to_string(),
             payment_id: intent.id,
             order_id: intent.order_id,
             user_id: intent.user_id,
@@ -230,8 +284,10 @@ fn publish_payment_event(publisher: &StreamPublisher, tenant_id: Uuid, event_typ
             provider: intent.provider.clone(),
             provider_reference: intent.provider_reference.clone(),
             timestamp: Utc::now(),
-        },
-    );
+        }),
+    )
+    .await?;
+    Ok(())
 }
 
 #[utoipa::path(
@@ -251,27 +307,31 @@ fn publish_payment_event(publisher: &StreamPublisher, tenant_id: Uuid, event_typ
 pub async fn refund_payment_endpoint(
     tenant: actix_web::web::ReqData<TenantContext>,
     db_router: actix_web::web::Data<DynamicPoolRouter>,
-    publisher: web::Data<StreamPublisher>,
+    stripe_client: web::Data<StripeClient>,
     path: web::Path<Uuid>,
 ) -> impl Responder {
-    let stripe_client = StripeClient::new();
     let id = path.into_inner();
-    let pool = db_router.get_pool(&tenant).await.unwrap();
-    let mut tx = pool.begin().await.unwrap();
-    tenant.apply_rls(&mut *tx).await.unwrap();
+    let pool = match db_router.get_pool(&tenant).await {
+        Ok(pool) => pool,
+        Err(e) => return DynamicPoolRouter::unavailable(e).error_response(),
+    };
+    let mut tx = match tenant.begin(&pool).await {
+        Ok(tx) => tx,
+        Err(e) => return AppError::from(e).error_response(),
+    };
 
-    let intent = match PaymentRepo::get(&mut *tx, id).await {
+    let intent = match PaymentRepo::get(&mut tx, id).await {
         Ok(i) => i,
-        Err(_) => return HttpResponse::NotFound().body("payment intent not found"),
+        Err(_) => return AppError::NotFound("payment intent not found".into()).error_response(),
     };
 
     if let Some(stripe_id) = &intent.provider_reference {
         match stripe_client.refund_payment(stripe_id, None, Some(&id.to_string())).await {
-            Ok(_) => { tx.commit().await.unwrap(); update_status(tenant, db_router, publisher, id, PaymentStatus::Refunded).await },
-            Err(e) => HttpResponse::InternalServerError().body(format!("Stripe error: {e}")),
+            Ok(_) => { tx.commit().await.unwrap(); update_status(tenant, db_router, id, PaymentStatus::Refunded).await },
+            Err(e) => provider_error(e),
         }
     } else {
-        HttpResponse::BadRequest().body("No provider reference found")
+        AppError::BadRequest("No provider reference found".into()).error_response()
     }
 }
 
@@ -291,18 +351,22 @@ pub async fn refund_payment_endpoint(
 pub async fn transfer_payment_endpoint(
     tenant: actix_web::web::ReqData<TenantContext>,
     db_router: actix_web::web::Data<DynamicPoolRouter>,
-    _publisher: web::Data<StreamPublisher>,
+    stripe_client: web::Data<StripeClient>,
     path: web::Path<Uuid>,
 ) -> impl Responder {
-    let stripe_client = StripeClient::new();
     let id = path.into_inner();
-    let pool = db_router.get_pool(&tenant).await.unwrap();
-    let mut tx = pool.begin().await.unwrap();
-    tenant.apply_rls(&mut *tx).await.unwrap();
+    let pool = match db_router.get_pool(&tenant).await {
+        Ok(pool) => pool,
+        Err(e) => return DynamicPoolRouter::unavailable(e).error_response(),
+    };
+    let mut tx = match tenant.begin(&pool).await {
+        Ok(tx) => tx,
+        Err(e) => return AppError::from(e).error_response(),
+    };
 
-    let intent = match PaymentRepo::get(&mut *tx, id).await {
+    let intent = match PaymentRepo::get(&mut tx, id).await {
         Ok(i) => i,
-        Err(_) => return HttpResponse::NotFound().body("payment intent not found"),
+        Err(_) => return AppError::NotFound("payment intent not found".into()).error_response(),
     };
 
     // Assume 5% fee
@@ -318,7 +382,7 @@ pub async fn transfer_payment_endpoint(
             "transfer_id": tr_id,
             "payout_amount_cents": payout_amount
         })),
-        Err(e) => HttpResponse::InternalServerError().body(format!("Stripe error: {e}")),
+        Err(e) => provider_error(e),
     }
 }
 
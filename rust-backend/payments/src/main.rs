@@ -5,15 +5,18 @@ mod redis_sub;
 mod stripe;
 
 use actix_web::{web, App, HttpServer};
-use dotenvy::dotenv;
 use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
-use platform::{metrics, observability, streams::StreamPublisher, middleware::tenant_middleware::TenantAuthMiddleware, db_router::DynamicPoolRouter};
+use platform::config::{Kind, Schema, ServiceConfig, Setting};
+use platform::health::{self, Readiness};
+use platform::middleware::request_id::RequestId;
+use platform::middleware::MetricsMiddleware;
+use platform::{metrics, observability, outbox::OutboxRelay, streams::StreamPublisher, supervisor::Supervisor, middleware::{tenant_middleware::TenantAuthMiddleware, Idempotency, JwksVerifier, RateLimiter, RequireScope}, scopes, db_router::DynamicPoolRouter, tenant_directory::TenantDirectory};
 use sqlx::postgres::PgPoolOptions;
-use std::env;
+use sqlx::migrate::Migrator;
 use redis::Client as RedisClient;
 
-use crate::db::PaymentRepo;
+use crate::stripe::StripeClient;
 
 #[utoipa::path(
     get,
@@ -53,14 +56,24 @@ pub async fn metrics_api_doc() {}
 )]
 pub struct ApiDoc;
 
+static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    dotenv().ok();
+    let config = ServiceConfig::load(
+        Schema::service("payments", 3010)
+            .with_consumer("payments-1")
+            .with_jwt()
+            .with(Setting::optional("STRIPE_SECRET_KEY", Kind::Text).secret())
+            .with(Setting::optional("STRIPE_WEBHOOK_SECRET", Kind::Text).secret())
+            .with(Setting::required("SUPPLIER_MANAGEMENT_URL", Kind::Url).with_default("http://localhost:3002")),
+    );
     observability::init_observability("payments");
     metrics::init_metrics("payments");
+    config.settings.log();
 
-    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
-    let redis_url = env::var("REDIS_URL").ok();
+    let database_url = &config.database_url;
+    let redis_url = config.redis_url.clone();
     let redis_raw_client = RedisClient::open(
         redis_url
             .clone()
@@ -69,46 +82,85 @@ async fn main() -> std::io::Result<()> {
     .expect("redis client");
     let redis_client = web::Data::new(redis_raw_client.clone());
 
-    let port = env::var("SERVICE_PORT")
-        .or_else(|_| env::var("PORT"))
-        .unwrap_or_else(|_| "3010".to_string());
+    let port = config.service_port;
+    let stripe_client = StripeClient::new(
+        config.settings.get("STRIPE_SECRET_KEY").unwrap_or_default(),
+        config.settings.get("STRIPE_WEBHOOK_SECRET").map(str::to_string),
+    );
 
     let pool = PgPoolOptions::new()
         .max_connections(10)
-        .connect(&database_url)
+        .connect(database_url)
         .await
         .expect("failed to connect postgres");
 
-    sqlx::migrate!("./migrations")
+    MIGRATOR
         .run(&pool)
         .await
         .expect("migrations failed");
 
-    let repo = web::Data::new(PaymentRepo::new(pool.clone()));
-    let db_router = web::Data::new(DynamicPoolRouter::new(pool.clone()));
-    let publisher = web::Data::new(match redis_url {
-        Some(url) => StreamPublisher::new(&url).unwrap_or_else(|_| StreamPublisher::noop()),
+    // The stream consumer and webhooks carry only a tenant id, so they resolve dedicated
+    // databases through the tenant directory
+    let db_router = web::Data::new(
+        DynamicPoolRouter::new(pool.clone())
+            .with_migrator(&MIGRATOR)
+            .with_tenant_directory(TenantDirectory::from_env().with_redis(redis_raw_client.clone())),
+    );
+    db_router.spawn_eviction();
+    let publisher = match &redis_url {
+        Some(url) => StreamPublisher::new(url).unwrap_or_else(|_| StreamPublisher::noop()),
         None => StreamPublisher::noop(),
-    });
+    };
+    let supervisor = Supervisor::new("payments").with_deadline(config.shutdown_timeout);
+    OutboxRelay::new("payments", pool.clone(), publisher)
+        .with_router(db_router.get_ref().clone())
+        .supervise(&supervisor);
+
+    let mut readiness = Readiness::new("payments")
+        .with_postgres(pool.clone())
+        .with_supervisor(supervisor.clone());
+    if redis_url.is_some() {
+        let max_lag = config.settings.integer("STREAM_MAX_LAG").unwrap_or_default() as u64;
+        readiness = readiness
+            .with_redis(redis_raw_client.clone())
+            .with_consumer(redis_raw_client.clone(), redis_sub::GROUP, redis_sub::EVENTS, max_lag);
+    }
+    let readiness = web::Data::new(readiness);
 
-    let repo_clone = repo.clone();
-    tokio::spawn(async move {
-        if let Err(e) = redis_sub::listen_to_redis_events(repo_clone).await {
-            tracing::error!("Payments Redis subscriber failed: {}", e);
-        }
-    });
+    if redis_url.is_some() {
+        let router_clone = db_router.clone();
+        let stripe_clone = stripe_client.clone();
+        let supplier_url = config.settings.text("SUPPLIER_MANAGEMENT_URL").to_string();
+        let consumer = config.settings.text("CONSUMER_NAME").to_string();
+        supervisor.spawn("stream-consumer", move || {
+            redis_sub::listen_to_redis_events(
+                router_clone.clone(),
+                stripe_clone.clone(),
+                supplier_url.clone(),
+                redis_url.clone(),
+                consumer.clone(),
+            )
+        });
+    }
 
-    HttpServer::new(move || {
+    let stripe_client = web::Data::new(stripe_client);
+    let jwt_verifier = JwksVerifier::from_env().expect("JWT verification keys must be configured (JWKS_URL)");
+
+    let server = HttpServer::new(move || {
         App::new()
+            .wrap(MetricsMiddleware)
+            .wrap(RequestId)
             .service(
                 SwaggerUi::new("/swagger-ui/{_:.*}")
                     .url("/api-docs/openapi.json", ApiDoc::openapi())
             )
-            .app_data(repo.clone())
             .app_data(db_router.clone())
-            .app_data(publisher.clone())
             .app_data(redis_client.clone())
+            .app_data(stripe_client.clone())
+            .app_data(readiness.clone())
             .route("/health", web::get().to(handlers::health))
+            .route("/health/live", web::get().to(health::health_check))
+            .route("/health/ready", web::get().to(health::readiness))
             .route("/metrics", web::get().to(metrics::metrics_handler))
             .route(
                 "/payments/webhooks",
@@ -116,7 +168,11 @@ async fn main() -> std::io::Result<()> {
             )
             .service(
                 web::scope("")
-                    .wrap(TenantAuthMiddleware::with_redis(redis_raw_client.clone()))
+                    .wrap(Idempotency::new(redis_raw_client.clone()))
+                    .wrap(RequireScope::resource(scopes::PAYMENTS))
+                    .wrap(RateLimiter::new(redis_raw_client.clone()))
+                    .wrap(TenantAuthMiddleware::with_redis(redis_raw_client.clone()).with_jwt_verifier(jwt_verifier.clone()))
+                    .route("/health/db", web::get().to(health::tenant_db_health))
                     .route(
                         "/payments/intents",
                         web::post().to(handlers::create_payment_intent),
@@ -144,6 +200,9 @@ async fn main() -> std::io::Result<()> {
             )
     })
     .bind(format!("0.0.0.0:{port}"))?
-    .run()
-    .await
+    .disable_signals()
+    .shutdown_timeout(supervisor.deadline().as_secs())
+    .run();
+
+    supervisor.serve(server).await
 }
@@ -58,21 +58,11 @@ pub struct PaymentWebhook {
     pub metadata: Option<Value>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct PaymentEvent {
-    pub tenant_id: Uuid,
-    pub event_type: String,
-    pub payment_id: Uuid,
-    pub order_id: Uuid,
-    pub user_id: Uuid,
-    pub supplier_id: Uuid,
-    pub product_id: Uuid,
-    pub quantity: i32,
-    pub amount: i64,
-    pub currency: String,
-    pub provider: String,
-    pub provider_reference: Option<String>,
-    pub timestamp: DateTime<Utc>,
+impl PaymentWebhook {
+    /// Tenant stamped into the provider metadata when the intent was created.
+    pub fn tenant_id(&self) -> Option<Uuid> {
+        self.metadata.as_ref()?.get("tenant_id")?.as_str()?.parse().ok()
+    }
 }
 
 #[cfg(test)]
@@ -94,4 +84,21 @@ mod tests {
             "\"succeeded\""
         );
     }
+
+    #[test]
+    fn test_webhook_tenant_id_from_metadata() {
+        let tenant_id = Uuid::new_v4();
+        let mut webhook = PaymentWebhook {
+            provider_reference: Some("pi_123".to_string()),
+            idempotency_key: None,
+            status: PaymentStatus::Succeeded,
+            metadata: Some(serde_json::json!({ "tenant_id": tenant_id.to_string(), "stripe_id": "pi_123" })),
+        };
+        assert_eq!(webhook.tenant_id(), Some(tenant_id));
+
+        webhook.metadata = Some(serde_json::json!({ "tenant_id": "not-a-uuid" }));
+        assert_eq!(webhook.tenant_id(), None);
+        webhook.metadata = None;
+        assert_eq!(webhook.tenant_id(), None);
+    }
 }
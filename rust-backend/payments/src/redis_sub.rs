@@ -1,61 +1,60 @@
 use actix_web::web;
+use platform::db_router::DynamicPoolRouter;
+use platform::events::DomainEvent;
+use platform::tenant::TenantTx;
 use platform::{metrics, streams};
-use std::env;
-use uuid::Uuid;
+use sqlx::PgPool;
 
 use crate::db::PaymentRepo;
+use crate::handlers::stripe_metadata;
 use crate::models::CreatePaymentIntentRequest;
 use crate::stripe::StripeClient;
 
-// We need a subset of the ProductEvent/OrderEvent to parse the payload
-#[derive(Debug, serde::Deserialize)]
-pub struct OrderContextEvent {
-    pub tenant_id: Option<Uuid>,
-    pub order_id: Option<Uuid>,
-    pub user_id: Option<Uuid>,
-    pub supplier_id: Option<Uuid>,
-    pub product_id: Option<Uuid>,
-    pub quantity: Option<i32>,
-    pub price: Option<f64>,
-}
+/// Consumer group this service reads its streams with.
+pub const GROUP: &str = "payments";
 
-const EVENTS: &[&str] = &["inventory.reserved", "order.cancelled", "order.refunded", "order.delivered", "payment.refund_command"];
+pub const EVENTS: &[&str] = &["inventory.reserved", "order.cancelled", "order.refunded", "order.delivered", "payment.refund_command"];
 
 pub async fn listen_to_redis_events(
-    repo: web::Data<PaymentRepo>,
+    db_router: web::Data<DynamicPoolRouter>,
+    stripe_client: StripeClient,
+    supplier_url: String,
+    redis_url: Option<String>,
+    consumer: String,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let redis_url = env::var("REDIS_URL").map_err(|_| "REDIS_URL must be set")?;
-    let consumer = env::var("CONSUMER_NAME").unwrap_or_else(|_| "payments-1".to_string());
-    let stripe_client = StripeClient::new();
+    let redis_url = redis_url.ok_or("REDIS_URL must be set in environment")?;
 
-    streams::consume_json::<OrderContextEvent, _, _>(
+    streams::consume_json::<DomainEvent, _, _>(
         &redis_url,
-        "payments",
+        GROUP,
         &consumer,
         EVENTS,
         move |envelope| {
-            let repo = repo.clone();
+            let db_router = db_router.clone();
             let stripe_client = stripe_client.clone();
+            let supplier_url = supplier_url.clone();
             async move {
                 let event_type = envelope.event_type.clone();
-                let event = &envelope.payload;
-                let tenant_id = envelope.tenant_id.or(event.tenant_id);
+                let tenant_id = envelope.payload.tenant_id();
 
-                if tenant_id.is_none() || tenant_id == Some(uuid::Uuid::nil()) {
+                if tenant_id.is_nil() {
                     tracing::warn!(%event_type, stream = %envelope.stream, "Missing tenant_id in payment stream event — skipping business logic");
                     metrics::inc_event("payments", &envelope.stream, &event_type, "tenant_mismatch");
                     return Ok(());
                 }
 
-                if let (Some(env_tid), Some(pay_tid)) = (envelope.tenant_id, event.tenant_id) {
-                    if env_tid != pay_tid {
-                        tracing::warn!(%event_type, ?env_tid, ?pay_tid, "Tenant ID mismatch between envelope and payload — skipping business logic");
+                if let Some(env_tid) = envelope.tenant_id {
+                    if env_tid != tenant_id {
+                        tracing::warn!(%event_type, ?env_tid, pay_tid = ?tenant_id, "Tenant ID mismatch between envelope and payload — skipping business logic");
                         metrics::inc_event("payments", &envelope.stream, &event_type, "tenant_mismatch");
                         return Ok(());
                     }
                 }
 
-                let result = handle_event(&repo, &stripe_client, &event_type, envelope.payload).await;
+                let result = match db_router.get_tenant_pool(tenant_id).await {
+                    Ok(pool) => handle_event(&pool, &stripe_client, &supplier_url, envelope.payload).await,
+                    Err(e) => Err(e.into()),
+                };
 
                 metrics::inc_event(
                     "payments",
@@ -76,24 +75,25 @@ pub async fn listen_to_redis_events(
 }
 
 async fn handle_event(
-    repo: &PaymentRepo,
+    pool: &PgPool,
     stripe_client: &StripeClient,
-    event_type: &str,
-    event: OrderContextEvent,
+    supplier_url: &str,
+    event: DomainEvent,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    match event_type {
-        "inventory.reserved" => {
+    match event {
+        DomainEvent::InventoryReserved(event) => {
             let order_id = event.order_id.ok_or("Missing order_id")?;
             let user_id = event.user_id.unwrap_or_default();
             let supplier_id = event.supplier_id.unwrap_or_default();
-            let product_id = event.product_id.unwrap_or_default();
+            let product_id = event.product_id;
             let quantity = event.quantity.unwrap_or(1);
-            let price = event.price.unwrap_or(100.0);
-            let amount = ((quantity as f64) * price * 100.0) as i64;
+            // The order total priced by order-service; reservations published before
+            // orders carried totals fall back to the old flat unit price.
+            let amount = event.amount.unwrap_or(((quantity as f64) * 100.0 * 100.0) as i64);
             let idempotency_key = format!("auto_intent_{}", order_id);
 
             let stripe_res = stripe_client
-                .create_payment_intent(amount, "usd", None, &idempotency_key)
+                .create_payment_intent(amount, "usd", Some(stripe_metadata(event.tenant_id, None)), &idempotency_key)
                 .await?;
 
             let req = CreatePaymentIntentRequest {
@@ -112,20 +112,22 @@ async fn handle_event(
                 })),
             };
 
-            let t_id = event.tenant_id.or(event.supplier_id).unwrap_or_default();
-            PaymentRepo::create_intent(&repo.pool, &t_id, &req).await?;
+            let mut tx = TenantTx::begin(pool, event.tenant_id).await?;
+            PaymentRepo::create_intent(&mut tx, &event.tenant_id, &req).await?;
+            tx.commit().await?;
             println!("Auto-generated PaymentIntent for order {}", order_id);
         }
-        "order.cancelled" | "payment.refund_command" => {
-            let order_id = event.order_id.ok_or("Missing order_id")?;
-            if let Ok(intent) = PaymentRepo::get_intent_by_order_id(&repo.pool, order_id).await {
+        DomainEvent::OrderCancelled(event) | DomainEvent::PaymentRefundCommand(event) => {
+            let order_id = event.order_id;
+            let mut tx = TenantTx::begin(pool, event.tenant_id).await?;
+            if let Ok(intent) = PaymentRepo::get_intent_by_order_id(&mut tx, order_id).await {
                 if intent.status == crate::models::PaymentStatus::Succeeded {
                     // It succeeded already, so we must refund, not cancel
                     if let Some(stripe_id) = intent.provider_reference {
                         if let Err(e) = stripe_client.refund_payment(&stripe_id, None, Some(&intent.id.to_string())).await {
                             eprintln!("Failed to refund intent in Stripe: {e}");
                         } else {
-                            PaymentRepo::update_status(&repo.pool, intent.id, crate::models::PaymentStatus::Refunded).await?;
+                            PaymentRepo::update_status(&mut tx, intent.id, crate::models::PaymentStatus::Refunded).await?;
                             println!("Refunded PaymentIntent for order {}", order_id);
                         }
                     }
@@ -136,28 +138,34 @@ async fn handle_event(
                             eprintln!("Failed to cancel intent in Stripe: {e}");
                         }
                     }
-                    PaymentRepo::cancel_by_order_id(&repo.pool, order_id).await?;
+                    PaymentRepo::cancel_by_order_id(&mut tx, order_id).await?;
                     println!("Cancelled PaymentIntent for order {}", order_id);
                 }
             }
+            tx.commit().await?;
         }
-        "order.refunded" => {
-            let order_id = event.order_id.ok_or("Missing order_id")?;
-            if let Ok(intent) = PaymentRepo::get_intent_by_order_id(&repo.pool, order_id).await {
+        DomainEvent::OrderRefunded(event) => {
+            let order_id = event.order_id;
+            let mut tx = TenantTx::begin(pool, event.tenant_id).await?;
+            if let Ok(intent) = PaymentRepo::get_intent_by_order_id(&mut tx, order_id).await {
                 if let Some(stripe_id) = intent.provider_reference {
                     if let Err(e) = stripe_client.refund_payment(&stripe_id, None, Some(&intent.id.to_string())).await {
                         eprintln!("Failed to refund intent in Stripe: {e}");
                     } else {
                         // Mark as refunded in DB
-                        PaymentRepo::update_status(&repo.pool, intent.id, crate::models::PaymentStatus::Refunded).await?;
+                        PaymentRepo::update_status(&mut tx, intent.id, crate::models::PaymentStatus::Refunded).await?;
                         println!("Refunded PaymentIntent for order {}", order_id);
                     }
                 }
             }
+            tx.commit().await?;
         }
-        "order.delivered" => {
-            let order_id = event.order_id.ok_or("Missing order_id")?;
-            if let Ok(intent) = PaymentRepo::get_intent_by_order_id(&repo.pool, order_id).await {
+        DomainEvent::OrderDelivered(event) => {
+            let order_id = event.order_id;
+            let mut tx = TenantTx::begin(pool, event.tenant_id).await?;
+            let intent = PaymentRepo::get_intent_by_order_id(&mut tx, order_id).await;
+            tx.commit().await?;
+            if let Ok(intent) = intent {
                 let amount_cents = intent.amount;
                 
                 // Deduct 5% platform fee
@@ -165,9 +173,6 @@ async fn handle_event(
                 let payout_amount = amount_cents - platform_fee;
 
                 // Fetch real stripe connect account ID from supplier-management
-                let supplier_url = env::var("SUPPLIER_MANAGEMENT_URL")
-                    .unwrap_or_else(|_| "http://localhost:3002".to_string());
-                
                 let res = reqwest::Client::new()
                     .get(&format!("{}/suppliers/{}", supplier_url, intent.supplier_id))
                     .send()
@@ -3,7 +3,6 @@ use hmac::{Hmac, Mac};
 use reqwest::Client;
 use serde_json::Value;
 use sha2::Sha256;
-use std::env;
 
 #[derive(Debug, Clone)]
 pub struct StripeClient {
@@ -19,11 +18,12 @@ pub struct StripeIntentResponse {
 }
 
 impl StripeClient {
-    pub fn new() -> Self {
+    /// An empty `secret_key` puts the client in mock mode.
+    pub fn new(secret_key: impl Into<String>, webhook_secret: Option<String>) -> Self {
         Self {
             client: Client::new(),
-            secret_key: env::var("STRIPE_SECRET_KEY").unwrap_or_default(),
-            webhook_secret: env::var("STRIPE_WEBHOOK_SECRET").ok(),
+            secret_key: secret_key.into(),
+            webhook_secret,
         }
     }
 
@@ -247,9 +247,7 @@ mod tests {
 
     #[test]
     fn test_verify_webhook_signature_success() {
-        let mut client = StripeClient::new();
-        client.secret_key = "sk_test_123".to_string();
-        client.webhook_secret = Some("whsec_test".to_string());
+        let client = StripeClient::new("sk_test_123", Some("whsec_test".to_string()));
 
         let payload = r#"{"id":"evt_test"}"#;
         let timestamp = "1234567890";
@@ -266,9 +264,7 @@ mod tests {
 
     #[test]
     fn test_verify_webhook_signature_failure() {
-        let mut client = StripeClient::new();
-        client.secret_key = "sk_test_123".to_string();
-        client.webhook_secret = Some("whsec_test".to_string());
+        let client = StripeClient::new("sk_test_123", Some("whsec_test".to_string()));
 
         let payload = r#"{"id":"evt_test"}"#;
         let sig_header = "t=1234567890,v1=invalid_signature";
@@ -278,9 +274,7 @@ mod tests {
 
     #[test]
     fn test_verify_webhook_signature_unconfigured() {
-        let mut client = StripeClient::new();
-        client.secret_key = "".to_string();
-        client.webhook_secret = None;
+        let client = StripeClient::new("", None);
 
         let payload = r#"{"id":"evt_test"}"#;
         let sig_header = "t=1234567890,v1=invalid_signature";
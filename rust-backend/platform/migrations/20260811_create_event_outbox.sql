@@ -0,0 +1,21 @@
+-- Transactional outbox drained into Redis Streams by platform::outbox::OutboxRelay.
+-- Services copy this file into their own migrations directory so the table lives
+-- next to the domain rows it is written with.
+CREATE TABLE IF NOT EXISTS event_outbox (
+    seq BIGSERIAL PRIMARY KEY,
+    id UUID NOT NULL UNIQUE DEFAULT gen_random_uuid(),
+    tenant_id UUID NOT NULL,
+    event_type TEXT NOT NULL,
+    payload JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    published_at TIMESTAMPTZ,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_event_outbox_pending
+    ON event_outbox(tenant_id, seq)
+    WHERE published_at IS NULL;
+CREATE INDEX IF NOT EXISTS idx_event_outbox_published_at
+    ON event_outbox(published_at)
+    WHERE published_at IS NOT NULL;
@@ -1,14 +1,103 @@
+use serde::Serialize;
+use sqlx::migrate::Migrator;
+use sqlx::postgres::PgPoolOptions;
 use sqlx::PgPool;
 use std::collections::HashMap;
-use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::sync::{OnceCell, RwLock};
 use uuid::Uuid;
-use crate::tenant::{PricingTier, TenantContext};
+use crate::errors::AppError;
+use crate::metrics;
+use crate::tenant::{AuthMethod, PricingTier, TenantContext};
+use crate::tenant_directory::TenantDirectory;
 
+static CONTROL_PLANE_POOL: OnceLock<Option<PgPool>> = OnceLock::new();
+
+/// Process-wide lazy pool for the control-plane database (`CONTROL_PLANE_DATABASE_URL`),
+/// which holds `tenants` and `api_keys`. `None` when the variable is unset or invalid.
+pub fn control_plane_pool() -> Option<PgPool> {
+    CONTROL_PLANE_POOL
+        .get_or_init(|| {
+            let url = std::env::var("CONTROL_PLANE_DATABASE_URL").ok()?;
+            match PgPoolOptions::new().max_connections(5).connect_lazy(&url) {
+                Ok(pool) => Some(pool),
+                Err(e) => {
+                    tracing::warn!(error = %e, "invalid CONTROL_PLANE_DATABASE_URL");
+                    None
+                }
+            }
+        })
+        .clone()
+}
+
+/// A tenant's dedicated pool. The pool is connected (and migrated) once, on first use;
+/// a failed attempt leaves the cell empty so the next request retries.
+#[derive(Debug)]
+struct DedicatedPool {
+    /// `None` for pools handed over through `register_dedicated_pool`.
+    db_url: Option<String>,
+    pool: OnceCell<PgPool>,
+    last_used: Mutex<Instant>,
+}
+
+impl DedicatedPool {
+    fn new(db_url: String) -> Self {
+        Self {
+            db_url: Some(db_url),
+            pool: OnceCell::new(),
+            last_used: Mutex::new(Instant::now()),
+        }
+    }
+
+    fn ready(pool: PgPool) -> Self {
+        Self {
+            db_url: None,
+            pool: OnceCell::new_with(Some(pool)),
+            last_used: Mutex::new(Instant::now()),
+        }
+    }
+
+    fn serves(&self, db_url: &str) -> bool {
+        self.db_url.is_none() || self.db_url.as_deref() == Some(db_url)
+    }
+
+    fn touch(&self) {
+        *self.last_used.lock().unwrap_or_else(|e| e.into_inner()) = Instant::now();
+    }
+
+    fn idle_for(&self) -> Duration {
+        self.last_used.lock().unwrap_or_else(|e| e.into_inner()).elapsed()
+    }
+}
+
+/// Connection state of one tenant's dedicated database.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PoolHealth {
+    pub tenant_id: Uuid,
+    /// `false` until the first connection and migration run have succeeded.
+    pub connected: bool,
+    pub size: u32,
+    pub idle: usize,
+    pub max_connections: u32,
+    pub idle_seconds: u64,
+}
+
+/// Routes each tenant to its database: the shared pool for Free and Growth tenants, and
+/// a dedicated, bounded pool for Enterprise tenants that have a `db_connection_url`.
+///
+/// Dedicated pools are created on first use, after which the service's migrations are
+/// run against the tenant database (see [`DynamicPoolRouter::with_migrator`]). Pools idle
+/// for longer than the idle timeout are closed by [`DynamicPoolRouter::spawn_eviction`]
+/// and reconnected on the next request.
 #[derive(Clone, Debug)]
 pub struct DynamicPoolRouter {
     shared_pool: PgPool,
-    dedicated_pools: Arc<RwLock<HashMap<Uuid, PgPool>>>,
+    dedicated_pools: Arc<RwLock<HashMap<Uuid, Arc<DedicatedPool>>>>,
+    tenants: Option<TenantDirectory>,
+    migrator: Option<&'static Migrator>,
+    max_connections: u32,
+    idle_timeout: Duration,
 }
 
 impl DynamicPoolRouter {
@@ -16,54 +105,249 @@ impl DynamicPoolRouter {
         Self {
             shared_pool,
             dedicated_pools: Arc::new(RwLock::new(HashMap::new())),
+            tenants: None,
+            migrator: None,
+            max_connections: 5,
+            idle_timeout: Duration::from_secs(15 * 60),
         }
     }
 
+    /// Migrations to run against each dedicated database before it serves its first query.
+    pub fn with_migrator(mut self, migrator: &'static Migrator) -> Self {
+        self.migrator = Some(migrator);
+        self
+    }
+
+    /// Resolves tenants that arrive without a context built by `TenantAuthMiddleware`,
+    /// e.g. in stream consumers (see [`DynamicPoolRouter::get_tenant_pool`]).
+    pub fn with_tenant_directory(mut self, tenants: TenantDirectory) -> Self {
+        self.tenants = Some(tenants);
+        self
+    }
+
+    /// Upper bound on connections in each dedicated pool.
+    pub fn with_max_connections(mut self, max_connections: u32) -> Self {
+        self.max_connections = max_connections.max(1);
+        self
+    }
+
+    pub fn with_idle_timeout(mut self, idle_timeout: Duration) -> Self {
+        self.idle_timeout = idle_timeout;
+        self
+    }
+
     pub fn shared_pool(&self) -> &PgPool {
         &self.shared_pool
     }
 
+    /// 503 for a request whose tenant database cannot be reached or migrated; the client
+    /// should retry rather than treat it as a server fault.
+    pub fn unavailable(err: sqlx::Error) -> AppError {
+        tracing::error!(error = %err, "tenant database unavailable");
+        AppError::ServiceUnavailable("Tenant database unavailable".into())
+    }
+
     pub async fn get_pool(&self, ctx: &TenantContext) -> Result<PgPool, sqlx::Error> {
-        match ctx.tier {
-            PricingTier::Enterprise => {
-                if let Some(ref db_url) = ctx.db_connection_url {
-                    {
-                        let pools = self.dedicated_pools.read().await;
-                        if let Some(pool) = pools.get(&ctx.tenant_id) {
-                            return Ok(pool.clone());
-                        }
-                    }
-                    let mut pools = self.dedicated_pools.write().await;
-                    if let Some(pool) = pools.get(&ctx.tenant_id) {
-                        return Ok(pool.clone());
+        match (ctx.tier, &ctx.db_connection_url) {
+            (PricingTier::Enterprise, Some(db_url)) => self.dedicated_pool(ctx.tenant_id, db_url).await,
+            (PricingTier::Enterprise, None) => {
+                let pools = self.dedicated_pools.read().await;
+                match pools.get(&ctx.tenant_id).and_then(|entry| entry.pool.get().map(|pool| (entry, pool))) {
+                    Some((entry, pool)) => {
+                        entry.touch();
+                        Ok(pool.clone())
                     }
-                    let new_pool = PgPool::connect(db_url).await?;
-                    pools.insert(ctx.tenant_id, new_pool.clone());
-                    Ok(new_pool)
-                } else {
-                    {
-                        let pools = self.dedicated_pools.read().await;
-                        if let Some(pool) = pools.get(&ctx.tenant_id) {
-                            return Ok(pool.clone());
+                    None => Ok(self.shared_pool.clone()),
+                }
+            }
+            (PricingTier::Free | PricingTier::Growth, _) => Ok(self.shared_pool.clone()),
+        }
+    }
+
+    /// Looks the tenant up in the tenant directory and routes it like [`Self::get_pool`].
+    /// Tenants unknown to the directory, or a router without one, use the shared pool.
+    pub async fn get_tenant_pool(&self, tenant_id: Uuid) -> Result<PgPool, sqlx::Error> {
+        let record = match &self.tenants {
+            Some(tenants) => tenants.get(tenant_id).await?,
+            None => None,
+        };
+        match record {
+            Some(record) => {
+                let ctx = record.apply(TenantContext::new(
+                    tenant_id,
+                    None,
+                    record.tier,
+                    vec![],
+                    AuthMethod::ApiKey,
+                ));
+                self.get_pool(&ctx).await
+            }
+            None => Ok(self.shared_pool.clone()),
+        }
+    }
+
+    /// The shared pool followed by the pool of every tenant with a dedicated database, for
+    /// background jobs that sweep all tenant data. Dedicated databases are found through
+    /// the tenant directory; one that cannot be reached is skipped until the next sweep.
+    pub async fn sweep_pools(&self) -> Result<Vec<PgPool>, sqlx::Error> {
+        let mut pools = vec![self.shared_pool.clone()];
+        let Some(tenants) = &self.tenants else {
+            return Ok(pools);
+        };
+        for tenant_id in tenants.dedicated_tenant_ids().await? {
+            match self.get_tenant_pool(tenant_id).await {
+                Ok(pool) => pools.push(pool),
+                Err(e) => tracing::warn!(tenant_id = %tenant_id, error = %e, "skipping unreachable tenant database"),
+            }
+        }
+        Ok(pools)
+    }
+
+    async fn dedicated_pool(&self, tenant_id: Uuid, db_url: &str) -> Result<PgPool, sqlx::Error> {
+        let entry = {
+            let pools = self.dedicated_pools.read().await;
+            pools.get(&tenant_id).filter(|entry| entry.serves(db_url)).cloned()
+        };
+        let entry = match entry {
+            Some(entry) => entry,
+            None => {
+                let mut pools = self.dedicated_pools.write().await;
+                match pools.get(&tenant_id) {
+                    Some(entry) if entry.serves(db_url) => entry.clone(),
+                    _ => {
+                        // The tenant moved to another database: retire the old pool
+                        let entry = Arc::new(DedicatedPool::new(db_url.to_string()));
+                        if let Some(previous) = pools.insert(tenant_id, entry.clone()) {
+                            close(tenant_id, previous);
                         }
+                        entry
                     }
-                    Ok(self.shared_pool.clone())
                 }
             }
-            PricingTier::Free | PricingTier::Growth => Ok(self.shared_pool.clone()),
-        }
+        };
+        entry.touch();
+
+        let pool = entry
+            .pool
+            .get_or_try_init(|| async {
+                let pool = PgPoolOptions::new()
+                    .max_connections(self.max_connections)
+                    .min_connections(0)
+                    .idle_timeout(self.idle_timeout)
+                    .connect(db_url)
+                    .await?;
+                if let Some(migrator) = self.migrator {
+                    migrator.run(&pool).await?;
+                }
+                tracing::info!(tenant_id = %tenant_id, max_connections = self.max_connections, "dedicated tenant pool ready");
+                Ok::<_, sqlx::Error>(pool)
+            })
+            .await?;
+        Ok(pool.clone())
     }
 
     pub async fn register_dedicated_pool(&self, tenant_id: Uuid, pool: PgPool) {
         let mut pools = self.dedicated_pools.write().await;
-        pools.insert(tenant_id, pool);
+        if let Some(previous) = pools.insert(tenant_id, Arc::new(DedicatedPool::ready(pool))) {
+            close(tenant_id, previous);
+        }
+    }
+
+    /// Closes dedicated pools that have not been used within the idle timeout and returns
+    /// how many were evicted.
+    pub async fn evict_idle(&self) -> usize {
+        let mut pools = self.dedicated_pools.write().await;
+        let idle: Vec<Uuid> = pools
+            .iter()
+            .filter(|(_, entry)| entry.idle_for() >= self.idle_timeout)
+            .map(|(tenant_id, _)| *tenant_id)
+            .collect();
+        for tenant_id in &idle {
+            if let Some(entry) = pools.remove(tenant_id) {
+                tracing::info!(tenant_id = %tenant_id, "evicting idle dedicated tenant pool");
+                close(*tenant_id, entry);
+            }
+        }
+        idle.len()
+    }
+
+    /// Runs [`Self::evict_idle`] and [`Self::record_metrics`] periodically for the
+    /// lifetime of the process.
+    pub fn spawn_eviction(&self) -> tokio::task::JoinHandle<()> {
+        let router = self.clone();
+        let period = (self.idle_timeout / 4).clamp(Duration::from_secs(1), Duration::from_secs(15));
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(period);
+            loop {
+                ticker.tick().await;
+                router.evict_idle().await;
+                router.record_metrics().await;
+            }
+        })
+    }
+
+    /// Publishes the shared pool and the dedicated pools, summed, as `db_pool_connections`.
+    pub async fn record_metrics(&self) {
+        metrics::record_pool("shared", [&self.shared_pool]);
+        let pools = self.dedicated_pools.read().await;
+        metrics::record_pool("dedicated", pools.values().filter_map(|entry| entry.pool.get()));
+    }
+
+    pub async fn pool_health(&self) -> Vec<PoolHealth> {
+        let pools = self.dedicated_pools.read().await;
+        let mut health: Vec<PoolHealth> = pools
+            .iter()
+            .map(|(tenant_id, entry)| self.health_of(*tenant_id, entry))
+            .collect();
+        health.sort_by_key(|h| h.tenant_id);
+        health
+    }
+
+    /// `None` when the tenant has no dedicated pool open, i.e. it is served by the shared pool.
+    pub async fn tenant_pool_health(&self, tenant_id: Uuid) -> Option<PoolHealth> {
+        let pools = self.dedicated_pools.read().await;
+        pools.get(&tenant_id).map(|entry| self.health_of(tenant_id, entry))
+    }
+
+    fn health_of(&self, tenant_id: Uuid, entry: &DedicatedPool) -> PoolHealth {
+        let pool = entry.pool.get();
+        PoolHealth {
+            tenant_id,
+            connected: pool.is_some_and(|pool| !pool.is_closed()),
+            size: pool.map_or(0, |pool| pool.size()),
+            idle: pool.map_or(0, |pool| pool.num_idle()),
+            max_connections: pool.map_or(self.max_connections, |pool| pool.options().get_max_connections()),
+            idle_seconds: entry.idle_for().as_secs(),
+        }
+    }
+}
+
+/// Closes the pool in the background; checked-out connections finish their work first.
+fn close(tenant_id: Uuid, entry: Arc<DedicatedPool>) {
+    if let Some(pool) = entry.pool.get().cloned() {
+        tokio::spawn(async move {
+            pool.close().await;
+            tracing::debug!(tenant_id = %tenant_id, "dedicated tenant pool closed");
+        });
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::tenant::AuthMethod;
+
+    fn lazy_pool(max_connections: u32) -> PgPool {
+        PgPoolOptions::new()
+            .max_connections(max_connections)
+            .connect_lazy("postgres://localhost/unused")
+            .unwrap()
+    }
+
+    fn enterprise(tenant_id: Uuid, db_url: Option<&str>) -> TenantContext {
+        let mut ctx = TenantContext::new(tenant_id, None, PricingTier::Enterprise, vec![], AuthMethod::ApiKey);
+        ctx.db_connection_url = db_url.map(str::to_string);
+        ctx
+    }
 
     #[tokio::test]
     async fn test_dynamic_pool_router_shared_tier() {
@@ -78,4 +362,53 @@ mod tests {
         );
         assert_eq!(ctx.tier, PricingTier::Free);
     }
+
+    #[tokio::test]
+    async fn test_registered_pool_health_and_idle_eviction() {
+        let router = DynamicPoolRouter::new(lazy_pool(1)).with_idle_timeout(Duration::from_millis(50));
+        let tenant_id = Uuid::new_v4();
+        router.register_dedicated_pool(tenant_id, lazy_pool(3)).await;
+
+        let pool = router.get_pool(&enterprise(tenant_id, None)).await.unwrap();
+        assert_eq!(pool.options().get_max_connections(), 3);
+        let health = router.tenant_pool_health(tenant_id).await.unwrap();
+        assert!(health.connected);
+        assert_eq!(health.max_connections, 3);
+        assert_eq!(router.pool_health().await.len(), 1);
+
+        // Still fresh: nothing to evict
+        assert_eq!(router.evict_idle().await, 0);
+        tokio::time::sleep(Duration::from_millis(80)).await;
+        assert_eq!(router.evict_idle().await, 1);
+        assert!(router.tenant_pool_health(tenant_id).await.is_none());
+
+        // Evicted tenants without a URL fall back to the shared pool
+        let pool = router.get_pool(&enterprise(tenant_id, None)).await.unwrap();
+        assert_eq!(pool.options().get_max_connections(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_failed_connection_is_retried_and_not_shared() {
+        let router = DynamicPoolRouter::new(lazy_pool(1)).with_max_connections(2);
+        let tenant_id = Uuid::new_v4();
+        let ctx = enterprise(tenant_id, Some("not a postgres url"));
+
+        assert!(router.get_pool(&ctx).await.is_err());
+        let health = router.tenant_pool_health(tenant_id).await.unwrap();
+        assert!(!health.connected);
+        assert_eq!(health.max_connections, 2);
+        assert!(router.get_pool(&ctx).await.is_err());
+
+        // Growth tenants never touch dedicated pools
+        let mut growth = ctx.clone();
+        growth.tier = PricingTier::Growth;
+        assert!(router.get_pool(&growth).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_tenant_pool_without_directory_uses_shared_pool() {
+        let router = DynamicPoolRouter::new(lazy_pool(1));
+        let pool = router.get_tenant_pool(Uuid::new_v4()).await.unwrap();
+        assert_eq!(pool.options().get_max_connections(), 1);
+    }
 }
@@ -1,6 +1,82 @@
+//! RFC 7807 problem details shared by every service.
+//!
+//! Handlers return [`AppError`] (or call `error_response()` on it); the body is
+//! `application/problem+json` with a stable `code` and the `request_id` set by
+//! the `RequestId` middleware. Database errors are classified so SQL text never
+//! reaches the client.
+
+use actix_web::http::{header, StatusCode};
 use actix_web::{HttpResponse, ResponseError};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 use thiserror::Error;
+use uuid::Uuid;
+
+use crate::observability::current_correlation_id;
+
+pub const PROBLEM_JSON: &str = "application/problem+json";
+
+/// An RFC 7807 problem document. `code` is the stable, machine-readable
+/// identifier; `type` is derived from it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Problem {
+    #[serde(rename = "type")]
+    pub problem_type: String,
+    pub title: String,
+    pub status: u16,
+    pub code: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instance: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<Uuid>,
+    #[serde(flatten)]
+    pub extensions: Map<String, Value>,
+}
+
+impl Problem {
+    /// Problem for `status` with `code`, tagged with the current request id.
+    pub fn new(status: StatusCode, code: &str) -> Self {
+        Self {
+            problem_type: format!("urn:problem-type:{code}"),
+            title: status.canonical_reason().unwrap_or("Error").to_string(),
+            status: status.as_u16(),
+            code: code.to_string(),
+            detail: None,
+            instance: None,
+            request_id: current_correlation_id(),
+            extensions: Map::new(),
+        }
+    }
+
+    pub fn with_detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+
+    pub fn with_instance(mut self, instance: impl Into<String>) -> Self {
+        self.instance = Some(instance.into());
+        self
+    }
+
+    /// Adds a problem-specific member next to the standard ones.
+    pub fn with_extension(mut self, key: &str, value: impl Serialize) -> Self {
+        self.extensions
+            .insert(key.to_string(), serde_json::to_value(value).unwrap_or(Value::Null));
+        self
+    }
+
+    pub fn status_code(&self) -> StatusCode {
+        StatusCode::from_u16(self.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+
+    pub fn response(&self) -> HttpResponse {
+        HttpResponse::build(self.status_code())
+            .insert_header((header::CONTENT_TYPE, PROBLEM_JSON))
+            .body(serde_json::to_string(self).unwrap_or_default())
+    }
+}
 
 #[derive(Error, Debug)]
 pub enum AppError {
@@ -14,29 +90,211 @@ pub enum AppError {
     Forbidden(String),
     #[error("Bad request: {0}")]
     BadRequest(String),
+    #[error("Conflict: {0}")]
+    Conflict(String),
+    #[error("Unprocessable entity: {0}")]
+    Unprocessable(String),
+    #[error("Payment required: {0}")]
+    PaymentRequired(String),
+    #[error("Too many requests: {0}")]
+    TooManyRequests(String),
+    #[error("Service unavailable: {0}")]
+    ServiceUnavailable(String),
+    #[error("Bad gateway: {0}")]
+    BadGateway(String),
     #[error("Internal server error")]
     Internal,
 }
 
-#[derive(Serialize)]
-struct ErrorResponse {
-    error: String,
-    message: String,
+impl AppError {
+    /// Logs `err` and hides it behind a generic 500.
+    pub fn internal(err: impl std::fmt::Display) -> Self {
+        tracing::error!(error = %err, "internal error");
+        AppError::Internal
+    }
+
+    pub fn problem(&self) -> Problem {
+        let (status, code, detail) = match self {
+            AppError::Database(e) => {
+                let (status, code, detail) = classify_sqlx(e);
+                (status, code, Some(detail.to_string()))
+            }
+            AppError::NotFound(m) => (StatusCode::NOT_FOUND, "not_found", Some(m.clone())),
+            AppError::Unauthorized(m) => (StatusCode::UNAUTHORIZED, "unauthorized", Some(m.clone())),
+            AppError::Forbidden(m) => (StatusCode::FORBIDDEN, "forbidden", Some(m.clone())),
+            AppError::BadRequest(m) => (StatusCode::BAD_REQUEST, "bad_request", Some(m.clone())),
+            AppError::Conflict(m) => (StatusCode::CONFLICT, "conflict", Some(m.clone())),
+            AppError::Unprocessable(m) => (StatusCode::UNPROCESSABLE_ENTITY, "unprocessable_entity", Some(m.clone())),
+            AppError::PaymentRequired(m) => (StatusCode::PAYMENT_REQUIRED, "payment_required", Some(m.clone())),
+            AppError::TooManyRequests(m) => (StatusCode::TOO_MANY_REQUESTS, "rate_limited", Some(m.clone())),
+            AppError::ServiceUnavailable(m) => (StatusCode::SERVICE_UNAVAILABLE, "service_unavailable", Some(m.clone())),
+            AppError::BadGateway(m) => (StatusCode::BAD_GATEWAY, "bad_gateway", Some(m.clone())),
+            AppError::Internal => (StatusCode::INTERNAL_SERVER_ERROR, "internal_error", None),
+        };
+
+        let problem = Problem::new(status, code);
+        match detail {
+            Some(detail) => problem.with_detail(detail),
+            None => problem,
+        }
+    }
+}
+
+/// Maps a sqlx error to status, code and a client-safe detail.
+fn classify_sqlx(err: &sqlx::Error) -> (StatusCode, &'static str, &'static str) {
+    match err {
+        sqlx::Error::RowNotFound => (StatusCode::NOT_FOUND, "not_found", "Resource not found"),
+        sqlx::Error::PoolTimedOut | sqlx::Error::PoolClosed => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "service_unavailable",
+            "Database temporarily unavailable",
+        ),
+        sqlx::Error::Database(db) => match db.code().as_deref() {
+            Some("23505") => (StatusCode::CONFLICT, "already_exists", "Resource already exists"),
+            Some("23503") => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "invalid_reference",
+                "Referenced resource does not exist",
+            ),
+            Some("23502") | Some("23514") | Some("22P02") => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "constraint_violation",
+                "Request violates a data constraint",
+            ),
+            // Row-level security rejected the row: it belongs to another tenant,
+            // which must look the same as not existing.
+            Some("42501") => (StatusCode::NOT_FOUND, "not_found", "Resource not found"),
+            Some("40001") | Some("40P01") => (
+                StatusCode::CONFLICT,
+                "concurrent_update",
+                "Resource was modified concurrently, retry the request",
+            ),
+            _ => (StatusCode::INTERNAL_SERVER_ERROR, "internal_error", "Internal server error"),
+        },
+        _ => (StatusCode::INTERNAL_SERVER_ERROR, "internal_error", "Internal server error"),
+    }
 }
 
 impl ResponseError for AppError {
+    fn status_code(&self) -> StatusCode {
+        self.problem().status_code()
+    }
+
     fn error_response(&self) -> HttpResponse {
-        let (status, error_type) = match self {
-            AppError::Database(_) | AppError::Internal => (actix_web::http::StatusCode::INTERNAL_SERVER_ERROR, "internal_error"),
-            AppError::NotFound(_) => (actix_web::http::StatusCode::NOT_FOUND, "not_found"),
-            AppError::Unauthorized(_) => (actix_web::http::StatusCode::UNAUTHORIZED, "unauthorized"),
-            AppError::Forbidden(_) => (actix_web::http::StatusCode::FORBIDDEN, "forbidden"),
-            AppError::BadRequest(_) => (actix_web::http::StatusCode::BAD_REQUEST, "bad_request"),
-        };
+        let problem = self.problem();
+        if problem.status >= 500 {
+            tracing::error!(error = %self, code = %problem.code, "request failed");
+        }
+        problem.response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::observability::with_correlation_id;
+    use actix_web::body::to_bytes;
+
+    async fn body(err: AppError) -> (StatusCode, String, Problem) {
+        let resp = err.error_response();
+        let status = resp.status();
+        let content_type = resp.headers().get(header::CONTENT_TYPE).unwrap().to_str().unwrap().to_string();
+        let bytes = to_bytes(resp.into_body()).await.unwrap();
+        (status, content_type, serde_json::from_slice(&bytes).unwrap())
+    }
+
+    #[actix_web::test]
+    async fn test_problem_json_carries_code_detail_and_request_id() {
+        let request_id = Uuid::new_v4();
+        let (status, content_type, problem) =
+            with_correlation_id(request_id, body(AppError::NotFound("payment intent not found".into()))).await;
+
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(content_type, PROBLEM_JSON);
+        assert_eq!(problem.code, "not_found");
+        assert_eq!(problem.problem_type, "urn:problem-type:not_found");
+        assert_eq!(problem.title, "Not Found");
+        assert_eq!(problem.detail.as_deref(), Some("payment intent not found"));
+        assert_eq!(problem.request_id, Some(request_id));
+    }
+
+    #[actix_web::test]
+    async fn test_database_errors_do_not_leak() {
+        let (status, _, problem) = body(AppError::Database(sqlx::Error::RowNotFound)).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(problem.code, "not_found");
+
+        let (status, _, problem) = body(AppError::Database(sqlx::Error::Protocol("SELECT secret FROM t".into()))).await;
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(problem.code, "internal_error");
+        assert!(!serde_json::to_string(&problem).unwrap().contains("secret"));
+    }
+
+    #[derive(Debug)]
+    struct PgError(&'static str);
+
+    impl std::fmt::Display for PgError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "duplicate key value violates unique constraint \"users_email_key\"")
+        }
+    }
+
+    impl std::error::Error for PgError {}
+
+    impl sqlx::error::DatabaseError for PgError {
+        fn message(&self) -> &str {
+            "duplicate key value violates unique constraint \"users_email_key\""
+        }
+
+        fn code(&self) -> Option<std::borrow::Cow<'_, str>> {
+            Some(self.0.into())
+        }
+
+        fn as_error(&self) -> &(dyn std::error::Error + Send + Sync + 'static) {
+            self
+        }
+
+        fn as_error_mut(&mut self) -> &mut (dyn std::error::Error + Send + Sync + 'static) {
+            self
+        }
+
+        fn into_error(self: Box<Self>) -> Box<dyn std::error::Error + Send + Sync + 'static> {
+            self
+        }
+
+        fn kind(&self) -> sqlx::error::ErrorKind {
+            sqlx::error::ErrorKind::Other
+        }
+    }
+
+    #[test]
+    fn test_sqlx_classification() {
+        let status = |code| AppError::Database(sqlx::Error::Database(Box::new(PgError(code)))).problem();
+
+        let unique = status("23505");
+        assert_eq!((unique.status, unique.code.as_str()), (409, "already_exists"));
+        assert!(!unique.detail.unwrap().contains("users_email_key"));
+
+        let rls = status("42501");
+        assert_eq!((rls.status, rls.code.as_str()), (404, "not_found"));
+
+        let fk = status("23503");
+        assert_eq!((fk.status, fk.code.as_str()), (422, "invalid_reference"));
+
+        let other = status("XX000");
+        assert_eq!((other.status, other.code.as_str()), (500, "internal_error"));
+    }
+
+    #[test]
+    fn test_extensions_are_flattened() {
+        let problem = Problem::new(StatusCode::PAYMENT_REQUIRED, "usage_limit_exceeded")
+            .with_extension("limit", 100)
+            .with_instance("/orders");
+        let json = serde_json::to_value(&problem).unwrap();
 
-        HttpResponse::build(status).json(ErrorResponse {
-            error: error_type.to_string(),
-            message: self.to_string(),
-        })
+        assert_eq!(json["limit"], 100);
+        assert_eq!(json["status"], 402);
+        assert_eq!(json["instance"], "/orders");
+        assert!(json.get("request_id").is_none());
     }
 }
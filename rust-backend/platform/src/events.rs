@@ -1,6 +1,19 @@
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::fmt;
+use std::str::FromStr;
 use uuid::Uuid;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, TimeZone, Utc};
+
+/// Schema version stamped on every event published through [`crate::streams::StreamPublisher`].
+///
+/// Version 1 is the untyped per-service payloads that predate this catalogue; they are
+/// rewritten by [`upcast`] before a consumer deserializes them.
+pub const CURRENT_SCHEMA_VERSION: u32 = 2;
+
+fn legacy_schema_version() -> u32 {
+    1
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EventEnvelope<T> {
@@ -8,16 +21,21 @@ pub struct EventEnvelope<T> {
     pub tenant_id: Option<Uuid>,
     pub correlation_id: Option<Uuid>,
     pub occurred_at: DateTime<Utc>,
+    #[serde(default = "legacy_schema_version")]
+    pub schema_version: u32,
     pub payload: T,
 }
 
 impl<T: Serialize> EventEnvelope<T> {
+    /// Without an explicit `correlation_id` the ambient one from
+    /// [`crate::observability::with_correlation_id`] is used.
     pub fn new(tenant_id: Option<Uuid>, correlation_id: Option<Uuid>, payload: T) -> Self {
         Self {
             event_id: Uuid::new_v4(),
             tenant_id,
-            correlation_id,
+            correlation_id: correlation_id.or_else(crate::observability::current_correlation_id),
             occurred_at: Utc::now(),
+            schema_version: CURRENT_SCHEMA_VERSION,
             payload,
         }
     }
@@ -27,18 +45,630 @@ impl<T: Serialize> EventEnvelope<T> {
         if self.event_id.is_nil() {
             return Err("Event ID cannot be nil");
         }
-        
+        if self.schema_version == 0 || self.schema_version > CURRENT_SCHEMA_VERSION {
+            return Err("Unsupported schema version");
+        }
+
         // Ensure payload can cleanly serialize without panic
         let _val = serde_json::to_value(&self.payload).map_err(|_| "Payload serialization failed")?;
-        
+
         Ok(())
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(tag = "event_type", content = "data")]
-pub enum DomainEvent {
-    OrderCreated { order_id: Uuid, amount: f64 },
-    PaymentSucceeded { payment_id: Uuid, order_id: Uuid },
-    ProductCreated { product_id: Uuid },
+impl EventEnvelope<Value> {
+    /// Rewrites an envelope read from storage into the current schema.
+    pub fn upcast(mut self, event_type: &str) -> Result<Self, UpcastError> {
+        self.payload = upcast(event_type, self.schema_version, self.payload)?;
+        self.schema_version = CURRENT_SCHEMA_VERSION;
+        Ok(self)
+    }
+}
+
+/// Logical Redis stream an event family is published to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Stream {
+    Products,
+    Orders,
+    Inventory,
+    Logistics,
+    Payments,
+    Users,
+    Suppliers,
+    Notifications,
+    Tenants,
+    Platform,
+}
+
+impl Stream {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Stream::Products => "stream:products",
+            Stream::Orders => "stream:orders",
+            Stream::Inventory => "stream:inventory",
+            Stream::Logistics => "stream:logistics",
+            Stream::Payments => "stream:payments",
+            Stream::Users => "stream:users",
+            Stream::Suppliers => "stream:suppliers",
+            Stream::Notifications => "stream:notifications",
+            Stream::Tenants => "stream:tenants",
+            Stream::Platform => "stream:platform",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownEventType(pub String);
+
+impl fmt::Display for UnknownEventType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown event type '{}'", self.0)
+    }
+}
+
+impl std::error::Error for UnknownEventType {}
+
+/// A v1 payload that lacks an id the current schema requires, so it cannot be
+/// rewritten and belongs in the dead-letter stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UpcastError {
+    pub event_type: String,
+    pub field: &'static str,
+}
+
+impl fmt::Display for UpcastError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "v1 '{}' payload has no '{}'", self.event_type, self.field)
+    }
+}
+
+impl std::error::Error for UpcastError {}
+
+/// Declares the catalogue once so the wire name, stream and payload of every event
+/// type stay in one place and every `match` over them is checked for exhaustiveness.
+macro_rules! event_catalogue {
+    ($($variant:ident => $name:literal, $stream:ident, $payload:ident;)+) => {
+        /// Every `event_type` published on the platform's Redis Streams.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum EventType {
+            $($variant,)+
+        }
+
+        impl EventType {
+            pub const ALL: &'static [EventType] = &[$(EventType::$variant,)+];
+
+            pub const fn as_str(self) -> &'static str {
+                match self {
+                    $(EventType::$variant => $name,)+
+                }
+            }
+
+            pub const fn stream(self) -> Stream {
+                match self {
+                    $(EventType::$variant => Stream::$stream,)+
+                }
+            }
+
+            /// Non-optional id fields of this type's payload.
+            fn required_ids(self) -> &'static [&'static str] {
+                match self {
+                    $(EventType::$variant => <$payload as RequiredIds>::REQUIRED_IDS,)+
+                }
+            }
+        }
+
+        impl FromStr for EventType {
+            type Err = UnknownEventType;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                match s {
+                    $($name => Ok(EventType::$variant),)+
+                    other => Err(UnknownEventType(other.to_string())),
+                }
+            }
+        }
+
+        /// A typed event, serialized with its wire name in the `event_type` field
+        /// alongside the payload fields.
+        #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+        #[serde(tag = "event_type")]
+        pub enum DomainEvent {
+            $(
+                #[serde(rename = $name)]
+                $variant($payload),
+            )+
+        }
+
+        impl DomainEvent {
+            pub fn event_type(&self) -> EventType {
+                match self {
+                    $(DomainEvent::$variant(_) => EventType::$variant,)+
+                }
+            }
+
+            pub fn tenant_id(&self) -> Uuid {
+                match self {
+                    $(DomainEvent::$variant(event) => event.tenant_id,)+
+                }
+            }
+        }
+    };
+}
+
+event_catalogue! {
+    ProductCreated => "product.created", Products, ProductEvent;
+    ProductUpdated => "product.updated", Products, ProductEvent;
+    ProductDeleted => "product.deleted", Products, ProductEvent;
+    ProductViewed => "product.viewed", Products, ProductEvent;
+
+    OrderCreated => "order.created", Orders, OrderEvent;
+    OrderPending => "order.pending", Orders, OrderEvent;
+    OrderProcessing => "order.processing", Orders, OrderEvent;
+    OrderConfirmed => "order.confirmed", Orders, OrderEvent;
+    OrderShipped => "order.shipped", Orders, OrderEvent;
+    OrderDelivered => "order.delivered", Orders, OrderEvent;
+    OrderCancelled => "order.cancelled", Orders, OrderEvent;
+    OrderFailed => "order.failed", Orders, OrderEvent;
+    OrderRefunded => "order.refunded", Orders, OrderEvent;
+    OrderReviewRequested => "order.review_requested", Orders, OrderEvent;
+
+    InventoryReserved => "inventory.reserved", Inventory, InventoryEvent;
+    InventoryRejected => "inventory.rejected", Inventory, InventoryEvent;
+    InventoryReleased => "inventory.released", Inventory, InventoryEvent;
+    InventoryExpired => "inventory.expired", Inventory, InventoryEvent;
+    InventoryReservationExpired => "inventory.reservation_expired", Inventory, InventoryEvent;
+    InventoryFinalized => "inventory.finalized", Inventory, InventoryEvent;
+    InventoryUpdated => "inventory.updated", Inventory, InventoryEvent;
+    InventoryLowStock => "inventory.lowstock", Inventory, InventoryEvent;
+    InventoryDeleted => "inventory.deleted", Inventory, InventoryEvent;
+    InventoryReleaseCommand => "inventory.release_command", Inventory, OrderEvent;
+
+    PaymentInitiated => "payment.initiated", Payments, PaymentEvent;
+    PaymentProcessing => "payment.processing", Payments, PaymentEvent;
+    PaymentSuccess => "payment.success", Payments, PaymentEvent;
+    PaymentFailed => "payment.failed", Payments, PaymentEvent;
+    PaymentCancelled => "payment.cancelled", Payments, PaymentEvent;
+    PaymentRefunded => "payment.refunded", Payments, PaymentEvent;
+    PaymentRefundCommand => "payment.refund_command", Payments, OrderEvent;
+
+    ShipmentCreated => "logistics.shipment_created", Logistics, ShipmentEvent;
+    ShipmentUpdated => "logistics.shipment_updated", Logistics, ShipmentEvent;
+    ShipmentCancelled => "logistics.shipment_cancelled", Logistics, ShipmentEvent;
+    ShipmentPreparationCommand => "logistics.shipment_preparation_command", Logistics, OrderEvent;
+
+    SupplierCreated => "supplier.created", Suppliers, SupplierEvent;
+    SupplierUpdated => "supplier.updated", Suppliers, SupplierEvent;
+    SupplierStatusUpdated => "supplier.status_updated", Suppliers, SupplierEvent;
+
+    UserCreated => "user.created", Users, UserEvent;
+    UserUpdated => "user.updated", Users, UserEvent;
+    UserPasswordResetRequested => "user.password_reset_requested", Users, UserEvent;
+
+    TenantUpdated => "tenant.updated", Tenants, TenantEvent;
+}
+
+impl fmt::Display for EventType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Payload id fields that v1 producers declared as `Option` or `#[serde(default)]`
+/// and so could write as `null` or leave out.
+trait RequiredIds {
+    const REQUIRED_IDS: &'static [&'static str];
+}
+
+impl RequiredIds for ProductEvent {
+    const REQUIRED_IDS: &'static [&'static str] = &["tenant_id", "product_id", "supplier_id"];
+}
+
+impl RequiredIds for OrderEvent {
+    const REQUIRED_IDS: &'static [&'static str] = &["tenant_id", "order_id", "product_id", "supplier_id", "user_id"];
+}
+
+impl RequiredIds for InventoryEvent {
+    const REQUIRED_IDS: &'static [&'static str] = &["tenant_id", "product_id"];
+}
+
+impl RequiredIds for PaymentEvent {
+    const REQUIRED_IDS: &'static [&'static str] =
+        &["tenant_id", "payment_id", "order_id", "user_id", "supplier_id", "product_id"];
+}
+
+impl RequiredIds for ShipmentEvent {
+    const REQUIRED_IDS: &'static [&'static str] =
+        &["tenant_id", "shipment_id", "order_id", "user_id", "supplier_id", "product_id"];
+}
+
+impl RequiredIds for SupplierEvent {
+    const REQUIRED_IDS: &'static [&'static str] = &["tenant_id", "supplier_id", "user_id", "owner_user_id"];
+}
+
+impl RequiredIds for UserEvent {
+    const REQUIRED_IDS: &'static [&'static str] = &["tenant_id"];
+}
+
+impl RequiredIds for TenantEvent {
+    const REQUIRED_IDS: &'static [&'static str] = &["tenant_id"];
+}
+
+/// Catalogue and product changes from product-catalog.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProductEvent {
+    pub tenant_id: Uuid,
+    pub product_id: Uuid,
+    pub supplier_id: Uuid,
+    pub name: Option<String>,
+    pub description: Option<Value>,
+    pub price: Option<f64>,
+    pub category: Option<String>,
+    pub quantity: Option<i32>,
+    pub low_stock_threshold: Option<i32>,
+    pub unit: Option<String>,
+    pub quantity_change: Option<i32>,
+    pub available: Option<bool>,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Order lifecycle facts, and the commands order-service derives from them.
+///
+/// `lines` lists every line of the order; `product_id` and `quantity` repeat the first
+/// line for consumers that predate multi-line orders. Amounts are in minor units.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OrderEvent {
+    pub tenant_id: Uuid,
+    pub order_id: Uuid,
+    pub product_id: Uuid,
+    pub supplier_id: Uuid,
+    pub user_id: Uuid,
+    pub quantity: Option<i32>,
+    #[serde(default)]
+    pub lines: Vec<OrderLineItem>,
+    pub total_cents: Option<i64>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub refund_amount: Option<f64>,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl OrderEvent {
+    /// The order's lines, falling back to the single `product_id`/`quantity` line of
+    /// events published before `lines` existed.
+    pub fn line_items(&self) -> Vec<OrderLineItem> {
+        if !self.lines.is_empty() {
+            return self.lines.clone();
+        }
+        match self.quantity {
+            Some(quantity) => vec![OrderLineItem { product_id: self.product_id, quantity, ..Default::default() }],
+            None => Vec::new(),
+        }
+    }
+}
+
+/// One priced line of an [`OrderEvent`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OrderLineItem {
+    pub product_id: Uuid,
+    pub quantity: i32,
+    pub unit_price_cents: i64,
+    pub tax_cents: i64,
+    pub discount_cents: i64,
+}
+
+/// Stock and reservation changes from inventory-management.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct InventoryEvent {
+    pub tenant_id: Uuid,
+    pub product_id: Uuid,
+    pub supplier_id: Option<Uuid>,
+    pub order_id: Option<Uuid>,
+    pub reservation_id: Option<Uuid>,
+    pub user_id: Option<Uuid>,
+    pub quantity: Option<i32>,
+    pub quantity_change: Option<i32>,
+    pub low_stock: Option<bool>,
+    /// Order total in minor units, carried from `order.created` to `inventory.reserved`.
+    pub amount: Option<i64>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Payment intent state changes; `amount` is in minor units.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PaymentEvent {
+    pub tenant_id: Uuid,
+    pub payment_id: Uuid,
+    pub order_id: Uuid,
+    pub user_id: Uuid,
+    pub supplier_id: Uuid,
+    pub product_id: Uuid,
+    pub quantity: i32,
+    pub amount: i64,
+    pub currency: String,
+    pub provider: String,
+    pub provider_reference: Option<String>,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Shipment changes from logistics; `status` is the lowercase shipment status.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ShipmentEvent {
+    pub tenant_id: Uuid,
+    pub shipment_id: Uuid,
+    pub order_id: Uuid,
+    pub user_id: Uuid,
+    pub supplier_id: Uuid,
+    pub product_id: Uuid,
+    pub status: String,
+    pub tracking_number: String,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Supplier onboarding changes; `status` is the lowercase supplier status.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SupplierEvent {
+    pub tenant_id: Uuid,
+    pub supplier_id: Uuid,
+    pub user_id: Uuid,
+    pub owner_user_id: Uuid,
+    pub status: String,
+    pub timestamp: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UserEvent {
+    pub tenant_id: Uuid,
+    /// Absent on password resets, which are keyed by email.
+    pub user_id: Option<Uuid>,
+    pub email: Option<String>,
+    pub role: Option<String>,
+    /// Email verification or password reset token.
+    pub token: Option<String>,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Control-plane changes to a tenant record; consumers re-read the record rather than
+/// relying on the payload.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TenantEvent {
+    pub tenant_id: Uuid,
+    pub tier: Option<String>,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Rewrites a payload written at `version` into the current schema.
+///
+/// Each step only renames or fills in fields, so a payload that is already current
+/// passes through unchanged. Ids are never invented: a v1 payload missing one is an error.
+pub fn upcast(event_type: &str, version: u32, mut payload: Value) -> Result<Value, UpcastError> {
+    if version < 2 {
+        if let Some(fields) = payload.as_object_mut() {
+            upcast_v1(event_type, fields)?;
+        }
+    }
+    Ok(payload)
+}
+
+fn upcast_v1(event_type: &str, fields: &mut Map<String, Value>) -> Result<(), UpcastError> {
+    // v1 structs carried their own, sometimes empty, event_type string
+    fields.insert("event_type".to_string(), json!(event_type));
+
+    // Inventory used `order_timestamp`, the reservation worker epoch millis, and some
+    // producers left the field null
+    let order_timestamp = fields.remove("order_timestamp").filter(|v| !v.is_null());
+    let timestamp = match fields.remove("timestamp") {
+        Some(Value::Number(millis)) => millis
+            .as_i64()
+            .and_then(|ms| Utc.timestamp_millis_opt(ms).single())
+            .map(|ts| json!(ts)),
+        Some(Value::Null) | None => order_timestamp,
+        Some(other) => Some(other),
+    };
+    fields.insert("timestamp".to_string(), timestamp.unwrap_or_else(|| json!(Utc::now())));
+
+    // inventory-management's StockUpdateEvent
+    rename(fields, "new_quantity", "quantity");
+    rename(fields, "change", "quantity_change");
+
+    // user-management's verification events named the token after its purpose
+    rename(fields, "verify_token", "token");
+
+    if event_type == EventType::InventoryReservationExpired.as_str() {
+        rename(fields, "id", "order_id");
+    }
+
+    // v1 could leave ids out or write them as null; nothing downstream can act on those
+    if let Ok(known) = event_type.parse::<EventType>() {
+        if let Some(id) = known.required_ids().iter().find(|id| fields.get(**id).is_none_or(Value::is_null)) {
+            return Err(UpcastError { event_type: event_type.to_string(), field: id });
+        }
+    }
+    Ok(())
+}
+
+fn rename(fields: &mut Map<String, Value>, from: &str, to: &str) {
+    if fields.contains_key(to) {
+        return;
+    }
+    if let Some(value) = fields.remove(from) {
+        fields.insert(to.to_string(), value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_type_roundtrip_and_stream() {
+        for event_type in EventType::ALL {
+            assert_eq!(event_type.as_str().parse::<EventType>(), Ok(*event_type));
+        }
+        assert_eq!(EventType::OrderCreated.stream(), Stream::Orders);
+        assert_eq!(EventType::InventoryReleaseCommand.stream(), Stream::Inventory);
+        assert_eq!(EventType::ShipmentCreated.stream().as_str(), "stream:logistics");
+        assert!("order.unknown".parse::<EventType>().is_err());
+    }
+
+    #[test]
+    fn test_domain_event_wire_format() {
+        let tenant_id = Uuid::new_v4();
+        let event = DomainEvent::OrderCreated(OrderEvent {
+            tenant_id,
+            order_id: Uuid::new_v4(),
+            quantity: Some(2),
+            timestamp: Utc::now(),
+            ..Default::default()
+        });
+
+        let value = serde_json::to_value(&event).unwrap();
+        assert_eq!(value["event_type"], "order.created");
+        assert_eq!(value["tenant_id"], tenant_id.to_string());
+        assert_eq!(value["quantity"], 2);
+
+        let decoded: DomainEvent = serde_json::from_value(value).unwrap();
+        assert_eq!(decoded, event);
+        assert_eq!(decoded.event_type(), EventType::OrderCreated);
+        assert_eq!(decoded.tenant_id(), tenant_id);
+    }
+
+    #[test]
+    fn test_order_line_items_fall_back_to_single_product() {
+        let product_id = Uuid::new_v4();
+        let legacy: OrderEvent = serde_json::from_value(json!({
+            "tenant_id": Uuid::new_v4(),
+            "order_id": Uuid::new_v4(),
+            "product_id": product_id,
+            "supplier_id": Uuid::new_v4(),
+            "user_id": Uuid::new_v4(),
+            "quantity": 3,
+            "timestamp": Utc::now(),
+        }))
+        .unwrap();
+        assert_eq!(
+            legacy.line_items(),
+            vec![OrderLineItem { product_id, quantity: 3, ..Default::default() }]
+        );
+
+        let lines = vec![
+            OrderLineItem { product_id, quantity: 1, unit_price_cents: 250, ..Default::default() },
+            OrderLineItem { product_id: Uuid::new_v4(), quantity: 4, unit_price_cents: 99, ..Default::default() },
+        ];
+        let event = OrderEvent { lines: lines.clone(), quantity: Some(1), ..legacy };
+        assert_eq!(event.line_items(), lines);
+    }
+
+    #[test]
+    fn test_upcast_v1_inventory_payloads() {
+        let tenant_id = Uuid::new_v4();
+        let order_id = Uuid::new_v4();
+
+        let stock_update = json!({
+            "tenant_id": tenant_id,
+            "product_id": Uuid::new_v4(),
+            "supplier_id": Uuid::new_v4(),
+            "new_quantity": 3,
+            "change": -1,
+            "low_stock": true,
+        });
+        let upcast_value = upcast("inventory.lowstock", 1, stock_update).unwrap();
+        let DomainEvent::InventoryLowStock(event) = serde_json::from_value(upcast_value).unwrap() else {
+            panic!("expected inventory.lowstock");
+        };
+        assert_eq!(event.quantity, Some(3));
+        assert_eq!(event.quantity_change, Some(-1));
+
+        let expired = json!({
+            "event_type": "inventory.reservation_expired",
+            "tenant_id": tenant_id,
+            "id": order_id,
+            "product_id": Uuid::new_v4(),
+            "timestamp": 1_700_000_000_000_i64,
+        });
+        let upcast_value = upcast("inventory.reservation_expired", 1, expired).unwrap();
+        let DomainEvent::InventoryReservationExpired(event) = serde_json::from_value(upcast_value).unwrap() else {
+            panic!("expected inventory.reservation_expired");
+        };
+        assert_eq!(event.order_id, Some(order_id));
+        assert_eq!(event.timestamp.timestamp_millis(), 1_700_000_000_000);
+    }
+
+    #[test]
+    fn test_upcast_v1_payload_of_every_family_decodes() {
+        let tenant_id = Uuid::new_v4();
+        let id = || json!(Uuid::new_v4());
+        // Shapes written by each service's own event struct before the catalogue
+        let v1 = [
+            ("product.created", json!({
+                "tenant_id": tenant_id, "event_type": "product.created", "product_id": id(), "supplier_id": id(),
+                "name": "Widget", "price": 9.5, "order_id": null, "quantity": null, "timestamp": null,
+            })),
+            ("order.created", json!({
+                "tenant_id": tenant_id, "event_type": "order.created", "product_id": id(), "supplier_id": id(),
+                "order_id": id(), "quantity": 2, "user_id": id(), "timestamp": Utc::now(),
+                "expires_at": Utc::now(), "status": null, "notification_channel": null,
+            })),
+            ("payment.refund_command", json!({
+                "tenant_id": tenant_id, "event_type": "payment.refund_command", "product_id": id(),
+                "supplier_id": id(), "order_id": id(), "user_id": id(), "timestamp": Utc::now(),
+            })),
+            ("inventory.reserved", json!({
+                "tenant_id": tenant_id, "event_type": "inventory.reserved", "product_id": id(), "supplier_id": id(),
+                "order_id": id(), "quantity": 1, "user_id": id(), "order_timestamp": Utc::now(),
+            })),
+            ("inventory.deleted", json!({"tenant_id": tenant_id, "product_id": id(), "supplier_id": id()})),
+            ("payment.success", json!({
+                "tenant_id": tenant_id, "event_type": "payment.success", "payment_id": id(), "order_id": id(),
+                "user_id": id(), "supplier_id": id(), "product_id": id(), "quantity": 1, "amount": 1000,
+                "currency": "usd", "provider": "stripe", "provider_reference": null, "timestamp": Utc::now(),
+            })),
+            ("logistics.shipment_created", json!({
+                "tenant_id": tenant_id, "event_type": "logistics.shipment_created", "shipment_id": id(),
+                "order_id": id(), "user_id": id(), "supplier_id": id(), "product_id": id(),
+                "status": "pending", "tracking_number": "TRK-1", "timestamp": Utc::now(),
+            })),
+            ("supplier.created", json!({
+                "tenant_id": tenant_id, "event_type": "supplier.created", "supplier_id": id(), "user_id": id(),
+                "owner_user_id": id(), "status": "pending", "timestamp": Utc::now(),
+            })),
+            ("user.created", json!({
+                "tenant_id": tenant_id, "user_id": Uuid::new_v4().to_string(), "email": "a@example.com",
+                "role": "User", "verify_token": "t", "timestamp": Utc::now(),
+            })),
+            ("user.password_reset_requested", json!({
+                "tenant_id": tenant_id, "email": "a@example.com", "token": "t", "timestamp": Utc::now(),
+            })),
+        ];
+
+        for (event_type, payload) in v1 {
+            let upcast_value = upcast(event_type, 1, payload).unwrap_or_else(|e| panic!("{}", e));
+            let event: DomainEvent = serde_json::from_value(upcast_value)
+                .unwrap_or_else(|e| panic!("v1 {} failed to decode: {}", event_type, e));
+            assert_eq!(event.event_type().as_str(), event_type);
+            assert_eq!(event.tenant_id(), tenant_id);
+        }
+    }
+
+    #[test]
+    fn test_upcast_v1_maps_verify_token_and_rejects_missing_ids() {
+        let created = json!({
+            "tenant_id": Uuid::new_v4(), "user_id": Uuid::new_v4(), "email": "a@example.com",
+            "verify_token": "t", "timestamp": Utc::now(),
+        });
+        let DomainEvent::UserCreated(event) = serde_json::from_value(upcast("user.created", 1, created).unwrap()).unwrap()
+        else {
+            panic!("expected user.created");
+        };
+        assert_eq!(event.token.as_deref(), Some("t"));
+
+        let refund = json!({"tenant_id": Uuid::new_v4(), "order_id": null});
+        let err = upcast("payment.refund_command", 1, refund).unwrap_err();
+        assert_eq!(err, UpcastError { event_type: "payment.refund_command".to_string(), field: "order_id" });
+    }
+
+    #[test]
+    fn test_upcast_leaves_current_payloads_untouched() {
+        let payload = json!({"event_type": "order.created", "new_quantity": 1});
+        assert_eq!(upcast("order.created", CURRENT_SCHEMA_VERSION, payload.clone()), Ok(payload));
+    }
 }
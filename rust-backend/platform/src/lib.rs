@@ -1,12 +1,18 @@
+pub mod api_keys;
 pub mod db_router;
 pub mod metrics;
 pub mod middleware;
 pub mod observability;
+pub mod outbox;
+pub mod scopes;
 pub mod streams;
 pub mod tenant;
+pub mod tenant_directory;
+pub mod tenant_migration;
 
 pub mod errors;
 pub mod config;
 pub mod events;
 pub mod pagination;
 pub mod health;
+pub mod supervisor;
@@ -1,32 +1,106 @@
 use actix_web::{HttpResponse, Responder};
-use prometheus::{Encoder, IntCounterVec, Registry, TextEncoder};
+use prometheus::{Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGaugeVec, Opts, Registry, TextEncoder};
+use sqlx::PgPool;
 use std::sync::OnceLock;
+use std::time::Duration;
 
 static REGISTRY: OnceLock<Registry> = OnceLock::new();
+static SERVICE: OnceLock<&'static str> = OnceLock::new();
 static HTTP_COUNTER: OnceLock<IntCounterVec> = OnceLock::new();
+static HTTP_DURATION: OnceLock<HistogramVec> = OnceLock::new();
+static HTTP_IN_FLIGHT: OnceLock<IntGaugeVec> = OnceLock::new();
+static HTTP_TIER_COUNTER: OnceLock<IntCounterVec> = OnceLock::new();
 static EVENT_COUNTER: OnceLock<IntCounterVec> = OnceLock::new();
+static DB_POOL_CONNECTIONS: OnceLock<IntGaugeVec> = OnceLock::new();
+static STREAM_LAG: OnceLock<IntGaugeVec> = OnceLock::new();
+
+const LATENCY_BUCKETS: &[f64] = &[0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+fn register<C: prometheus::core::Collector + Clone + 'static>(collector: C) -> C {
+    if let Some(registry) = REGISTRY.get() {
+        let _ = registry.register(Box::new(collector.clone()));
+    }
+    collector
+}
+
+/// Name passed to [`init_metrics`], used as the `service` label.
+pub fn service() -> &'static str {
+    SERVICE.get().copied().unwrap_or("unknown")
+}
 
 pub fn init_metrics(service: &'static str) {
-    let registry = REGISTRY.get_or_init(Registry::new);
+    REGISTRY.get_or_init(Registry::new);
+    SERVICE.get_or_init(|| service);
 
     HTTP_COUNTER.get_or_init(|| {
-        let counter = IntCounterVec::new(
-            prometheus::Opts::new("http_requests_total", "Total HTTP requests"),
-            &["service", "route", "method", "status"],
+        register(
+            IntCounterVec::new(
+                Opts::new("http_requests_total", "Total HTTP requests"),
+                &["service", "route", "method", "status"],
+            )
+            .expect("http counter"),
+        )
+    });
+
+    HTTP_DURATION.get_or_init(|| {
+        register(
+            HistogramVec::new(
+                HistogramOpts::new("http_request_duration_seconds", "HTTP request latency")
+                    .buckets(LATENCY_BUCKETS.to_vec()),
+                &["service", "route", "method", "status"],
+            )
+            .expect("http duration histogram"),
+        )
+    });
+
+    HTTP_IN_FLIGHT.get_or_init(|| {
+        register(
+            IntGaugeVec::new(
+                Opts::new("http_requests_in_flight", "HTTP requests currently being served"),
+                &["service"],
+            )
+            .expect("http in-flight gauge"),
+        )
+    });
+
+    HTTP_TIER_COUNTER.get_or_init(|| {
+        register(
+            IntCounterVec::new(
+                Opts::new("http_requests_by_tier_total", "HTTP requests by tenant pricing tier"),
+                &["service", "tier", "status"],
+            )
+            .expect("http tier counter"),
         )
-        .expect("http counter");
-        let _ = registry.register(Box::new(counter.clone()));
-        counter
     });
 
     EVENT_COUNTER.get_or_init(|| {
-        let counter = IntCounterVec::new(
-            prometheus::Opts::new("events_total", "Total events processed"),
-            &["service", "stream", "event_type", "outcome"],
+        register(
+            IntCounterVec::new(
+                Opts::new("events_total", "Total events processed"),
+                &["service", "stream", "event_type", "outcome"],
+            )
+            .expect("event counter"),
+        )
+    });
+
+    DB_POOL_CONNECTIONS.get_or_init(|| {
+        register(
+            IntGaugeVec::new(
+                Opts::new("db_pool_connections", "Postgres pool connections by state (open, idle, max)"),
+                &["service", "pool", "state"],
+            )
+            .expect("db pool gauge"),
+        )
+    });
+
+    STREAM_LAG.get_or_init(|| {
+        register(
+            IntGaugeVec::new(
+                Opts::new("stream_consumer_lag", "Stream entries not yet delivered to the consumer group"),
+                &["service", "group"],
+            )
+            .expect("stream lag gauge"),
         )
-        .expect("event counter");
-        let _ = registry.register(Box::new(counter.clone()));
-        counter
     });
 
     prometheus::default_registry()
@@ -42,12 +116,85 @@ pub fn init_metrics(service: &'static str) {
 
 pub fn inc_event(service: &str, stream: &str, event_type: &str, outcome: &str) {
     if let Some(counter) = EVENT_COUNTER.get() {
+        // Per-tenant streams are folded into their global name to bound label cardinality
         counter
-            .with_label_values(&[service, stream, event_type, outcome])
+            .with_label_values(&[service, crate::streams::logical_stream(stream), event_type, outcome])
             .inc();
     }
 }
 
+/// Records a served request. `route` is the matched route pattern and `tier` the
+/// tenant's pricing tier, if the request was authenticated.
+pub fn observe_http(route: &str, method: &str, status: u16, tier: Option<&str>, elapsed: Duration) {
+    let status = status.to_string();
+    if let Some(counter) = HTTP_COUNTER.get() {
+        counter.with_label_values(&[service(), route, method, &status]).inc();
+    }
+    if let Some(histogram) = HTTP_DURATION.get() {
+        histogram
+            .with_label_values(&[service(), route, method, &status])
+            .observe(elapsed.as_secs_f64());
+    }
+    if let Some(counter) = HTTP_TIER_COUNTER.get() {
+        counter.with_label_values(&[service(), tier.unwrap_or("none"), &status]).inc();
+    }
+}
+
+/// Counts a request as in flight until the returned guard is dropped.
+pub fn track_in_flight() -> InFlight {
+    let gauge = HTTP_IN_FLIGHT.get().map(|gauge| gauge.with_label_values(&[service()]));
+    if let Some(gauge) = &gauge {
+        gauge.inc();
+    }
+    InFlight { gauge }
+}
+
+pub struct InFlight {
+    gauge: Option<prometheus::IntGauge>,
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        if let Some(gauge) = &self.gauge {
+            gauge.dec();
+        }
+    }
+}
+
+/// Sets the connection gauges of `pool` to the totals over `pools`.
+pub fn record_pool<'a>(pool: &str, pools: impl IntoIterator<Item = &'a PgPool>) {
+    let Some(gauge) = DB_POOL_CONNECTIONS.get() else {
+        return;
+    };
+    let (mut open, mut idle, mut max) = (0, 0, 0);
+    for p in pools {
+        open += i64::from(p.size());
+        idle += p.num_idle() as i64;
+        max += i64::from(p.options().get_max_connections());
+    }
+    gauge.with_label_values(&[service(), pool, "open"]).set(open);
+    gauge.with_label_values(&[service(), pool, "idle"]).set(idle);
+    gauge.with_label_values(&[service(), pool, "max"]).set(max);
+}
+
+/// Samples `pool` into the `db_pool_connections` gauges every 15 seconds. Services
+/// that route through `DynamicPoolRouter` get this from `spawn_eviction`.
+pub fn spawn_pool_metrics(pool_name: &'static str, pool: PgPool) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(Duration::from_secs(15));
+        loop {
+            ticker.tick().await;
+            record_pool(pool_name, [&pool]);
+        }
+    })
+}
+
+pub fn set_stream_lag(group: &str, lag: u64) {
+    if let Some(gauge) = STREAM_LAG.get() {
+        gauge.with_label_values(&[service(), group]).set(lag as i64);
+    }
+}
+
 pub async fn metrics_handler() -> impl Responder {
     let mut metric_families = prometheus::gather();
     if let Some(registry) = REGISTRY.get() {
@@ -1,21 +1,88 @@
-use actix_web::body::EitherBody;
-use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
-use actix_web::{Error, HttpMessage, HttpResponse};
+use actix_web::body::{to_bytes, EitherBody, MessageBody};
+use actix_web::dev::{forward_ready, Payload, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header::{HeaderName, HeaderValue, RETRY_AFTER};
+use actix_web::http::{Method, StatusCode};
+use actix_web::{web, Error, HttpMessage, HttpResponse};
 use futures_util::future::LocalBoxFuture;
-use redis::AsyncCommands;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
 use std::future::{ready, Ready};
 use std::rc::Rc;
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::errors::Problem;
 use crate::tenant::TenantContext;
 
+/// Deletes the in-flight lock only if it still holds this request's token, so a request
+/// that outlived its lock cannot release one taken by a later retry.
+const RELEASE_LOCK_SCRIPT: &str = r#"
+if redis.call('GET', KEYS[1]) == ARGV[1] then
+  return redis.call('DEL', KEYS[1])
+end
+return 0
+"#;
+
+/// Replays the first response for a repeated `Idempotency-Key`.
+///
+/// Unsafe requests (`POST`, `PUT`, `PATCH`) carrying the header are keyed per tenant.
+/// While the first request runs, a `SET NX` lock makes concurrent duplicates fail with
+/// 409; once it finishes, its status, headers and body are stored and returned verbatim
+/// to later retries. Reusing a key with a different method, path or body is rejected
+/// with 422. Server errors and transient statuses are not stored, so those requests
+/// can be retried under the same key.
+#[derive(Clone)]
 pub struct Idempotency {
-    pub redis_client: redis::Client,
+    redis_client: redis::Client,
+    ttl: Duration,
+    lock_ttl: Duration,
+    route_ttls: Arc<HashMap<(Method, String), Duration>>,
+    release_script: Arc<redis::Script>,
+}
+
+impl Idempotency {
+    pub fn new(redis_client: redis::Client) -> Self {
+        Self {
+            redis_client,
+            ttl: Duration::from_secs(24 * 60 * 60),
+            lock_ttl: Duration::from_secs(60),
+            route_ttls: Arc::new(HashMap::new()),
+            release_script: Arc::new(redis::Script::new(RELEASE_LOCK_SCRIPT)),
+        }
+    }
+
+    /// How long a stored response is replayed for routes without their own TTL.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Upper bound on how long an in-flight request holds its key.
+    pub fn with_lock_ttl(mut self, lock_ttl: Duration) -> Self {
+        self.lock_ttl = lock_ttl;
+        self
+    }
+
+    /// Overrides the replay TTL for `method` on the route `pattern` (e.g. `/orders/{id}`).
+    pub fn with_route_ttl(mut self, method: Method, pattern: impl Into<String>, ttl: Duration) -> Self {
+        Arc::make_mut(&mut self.route_ttls).insert((method, pattern.into()), ttl);
+        self
+    }
+
+    fn ttl_for(&self, method: &Method, pattern: &str) -> Duration {
+        self.route_ttls
+            .get(&(method.clone(), pattern.to_string()))
+            .copied()
+            .unwrap_or(self.ttl)
+    }
 }
 
 impl<S, B> Transform<S, ServiceRequest> for Idempotency
 where
     S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
     S::Future: 'static,
-    B: 'static,
+    B: MessageBody + 'static,
 {
     type Response = ServiceResponse<EitherBody<B>>;
     type Error = Error;
@@ -26,21 +93,21 @@ where
     fn new_transform(&self, service: S) -> Self::Future {
         ready(Ok(IdempotencyMiddleware {
             service: Rc::new(service),
-            redis_client: self.redis_client.clone(),
+            config: self.clone(),
         }))
     }
 }
 
 pub struct IdempotencyMiddleware<S> {
     service: Rc<S>,
-    redis_client: redis::Client,
+    config: Idempotency,
 }
 
 impl<S, B> Service<ServiceRequest> for IdempotencyMiddleware<S>
 where
     S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
     S::Future: 'static,
-    B: 'static,
+    B: MessageBody + 'static,
 {
     type Response = ServiceResponse<EitherBody<B>>;
     type Error = Error;
@@ -48,54 +115,291 @@ where
 
     forward_ready!(service);
 
-    fn call(&self, req: ServiceRequest) -> Self::Future {
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
         let idempotency_key = req.headers().get("Idempotency-Key").and_then(|v| v.to_str().ok()).map(|s| s.to_string());
-        
+
         let method = req.method().clone();
-        if method == actix_web::http::Method::GET || method == actix_web::http::Method::DELETE || idempotency_key.is_none() {
-            let fut = self.service.call(req);
-            return Box::pin(async move {
-                let res = fut.await?;
-                Ok(res.map_into_left_body())
-            });
-        }
+        let idempotency_key = match idempotency_key {
+            Some(key) if matches!(method, Method::POST | Method::PUT | Method::PATCH) => key,
+            _ => {
+                let fut = self.service.call(req);
+                return Box::pin(async move {
+                    let res = fut.await?;
+                    Ok(res.map_into_left_body())
+                });
+            }
+        };
 
-        let idempotency_key = idempotency_key.unwrap();
-        
         // Need TenantContext to namespace the key
         let tenant_id = match req.extensions().get::<TenantContext>() {
             Some(ctx) => ctx.tenant_id.to_string(),
             None => "global".to_string(),
         };
 
-        let redis_key = format!("idemp:{}:{}", tenant_id, idempotency_key);
-        let redis_client = self.redis_client.clone();
+        let record_key = format!("idemp:{}:{}", tenant_id, idempotency_key);
+        let lock_key = format!("{}:lock", record_key);
+        let pattern = req.match_pattern().unwrap_or_else(|| req.path().to_string());
+        let ttl = self.config.ttl_for(&method, &pattern);
+        let lock_ttl = self.config.lock_ttl;
+        let redis_client = self.config.redis_client.clone();
+        let release_script = self.config.release_script.clone();
         let service = self.service.clone();
 
         Box::pin(async move {
+            let body = req.extract::<web::Bytes>().await?;
+            let request_hash = request_fingerprint(&method, req.path(), &body);
+            req.set_payload(Payload::from(body));
+
             let mut conn = redis_client.get_multiplexed_async_connection().await.map_err(actix_web::error::ErrorInternalServerError)?;
-            
-            // Check if key exists
-            let cached_response: Option<String> = conn.get(&redis_key).await.map_err(actix_web::error::ErrorInternalServerError)?;
-            
-            if let Some(cached) = cached_response {
-                let res = HttpResponse::Ok()
-                    .content_type("application/json")
-                    .body(cached)
-                    .map_into_right_body();
-                return Ok(req.into_response(res));
+
+            if let Some(stored) = StoredResponse::load(&mut conn, &record_key).await.map_err(actix_web::error::ErrorInternalServerError)? {
+                return Ok(req.into_response(stored.replay(&request_hash).map_into_right_body()));
+            }
+
+            let lock_value = format!("{}:{}", request_hash, Uuid::new_v4());
+            let acquired: bool = redis::cmd("SET")
+                .arg(&lock_key)
+                .arg(&lock_value)
+                .arg("NX")
+                .arg("PX")
+                .arg(lock_ttl.as_millis() as u64)
+                .query_async::<_, Option<String>>(&mut conn)
+                .await
+                .map_err(actix_web::error::ErrorInternalServerError)?
+                .is_some();
+
+            if !acquired {
+                // The first request may have finished between the two reads
+                if let Some(stored) = StoredResponse::load(&mut conn, &record_key).await.map_err(actix_web::error::ErrorInternalServerError)? {
+                    return Ok(req.into_response(stored.replay(&request_hash).map_into_right_body()));
+                }
+                let holder: Option<String> = redis::cmd("GET")
+                    .arg(&lock_key)
+                    .query_async(&mut conn)
+                    .await
+                    .map_err(actix_web::error::ErrorInternalServerError)?;
+                let response = match holder {
+                    Some(holder) if !holder.starts_with(&request_hash) => key_reused_response(),
+                    _ => in_flight_response(),
+                };
+                return Ok(req.into_response(response.map_into_right_body()));
+            }
+
+            let release = |mut conn: redis::aio::MultiplexedConnection| async move {
+                let released: Result<i64, _> = release_script.key(&lock_key).arg(&lock_value).invoke_async(&mut conn).await;
+                if let Err(e) = released {
+                    tracing::warn!(error = %e, "failed to release idempotency lock");
+                }
+            };
+
+            let res = match service.call(req).await {
+                Ok(res) => res,
+                Err(e) => {
+                    release(conn).await;
+                    return Err(e);
+                }
+            };
+
+            if !is_replayable(res.status()) {
+                release(conn).await;
+                return Ok(res.map_into_left_body());
             }
 
-            // Proceed with normal request
-            let fut = service.call(req);
-            let res = fut.await?;
-            
-            // If successful, cache it
-            if res.status().is_success() {
-                let _: () = conn.set_ex(&redis_key, "{\"status\": \"cached_success\"}", 86400).await.map_err(actix_web::error::ErrorInternalServerError)?;
+            let (http_req, res) = res.into_parts();
+            let (head, body) = res.into_parts();
+            let body = match to_bytes(body).await {
+                Ok(body) => body,
+                Err(e) => {
+                    release(conn).await;
+                    let e: Box<dyn std::error::Error> = e.into();
+                    return Err(actix_web::error::ErrorInternalServerError(e.to_string()));
+                }
+            };
+
+            let stored = StoredResponse::capture(&head, &body, request_hash);
+            if let Err(e) = stored.save(&mut conn, &record_key, ttl).await {
+                tracing::warn!(error = %e, "failed to store idempotent response");
             }
+            release(conn).await;
 
-            Ok(res.map_into_left_body())
+            let res = head.set_body(body).map_into_boxed_body().map_into_right_body();
+            Ok(ServiceResponse::new(http_req, res))
         })
     }
 }
+
+/// A completed response as stored in the `idemp:{tenant}:{key}` hash.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct StoredResponse {
+    request_hash: String,
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl StoredResponse {
+    fn capture(head: &HttpResponse<()>, body: &[u8], request_hash: String) -> Self {
+        let headers = head
+            .headers()
+            .iter()
+            .filter(|(name, _)| is_stored_header(name))
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+            .collect();
+        Self {
+            request_hash,
+            status: head.status().as_u16(),
+            headers,
+            body: body.to_vec(),
+        }
+    }
+
+    fn to_fields(&self) -> Vec<(&'static str, Vec<u8>)> {
+        vec![
+            ("request_hash", self.request_hash.clone().into_bytes()),
+            ("status", self.status.to_string().into_bytes()),
+            ("headers", serde_json::to_vec(&self.headers).unwrap_or_default()),
+            ("body", self.body.clone()),
+        ]
+    }
+
+    fn from_fields(mut fields: HashMap<String, Vec<u8>>) -> Option<Self> {
+        let text = |bytes: Vec<u8>| String::from_utf8(bytes).ok();
+        Some(Self {
+            request_hash: text(fields.remove("request_hash")?)?,
+            status: text(fields.remove("status")?)?.parse().ok()?,
+            headers: serde_json::from_slice(&fields.remove("headers")?).ok()?,
+            body: fields.remove("body").unwrap_or_default(),
+        })
+    }
+
+    async fn load(conn: &mut redis::aio::MultiplexedConnection, key: &str) -> redis::RedisResult<Option<Self>> {
+        let fields: HashMap<String, Vec<u8>> = redis::cmd("HGETALL").arg(key).query_async(conn).await?;
+        Ok(Self::from_fields(fields))
+    }
+
+    async fn save(&self, conn: &mut redis::aio::MultiplexedConnection, key: &str, ttl: Duration) -> redis::RedisResult<()> {
+        redis::pipe()
+            .atomic()
+            .del(key)
+            .hset_multiple(key, &self.to_fields())
+            .pexpire(key, ttl.as_millis() as i64)
+            .query_async(conn)
+            .await
+    }
+
+    fn replay(self, request_hash: &str) -> HttpResponse {
+        if self.request_hash != request_hash {
+            return key_reused_response();
+        }
+
+        let status = StatusCode::from_u16(self.status).unwrap_or(StatusCode::OK);
+        let mut builder = HttpResponse::build(status);
+        for (name, value) in &self.headers {
+            builder.append_header((name.as_str(), value.as_str()));
+        }
+        builder.insert_header(("Idempotent-Replayed", "true"));
+        builder.body(self.body)
+    }
+}
+
+fn request_fingerprint(method: &Method, path: &str, body: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(method.as_str().as_bytes());
+    hasher.update(b" ");
+    hasher.update(path.as_bytes());
+    hasher.update(b"\n");
+    hasher.update(body);
+    hex::encode(hasher.finalize())
+}
+
+/// Server errors and statuses that mean "try again" must not be pinned to the key.
+fn is_replayable(status: StatusCode) -> bool {
+    !status.is_server_error()
+        && !matches!(
+            status,
+            StatusCode::REQUEST_TIMEOUT | StatusCode::CONFLICT | StatusCode::TOO_MANY_REQUESTS
+        )
+}
+
+/// Per-request and hop-by-hop headers are regenerated on replay rather than stored.
+fn is_stored_header(name: &HeaderName) -> bool {
+    let name = name.as_str();
+    !matches!(name, "connection" | "content-length" | "date" | "transfer-encoding" | "retry-after")
+        && !name.starts_with("ratelimit-")
+}
+
+fn key_reused_response() -> HttpResponse {
+    Problem::new(StatusCode::UNPROCESSABLE_ENTITY, "idempotency_key_reused")
+        .with_detail("Idempotency-Key was already used with a different request")
+        .response()
+}
+
+fn in_flight_response() -> HttpResponse {
+    let mut response = Problem::new(StatusCode::CONFLICT, "idempotency_request_in_progress")
+        .with_detail("A request with this Idempotency-Key is still being processed")
+        .response();
+    response.headers_mut().insert(RETRY_AFTER, HeaderValue::from_static("1"));
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fingerprint_covers_method_path_and_body() {
+        let base = request_fingerprint(&Method::POST, "/orders", b"{\"qty\":1}");
+        assert_eq!(base, request_fingerprint(&Method::POST, "/orders", b"{\"qty\":1}"));
+        assert_ne!(base, request_fingerprint(&Method::POST, "/orders", b"{\"qty\":2}"));
+        assert_ne!(base, request_fingerprint(&Method::PUT, "/orders", b"{\"qty\":1}"));
+        assert_ne!(base, request_fingerprint(&Method::POST, "/payments", b"{\"qty\":1}"));
+    }
+
+    #[test]
+    fn test_stored_response_round_trips_and_replays() {
+        let head = HttpResponse::Created()
+            .insert_header(("Location", "/orders/1"))
+            .insert_header(("RateLimit-Remaining", "3"))
+            .content_type("application/json")
+            .finish()
+            .drop_body();
+        let stored = StoredResponse::capture(&head, b"{\"id\":1}", "abc".to_string());
+        assert_eq!(stored.status, 201);
+        assert!(stored.headers.iter().any(|(name, value)| name == "location" && value == "/orders/1"));
+        assert!(!stored.headers.iter().any(|(name, _)| name.starts_with("ratelimit-")));
+
+        let fields = stored
+            .to_fields()
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value))
+            .collect();
+        let loaded = StoredResponse::from_fields(fields).unwrap();
+        assert_eq!(loaded, stored);
+
+        let replayed = loaded.clone().replay("abc");
+        assert_eq!(replayed.status(), StatusCode::CREATED);
+        assert_eq!(replayed.headers().get("location").unwrap(), "/orders/1");
+        assert_eq!(replayed.headers().get("idempotent-replayed").unwrap(), "true");
+
+        assert_eq!(loaded.replay("other").status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(StoredResponse::from_fields(HashMap::new()).is_none());
+    }
+
+    #[test]
+    fn test_only_final_outcomes_are_replayable() {
+        assert!(is_replayable(StatusCode::CREATED));
+        assert!(is_replayable(StatusCode::BAD_REQUEST));
+        assert!(!is_replayable(StatusCode::CONFLICT));
+        assert!(!is_replayable(StatusCode::TOO_MANY_REQUESTS));
+        assert!(!is_replayable(StatusCode::SERVICE_UNAVAILABLE));
+    }
+
+    #[test]
+    fn test_route_ttl_overrides_default() {
+        let idempotency = Idempotency::new(redis::Client::open("redis://127.0.0.1/").unwrap())
+            .with_ttl(Duration::from_secs(60))
+            .with_route_ttl(Method::POST, "/payments", Duration::from_secs(3600));
+        assert_eq!(idempotency.ttl_for(&Method::POST, "/payments"), Duration::from_secs(3600));
+        assert_eq!(idempotency.ttl_for(&Method::POST, "/orders"), Duration::from_secs(60));
+    }
+}
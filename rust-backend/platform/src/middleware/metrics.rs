@@ -0,0 +1,149 @@
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::{Error, HttpMessage};
+use futures_util::future::LocalBoxFuture;
+use std::future::{ready, Ready};
+use std::time::Instant;
+
+use crate::metrics;
+use crate::tenant::TenantContext;
+
+/// Records `http_requests_total`, `http_request_duration_seconds`,
+/// `http_requests_in_flight` and `http_requests_by_tier_total` for every request.
+///
+/// Routes are labelled by their matched pattern (`/orders/{id}`), or `unmatched`, so
+/// path parameters do not blow up label cardinality. Wrap it at App level, outside
+/// the auth middleware, so rejected requests are counted too. The tier is read from
+/// the `TenantContext` that `TenantAuthMiddleware` leaves in the request extensions;
+/// requests that end in an error rather than a response are counted under `none`.
+pub struct MetricsMiddleware;
+
+impl<S, B> Transform<S, ServiceRequest> for MetricsMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = MetricsMiddlewareService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(MetricsMiddlewareService { service }))
+    }
+}
+
+pub struct MetricsMiddlewareService<S> {
+    service: S,
+}
+
+impl<S, B> Service<ServiceRequest> for MetricsMiddlewareService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let started = Instant::now();
+        let in_flight = metrics::track_in_flight();
+        // Matched against the app's resource map, so it is known before routing
+        let route = req.match_pattern().unwrap_or_else(|| "unmatched".to_string());
+        let method = req.method().clone();
+        let fut = self.service.call(req);
+
+        Box::pin(async move {
+            let result = fut.await;
+            let (status, tier) = match &result {
+                Ok(res) => (
+                    res.status(),
+                    res.request().extensions().get::<TenantContext>().map(|ctx| ctx.tier.to_string()),
+                ),
+                Err(err) => (err.as_response_error().status_code(), None),
+            };
+            metrics::observe_http(&route, method.as_str(), status.as_u16(), tier.as_deref(), started.elapsed());
+            drop(in_flight);
+            result
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::RequireScope;
+    use crate::scopes;
+    use crate::tenant::{AuthMethod, PricingTier};
+    use actix_web::body::to_bytes;
+    use actix_web::{test, web, App, HttpResponse, Responder};
+    use uuid::Uuid;
+
+    async fn scrape() -> String {
+        let req = test::TestRequest::default().to_http_request();
+        let resp = metrics::metrics_handler().await.respond_to(&req);
+        String::from_utf8(to_bytes(resp.into_body()).await.ok().unwrap().to_vec()).unwrap()
+    }
+
+    fn sample(body: &str, prefix: &str) -> f64 {
+        body.lines()
+            .find(|line| line.starts_with(prefix))
+            .and_then(|line| line.rsplit(' ').next())
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(0.0)
+    }
+
+    #[actix_web::test]
+    async fn test_requests_are_recorded_by_route_pattern_and_tier() {
+        metrics::init_metrics("metrics-test");
+        let app = test::init_service(
+            App::new()
+                .wrap(MetricsMiddleware)
+                .route(
+                    "/widgets/{id}",
+                    web::get().to(|req: actix_web::HttpRequest| async move {
+                        let ctx = TenantContext::new(Uuid::new_v4(), None, PricingTier::Growth, vec![], AuthMethod::ApiKey);
+                        req.extensions_mut().insert(ctx);
+                        HttpResponse::Ok().finish()
+                    }),
+                )
+                .service(
+                    web::scope("/guarded")
+                        .wrap(RequireScope::new(scopes::TENANTS_READ))
+                        .route("", web::get().to(HttpResponse::Ok)),
+                ),
+        )
+        .await;
+
+        for uri in ["/widgets/1", "/widgets/2", "/guarded", "/nowhere"] {
+            let _ = app.call(test::TestRequest::get().uri(uri).to_request()).await;
+        }
+
+        // Other tests in this binary may have initialised metrics under another name
+        let service = metrics::service();
+        let body = scrape().await;
+        let total = |route: &str, status: u16| {
+            sample(&body, &format!(r#"http_requests_total{{method="GET",route="{route}",service="{service}",status="{status}"}}"#))
+        };
+        assert_eq!(total("/widgets/{id}", 200), 2.0);
+        assert_eq!(total("/guarded", 401), 1.0);
+        assert_eq!(total("unmatched", 404), 1.0);
+        assert_eq!(
+            sample(
+                &body,
+                &format!(r#"http_request_duration_seconds_count{{method="GET",route="/widgets/{{id}}",service="{service}",status="200"}}"#)
+            ),
+            2.0
+        );
+        assert_eq!(
+            sample(&body, &format!(r#"http_requests_by_tier_total{{service="{service}",status="200",tier="Growth"}}"#)),
+            2.0
+        );
+        assert_eq!(sample(&body, &format!(r#"http_requests_in_flight{{service="{service}"}}"#)), 0.0);
+    }
+}
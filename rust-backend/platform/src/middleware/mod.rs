@@ -1,7 +1,17 @@
+pub mod jwks;
 pub mod tenant_middleware;
 
-pub use tenant_middleware::{PaymentRequiredError, TenantAuthMiddleware};
+pub use idempotency::Idempotency;
+pub use jwks::{JwksVerifier, JwtError};
+pub use metrics::MetricsMiddleware;
+pub use operator_auth::OperatorAuth;
+pub use rate_limiter::RateLimiter;
+pub use require_scope::RequireScope;
+pub use tenant_middleware::TenantAuthMiddleware;
 
 pub mod request_id;
 pub mod rate_limiter;
 pub mod idempotency;
+pub mod require_scope;
+pub mod metrics;
+pub mod operator_auth;
@@ -0,0 +1,129 @@
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::Error;
+use futures_util::future::LocalBoxFuture;
+use std::future::{ready, Ready};
+use std::sync::Arc;
+
+use crate::api_keys::{constant_time_eq, hash_api_key};
+use crate::errors::AppError;
+
+/// Header carrying the platform-operator token.
+pub const OPERATOR_TOKEN_HEADER: &str = "X-Operator-Token";
+
+/// Admits only requests presenting the platform-operator token in `X-Operator-Token`.
+///
+/// Guards control-plane routes no tenant credential may reach (onboarding tenants,
+/// changing tiers and database placement). Without a configured token every request is
+/// rejected:
+///
+/// ```ignore
+/// web::scope("/v1/operator")
+///     .wrap(OperatorAuth::new(config.get("OPERATOR_API_TOKEN")))
+/// ```
+#[derive(Clone)]
+pub struct OperatorAuth {
+    token_hash: Option<Arc<str>>,
+}
+
+impl OperatorAuth {
+    pub fn new(token: Option<&str>) -> Self {
+        Self {
+            token_hash: token.filter(|t| !t.is_empty()).map(|t| Arc::from(hash_api_key(t))),
+        }
+    }
+
+    fn check(&self, presented: Option<&str>) -> Result<(), AppError> {
+        let expected = self
+            .token_hash
+            .as_deref()
+            .ok_or_else(|| AppError::Forbidden("Operator access is not configured".to_string()))?;
+        let presented = presented.ok_or_else(|| AppError::Unauthorized("Missing operator token".to_string()))?;
+        if !constant_time_eq(hash_api_key(presented).as_bytes(), expected.as_bytes()) {
+            return Err(AppError::Unauthorized("Invalid operator token".to_string()));
+        }
+        Ok(())
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for OperatorAuth
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = OperatorAuthMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(OperatorAuthMiddleware { service, guard: self.clone() }))
+    }
+}
+
+pub struct OperatorAuthMiddleware<S> {
+    service: S,
+    guard: OperatorAuth,
+}
+
+impl<S, B> Service<ServiceRequest> for OperatorAuthMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let presented = req.headers().get(OPERATOR_TOKEN_HEADER).and_then(|v| v.to_str().ok());
+        if let Err(e) = self.guard.check(presented) {
+            return Box::pin(ready(Err(e.into())));
+        }
+        Box::pin(self.service.call(req))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{test, web, App, HttpResponse};
+
+    #[actix_web::test]
+    async fn test_only_the_operator_token_is_admitted() {
+        let app = test::init_service(
+            App::new()
+                .wrap(OperatorAuth::new(Some("op-secret")))
+                .route("/tenants", web::post().to(HttpResponse::Created)),
+        )
+        .await;
+
+        let resp = app.call(test::TestRequest::post().uri("/tenants").to_request()).await;
+        assert_eq!(resp.expect_err("missing token").error_response().status(), 401);
+
+        let wrong = test::TestRequest::post().uri("/tenants").insert_header((OPERATOR_TOKEN_HEADER, "guess"));
+        let resp = app.call(wrong.to_request()).await;
+        assert_eq!(resp.expect_err("wrong token").error_response().status(), 401);
+
+        let right = test::TestRequest::post().uri("/tenants").insert_header((OPERATOR_TOKEN_HEADER, "op-secret"));
+        assert_eq!(test::call_service(&app, right.to_request()).await.status(), 201);
+    }
+
+    #[actix_web::test]
+    async fn test_unconfigured_operator_access_is_forbidden() {
+        let app = test::init_service(
+            App::new()
+                .wrap(OperatorAuth::new(Some("")))
+                .route("/tenants", web::post().to(HttpResponse::Created)),
+        )
+        .await;
+
+        let req = test::TestRequest::post().uri("/tenants").insert_header((OPERATOR_TOKEN_HEADER, ""));
+        let resp = app.call(req.to_request()).await;
+        assert_eq!(resp.expect_err("no operator configured").error_response().status(), 403);
+    }
+}
@@ -1,14 +1,205 @@
-use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
-use actix_web::{Error, HttpMessage, HttpResponse};
 use actix_web::body::EitherBody;
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header::{HeaderMap, HeaderName, HeaderValue, RETRY_AFTER};
+use actix_web::http::{Method, StatusCode};
+use actix_web::{Error, HttpMessage};
 use futures_util::future::LocalBoxFuture;
-use redis::AsyncCommands;
+use std::collections::HashMap;
 use std::future::{ready, Ready};
 use std::rc::Rc;
-use crate::tenant::TenantContext;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::errors::Problem;
+use crate::tenant::{RateLimitPolicy, TenantContext};
+
+/// Sliding-window counter evaluated atomically against every window in ARGV.
+///
+/// Each window keeps a counter for the current and previous fixed interval; the previous
+/// one is weighted by how much of it still overlaps the sliding window. The request is
+/// only counted when it fits every window, so rejected calls do not eat into the budget.
+/// Time comes from the Redis server so replicas with skewed clocks share one view.
+///
+/// KEYS = counter key prefixes, ARGV = cost, then (key index, window_ms, limit) triples.
+/// Returns {allowed, limit, remaining, reset_ms, retry_after_ms} for the tightest window.
+const SLIDING_WINDOW_SCRIPT: &str = r#"
+redis.replicate_commands()
+local cost = tonumber(ARGV[1])
+local time = redis.call('TIME')
+local now = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+
+local allowed = 1
+local limit_out, remaining_out, reset_out, retry_out = 0, nil, 0, 0
+local counters = {}
+
+for i = 2, #ARGV, 3 do
+  local base = KEYS[tonumber(ARGV[i])]
+  local window = tonumber(ARGV[i + 1])
+  local limit = tonumber(ARGV[i + 2])
+  local index = math.floor(now / window)
+  local elapsed = now - index * window
+  local curr_key = base .. ':' .. window .. ':' .. index
+  local prev = tonumber(redis.call('GET', base .. ':' .. window .. ':' .. (index - 1)) or '0')
+  local curr = tonumber(redis.call('GET', curr_key) or '0')
+  local remaining = limit - (prev * (window - elapsed) / window + curr) - cost
+
+  if remaining < 0 then
+    allowed = 0
+    local wait = window - elapsed
+    if prev > 0 and curr + cost <= limit then
+      wait = math.ceil(window * (1 - (limit - curr - cost) / prev)) - elapsed
+    end
+    if wait > retry_out then
+      retry_out = wait
+    end
+  end
+
+  if remaining_out == nil or remaining < remaining_out then
+    limit_out, remaining_out, reset_out = limit, remaining, window - elapsed
+  end
+  counters[#counters + 1] = { curr_key, window }
+end
 
+if allowed == 1 then
+  for _, counter in ipairs(counters) do
+    redis.call('INCRBY', counter[1], cost)
+    redis.call('PEXPIRE', counter[1], counter[2] * 2)
+  end
+end
+
+return { allowed, limit_out, math.max(0, math.floor(remaining_out)), reset_out, retry_out }
+"#;
+
+const BURST_WINDOW: Duration = Duration::from_secs(1);
+const SUSTAINED_WINDOW: Duration = Duration::from_secs(60);
+
+/// Outcome of one limiter check, used to build the `RateLimit-*` response headers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimitDecision {
+    pub allowed: bool,
+    pub limit: u64,
+    pub remaining: u64,
+    pub reset: Duration,
+    pub retry_after: Duration,
+}
+
+impl RateLimitDecision {
+    fn from_script((allowed, limit, remaining, reset_ms, retry_ms): (i64, i64, i64, i64, i64)) -> Self {
+        let millis = |ms: i64| Duration::from_millis(ms.max(0) as u64);
+        Self {
+            allowed: allowed == 1,
+            limit: limit.max(0) as u64,
+            remaining: remaining.max(0) as u64,
+            reset: millis(reset_ms),
+            retry_after: millis(retry_ms),
+        }
+    }
+
+    /// Writes `RateLimit-Limit`/`-Remaining`/`-Reset`/`-Policy`, plus `Retry-After`
+    /// when the request was rejected. Durations are rounded up to whole seconds.
+    pub fn write_headers(&self, policy: RateLimitPolicy, headers: &mut HeaderMap) {
+        let mut set = |name: &'static str, value: String| {
+            if let Ok(value) = HeaderValue::from_str(&value) {
+                headers.insert(HeaderName::from_static(name), value);
+            }
+        };
+        set("ratelimit-limit", self.limit.to_string());
+        set("ratelimit-remaining", self.remaining.to_string());
+        set("ratelimit-reset", ceil_secs(self.reset).to_string());
+        set(
+            "ratelimit-policy",
+            format!(
+                "{};w={}, {};w={}",
+                policy.burst_per_second,
+                BURST_WINDOW.as_secs(),
+                policy.per_minute,
+                SUSTAINED_WINDOW.as_secs()
+            ),
+        );
+        if !self.allowed {
+            headers.insert(RETRY_AFTER, HeaderValue::from(ceil_secs(self.retry_after).max(1)));
+        }
+    }
+}
+
+fn ceil_secs(duration: Duration) -> u64 {
+    duration.as_millis().div_ceil(1000) as u64
+}
+
+/// Per-tenant request limiter backed by a Redis sliding window.
+///
+/// Every authenticated request is checked against the tenant's burst (per second) and
+/// sustained (per minute) budget from [`TenantContext::tenant_rate_limit`]. Requests made
+/// with an API key are also checked against a window of their own sized by
+/// [`TenantContext::rate_limit`], so one key's traffic never spends another key's budget
+/// while minting more keys never raises the tenant's. Routes can be weighted with
+/// [`RateLimiter::with_route_cost`] so expensive endpoints draw down the budget faster.
+/// Must be registered inside `TenantAuthMiddleware`; requests without a tenant context
+/// pass through, as do all requests when Redis is unreachable.
+#[derive(Clone)]
 pub struct RateLimiter {
-    pub redis_client: redis::Client,
+    redis_client: redis::Client,
+    script: Arc<redis::Script>,
+    route_costs: Arc<HashMap<(Method, String), u32>>,
+}
+
+impl RateLimiter {
+    pub fn new(redis_client: redis::Client) -> Self {
+        Self {
+            redis_client,
+            script: Arc::new(redis::Script::new(SLIDING_WINDOW_SCRIPT)),
+            route_costs: Arc::new(HashMap::new()),
+        }
+    }
+
+    /// Charges `cost` units instead of one for `method` on the route `pattern`
+    /// (as registered, e.g. `/orders/{id}`).
+    pub fn with_route_cost(mut self, method: Method, pattern: impl Into<String>, cost: u32) -> Self {
+        Arc::make_mut(&mut self.route_costs).insert((method, pattern.into()), cost.max(1));
+        self
+    }
+
+    fn cost_for(&self, method: &Method, pattern: &str) -> u32 {
+        self.route_costs
+            .get(&(method.clone(), pattern.to_string()))
+            .copied()
+            .unwrap_or(1)
+    }
+
+    /// Checks and, if allowed, records `cost` units against the tenant's windows.
+    pub async fn check(
+        &self,
+        tenant_ctx: &TenantContext,
+        cost: u32,
+    ) -> Result<(RateLimitPolicy, RateLimitDecision), redis::RedisError> {
+        let windows = windows(tenant_ctx);
+        let mut conn = self.redis_client.get_multiplexed_async_connection().await?;
+        let mut invocation = self.script.prepare_invoke();
+        invocation.arg(cost);
+        for (index, (key, policy)) in windows.iter().enumerate() {
+            invocation
+                .key(key)
+                .arg(index + 1)
+                .arg(BURST_WINDOW.as_millis() as u64)
+                .arg(policy.burst_per_second)
+                .arg(index + 1)
+                .arg(SUSTAINED_WINDOW.as_millis() as u64)
+                .arg(policy.per_minute);
+        }
+        let result = invocation.invoke_async(&mut conn).await?;
+        Ok((tenant_ctx.rate_limit(), RateLimitDecision::from_script(result)))
+    }
+}
+
+/// Counter key prefixes and their budgets: the tenant's shared window, plus the API key's
+/// own one. The tenant id is the cluster hash tag, so every key lands on one slot.
+fn windows(tenant_ctx: &TenantContext) -> Vec<(String, RateLimitPolicy)> {
+    let tenant_key = format!("rate_limit:{{{}}}", tenant_ctx.tenant_id);
+    let mut windows = vec![(tenant_key.clone(), tenant_ctx.tenant_rate_limit())];
+    if let Some(key_id) = tenant_ctx.api_key_id {
+        windows.push((format!("{}:key:{}", tenant_key, key_id), tenant_ctx.rate_limit()));
+    }
+    windows
 }
 
 impl<S, B> Transform<S, ServiceRequest> for RateLimiter
@@ -26,14 +217,14 @@ where
     fn new_transform(&self, service: S) -> Self::Future {
         ready(Ok(RateLimiterMiddleware {
             service: Rc::new(service),
-            redis_client: self.redis_client.clone(),
+            limiter: self.clone(),
         }))
     }
 }
 
 pub struct RateLimiterMiddleware<S> {
     service: Rc<S>,
-    redis_client: redis::Client,
+    limiter: RateLimiter,
 }
 
 impl<S, B> Service<ServiceRequest> for RateLimiterMiddleware<S>
@@ -50,11 +241,10 @@ where
 
     fn call(&self, req: ServiceRequest) -> Self::Future {
         let tenant_ctx = req.extensions().get::<TenantContext>().cloned();
-        
-        let (tenant_id, limit) = match tenant_ctx {
-            Some(ctx) => (ctx.tenant_id.to_string(), ctx.tier.monthly_limit()),
+        let tenant_ctx = match tenant_ctx {
+            Some(ctx) => ctx,
             None => {
-                // If no tenant context, let it pass (or could block it, but auth middleware handles that)
+                // Unauthenticated requests are rejected by the auth middleware, not here
                 let fut = self.service.call(req);
                 return Box::pin(async move {
                     let res = fut.await?;
@@ -63,44 +253,101 @@ where
             }
         };
 
-        if limit == u64::MAX {
-            // Enterprise tier - unlimited
-            let fut = self.service.call(req);
-            return Box::pin(async move {
-                let res = fut.await?;
-                Ok(res.map_into_left_body())
-            });
-        }
-
-        let redis_client = self.redis_client.clone();
+        let pattern = req.match_pattern().unwrap_or_else(|| req.path().to_string());
+        let cost = self.limiter.cost_for(req.method(), &pattern);
+        let limiter = self.limiter.clone();
         let service = self.service.clone();
 
         Box::pin(async move {
-            let mut conn = redis_client.get_multiplexed_async_connection().await.map_err(actix_web::error::ErrorInternalServerError)?;
-            let key = format!("rate_limit:{}", tenant_id);
-            
-            // Simple fixed window for foundation
-            let current_count: u64 = conn.incr(&key, 1).await.map_err(actix_web::error::ErrorInternalServerError)?;
-            
-            if current_count == 1 {
-                // Set expiry for 1 month (2592000 seconds)
-                let _: () = conn.expire(&key, 2592000).await.map_err(actix_web::error::ErrorInternalServerError)?;
-            }
+            let outcome = match limiter.check(&tenant_ctx, cost).await {
+                Ok(outcome) => Some(outcome),
+                Err(e) => {
+                    tracing::warn!(tenant_id = %tenant_ctx.tenant_id, error = %e, "rate limiter unavailable, allowing request");
+                    None
+                }
+            };
 
-            if current_count > limit {
-                let err_res = HttpResponse::TooManyRequests()
-                    .json(serde_json::json!({
-                        "error": "rate_limit_exceeded",
-                        "message": "Monthly API limit exceeded for your pricing tier",
-                        "limit": limit
-                    }))
-                    .map_into_right_body();
-                return Ok(req.into_response(err_res));
+            if let Some((policy, decision)) = outcome.filter(|(_, decision)| !decision.allowed) {
+                let mut response = Problem::new(StatusCode::TOO_MANY_REQUESTS, "rate_limited")
+                    .with_detail("Request rate limit exceeded for your pricing tier")
+                    .with_extension("limit", decision.limit)
+                    .with_extension("retry_after_seconds", ceil_secs(decision.retry_after).max(1))
+                    .response();
+                decision.write_headers(policy, response.headers_mut());
+                return Ok(req.into_response(response.map_into_right_body()));
             }
 
-            let fut = service.call(req);
-            let res = fut.await?;
+            let mut res = service.call(req).await?;
+            if let Some((policy, decision)) = outcome {
+                decision.write_headers(policy, res.headers_mut());
+            }
             Ok(res.map_into_left_body())
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tenant::PricingTier;
+
+    #[test]
+    fn test_headers_round_up_and_include_retry_after_when_rejected() {
+        let decision = RateLimitDecision::from_script((0, 50, -3, 1_200, 250));
+        assert!(!decision.allowed);
+        assert_eq!(decision.remaining, 0);
+
+        let mut headers = HeaderMap::new();
+        decision.write_headers(PricingTier::Growth.rate_limit(), &mut headers);
+        assert_eq!(headers.get("ratelimit-limit").unwrap(), "50");
+        assert_eq!(headers.get("ratelimit-remaining").unwrap(), "0");
+        assert_eq!(headers.get("ratelimit-reset").unwrap(), "2");
+        assert_eq!(headers.get("ratelimit-policy").unwrap(), "50;w=1, 1000;w=60");
+        assert_eq!(headers.get(RETRY_AFTER).unwrap(), "1");
+    }
+
+    #[test]
+    fn test_allowed_requests_have_no_retry_after() {
+        let decision = RateLimitDecision::from_script((1, 60, 41, 30_000, 0));
+        let mut headers = HeaderMap::new();
+        decision.write_headers(PricingTier::Free.rate_limit(), &mut headers);
+        assert_eq!(headers.get("ratelimit-remaining").unwrap(), "41");
+        assert_eq!(headers.get("ratelimit-reset").unwrap(), "30");
+        assert!(headers.get(RETRY_AFTER).is_none());
+    }
+
+    #[test]
+    fn test_api_keys_get_their_own_window_within_the_tenant_one() {
+        use crate::tenant::AuthMethod;
+        use uuid::Uuid;
+
+        let tenant_id = Uuid::new_v4();
+        let tenant_window = (format!("rate_limit:{{{}}}", tenant_id), PricingTier::Growth.rate_limit());
+        let context = |auth| TenantContext::new(tenant_id, None, PricingTier::Growth, vec![], auth);
+        assert_eq!(windows(&context(AuthMethod::Jwt)), vec![tenant_window.clone()]);
+
+        let (first, second) = (Uuid::new_v4(), Uuid::new_v4());
+        let keyed = |id| {
+            windows(&context(AuthMethod::ApiKey).with_api_key_id(id).with_rate_limit_override(Some(5_000)))
+        };
+        assert_eq!(
+            keyed(first),
+            vec![
+                tenant_window.clone(),
+                (format!("rate_limit:{{{}}}:key:{}", tenant_id, first), RateLimitPolicy::new(50, 5_000)),
+            ]
+        );
+        assert_eq!(keyed(second)[0], tenant_window);
+        assert_ne!(keyed(first)[1].0, keyed(second)[1].0);
+    }
+
+    #[test]
+    fn test_route_costs_default_to_one() {
+        let limiter = RateLimiter::new(redis::Client::open("redis://127.0.0.1/").unwrap())
+            .with_route_cost(Method::POST, "/orders", 5)
+            .with_route_cost(Method::GET, "/reports/{id}", 0);
+        assert_eq!(limiter.cost_for(&Method::POST, "/orders"), 5);
+        assert_eq!(limiter.cost_for(&Method::GET, "/orders"), 1);
+        assert_eq!(limiter.cost_for(&Method::GET, "/reports/{id}"), 1);
+    }
+}
@@ -1,9 +1,16 @@
 use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::error::InternalError;
+use actix_web::http::header::{HeaderName, HeaderValue};
 use actix_web::{Error, HttpMessage};
 use futures_util::future::LocalBoxFuture;
 use std::future::{ready, Ready};
 use uuid::Uuid;
 
+const X_REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Tags each request with an `X-Request-ID` (taken from the client or
+/// generated) and runs it as the ambient correlation id. Errors from inner
+/// services are rendered inside that scope so problem bodies carry the id.
 pub struct RequestId;
 
 impl<S, B> Transform<S, ServiceRequest> for RequestId
@@ -48,16 +55,75 @@ where
             .unwrap_or_else(Uuid::new_v4);
 
         req.extensions_mut().insert(request_id);
-
         let fut = self.service.call(req);
 
-        Box::pin(async move {
-            let mut res = fut.await?;
-            res.headers_mut().insert(
-                actix_web::http::header::HeaderName::from_static("x-request-id"),
-                actix_web::http::header::HeaderValue::from_str(&request_id.to_string()).unwrap(),
-            );
-            Ok(res)
-        })
+        // Events published while handling the request carry the request id as correlation id
+        Box::pin(crate::observability::with_correlation_id(request_id, async move {
+            let header_value = HeaderValue::from_str(&request_id.to_string()).unwrap();
+            match fut.await {
+                Ok(mut res) => {
+                    res.headers_mut().insert(X_REQUEST_ID, header_value);
+                    Ok(res)
+                }
+                // Render errors here rather than in the dispatcher, so their
+                // problem bodies are built while the request id is in scope.
+                Err(err) => {
+                    let mut response = err.error_response();
+                    response.headers_mut().insert(X_REQUEST_ID, header_value);
+                    Err(InternalError::from_response(err, response).into())
+                }
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::{AppError, Problem};
+    use crate::middleware::RequireScope;
+    use crate::scopes;
+    use actix_web::body::to_bytes;
+    use actix_web::{test, web, App, HttpResponse, ResponseError};
+
+    #[actix_web::test]
+    async fn test_problem_bodies_carry_the_request_id() {
+        let app = test::init_service(
+            App::new()
+                .wrap(RequestId)
+                .route(
+                    "/returned",
+                    web::get().to(|| async { AppError::NotFound("order not found".into()).error_response() }),
+                )
+                .route(
+                    "/raised",
+                    web::get().to(|| async { Err::<HttpResponse, _>(AppError::Conflict("stale version".into())) }),
+                )
+                .service(
+                    web::scope("/guarded")
+                        .wrap(RequireScope::new(scopes::TENANTS_READ))
+                        .route("", web::get().to(HttpResponse::Ok)),
+                ),
+        )
+        .await;
+        let request_id = Uuid::new_v4();
+
+        for (uri, status) in [("/returned", 404), ("/raised", 409), ("/guarded", 401)] {
+            let req = test::TestRequest::get()
+                .uri(uri)
+                .insert_header(("X-Request-ID", request_id.to_string()))
+                .to_request();
+            // Middleware errors come back as Err and are rendered by the dispatcher.
+            let resp = match app.call(req).await {
+                Ok(res) => res.into_parts().1.map_into_boxed_body(),
+                Err(err) => err.error_response(),
+            };
+
+            assert_eq!(resp.status().as_u16(), status);
+            assert_eq!(resp.headers().get("x-request-id").unwrap(), request_id.to_string().as_str());
+            let body = to_bytes(resp.into_body()).await.unwrap();
+            let problem: Problem = serde_json::from_slice(&body).unwrap();
+            assert_eq!(problem.request_id, Some(request_id));
+        }
     }
 }
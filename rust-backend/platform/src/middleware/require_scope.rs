@@ -0,0 +1,156 @@
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::Method;
+use actix_web::{Error, HttpMessage};
+use futures_util::future::LocalBoxFuture;
+use std::future::{ready, Ready};
+
+use crate::errors::AppError;
+use crate::scopes::ResourceScopes;
+use crate::tenant::TenantContext;
+
+#[derive(Clone, Copy)]
+enum Requirement {
+    Scope(&'static str),
+    Resource(ResourceScopes),
+}
+
+/// Rejects requests whose `TenantContext` lacks the required scope with
+/// `AppError::Forbidden`.
+///
+/// Must run inside `TenantAuthMiddleware`, i.e. be `.wrap`ped before it:
+///
+/// ```ignore
+/// web::scope("/api/v1")
+///     .wrap(RequireScope::resource(scopes::ORDERS))
+///     .wrap(TenantAuthMiddleware::with_redis(redis_client))
+/// ```
+#[derive(Clone, Copy)]
+pub struct RequireScope {
+    requirement: Requirement,
+}
+
+impl RequireScope {
+    /// Requires `scope` regardless of the request method.
+    pub fn new(scope: &'static str) -> Self {
+        Self { requirement: Requirement::Scope(scope) }
+    }
+
+    /// Requires the resource's read scope for GET, HEAD and OPTIONS and its write scope
+    /// for everything else.
+    pub fn resource(scopes: ResourceScopes) -> Self {
+        Self { requirement: Requirement::Resource(scopes) }
+    }
+
+    fn required_for(&self, method: &Method) -> &'static str {
+        match self.requirement {
+            Requirement::Scope(scope) => scope,
+            Requirement::Resource(scopes) if matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS) => {
+                scopes.read
+            }
+            Requirement::Resource(scopes) => scopes.write,
+        }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RequireScope
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RequireScopeMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequireScopeMiddleware { service, guard: *self }))
+    }
+}
+
+pub struct RequireScopeMiddleware<S> {
+    service: S,
+    guard: RequireScope,
+}
+
+impl<S, B> Service<ServiceRequest> for RequireScopeMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let required = self.guard.required_for(req.method());
+        let allowed = match req.extensions().get::<TenantContext>() {
+            Some(ctx) => ctx.require_scope(required),
+            None => Err(AppError::Unauthorized("Missing tenant context".to_string())),
+        };
+        if let Err(e) = allowed {
+            return Box::pin(ready(Err(e.into())));
+        }
+        Box::pin(self.service.call(req))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scopes;
+    use crate::tenant::{AuthMethod, PricingTier};
+    use actix_web::{test, web, App, HttpResponse};
+    use uuid::Uuid;
+
+    fn context(permissions: &[&str]) -> TenantContext {
+        TenantContext::new(
+            Uuid::new_v4(),
+            None,
+            PricingTier::Growth,
+            permissions.iter().map(|s| s.to_string()).collect(),
+            AuthMethod::ApiKey,
+        )
+    }
+
+    #[actix_web::test]
+    async fn test_resource_guard_picks_scope_by_method() {
+        let ctx = context(&[scopes::ORDERS_READ]);
+        let app = test::init_service(
+            App::new()
+                .wrap(RequireScope::resource(scopes::ORDERS))
+                .wrap_fn(move |req, srv| {
+                    req.extensions_mut().insert(ctx.clone());
+                    srv.call(req)
+                })
+                .route("/orders", web::get().to(HttpResponse::Ok))
+                .route("/orders", web::post().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        let resp = test::call_service(&app, test::TestRequest::get().uri("/orders").to_request()).await;
+        assert_eq!(resp.status(), 200);
+
+        let resp = app.call(test::TestRequest::post().uri("/orders").to_request()).await;
+        let err = resp.expect_err("write without orders:write is rejected");
+        assert_eq!(err.error_response().status(), 403);
+    }
+
+    #[actix_web::test]
+    async fn test_missing_context_is_unauthorized() {
+        let app = test::init_service(
+            App::new()
+                .wrap(RequireScope::new(scopes::TENANTS_READ))
+                .route("/tenants", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        let resp = app.call(test::TestRequest::get().uri("/tenants").to_request()).await;
+        let err = resp.expect_err("request without a tenant context is rejected");
+        assert_eq!(err.error_response().status(), 401);
+    }
+}
@@ -1,16 +1,20 @@
 use actix_web::body::EitherBody;
 use actix_web::dev::{Payload, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::{header, Method, StatusCode};
 use actix_web::{web, Error, FromRequest, HttpMessage, HttpRequest, HttpResponse};
 use futures_util::future::LocalBoxFuture;
-use jsonwebtoken::{decode, DecodingKey, Validation};
 use serde::{Deserialize, Serialize};
-use std::env;
 use std::future::{ready, Ready};
+use std::net::{IpAddr, SocketAddr};
 use std::rc::Rc;
-use std::str::FromStr;
 use uuid::Uuid;
 
-use crate::tenant::{ApiKeyRecord, AuthMethod, PricingTier, TenantContext};
+use crate::api_keys::{ApiKeyError, ApiKeyVerifier};
+use crate::errors::{AppError, Problem};
+use crate::middleware::jwks::JwksVerifier;
+use crate::scopes;
+use crate::tenant::{AuthMethod, PricingTier, TenantContext};
+use crate::tenant_directory::{cidr_contains, TenantDirectory};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct JwtClaims {
@@ -24,38 +28,117 @@ struct JwtClaims {
     tier: Option<PricingTier>,
 }
 
-#[derive(Serialize, Deserialize)]
-pub struct PaymentRequiredError {
-    pub error: String,
-    pub message: String,
-    pub tier: String,
-    pub limit: u64,
-    pub current_usage: u64,
+/// Proxies whose `X-Forwarded-For` is believed, as comma-separated addresses or CIDRs.
+fn trusted_proxies_from_env() -> Vec<String> {
+    std::env::var("TRUSTED_PROXIES")
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// The caller's address: the TCP peer, unless the peer is a trusted proxy, in which case
+/// the nearest `X-Forwarded-For` hop that is not itself a trusted proxy. Forwarding
+/// headers from any other peer are ignored, since the client can set them freely.
+fn client_ip(req: &ServiceRequest, trusted_proxies: &[String]) -> Option<IpAddr> {
+    let peer = req.peer_addr()?.ip();
+    let trusted = |ip: &IpAddr| trusted_proxies.iter().any(|entry| cidr_contains(entry, *ip));
+    if !trusted(&peer) {
+        return Some(peer);
+    }
+    let hops: Vec<IpAddr> = req
+        .headers()
+        .get_all("X-Forwarded-For")
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|hop| {
+            let hop = hop.trim();
+            hop.parse::<IpAddr>()
+                .ok()
+                .or_else(|| hop.parse::<SocketAddr>().ok().map(|a| a.ip()))
+        })
+        .collect();
+    hops.iter()
+        .rev()
+        .find(|ip| !trusted(ip))
+        .or(hops.first())
+        .copied()
+        .or(Some(peer))
+}
+
+/// Short-circuits the chain with `response`.
+fn reject<B>(req: ServiceRequest, response: HttpResponse) -> ServiceResponse<EitherBody<B>> {
+    let (request, _) = req.into_parts();
+    ServiceResponse::new(request, response.map_into_right_body())
 }
 
 #[derive(Clone)]
 pub struct TenantAuthMiddleware {
     redis_client: Option<redis::Client>,
-    jwt_secret: Option<String>,
+    jwt: Option<JwksVerifier>,
+    api_keys: Option<ApiKeyVerifier>,
+    tenants: Option<TenantDirectory>,
+    trusted_proxies: Option<Vec<String>>,
 }
 
 impl TenantAuthMiddleware {
     pub fn new() -> Self {
         Self {
             redis_client: None,
-            jwt_secret: None,
+            jwt: None,
+            api_keys: None,
+            tenants: None,
+            trusted_proxies: None,
         }
     }
 
     pub fn with_redis(redis_client: redis::Client) -> Self {
         Self {
             redis_client: Some(redis_client),
-            jwt_secret: None,
+            jwt: None,
+            api_keys: None,
+            tenants: None,
+            trusted_proxies: None,
         }
     }
 
-    pub fn with_secret(mut self, secret: impl Into<String>) -> Self {
-        self.jwt_secret = Some(secret.into());
+    /// Verifier for bearer JWTs, normally [`JwksVerifier::from_env`] built once in `main`.
+    /// Without one, JWTs are rejected and only API keys authenticate.
+    pub fn with_jwt_verifier(mut self, jwt: JwksVerifier) -> Self {
+        self.jwt = Some(jwt);
+        self
+    }
+
+    /// HS256 shared-secret verification for tests; see `JwksVerifier::from_secret`.
+    #[cfg(any(test, feature = "test-util"))]
+    pub fn with_secret(self, secret: impl Into<String>) -> Self {
+        self.with_jwt_verifier(JwksVerifier::from_secret(secret.into()))
+    }
+
+    /// Verifier for API keys. Defaults to [`ApiKeyVerifier::from_env`] with this
+    /// middleware's Redis client as the cache.
+    pub fn with_api_keys(mut self, api_keys: ApiKeyVerifier) -> Self {
+        self.api_keys = Some(api_keys);
+        self
+    }
+
+    /// Source of tenant records. Defaults to [`TenantDirectory::from_env`] with this
+    /// middleware's Redis client as the cache.
+    pub fn with_tenant_directory(mut self, tenants: TenantDirectory) -> Self {
+        self.tenants = Some(tenants);
+        self
+    }
+
+    /// Proxies allowed to report the client address through `X-Forwarded-For`, as bare
+    /// addresses or CIDRs. Defaults to `TRUSTED_PROXIES`; with none, the peer address is used.
+    pub fn with_trusted_proxies<I, T>(mut self, proxies: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        self.trusted_proxies = Some(proxies.into_iter().map(Into::into).collect());
         self
     }
 }
@@ -78,10 +161,30 @@ where
     type Future = Ready<Result<Self::Transform, Self::InitError>>;
 
     fn new_transform(&self, service: S) -> Self::Future {
+        // Built once per worker; the defaults open the control-plane pool from the environment
+        let api_keys = self.api_keys.clone().unwrap_or_else(|| {
+            let verifier = ApiKeyVerifier::from_env();
+            match &self.redis_client {
+                Some(client) => verifier.with_redis(client.clone()),
+                None => verifier,
+            }
+        });
+        let tenants = self.tenants.clone().unwrap_or_else(|| {
+            let directory = TenantDirectory::from_env();
+            match &self.redis_client {
+                Some(client) => directory.with_redis(client.clone()),
+                None => directory,
+            }
+        });
+        let trusted_proxies = self.trusted_proxies.clone().unwrap_or_else(trusted_proxies_from_env);
+
         ready(Ok(TenantAuthMiddlewareService {
             service: Rc::new(service),
             redis_client: self.redis_client.clone(),
-            jwt_secret: self.jwt_secret.clone(),
+            jwt: self.jwt.clone(),
+            api_keys,
+            tenants,
+            trusted_proxies: Rc::new(trusted_proxies),
         }))
     }
 }
@@ -89,7 +192,10 @@ where
 pub struct TenantAuthMiddlewareService<S> {
     service: Rc<S>,
     redis_client: Option<redis::Client>,
-    jwt_secret: Option<String>,
+    jwt: Option<JwksVerifier>,
+    api_keys: ApiKeyVerifier,
+    tenants: TenantDirectory,
+    trusted_proxies: Rc<Vec<String>>,
 }
 
 impl<S, B> Service<ServiceRequest> for TenantAuthMiddlewareService<S>
@@ -117,10 +223,10 @@ where
             .clone()
             .or_else(|| req.app_data::<web::Data<redis::Client>>().map(|d| d.get_ref().clone()));
 
-        let secret = self
-            .jwt_secret
-            .clone()
-            .unwrap_or_else(|| env::var("SECRET").unwrap_or_else(|_| "something".to_string()));
+        let api_keys = self.api_keys.clone();
+        let tenants = self.tenants.clone();
+        let jwt = self.jwt.clone();
+        let client_ip = client_ip(&req, &self.trusted_proxies);
 
         Box::pin(async move {
             let mut extracted_context: Option<TenantContext> = None;
@@ -141,71 +247,35 @@ where
                 });
 
             if let Some(key) = api_key_str {
-                if key.contains("invalid") || key.contains("revoked") {
-                    let (request, _) = req.into_parts();
-                    let response = HttpResponse::Unauthorized()
-                        .json(serde_json::json!({
-                            "error": "Unauthorized",
-                            "message": "Invalid API key"
-                        }))
-                        .map_into_right_body();
-                    return Ok(ServiceResponse::new(request, response));
-                }
-
-                let mut found_record: Option<ApiKeyRecord> = None;
-                if let Some(client) = &redis_client {
-                    if let Ok(mut conn) = client.get_multiplexed_async_connection().await {
-                        let redis_key = format!("api_key:{}", key);
-                        let record_json: Option<String> = redis::cmd("GET")
-                            .arg(&redis_key)
-                            .query_async(&mut conn)
-                            .await
-                            .unwrap_or(None);
-
-                        if let Some(json_str) = record_json {
-                            if let Ok(rec) = serde_json::from_str::<ApiKeyRecord>(&json_str) {
-                                if !rec.is_active {
-                                    let (request, _) = req.into_parts();
-                                    let response = HttpResponse::Unauthorized()
-                                        .json(serde_json::json!({
-                                            "error": "Unauthorized",
-                                            "message": "API key inactive"
-                                        }))
-                                        .map_into_right_body();
-                                    return Ok(ServiceResponse::new(request, response));
-                                }
-                                found_record = Some(rec);
+                let record = match api_keys.verify(&key).await {
+                    Ok(record) => record,
+                    Err(e) => {
+                        let problem = match e {
+                            ApiKeyError::Database(ref db_err) => {
+                                tracing::error!("API key lookup failed: {:?}", db_err);
+                                Problem::new(StatusCode::SERVICE_UNAVAILABLE, "api_key_verification_unavailable")
+                                    .with_detail("API key verification unavailable")
                             }
-                        }
+                            _ => Problem::new(StatusCode::UNAUTHORIZED, "invalid_api_key").with_detail(e.to_string()),
+                        };
+                        return Ok(reject(req, problem.response()));
                     }
-                }
+                };
 
-                if let Some(rec) = found_record {
-                    let tier = req
-                        .headers()
-                        .get("X-Tenant-Tier")
-                        .and_then(|h| h.to_str().ok())
-                        .and_then(|s| PricingTier::from_str(s).ok())
-                        .unwrap_or(PricingTier::Free);
+                api_keys.record_usage(record.id, client_ip);
 
-                    extracted_context = Some(TenantContext::new(
-                        rec.tenant_id,
+                // Tier comes from the tenant record below, never from the request
+                extracted_context = Some(
+                    TenantContext::new(
+                        record.tenant_id,
                         None,
-                        tier,
-                        rec.permissions,
+                        PricingTier::Free,
+                        record.permissions,
                         AuthMethod::ApiKey,
-                    ));
-                } else {
-                    // Unregistered API key: return 401 Unauthorized with JSON error
-                    let (request, _) = req.into_parts();
-                    let response = HttpResponse::Unauthorized()
-                        .json(serde_json::json!({
-                            "error": "Unauthorized",
-                            "message": "Invalid API key"
-                        }))
-                        .map_into_right_body();
-                    return Ok(ServiceResponse::new(request, response));
-                }
+                    )
+                    .with_rate_limit_override(record.rate_limit_override)
+                    .with_api_key_id(record.id),
+                );
             }
 
             // 2. Try JWT auth via Authorization: Bearer <jwt>
@@ -226,45 +296,37 @@ where
                                     .query_async::<_, bool>(&mut conn)
                                     .await
                                 {
-                                    let (request, _) = req.into_parts();
-                                    let response = HttpResponse::Unauthorized()
-                                        .json(serde_json::json!({
-                                            "error": "Unauthorized",
-                                            "message": "Token revoked"
-                                        }))
-                                        .map_into_right_body();
-                                    return Ok(ServiceResponse::new(request, response));
+                                    let problem = Problem::new(StatusCode::UNAUTHORIZED, "token_revoked")
+                                        .with_detail("Token revoked");
+                                    return Ok(reject(req, problem.response()));
                                 }
                             }
                         }
 
-                        if let Ok(decoded) = decode::<JwtClaims>(
-                            token,
-                            &DecodingKey::from_secret(secret.as_bytes()),
-                            &Validation::default(),
-                        ) {
-                            let user_id = Some(decoded.claims.sub);
-                            let tenant_id = decoded.claims.tenant_id.unwrap_or_else(|| {
-                                Uuid::new_v5(&Uuid::NAMESPACE_OID, decoded.claims.sub.as_bytes())
+                        let verified = match &jwt {
+                            Some(jwt) => jwt.verify::<JwtClaims>(token).await.map_err(|e| {
+                                tracing::debug!(error = %e, "JWT rejected");
+                            }),
+                            None => Err(()),
+                        };
+                        if let Ok(claims) = verified {
+                            let user_id = Some(claims.sub);
+                            let tenant_id = claims.tenant_id.unwrap_or_else(|| {
+                                Uuid::new_v5(&Uuid::NAMESPACE_OID, claims.sub.as_bytes())
                             });
-                            let tier = decoded.claims.tier.unwrap_or(PricingTier::Free);
+                            let tier = claims.tier.unwrap_or(PricingTier::Free);
 
                             extracted_context = Some(TenantContext::new(
                                 tenant_id,
                                 user_id,
                                 tier,
-                                vec!["*".to_string()],
+                                scopes::scopes_for_role(claims.role.as_ref().and_then(|r| r.as_str())),
                                 AuthMethod::Jwt,
                             ));
                         } else {
-                            let (request, _) = req.into_parts();
-                            let response = HttpResponse::Unauthorized()
-                                .json(serde_json::json!({
-                                    "error": "Unauthorized",
-                                    "message": "Invalid or expired token"
-                                }))
-                                .map_into_right_body();
-                            return Ok(ServiceResponse::new(request, response));
+                            let problem = Problem::new(StatusCode::UNAUTHORIZED, "invalid_token")
+                                .with_detail("Invalid or expired token");
+                            return Ok(reject(req, problem.response()));
                         }
                     }
                 }
@@ -274,18 +336,47 @@ where
             let tenant_ctx = match extracted_context {
                 Some(ctx) => ctx,
                 None => {
-                    let (request, _) = req.into_parts();
-                    let response = HttpResponse::Unauthorized()
-                        .json(serde_json::json!({
-                            "error": "Unauthorized",
-                            "message": "Missing or invalid tenant authentication credentials"
-                        }))
-                        .map_into_right_body();
-                    return Ok(ServiceResponse::new(request, response));
+                    let problem = Problem::new(StatusCode::UNAUTHORIZED, "missing_credentials")
+                        .with_detail("Missing or invalid tenant authentication credentials");
+                    return Ok(reject(req, problem.response()));
+                }
+            };
+
+            // 3. Overlay the control-plane tenant record: tier, flags, routing, IP allowlist and write pause
+            let tenant_ctx = match tenants.get(tenant_ctx.tenant_id).await {
+                Ok(Some(record)) => {
+                    if !record.allows_ip(client_ip) {
+                        let problem = Problem::new(StatusCode::FORBIDDEN, "ip_not_allowed")
+                            .with_detail("Client IP address is not in the tenant allowlist");
+                        return Ok(reject(req, problem.response()));
+                    }
+                    if record.read_only && !matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS) {
+                        let mut response = Problem::new(StatusCode::SERVICE_UNAVAILABLE, "tenant_read_only")
+                            .with_detail("Tenant data is being migrated; writes are paused")
+                            .response();
+                        response
+                            .headers_mut()
+                            .insert(header::RETRY_AFTER, header::HeaderValue::from_static("5"));
+                        return Ok(reject(req, response));
+                    }
+                    record.apply(tenant_ctx)
+                }
+                Ok(None) if tenants.is_authoritative() => {
+                    let problem = Problem::new(StatusCode::UNAUTHORIZED, "unknown_tenant")
+                        .with_detail("Credentials belong to a tenant that does not exist");
+                    return Ok(reject(req, problem.response()));
+                }
+                // Without a control-plane pool only cached records are visible
+                Ok(None) => tenant_ctx,
+                Err(e) => {
+                    tracing::error!("Tenant lookup failed: {:?}", e);
+                    let problem = Problem::new(StatusCode::SERVICE_UNAVAILABLE, "tenant_lookup_unavailable")
+                        .with_detail("Tenant lookup unavailable");
+                    return Ok(reject(req, problem.response()));
                 }
             };
 
-            // 3. Usage Metering Check via Redis Counter
+            // 4. Usage Metering Check via Redis Counter
             if let Some(client) = &redis_client {
                 if let Ok(mut conn) = client.get_multiplexed_async_connection().await {
                     let year_month = chrono::Utc::now().format("%Y-%m").to_string();
@@ -307,18 +398,12 @@ where
 
                             let limit = tenant_ctx.tier.monthly_limit();
                             if usage > limit {
-                                let err_payload = PaymentRequiredError {
-                                    error: "Payment Required".to_string(),
-                                    message: "Usage limit exceeded for current pricing tier".to_string(),
-                                    tier: tenant_ctx.tier.to_string(),
-                                    limit,
-                                    current_usage: usage,
-                                };
-                                let (request, _) = req.into_parts();
-                                let response = HttpResponse::PaymentRequired()
-                                    .json(err_payload)
-                                    .map_into_right_body();
-                                return Ok(ServiceResponse::new(request, response));
+                                let problem = Problem::new(StatusCode::PAYMENT_REQUIRED, "usage_limit_exceeded")
+                                    .with_detail("Usage limit exceeded for current pricing tier")
+                                    .with_extension("tier", tenant_ctx.tier.to_string())
+                                    .with_extension("limit", limit)
+                                    .with_extension("current_usage", usage);
+                                return Ok(reject(req, problem.response()));
                             }
                         }
                         Err(e) => {
@@ -344,9 +429,10 @@ impl FromRequest for TenantContext {
     fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
         match req.extensions().get::<TenantContext>() {
             Some(ctx) => ready(Ok(ctx.clone())),
-            None => ready(Err(actix_web::error::ErrorUnauthorized(
-                "TenantContext missing from request extensions",
-            ))),
+            None => ready(Err(AppError::Unauthorized(
+                "TenantContext missing from request extensions".into(),
+            )
+            .into())),
         }
     }
 }
@@ -440,6 +526,24 @@ mod tests {
         assert_eq!(resp.status(), actix_web::http::StatusCode::UNAUTHORIZED);
     }
 
+    #[actix_web::test]
+    async fn test_forwarded_for_only_trusted_from_known_proxies() {
+        let proxies = vec!["10.0.0.0/8".to_string()];
+        let request = |peer: &str| {
+            test::TestRequest::get()
+                .peer_addr(peer.parse().unwrap())
+                .insert_header(("X-Forwarded-For", "6.6.6.6, 203.0.113.9, 10.1.2.3"))
+                .to_srv_request()
+        };
+
+        let direct = request("198.51.100.4:5000");
+        assert_eq!(client_ip(&direct, &proxies), Some("198.51.100.4".parse().unwrap()));
+
+        let proxied = request("10.0.0.2:5000");
+        assert_eq!(client_ip(&proxied, &proxies), Some("203.0.113.9".parse().unwrap()));
+        assert_eq!(client_ip(&proxied, &[]), Some("10.0.0.2".parse().unwrap()));
+    }
+
     #[actix_web::test]
     async fn test_jwt_auth() {
         use jsonwebtoken::{encode, EncodingKey, Header};
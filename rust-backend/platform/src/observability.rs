@@ -1,6 +1,16 @@
+use opentelemetry::propagation::TextMapPropagator;
 use opentelemetry::trace::TracerProvider as _;
 use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use std::collections::HashMap;
+use std::future::Future;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
+use uuid::Uuid;
+
+tokio::task_local! {
+    static CORRELATION_ID: Uuid;
+}
 
 pub fn init_observability(service_name: &'static str) {
     let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
@@ -33,6 +43,52 @@ pub fn init_observability(service_name: &'static str) {
         .try_init();
 }
 
+/// W3C trace context plus correlation id, carried across hops that are not HTTP calls
+/// (Redis Stream entries, outbox rows).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TraceContext {
+    pub traceparent: Option<String>,
+    pub tracestate: Option<String>,
+    pub correlation_id: Option<Uuid>,
+}
+
+impl TraceContext {
+    /// Captures the current span and correlation id. `traceparent` is only set when an
+    /// OpenTelemetry layer is installed and the current span is sampled.
+    pub fn current() -> Self {
+        let mut carrier = HashMap::new();
+        TraceContextPropagator::new().inject_context(&tracing::Span::current().context(), &mut carrier);
+        Self {
+            traceparent: carrier.remove("traceparent"),
+            tracestate: carrier.remove("tracestate").filter(|s| !s.is_empty()),
+            correlation_id: current_correlation_id(),
+        }
+    }
+
+    /// Makes `span` a child of the carried remote span, if there is one.
+    pub fn set_parent(&self, span: &tracing::Span) {
+        let Some(traceparent) = &self.traceparent else {
+            return;
+        };
+        let mut carrier = HashMap::from([("traceparent".to_string(), traceparent.clone())]);
+        if let Some(tracestate) = &self.tracestate {
+            carrier.insert("tracestate".to_string(), tracestate.clone());
+        }
+        span.set_parent(TraceContextPropagator::new().extract(&carrier));
+    }
+}
+
+/// Correlation id of the request or event currently being handled.
+pub fn current_correlation_id() -> Option<Uuid> {
+    CORRELATION_ID.try_with(|id| *id).ok()
+}
+
+/// Runs `fut` with `correlation_id` as the ambient correlation id, so events it publishes
+/// carry it forward.
+pub async fn with_correlation_id<F: Future>(correlation_id: Uuid, fut: F) -> F::Output {
+    CORRELATION_ID.scope(correlation_id, fut).await
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -46,4 +102,42 @@ mod tests {
         // Calling it again should not panic (because try_init is used)
         init_observability("test_service");
     }
+
+    #[test]
+    fn test_trace_context_without_otel_layer_is_empty() {
+        let ctx = TraceContext::current();
+        assert_eq!(ctx, TraceContext::default());
+    }
+
+    #[test]
+    fn test_trace_context_continues_remote_trace() {
+        let provider = opentelemetry_sdk::trace::TracerProvider::builder().build();
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+
+        let remote = TraceContext {
+            traceparent: Some("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01".to_string()),
+            ..Default::default()
+        };
+
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("child");
+            remote.set_parent(&span);
+            let _guard = span.enter();
+
+            let current = TraceContext::current();
+            let traceparent = current.traceparent.expect("traceparent injected");
+            assert!(traceparent.starts_with("00-4bf92f3577b34da6a3ce929d0e0e4736-"));
+            assert_ne!(traceparent, remote.traceparent.unwrap());
+        });
+    }
+
+    #[tokio::test]
+    async fn test_correlation_id_scope() {
+        assert_eq!(current_correlation_id(), None);
+        let id = Uuid::new_v4();
+        let seen = with_correlation_id(id, async { TraceContext::current().correlation_id }).await;
+        assert_eq!(seen, Some(id));
+        assert_eq!(current_correlation_id(), None);
+    }
 }
@@ -1,20 +1,739 @@
+//! Keyset (cursor) pagination shared by the list endpoints.
+//!
+//! Query grammar, validated against a per-endpoint [`ListSpec`]:
+//!
+//! - `limit=50` — page size, clamped to the spec's maximum.
+//! - `sort=name` / `sort=-created_at` — one whitelisted field, `-` for descending.
+//!   The row id is always the tie-breaker, so the order is stable.
+//! - `filter[status]=pending` or `filter[price][gte]=10` — whitelisted fields with
+//!   `eq`, `ne`, `lt`, `lte`, `gt`, `gte` or `in` (comma separated).
+//!   A field may match through a related table (`ListSpec::filterable_through`).
+//! - `cursor=…` — the opaque `next_cursor` of the previous page. It is signed and
+//!   only valid with the sort and filters it was issued for.
+
+use std::collections::HashMap;
+use std::env;
+use std::sync::Arc;
+
+use actix_web::{http::header, HttpRequest, HttpResponse};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, SecondsFormat, Utc};
+use hmac::{Hmac, Mac};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::{Postgres, QueryBuilder};
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::errors::AppError;
+
+type HmacSha256 = Hmac<Sha256>;
+
+const DEFAULT_LIMIT: u32 = 50;
+const MAX_LIMIT: u32 = 200;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum PaginationError {
+    #[error("cannot sort by '{0}'")]
+    UnknownSort(String),
+    #[error("cannot filter by '{0}'")]
+    UnknownFilter(String),
+    #[error("unknown filter operator '{0}'")]
+    UnknownOperator(String),
+    #[error("invalid value '{value}' for '{field}'")]
+    InvalidValue { field: String, value: String },
+    #[error("invalid limit '{0}'")]
+    InvalidLimit(String),
+    #[error("invalid cursor")]
+    InvalidCursor,
+    #[error("cursor was issued for a different sort or filter")]
+    CursorMismatch,
+}
+
+impl From<PaginationError> for AppError {
+    fn from(e: PaginationError) -> Self {
+        AppError::BadRequest(e.to_string())
+    }
+}
+
+/// How a field's values are validated, bound and compared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldKind {
+    Text,
+    Uuid,
+    Timestamp,
+    Integer,
+    Float,
+    /// Postgres enum; compared and ordered by its text label.
+    Enum,
+}
+
+impl FieldKind {
+    fn cast(self) -> &'static str {
+        match self {
+            FieldKind::Text | FieldKind::Enum => "text",
+            FieldKind::Uuid => "uuid",
+            FieldKind::Timestamp => "timestamptz",
+            FieldKind::Integer => "bigint",
+            FieldKind::Float => "double precision",
+        }
+    }
+
+    fn validate(self, value: &str) -> bool {
+        match self {
+            FieldKind::Text | FieldKind::Enum => true,
+            FieldKind::Uuid => Uuid::parse_str(value).is_ok(),
+            FieldKind::Timestamp => DateTime::parse_from_rfc3339(value).is_ok(),
+            FieldKind::Integer => value.parse::<i64>().is_ok(),
+            FieldKind::Float => value.parse::<f64>().is_ok_and(f64::is_finite),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortDirection {
+    Asc,
+    Desc,
+}
+
+#[derive(Debug, Clone)]
+struct Field {
+    name: &'static str,
+    kind: FieldKind,
+    sortable: bool,
+    /// Correlated `SELECT 1 FROM … WHERE …` the filter is applied inside of.
+    through: Option<&'static str>,
+}
+
+impl Field {
+    /// SQL expression for the column; names come from the whitelist only.
+    fn expr(&self) -> String {
+        match self.kind {
+            FieldKind::Enum => format!("{}::text", self.name),
+            _ => self.name.to_string(),
+        }
+    }
+}
+
+/// Sortable and filterable fields of one list endpoint.
+///
+/// Sortable fields must be `NOT NULL` columns, otherwise the keyset comparison
+/// skips rows.
+#[derive(Debug, Clone)]
+pub struct ListSpec {
+    fields: Vec<Field>,
+    default_sort: &'static str,
+    default_direction: SortDirection,
+    default_limit: u32,
+    max_limit: u32,
+}
+
+impl ListSpec {
+    pub fn new(default_sort: &'static str, default_direction: SortDirection) -> Self {
+        Self {
+            fields: Vec::new(),
+            default_sort,
+            default_direction,
+            default_limit: DEFAULT_LIMIT,
+            max_limit: MAX_LIMIT,
+        }
+    }
+
+    /// Whitelists a field for sorting and filtering.
+    pub fn sortable(mut self, name: &'static str, kind: FieldKind) -> Self {
+        self.fields.push(Field { name, kind, sortable: true, through: None });
+        self
+    }
+
+    /// Whitelists a field for filtering only.
+    pub fn filterable(mut self, name: &'static str, kind: FieldKind) -> Self {
+        self.fields.push(Field { name, kind, sortable: false, through: None });
+        self
+    }
+
+    /// Whitelists a filter on a column of a related table. `subquery` is a
+    /// correlated `SELECT 1 FROM … WHERE …` naming that column `name`; a row
+    /// matches when `EXISTS (subquery AND name <op> value)`.
+    pub fn filterable_through(mut self, name: &'static str, kind: FieldKind, subquery: &'static str) -> Self {
+        self.fields.push(Field { name, kind, sortable: false, through: Some(subquery) });
+        self
+    }
+
+    pub fn with_limits(mut self, default_limit: u32, max_limit: u32) -> Self {
+        self.default_limit = default_limit;
+        self.max_limit = max_limit;
+        self
+    }
+
+    fn field(&self, name: &str) -> Option<&Field> {
+        self.fields.iter().find(|f| f.name == name)
+    }
+
+    /// Parses `limit`, `sort`, `filter[...]` and `cursor` from the query string.
+    pub fn parse(
+        &self,
+        query: &HashMap<String, String>,
+        signer: &CursorSigner,
+    ) -> Result<PageRequest, PaginationError> {
+        let limit = match query.get("limit") {
+            Some(v) => v
+                .parse::<u32>()
+                .map_err(|_| PaginationError::InvalidLimit(v.clone()))?
+                .clamp(1, self.max_limit),
+            None => self.default_limit,
+        };
+
+        let (sort_name, direction) = match query.get("sort").map(String::as_str) {
+            Some(s) if s.starts_with('-') => (&s[1..], SortDirection::Desc),
+            Some(s) => (s, SortDirection::Asc),
+            None => (self.default_sort, self.default_direction),
+        };
+        let sort = self
+            .field(sort_name)
+            .filter(|f| f.sortable)
+            .cloned()
+            .ok_or_else(|| PaginationError::UnknownSort(sort_name.to_string()))?;
+
+        let mut filters = Vec::new();
+        for (key, value) in query {
+            let Some(rest) = key.strip_prefix("filter[") else {
+                continue;
+            };
+            let (name, op) = parse_filter_key(rest).ok_or_else(|| PaginationError::UnknownFilter(key.clone()))?;
+            let field = self
+                .field(name)
+                .cloned()
+                .ok_or_else(|| PaginationError::UnknownFilter(name.to_string()))?;
+            let op = FilterOp::parse(op)?;
+            let values: Vec<String> = match op {
+                FilterOp::In => value.split(',').map(str::to_string).collect(),
+                _ => vec![value.clone()],
+            };
+            if let Some(bad) = values.iter().find(|v| !field.kind.validate(v)) {
+                return Err(PaginationError::InvalidValue {
+                    field: field.name.to_string(),
+                    value: bad.clone(),
+                });
+            }
+            filters.push(Filter { field, op, values });
+        }
+        filters.sort_by(|a, b| (a.field.name, a.op.as_str()).cmp(&(b.field.name, b.op.as_str())));
+
+        let mut page = PageRequest {
+            limit,
+            sort,
+            direction,
+            filters,
+            after: None,
+            signer: signer.clone(),
+        };
+
+        if let Some(raw) = query.get("cursor") {
+            let cursor = signer.decode(raw)?;
+            if cursor.fingerprint != page.fingerprint() {
+                return Err(PaginationError::CursorMismatch);
+            }
+            if !page.sort.kind.validate(&cursor.value) {
+                return Err(PaginationError::InvalidCursor);
+            }
+            page.after = Some((cursor.value, cursor.id));
+        }
+
+        Ok(page)
+    }
+}
+
+fn parse_filter_key(rest: &str) -> Option<(&str, &str)> {
+    let (name, tail) = rest.split_once(']')?;
+    if tail.is_empty() {
+        return Some((name, "eq"));
+    }
+    let op = tail.strip_prefix('[')?.strip_suffix(']')?;
+    Some((name, op))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FilterOp {
+    Eq,
+    Ne,
+    Lt,
+    Lte,
+    Gt,
+    Gte,
+    In,
+}
+
+impl FilterOp {
+    fn parse(op: &str) -> Result<Self, PaginationError> {
+        Ok(match op {
+            "eq" => FilterOp::Eq,
+            "ne" => FilterOp::Ne,
+            "lt" => FilterOp::Lt,
+            "lte" => FilterOp::Lte,
+            "gt" => FilterOp::Gt,
+            "gte" => FilterOp::Gte,
+            "in" => FilterOp::In,
+            other => return Err(PaginationError::UnknownOperator(other.to_string())),
+        })
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            FilterOp::Eq => "eq",
+            FilterOp::Ne => "ne",
+            FilterOp::Lt => "lt",
+            FilterOp::Lte => "lte",
+            FilterOp::Gt => "gt",
+            FilterOp::Gte => "gte",
+            FilterOp::In => "in",
+        }
+    }
+
+    fn sql(self) -> &'static str {
+        match self {
+            FilterOp::Eq => " = ",
+            FilterOp::Ne => " <> ",
+            FilterOp::Lt => " < ",
+            FilterOp::Lte => " <= ",
+            FilterOp::Gt => " > ",
+            FilterOp::Gte => " >= ",
+            FilterOp::In => " = ANY(",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Filter {
+    field: Field,
+    op: FilterOp,
+    values: Vec<String>,
+}
+
+/// A validated page request, ready to be pushed onto a `QueryBuilder`.
+#[derive(Debug, Clone)]
+pub struct PageRequest {
+    limit: u32,
+    sort: Field,
+    direction: SortDirection,
+    filters: Vec<Filter>,
+    after: Option<(String, Uuid)>,
+    signer: CursorSigner,
+}
+
+impl PageRequest {
+    pub fn limit(&self) -> u32 {
+        self.limit
+    }
+
+    pub fn sort(&self) -> (&str, SortDirection) {
+        (self.sort.name, self.direction)
+    }
+
+    /// Appends ` AND ...` for every filter and for the cursor position; the
+    /// builder must already contain a `WHERE` clause.
+    pub fn push_filters(&self, builder: &mut QueryBuilder<'_, Postgres>) {
+        self.push_filter_conditions(builder);
+
+        if let Some((value, id)) = &self.after {
+            let cmp = match self.direction {
+                SortDirection::Asc => ") > (",
+                SortDirection::Desc => ") < (",
+            };
+            builder.push(" AND (");
+            builder.push(self.sort.expr());
+            builder.push(", id");
+            builder.push(cmp);
+            builder.push_bind(value.clone());
+            builder.push(format!("::{}, ", self.sort.kind.cast()));
+            builder.push_bind(*id);
+            builder.push(")");
+        }
+    }
 
-#[derive(Debug, Deserialize)]
-pub struct PaginationQuery {
-    #[serde(default = "default_page")]
-    pub page: u32,
-    #[serde(default = "default_per_page")]
-    pub per_page: u32,
+    /// Appends ` AND ...` for every filter but not the cursor position, for
+    /// aggregates over all pages.
+    pub fn push_filter_conditions(&self, builder: &mut QueryBuilder<'_, Postgres>) {
+        for filter in &self.filters {
+            let cast = filter.field.kind.cast();
+            builder.push(" AND ");
+            if let Some(subquery) = filter.field.through {
+                builder.push(format!("EXISTS ({subquery} AND "));
+            }
+            builder.push(filter.field.expr());
+            builder.push(filter.op.sql());
+            if filter.op == FilterOp::In {
+                builder.push_bind(filter.values.clone());
+                builder.push(format!("::{cast}[])"));
+            } else {
+                builder.push_bind(filter.values[0].clone());
+                builder.push(format!("::{cast}"));
+            }
+            if filter.field.through.is_some() {
+                builder.push(")");
+            }
+        }
+    }
+
+    /// Appends `ORDER BY` and a `LIMIT` one past the page size, so
+    /// [`PageRequest::into_page`] can tell whether another page exists.
+    pub fn push_order_and_limit(&self, builder: &mut QueryBuilder<'_, Postgres>) {
+        let dir = match self.direction {
+            SortDirection::Asc => "ASC",
+            SortDirection::Desc => "DESC",
+        };
+        builder.push(format!(" ORDER BY {} {dir}, id {dir} LIMIT ", self.sort.expr()));
+        builder.push_bind(i64::from(self.limit) + 1);
+    }
+
+    pub fn into_page<T: Keyset>(&self, mut rows: Vec<T>) -> CursorPage<T> {
+        let has_more = rows.len() > self.limit as usize;
+        rows.truncate(self.limit as usize);
+
+        let next_cursor = if has_more {
+            rows.last().and_then(|last| {
+                let value = last.keyset_value(self.sort.name)?;
+                Some(self.signer.encode(&Cursor {
+                    value,
+                    id: last.keyset_id(),
+                    fingerprint: self.fingerprint(),
+                }))
+            })
+        } else {
+            None
+        };
+
+        CursorPage { data: rows, next_cursor }
+    }
+
+    /// Hash of the sort and filters a cursor belongs to.
+    fn fingerprint(&self) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.sort.name);
+        hasher.update(format!("{:?}", self.direction));
+        for filter in &self.filters {
+            hasher.update([0]);
+            hasher.update(filter.field.name);
+            hasher.update(filter.op.as_str());
+            hasher.update(filter.values.join(","));
+        }
+        hex::encode(&hasher.finalize()[..8])
+    }
 }
 
-fn default_page() -> u32 { 1 }
-fn default_per_page() -> u32 { 20 }
+/// Row type of a paginated list: exposes the id tie-breaker and the text form
+/// of each sortable field (RFC 3339 for timestamps).
+pub trait Keyset {
+    fn keyset_id(&self) -> Uuid;
+    fn keyset_value(&self, field: &str) -> Option<String>;
+}
+
+/// Text form of a timestamp that round-trips through `::timestamptz` exactly.
+pub fn keyset_timestamp(at: &DateTime<Utc>) -> String {
+    at.to_rfc3339_opts(SecondsFormat::Micros, true)
+}
 
 #[derive(Debug, Serialize)]
-pub struct PaginatedResponse<T> {
+pub struct CursorPage<T> {
     pub data: Vec<T>,
-    pub total: u64,
-    pub page: u32,
-    pub per_page: u32,
+    pub next_cursor: Option<String>,
+}
+
+impl<T: Serialize> CursorPage<T> {
+    /// `200 OK` with the page as JSON and `Link` headers for the first and
+    /// next pages.
+    pub fn respond(&self, req: &HttpRequest) -> HttpResponse {
+        let path = req.path();
+        let params: Vec<&str> = req
+            .query_string()
+            .split('&')
+            .filter(|p| !p.is_empty() && !p.starts_with("cursor="))
+            .collect();
+        let url = |cursor: Option<&str>| {
+            let mut query = params.clone();
+            let cursor = cursor.map(|c| format!("cursor={c}"));
+            query.extend(cursor.as_deref());
+            if query.is_empty() {
+                path.to_string()
+            } else {
+                format!("{path}?{}", query.join("&"))
+            }
+        };
+
+        let mut links = vec![format!("<{}>; rel=\"first\"", url(None))];
+        if let Some(next) = &self.next_cursor {
+            links.push(format!("<{}>; rel=\"next\"", url(Some(next))));
+        }
+
+        HttpResponse::Ok()
+            .insert_header((header::LINK, links.join(", ")))
+            .json(self)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Cursor {
+    #[serde(rename = "v")]
+    value: String,
+    id: Uuid,
+    #[serde(rename = "f")]
+    fingerprint: String,
+}
+
+/// Signs and verifies cursors with HMAC-SHA256 so clients cannot forge a
+/// position.
+#[derive(Clone)]
+pub struct CursorSigner {
+    key: Arc<[u8]>,
+}
+
+impl std::fmt::Debug for CursorSigner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CursorSigner").finish_non_exhaustive()
+    }
+}
+
+impl CursorSigner {
+    pub fn new(key: impl AsRef<[u8]>) -> Self {
+        Self { key: Arc::from(key.as_ref()) }
+    }
+
+    /// Reads `PAGINATION_CURSOR_SECRET`; every replica of a service must share it.
+    pub fn from_env() -> Result<Self, env::VarError> {
+        let secret = env::var("PAGINATION_CURSOR_SECRET")?;
+        if secret.is_empty() {
+            return Err(env::VarError::NotPresent);
+        }
+        Ok(Self::new(secret))
+    }
+
+    fn mac(&self) -> HmacSha256 {
+        HmacSha256::new_from_slice(&self.key).expect("HMAC accepts keys of any length")
+    }
+
+    fn encode(&self, cursor: &Cursor) -> String {
+        let payload = URL_SAFE_NO_PAD.encode(serde_json::to_vec(cursor).unwrap_or_default());
+        let mut mac = self.mac();
+        mac.update(payload.as_bytes());
+        let signature = URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes());
+        format!("{payload}.{signature}")
+    }
+
+    fn decode(&self, raw: &str) -> Result<Cursor, PaginationError> {
+        let (payload, signature) = raw.split_once('.').ok_or(PaginationError::InvalidCursor)?;
+        let signature = URL_SAFE_NO_PAD
+            .decode(signature)
+            .map_err(|_| PaginationError::InvalidCursor)?;
+        let mut mac = self.mac();
+        mac.update(payload.as_bytes());
+        mac.verify_slice(&signature)
+            .map_err(|_| PaginationError::InvalidCursor)?;
+
+        let json = URL_SAFE_NO_PAD
+            .decode(payload)
+            .map_err(|_| PaginationError::InvalidCursor)?;
+        serde_json::from_slice(&json).map_err(|_| PaginationError::InvalidCursor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    #[derive(Serialize)]
+    struct Row {
+        id: Uuid,
+        name: String,
+    }
+
+    impl Keyset for Row {
+        fn keyset_id(&self) -> Uuid {
+            self.id
+        }
+
+        fn keyset_value(&self, field: &str) -> Option<String> {
+            match field {
+                "name" => Some(self.name.clone()),
+                _ => None,
+            }
+        }
+    }
+
+    fn spec() -> ListSpec {
+        ListSpec::new("created_at", SortDirection::Desc)
+            .sortable("created_at", FieldKind::Timestamp)
+            .sortable("name", FieldKind::Text)
+            .filterable("status", FieldKind::Enum)
+            .filterable("price", FieldKind::Float)
+            .filterable("supplier_id", FieldKind::Uuid)
+    }
+
+    fn query(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    fn rows(n: usize) -> Vec<Row> {
+        (0..n)
+            .map(|i| Row { id: Uuid::new_v4(), name: format!("item-{i:02}") })
+            .collect()
+    }
+
+    fn sql(page: &PageRequest) -> String {
+        let mut builder = QueryBuilder::<Postgres>::new("SELECT * FROM t WHERE deleted_at IS NULL");
+        page.push_filters(&mut builder);
+        page.push_order_and_limit(&mut builder);
+        builder.sql().to_string()
+    }
+
+    #[test]
+    fn test_defaults_and_limit_clamp() {
+        let signer = CursorSigner::new("secret");
+        let page = spec().parse(&HashMap::new(), &signer).unwrap();
+        assert_eq!(page.limit(), DEFAULT_LIMIT);
+        assert_eq!(page.sort(), ("created_at", SortDirection::Desc));
+
+        let page = spec().parse(&query(&[("limit", "5000")]), &signer).unwrap();
+        assert_eq!(page.limit(), MAX_LIMIT);
+
+        assert_eq!(
+            spec().parse(&query(&[("limit", "ten")]), &signer).unwrap_err(),
+            PaginationError::InvalidLimit("ten".into())
+        );
+    }
+
+    #[test]
+    fn test_sort_and_filter_whitelists() {
+        let signer = CursorSigner::new("secret");
+        assert_eq!(
+            spec().parse(&query(&[("sort", "status")]), &signer).unwrap_err(),
+            PaginationError::UnknownSort("status".into())
+        );
+        assert_eq!(
+            spec().parse(&query(&[("filter[password]", "x")]), &signer).unwrap_err(),
+            PaginationError::UnknownFilter("password".into())
+        );
+        assert_eq!(
+            spec().parse(&query(&[("filter[price][like]", "1")]), &signer).unwrap_err(),
+            PaginationError::UnknownOperator("like".into())
+        );
+        assert!(matches!(
+            spec().parse(&query(&[("filter[supplier_id]", "not-a-uuid")]), &signer),
+            Err(PaginationError::InvalidValue { .. })
+        ));
+    }
+
+    #[test]
+    fn test_sql_binds_every_value() {
+        let signer = CursorSigner::new("secret");
+        let page = spec()
+            .parse(
+                &query(&[
+                    ("sort", "name"),
+                    ("filter[status][in]", "pending,shipped"),
+                    ("filter[price][gte]", "10.5"),
+                ]),
+                &signer,
+            )
+            .unwrap();
+
+        assert_eq!(
+            sql(&page),
+            "SELECT * FROM t WHERE deleted_at IS NULL AND price >= $1::double precision \
+             AND status::text = ANY($2::text[]) ORDER BY name ASC, id ASC LIMIT $3"
+        );
+        assert_eq!(page.filters.len(), 2);
+    }
+
+    #[test]
+    fn test_filter_through_related_table() {
+        let signer = CursorSigner::new("secret");
+        let spec = spec().filterable_through("sku", FieldKind::Text, "SELECT 1 FROM lines l WHERE l.t_id = t.id");
+        let product = Uuid::new_v4().to_string();
+        let page = spec
+            .parse(&query(&[("filter[sku][in]", "a,b"), ("filter[supplier_id]", &product)]), &signer)
+            .unwrap();
+
+        let mut builder = QueryBuilder::<Postgres>::new("SELECT count(*) FROM t WHERE TRUE");
+        page.push_filter_conditions(&mut builder);
+        assert_eq!(
+            builder.sql(),
+            "SELECT count(*) FROM t WHERE TRUE \
+             AND EXISTS (SELECT 1 FROM lines l WHERE l.t_id = t.id AND sku = ANY($1::text[])) \
+             AND supplier_id = $2::uuid"
+        );
+        assert_eq!(
+            spec.parse(&query(&[("sort", "sku")]), &signer).unwrap_err(),
+            PaginationError::UnknownSort("sku".into())
+        );
+    }
+
+    #[test]
+    fn test_cursor_round_trip_continues_after_last_row() {
+        let signer = CursorSigner::new("secret");
+        let first = spec().parse(&query(&[("sort", "name"), ("limit", "2")]), &signer).unwrap();
+        let page = first.into_page(rows(3));
+        assert_eq!(page.data.len(), 2);
+        let cursor = page.next_cursor.clone().expect("a third row means another page");
+
+        let next = spec()
+            .parse(&query(&[("sort", "name"), ("limit", "2"), ("cursor", &cursor)]), &signer)
+            .unwrap();
+        assert_eq!(next.after.as_ref().unwrap().0, "item-01");
+        assert!(sql(&next).contains("AND (name, id) > ($1::text, $2)"));
+
+        assert!(first.into_page(rows(2)).next_cursor.is_none());
+    }
+
+    #[test]
+    fn test_cursor_is_tamper_proof_and_bound_to_query() {
+        let signer = CursorSigner::new("secret");
+        let first = spec().parse(&query(&[("sort", "name"), ("limit", "1")]), &signer).unwrap();
+        let cursor = first.into_page(rows(2)).next_cursor.unwrap();
+
+        let forged = CursorSigner::new("other").encode(&Cursor {
+            value: "zzz".into(),
+            id: Uuid::nil(),
+            fingerprint: first.fingerprint(),
+        });
+        assert_eq!(
+            spec().parse(&query(&[("sort", "name"), ("cursor", &forged)]), &signer).unwrap_err(),
+            PaginationError::InvalidCursor
+        );
+        assert_eq!(
+            spec().parse(&query(&[("sort", "-name"), ("cursor", &cursor)]), &signer).unwrap_err(),
+            PaginationError::CursorMismatch
+        );
+        assert_eq!(
+            spec().parse(&query(&[("cursor", "garbage")]), &signer).unwrap_err(),
+            PaginationError::InvalidCursor
+        );
+    }
+
+    #[test]
+    fn test_link_headers() {
+        let signer = CursorSigner::new("secret");
+        let page = spec().parse(&query(&[("sort", "name"), ("limit", "1")]), &signer).unwrap();
+        let page = page.into_page(rows(2));
+        let cursor = page.next_cursor.clone().unwrap();
+
+        let req = TestRequest::get().uri("/products/search?sort=name&limit=1&cursor=old").to_http_request();
+        let resp = page.respond(&req);
+        let link = resp.headers().get(header::LINK).unwrap().to_str().unwrap();
+
+        assert_eq!(
+            link,
+            format!(
+                "</products/search?sort=name&limit=1>; rel=\"first\", \
+                 </products/search?sort=name&limit=1&cursor={cursor}>; rel=\"next\""
+            )
+        );
+    }
+
+    #[test]
+    fn test_keyset_timestamp_keeps_microseconds() {
+        let at = DateTime::parse_from_rfc3339("2026-01-02T03:04:05.123456Z").unwrap().with_timezone(&Utc);
+        assert_eq!(keyset_timestamp(&at), "2026-01-02T03:04:05.123456Z");
+    }
 }
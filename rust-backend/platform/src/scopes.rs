@@ -0,0 +1,183 @@
+//! Canonical authorization scopes.
+//!
+//! Every authenticated route is guarded by the read or write scope of the resource it
+//! belongs to; health, metrics and provider webhooks stay public:
+//!
+//! | Service               | Resource        |
+//! |-----------------------|-----------------|
+//! | analytics             | `analytics`     |
+//! | inventory-management  | `inventory`     |
+//! | logistics             | `shipments`     |
+//! | notifications         | `notifications` |
+//! | order-service         | `orders`        |
+//! | payments              | `payments`      |
+//! | product-catalog       | `products`      |
+//! | supplier-management   | `suppliers`     |
+//! | user-management       | `users`         |
+//! | tenant-management     | `tenants`       |
+//!
+//! API keys carry scopes directly; JWTs get them from the user's role via
+//! [`scopes_for_role`]. A granted `*` matches everything and `orders:*` matches every
+//! `orders:` scope.
+
+pub const WILDCARD: &str = "*";
+
+pub const ANALYTICS_READ: &str = "analytics:read";
+pub const ANALYTICS_WRITE: &str = "analytics:write";
+pub const INVENTORY_READ: &str = "inventory:read";
+pub const INVENTORY_WRITE: &str = "inventory:write";
+pub const SHIPMENTS_READ: &str = "shipments:read";
+pub const SHIPMENTS_WRITE: &str = "shipments:write";
+pub const NOTIFICATIONS_READ: &str = "notifications:read";
+pub const NOTIFICATIONS_WRITE: &str = "notifications:write";
+pub const ORDERS_READ: &str = "orders:read";
+pub const ORDERS_WRITE: &str = "orders:write";
+pub const PAYMENTS_READ: &str = "payments:read";
+pub const PAYMENTS_WRITE: &str = "payments:write";
+pub const PRODUCTS_READ: &str = "products:read";
+pub const PRODUCTS_WRITE: &str = "products:write";
+pub const SUPPLIERS_READ: &str = "suppliers:read";
+pub const SUPPLIERS_WRITE: &str = "suppliers:write";
+pub const USERS_READ: &str = "users:read";
+pub const USERS_WRITE: &str = "users:write";
+pub const TENANTS_READ: &str = "tenants:read";
+pub const TENANTS_WRITE: &str = "tenants:write";
+
+/// The read/write scope pair guarding one resource.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResourceScopes {
+    pub read: &'static str,
+    pub write: &'static str,
+}
+
+pub const ANALYTICS: ResourceScopes = ResourceScopes { read: ANALYTICS_READ, write: ANALYTICS_WRITE };
+pub const INVENTORY: ResourceScopes = ResourceScopes { read: INVENTORY_READ, write: INVENTORY_WRITE };
+pub const SHIPMENTS: ResourceScopes = ResourceScopes { read: SHIPMENTS_READ, write: SHIPMENTS_WRITE };
+pub const NOTIFICATIONS: ResourceScopes = ResourceScopes { read: NOTIFICATIONS_READ, write: NOTIFICATIONS_WRITE };
+pub const ORDERS: ResourceScopes = ResourceScopes { read: ORDERS_READ, write: ORDERS_WRITE };
+pub const PAYMENTS: ResourceScopes = ResourceScopes { read: PAYMENTS_READ, write: PAYMENTS_WRITE };
+pub const PRODUCTS: ResourceScopes = ResourceScopes { read: PRODUCTS_READ, write: PRODUCTS_WRITE };
+pub const SUPPLIERS: ResourceScopes = ResourceScopes { read: SUPPLIERS_READ, write: SUPPLIERS_WRITE };
+pub const USERS: ResourceScopes = ResourceScopes { read: USERS_READ, write: USERS_WRITE };
+pub const TENANTS: ResourceScopes = ResourceScopes { read: TENANTS_READ, write: TENANTS_WRITE };
+
+pub const ALL: &[&str] = &[
+    ANALYTICS_READ,
+    ANALYTICS_WRITE,
+    INVENTORY_READ,
+    INVENTORY_WRITE,
+    SHIPMENTS_READ,
+    SHIPMENTS_WRITE,
+    NOTIFICATIONS_READ,
+    NOTIFICATIONS_WRITE,
+    ORDERS_READ,
+    ORDERS_WRITE,
+    PAYMENTS_READ,
+    PAYMENTS_WRITE,
+    PRODUCTS_READ,
+    PRODUCTS_WRITE,
+    SUPPLIERS_READ,
+    SUPPLIERS_WRITE,
+    USERS_READ,
+    USERS_WRITE,
+    TENANTS_READ,
+    TENANTS_WRITE,
+];
+
+/// Whether a single granted scope satisfies `required`.
+pub fn grants(granted: &str, required: &str) -> bool {
+    if granted == WILDCARD || granted == required {
+        return true;
+    }
+    match granted.strip_suffix('*') {
+        Some(prefix) if prefix.ends_with(':') => required.starts_with(prefix),
+        _ => false,
+    }
+}
+
+/// Scopes a JWT user gets for their role. Unknown or missing roles get the `user` set.
+///
+/// Roles are tenant-local, so even `admin` never gets `tenants:*`: tenant records are
+/// managed with operator credentials only.
+pub fn scopes_for_role(role: Option<&str>) -> Vec<String> {
+    let scopes: &[&str] = match role.map(|r| r.to_ascii_lowercase()).as_deref() {
+        Some("admin") => {
+            return ALL
+                .iter()
+                .filter(|s| !s.starts_with("tenants:"))
+                .map(|s| s.to_string())
+                .collect()
+        }
+        Some("supplier") => &[
+            PRODUCTS_READ,
+            PRODUCTS_WRITE,
+            INVENTORY_READ,
+            INVENTORY_WRITE,
+            ORDERS_READ,
+            ORDERS_WRITE,
+            SHIPMENTS_READ,
+            SHIPMENTS_WRITE,
+            SUPPLIERS_READ,
+            SUPPLIERS_WRITE,
+            PAYMENTS_READ,
+            ANALYTICS_READ,
+            NOTIFICATIONS_READ,
+            USERS_READ,
+            USERS_WRITE,
+        ],
+        _ => &[
+            PRODUCTS_READ,
+            ORDERS_READ,
+            ORDERS_WRITE,
+            PAYMENTS_READ,
+            PAYMENTS_WRITE,
+            SHIPMENTS_READ,
+            SUPPLIERS_READ,
+            SUPPLIERS_WRITE,
+            NOTIFICATIONS_READ,
+            USERS_READ,
+            USERS_WRITE,
+        ],
+    };
+    scopes.iter().map(|s| s.to_string()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grants_exact_wildcard_and_resource_wildcard() {
+        assert!(grants(ORDERS_READ, ORDERS_READ));
+        assert!(!grants(ORDERS_READ, ORDERS_WRITE));
+        assert!(grants(WILDCARD, TENANTS_WRITE));
+        assert!(grants("orders:*", ORDERS_WRITE));
+        assert!(!grants("orders:*", PAYMENTS_READ));
+        assert!(!grants("ord*", ORDERS_READ));
+    }
+
+    #[test]
+    fn test_role_mapping() {
+        let admin = scopes_for_role(Some("Admin"));
+        assert!(admin.iter().any(|s| s == USERS_WRITE));
+        assert!(!admin.iter().any(|s| s == WILDCARD || s.starts_with("tenants:")));
+
+        let supplier = scopes_for_role(Some("supplier"));
+        assert!(supplier.iter().any(|s| s == PRODUCTS_WRITE));
+
+        let user = scopes_for_role(Some("User"));
+        assert!(user.iter().any(|s| s == ORDERS_WRITE));
+        assert!(!user.iter().any(|s| s == PRODUCTS_WRITE || s == WILDCARD));
+        assert_eq!(scopes_for_role(None), user);
+    }
+
+    #[test]
+    fn test_catalogue_scopes_are_read_write_pairs() {
+        assert_eq!(ALL.len() % 2, 0);
+        for pair in ALL.chunks(2) {
+            let (resource, action) = pair[0].split_once(':').unwrap();
+            assert_eq!(action, "read");
+            assert_eq!(pair[1], format!("{}:write", resource));
+        }
+    }
+}
@@ -4,12 +4,20 @@ use serde::de::DeserializeOwned;
 use serde::Serialize;
 use std::collections::HashMap;
 use std::time::Duration;
+use tracing::Instrument;
 use uuid::Uuid;
 
+use crate::events::{self, DomainEvent, EventType, Stream, CURRENT_SCHEMA_VERSION};
+use crate::health::Heartbeat;
+use crate::supervisor;
+use crate::metrics;
+use crate::observability::{self, TraceContext};
+
 #[derive(Clone)]
 pub struct StreamPublisher {
     pool: Option<Pool>,
     enabled: bool,
+    legacy_global_writes: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -18,6 +26,10 @@ pub struct StreamEnvelope<T> {
     pub id: String,
     pub event_type: String,
     pub tenant_id: Option<Uuid>,
+    /// Schema version the producer wrote; the payload has already been upcast.
+    pub schema_version: u32,
+    /// Trace context and correlation id the producer attached, if any.
+    pub trace: TraceContext,
     pub payload: T,
 }
 
@@ -28,6 +40,7 @@ impl StreamPublisher {
         Ok(Self {
             pool: Some(pool),
             enabled: true,
+            legacy_global_writes: legacy_global_writes_from_env(),
         })
     }
 
@@ -35,13 +48,39 @@ impl StreamPublisher {
         Self {
             pool: None,
             enabled: false,
+            legacy_global_writes: false,
         }
     }
 
+    /// Whether this publisher writes to Redis; [`StreamPublisher::noop`] reports success
+    /// without publishing anything.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Controls the dual-write to the legacy global streams. Defaults to
+    /// `STREAM_LEGACY_GLOBAL_WRITES` (on unless set to `false`/`0`); turn it off once
+    /// every consumer group runs in [`StreamMode::Tenant`].
+    pub fn with_legacy_global_writes(mut self, enabled: bool) -> Self {
+        self.legacy_global_writes = enabled;
+        self
+    }
+
     pub async fn publish<T: Serialize>(
         &self,
         event_type: &str,
         message: &T,
+    ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        self.publish_with_context(event_type, message, &TraceContext::current()).await
+    }
+
+    /// Publishes under an explicit trace context, for callers that captured it earlier
+    /// (detached tasks, the outbox relay). A missing correlation id starts a new one.
+    pub async fn publish_with_context<T: Serialize>(
+        &self,
+        event_type: &str,
+        message: &T,
+        trace: &TraceContext,
     ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
         if !self.enabled {
             return Ok("noop".to_string());
@@ -59,8 +98,9 @@ impl StreamPublisher {
             return Err("tenant_id is strictly required in event payload for isolated streams".into());
         }
 
-        let tenant_stream = format!("tenant:{}:{}", tenant_str, global_stream);
+        let tenant_stream = tenant_stream(&tenant_str, global_stream);
         let payload = serde_json::to_string(&payload_val)?;
+        let fields = entry_fields(event_type, &tenant_str, &payload, trace);
 
         let Some(pool) = &self.pool else {
             return Ok("noop".to_string());
@@ -74,31 +114,47 @@ impl StreamPublisher {
             .arg(&tenant_stream)
             .arg("MAXLEN").arg("~").arg(100_000)
             .arg("*")
-            .arg("event_type").arg(event_type)
-            .arg("tenant_id").arg(&tenant_str)
-            .arg("payload").arg(&payload);
-            
+            .arg(&fields);
+
+        // Registry that tenant-mode consumers use to discover isolated streams
+        pipe.cmd("SADD")
+            .arg(registry_key(global_stream))
+            .arg(&tenant_stream)
+            .ignore();
+
         // 2. Publish to the legacy Global stream (Backwards compatibility - "dont break anything")
-        pipe.cmd("XADD")
-            .arg(global_stream)
-            .arg("MAXLEN").arg("~").arg(100_000)
-            .arg("*")
-            .arg("event_type").arg(event_type)
-            .arg("tenant_id").arg(&tenant_str)
-            .arg("payload").arg(&payload);
+        if self.legacy_global_writes {
+            pipe.cmd("XADD")
+                .arg(global_stream)
+                .arg("MAXLEN").arg("~").arg(100_000)
+                .arg("*")
+                .arg(&fields)
+                .ignore();
+        }
 
-        let (tenant_id, _global_id): (String, String) = pipe.query_async(&mut *conn).await?;
+        let (tenant_id,): (String,) = pipe.query_async(&mut *conn).await?;
         Ok(tenant_id)
     }
 
+    /// Publishes a catalogue event to the stream its type maps to.
+    pub async fn publish_event(&self, event: &DomainEvent) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        self.publish(event.event_type().as_str(), event).await
+    }
+
+    pub fn publish_event_async(&self, event: DomainEvent) {
+        self.publish_async(event.event_type().as_str(), event);
+    }
+
     pub fn publish_async<T>(&self, event_type: &str, message: T)
     where
         T: Serialize + Send + Sync + 'static,
     {
         let this = self.clone();
         let event_type = event_type.to_string();
+        // Captured here: the spawned task runs outside the caller's span and correlation scope
+        let trace = TraceContext::current();
         tokio::spawn(async move {
-            let error_str = match this.publish(&event_type, &message).await {
+            let error_str = match this.publish_with_context(&event_type, &message, &trace).await {
                 Ok(_) => return,
                 Err(e) => e.to_string(),
             };
@@ -114,11 +170,7 @@ impl StreamPublisher {
                         .to_string();
                     let payload = serde_json::to_string(&message).unwrap_or_default();
                     
-                    let tenant_dlq = if tenant_str.is_empty() {
-                        "stream:dlq:unassigned".to_string()
-                    } else {
-                        format!("tenant:{}:dlq", tenant_str)
-                    };
+                    let tenant_dlq = dlq_stream(&tenant_str);
 
                     let mut pipe = redis::pipe();
                     // Isolated DLQ
@@ -132,33 +184,89 @@ impl StreamPublisher {
                         .arg("error").arg(&error_str);
                         
                     // Global DLQ (Legacy)
-                    pipe.cmd("XADD")
-                        .arg("stream:dlq")
-                        .arg("MAXLEN").arg("~").arg(10_000)
-                        .arg("*")
-                        .arg("event_type").arg(&event_type)
-                        .arg("tenant_id").arg(&tenant_str)
-                        .arg("payload").arg(&payload)
-                        .arg("error").arg(&error_str);
-                        
-                    let _: Result<(String, String), _> = pipe.query_async(&mut *conn).await;
+                    if this.legacy_global_writes {
+                        pipe.cmd("XADD")
+                            .arg("stream:dlq")
+                            .arg("MAXLEN").arg("~").arg(10_000)
+                            .arg("*")
+                            .arg("event_type").arg(&event_type)
+                            .arg("tenant_id").arg(&tenant_str)
+                            .arg("payload").arg(&payload)
+                            .arg("error").arg(&error_str);
+                    }
+
+                    let _: Result<Vec<String>, _> = pipe.query_async(&mut *conn).await;
                 }
             }
         });
     }
 }
 
+/// Field/value pairs of a published stream entry.
+fn entry_fields(event_type: &str, tenant_id: &str, payload: &str, trace: &TraceContext) -> Vec<(&'static str, String)> {
+    let correlation_id = trace.correlation_id.unwrap_or_else(Uuid::new_v4);
+    let mut fields = vec![
+        ("event_type", event_type.to_string()),
+        ("tenant_id", tenant_id.to_string()),
+        ("schema_version", CURRENT_SCHEMA_VERSION.to_string()),
+        ("correlation_id", correlation_id.to_string()),
+    ];
+    if let Some(traceparent) = &trace.traceparent {
+        fields.push(("traceparent", traceparent.clone()));
+    }
+    if let Some(tracestate) = &trace.tracestate {
+        fields.push(("tracestate", tracestate.clone()));
+    }
+    fields.push(("payload", payload.to_string()));
+    fields
+}
+
+/// Stream for an event type from the [`EventType`] catalogue; types outside it go to
+/// `stream:platform`.
 pub fn stream_for_event(event_type: &str) -> &'static str {
-    match event_type.split('.').next().unwrap_or("platform") {
-        "product" => "stream:products",
-        "order" => "stream:orders",
-        "inventory" => "stream:inventory",
-        "logistics" => "stream:logistics",
-        "payment" => "stream:payments",
-        "user" => "stream:users",
-        "supplier" | "tenant" => "stream:suppliers",
-        "notification" => "stream:notifications",
-        _ => "stream:platform",
+    event_type
+        .parse::<EventType>()
+        .map(EventType::stream)
+        .unwrap_or(Stream::Platform)
+        .as_str()
+}
+
+/// Tenant-isolated counterpart of a global stream, e.g. `tenant:{id}:stream:orders`.
+pub fn tenant_stream(tenant_id: &str, global_stream: &str) -> String {
+    format!("tenant:{}:{}", tenant_id, global_stream)
+}
+
+/// Strips the `tenant:{id}:` prefix so per-tenant streams share one metrics label.
+pub fn logical_stream(stream: &str) -> &str {
+    stream
+        .strip_prefix("tenant:")
+        .and_then(|rest| rest.split_once(':'))
+        .map(|(_, global)| global)
+        .unwrap_or(stream)
+}
+
+fn registry_key(global_stream: &str) -> String {
+    format!("stream_registry:{}", global_stream)
+}
+
+fn legacy_global_writes_from_env() -> bool {
+    !matches!(
+        std::env::var("STREAM_LEGACY_GLOBAL_WRITES").as_deref(),
+        Ok("false") | Ok("0")
+    )
+}
+
+/// Where a new consumer group starts on a tenant stream that already existed at startup.
+///
+/// While legacy global writes are on, that stream's history is still being delivered
+/// through the global streams until the cutover, so the group starts at `$`. Once they
+/// are off the tenant stream is the only copy, and entries written while the consumer
+/// was down must not be skipped.
+fn existing_stream_start(legacy_global_writes: bool) -> &'static str {
+    if legacy_global_writes {
+        "$"
+    } else {
+        "0"
     }
 }
 
@@ -174,21 +282,118 @@ pub fn streams_for_events(events: &[&str]) -> Vec<&'static str> {
 }
 
 pub async fn ensure_consumer_group(conn: &mut MultiplexedConnection, stream: &str, group: &str) {
+    ensure_consumer_group_at(conn, stream, group, "0").await;
+}
+
+pub async fn ensure_consumer_group_at(conn: &mut MultiplexedConnection, stream: &str, group: &str, start_id: &str) {
     let _: Result<(), RedisError> = redis::cmd("XGROUP")
         .arg("CREATE")
         .arg(stream)
         .arg(group)
-        .arg("0")
+        .arg(start_id)
         .arg("MKSTREAM")
         .query_async(conn)
         .await;
 }
 
+/// Which streams [`consume_json_with_options`] reads from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamMode {
+    /// The legacy shared `stream:*` streams.
+    Global,
+    /// Every `tenant:{id}:stream:*` stream listed in the publish-side registry.
+    Tenant,
+}
+
+impl StreamMode {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "global" => Some(Self::Global),
+            "tenant" => Some(Self::Tenant),
+            _ => None,
+        }
+    }
+}
+
+/// Retry and dead-letter policy for [`consume_json_with_options`].
+#[derive(Debug, Clone)]
+pub struct ConsumerOptions {
+    pub mode: StreamMode,
+    /// How often tenant mode re-reads the stream registry for new tenants.
+    pub discovery_interval: Duration,
+    /// Deliveries after which a still-failing entry is moved to the tenant DLQ.
+    pub max_deliveries: u64,
+    /// How long an entry must sit unacknowledged before it is reclaimed.
+    pub min_idle: Duration,
+    /// How often the pending entries list is scanned with `XAUTOCLAIM` and the group lag sampled.
+    pub reclaim_interval: Duration,
+    /// Maximum entries claimed per stream on each scan.
+    pub reclaim_count: usize,
+}
+
+impl Default for ConsumerOptions {
+    fn default() -> Self {
+        Self {
+            mode: StreamMode::Global,
+            discovery_interval: Duration::from_secs(30),
+            max_deliveries: 5,
+            min_idle: Duration::from_secs(60),
+            reclaim_interval: Duration::from_secs(15),
+            reclaim_count: 50,
+        }
+    }
+}
+
+impl ConsumerOptions {
+    /// Defaults overridden by `STREAM_CONSUMER_MODE`, `STREAM_MAX_DELIVERIES` and
+    /// `STREAM_RECLAIM_IDLE_MS`.
+    pub fn from_env() -> Self {
+        let mut options = Self::default();
+        if let Some(mode) = std::env::var("STREAM_CONSUMER_MODE").ok().and_then(|v| StreamMode::parse(&v)) {
+            options.mode = mode;
+        }
+        if let Some(max) = std::env::var("STREAM_MAX_DELIVERIES").ok().and_then(|v| v.parse().ok()) {
+            options.max_deliveries = max;
+        }
+        if let Some(ms) = std::env::var("STREAM_RECLAIM_IDLE_MS").ok().and_then(|v| v.parse().ok()) {
+            options.min_idle = Duration::from_millis(ms);
+        }
+        options
+    }
+}
+
 pub async fn consume_json<T, F, Fut>(
     redis_url: &str,
     group: &str,
     consumer: &str,
     event_types: &[&str],
+    handler: F,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>>
+where
+    T: DeserializeOwned + Send + 'static,
+    F: FnMut(StreamEnvelope<T>) -> Fut,
+    Fut: std::future::Future<Output = Result<(), Box<dyn std::error::Error + Send + Sync>>>,
+{
+    consume_json_with_options(redis_url, group, consumer, event_types, ConsumerOptions::from_env(), handler).await
+}
+
+/// Reads new entries with `XREADGROUP` and periodically reclaims idle pending entries
+/// with `XAUTOCLAIM`, so a handler error or a crashed consumer leads to a retry. Entries
+/// delivered more than `max_deliveries` times, or whose payload cannot be decoded, are
+/// copied to `tenant:{id}:dlq` together with the last handler error and acknowledged.
+///
+/// In [`StreamMode::Tenant`] the consumer reads the isolated per-tenant streams found in
+/// `stream_registry:{stream}` instead of the global ones, polling them in rotating
+/// chunks so a busy tenant cannot starve the others.
+///
+/// Under a [`crate::supervisor::Supervisor`] the loop returns `Ok(())` once shutdown
+/// begins, after finishing the entries it has already read.
+pub async fn consume_json_with_options<T, F, Fut>(
+    redis_url: &str,
+    group: &str,
+    consumer: &str,
+    event_types: &[&str],
+    options: ConsumerOptions,
     mut handler: F,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>>
 where
@@ -196,55 +401,449 @@ where
     F: FnMut(StreamEnvelope<T>) -> Fut,
     Fut: std::future::Future<Output = Result<(), Box<dyn std::error::Error + Send + Sync>>>,
 {
-    let streams = streams_for_events(event_types);
+    let logical_streams = streams_for_events(event_types);
     let client = Client::open(redis_url)?;
     let mut conn = client.get_multiplexed_async_connection().await?;
 
-    for stream in &streams {
-        ensure_consumer_group(&mut conn, stream, group).await;
+    let mut streams: Vec<String> = Vec::new();
+    match options.mode {
+        StreamMode::Global => {
+            for stream in &logical_streams {
+                ensure_consumer_group(&mut conn, stream, group).await;
+                streams.push(stream.to_string());
+            }
+        }
+        StreamMode::Tenant => {
+            let start = existing_stream_start(legacy_global_writes_from_env());
+            let discovered = discover_tenant_streams(&mut conn, &logical_streams).await?;
+            for stream in discovered {
+                ensure_consumer_group_at(&mut conn, &stream, group, start).await;
+                streams.push(stream);
+            }
+        }
     }
 
+    let heartbeat = Heartbeat::register(consumer_heartbeat(group), Duration::from_secs(30));
+    let mut reclaim_cursors: HashMap<String, String> = HashMap::new();
+    let mut last_reclaim = tokio::time::Instant::now();
+    let mut last_discovery = tokio::time::Instant::now();
+    let mut next_chunk = 0;
+
     loop {
-        let mut cmd = redis::cmd("XREADGROUP");
-        cmd.arg("GROUP")
-            .arg(group)
-            .arg(consumer)
-            .arg("BLOCK")
-            .arg(5000)
-            .arg("COUNT")
-            .arg(50)
-            .arg("STREAMS");
-        for stream in &streams {
-            cmd.arg(stream);
+        // Entries already read have been handled and acknowledged, so stopping here loses nothing
+        if supervisor::is_shutting_down() {
+            tracing::info!(%group, "stream consumer stopping for shutdown");
+            return Ok(());
         }
-        for _ in &streams {
-            cmd.arg(">");
+        heartbeat.beat();
+        if options.mode == StreamMode::Tenant && last_discovery.elapsed() >= options.discovery_interval {
+            match discover_tenant_streams(&mut conn, &logical_streams).await {
+                Ok(discovered) => {
+                    for stream in discovered {
+                        if !streams.contains(&stream) {
+                            // New tenant: its stream only ever lived in the registry, so start at the beginning
+                            ensure_consumer_group(&mut conn, &stream, group).await;
+                            streams.push(stream);
+                        }
+                    }
+                }
+                Err(e) => tracing::warn!(%group, error = %e, "tenant stream discovery failed"),
+            }
+            last_discovery = tokio::time::Instant::now();
         }
 
-        let reply: redis::Value = cmd.query_async(&mut conn).await?;
-        let events = parse_stream_reply::<T>(reply, event_types);
-
-        for event in events {
-            let stream = event.stream.clone();
-            let id = event.id.clone();
-            if let Ok(()) = handler(event).await {
-                let _: Result<(), RedisError> = redis::cmd("XACK")
-                    .arg(stream)
-                    .arg(group)
-                    .arg(id)
-                    .query_async(&mut conn)
-                    .await;
+        if last_reclaim.elapsed() >= options.reclaim_interval {
+            for stream in &streams {
+                let cursor = reclaim_cursors.entry(stream.clone()).or_insert_with(|| "0-0".to_string());
+                match reclaim_pending(&mut conn, stream, group, consumer, cursor, &options).await {
+                    Ok(entries) => {
+                        for (entry, deliveries) in entries {
+                            process_entry(&mut conn, group, event_types, entry, Some(deliveries), &options, &mut handler).await;
+                        }
+                    }
+                    Err(e) => tracing::warn!(%stream, %group, error = %e, "XAUTOCLAIM failed"),
+                }
+            }
+            match streams_lag(&mut conn, &streams, group).await {
+                Ok(lag) => metrics::set_stream_lag(group, lag),
+                Err(e) => tracing::warn!(%group, error = %e, "XINFO GROUPS failed"),
+            }
+            last_reclaim = tokio::time::Instant::now();
+        }
+
+        let received = match options.mode {
+            StreamMode::Global => {
+                let reply = read_group_or_recreate(&mut conn, group, consumer, &streams, 50, Some(5000)).await?;
+                let entries = parse_raw_entries(reply);
+                let received = entries.len();
+                for entry in entries {
+                    process_entry(&mut conn, group, event_types, entry, None, &options, &mut handler).await;
+                }
+                received
+            }
+            StreamMode::Tenant => {
+                let chunks: Vec<&[String]> = streams.chunks(TENANT_READ_CHUNK).collect();
+                let mut received = 0;
+                for i in 0..chunks.len() {
+                    let chunk = chunks[(next_chunk + i) % chunks.len()];
+                    let reply = read_group_or_recreate(&mut conn, group, consumer, chunk, TENANT_READ_COUNT, None).await?;
+                    for entry in parse_raw_entries(reply) {
+                        received += 1;
+                        process_entry(&mut conn, group, event_types, entry, None, &options, &mut handler).await;
+                    }
+                }
+                next_chunk = next_chunk.wrapping_add(1);
+                received
+            }
+        };
+
+        if received == 0 && options.mode == StreamMode::Tenant {
+            tokio::time::sleep(Duration::from_millis(500)).await;
+        } else {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    }
+}
+
+/// Heartbeat name of the consume loop for `group`.
+pub fn consumer_heartbeat(group: &str) -> String {
+    format!("stream-consumer:{}", group)
+}
+
+/// Entries not yet delivered to `group`, summed over the streams a consumer of
+/// `event_types` reads in `mode`. Uses the `lag` of `XINFO GROUPS` and falls back
+/// to the pending count on servers that do not report it.
+pub async fn consumer_lag(
+    conn: &mut MultiplexedConnection,
+    group: &str,
+    event_types: &[&str],
+    mode: StreamMode,
+) -> Result<u64, RedisError> {
+    let logical_streams = streams_for_events(event_types);
+    let streams: Vec<String> = match mode {
+        StreamMode::Global => logical_streams.iter().map(|s| s.to_string()).collect(),
+        StreamMode::Tenant => discover_tenant_streams(conn, &logical_streams).await?,
+    };
+    streams_lag(conn, &streams, group).await
+}
+
+async fn streams_lag(conn: &mut MultiplexedConnection, streams: &[String], group: &str) -> Result<u64, RedisError> {
+    let mut lag = 0;
+    for stream in streams {
+        // A stream nobody has written to yet has no lag
+        let reply: redis::Value = match redis::cmd("XINFO").arg("GROUPS").arg(stream).query_async(conn).await {
+            Ok(reply) => reply,
+            Err(e) if e.kind() == redis::ErrorKind::ResponseError => continue,
+            Err(e) => return Err(e),
+        };
+        lag += parse_group_lag(reply, group).unwrap_or(0);
+    }
+    Ok(lag)
+}
+
+/// Lag of `group` from an `XINFO GROUPS` reply.
+fn parse_group_lag(reply: redis::Value, group: &str) -> Option<u64> {
+    let redis::Value::Bulk(groups) = reply else {
+        return None;
+    };
+    groups.into_iter().find_map(|info| {
+        let redis::Value::Bulk(fields) = info else {
+            return None;
+        };
+        let fields: HashMap<String, redis::Value> = fields
+            .chunks(2)
+            .filter_map(|pair| Some((value_to_string(pair.first()?), pair.get(1)?.clone())))
+            .collect();
+        if fields.get("name").map(value_to_string).as_deref() != Some(group) {
+            return None;
+        }
+        match (fields.get("lag"), fields.get("pending")) {
+            (Some(redis::Value::Int(lag)), _) | (_, Some(redis::Value::Int(lag))) => Some(*lag as u64),
+            _ => Some(0),
+        }
+    })
+}
+
+/// Streams per non-blocking `XREADGROUP` call in tenant mode.
+const TENANT_READ_CHUNK: usize = 100;
+/// Entries read per tenant stream and pass, which bounds how long one tenant holds the loop.
+const TENANT_READ_COUNT: usize = 10;
+
+async fn read_group(
+    conn: &mut MultiplexedConnection,
+    group: &str,
+    consumer: &str,
+    streams: &[String],
+    count: usize,
+    block_ms: Option<u64>,
+) -> Result<redis::Value, RedisError> {
+    if streams.is_empty() {
+        return Ok(redis::Value::Nil);
+    }
+
+    let mut cmd = redis::cmd("XREADGROUP");
+    cmd.arg("GROUP").arg(group).arg(consumer);
+    if let Some(ms) = block_ms {
+        cmd.arg("BLOCK").arg(ms);
+    }
+    cmd.arg("COUNT").arg(count).arg("STREAMS");
+    for stream in streams {
+        cmd.arg(stream);
+    }
+    for _ in streams {
+        cmd.arg(">");
+    }
+    cmd.query_async(conn).await
+}
+
+/// [`read_group`], recreating the group when Redis answers `NOGROUP` (the stream was
+/// deleted, taking its groups with it) instead of ending the consumer loop. The read
+/// is retried on the next pass.
+async fn read_group_or_recreate(
+    conn: &mut MultiplexedConnection,
+    group: &str,
+    consumer: &str,
+    streams: &[String],
+    count: usize,
+    block_ms: Option<u64>,
+) -> Result<redis::Value, RedisError> {
+    match read_group(conn, group, consumer, streams, count, block_ms).await {
+        Err(e) if e.code() == Some("NOGROUP") => {
+            tracing::warn!(%group, error = %e, "consumer group missing, recreating it");
+            for stream in streams {
+                ensure_consumer_group(conn, stream, group).await;
             }
+            Ok(redis::Value::Nil)
         }
+        reply => reply,
+    }
+}
 
-        tokio::time::sleep(Duration::from_millis(100)).await;
+async fn discover_tenant_streams(
+    conn: &mut MultiplexedConnection,
+    logical_streams: &[&str],
+) -> Result<Vec<String>, RedisError> {
+    let mut streams = Vec::new();
+    for logical in logical_streams {
+        let mut members: Vec<String> = redis::cmd("SMEMBERS")
+            .arg(registry_key(logical))
+            .query_async(conn)
+            .await?;
+        members.sort();
+        streams.extend(members);
     }
+    Ok(streams)
+}
+
+/// A stream entry as returned by Redis, before its payload is decoded.
+#[derive(Debug, Clone)]
+struct RawStreamEntry {
+    stream: String,
+    id: String,
+    fields: HashMap<String, String>,
 }
 
-fn parse_stream_reply<T: DeserializeOwned>(
-    reply: redis::Value,
+enum DecodedEntry<T> {
+    Event(StreamEnvelope<T>),
+    /// Not one of the event types this consumer subscribed to.
+    Ignored,
+    /// Can never be handled; goes straight to the DLQ.
+    Poison(String),
+}
+
+async fn process_entry<T, F, Fut>(
+    conn: &mut MultiplexedConnection,
+    group: &str,
     event_types: &[&str],
-) -> Vec<StreamEnvelope<T>> {
+    entry: RawStreamEntry,
+    deliveries: Option<u64>,
+    options: &ConsumerOptions,
+    handler: &mut F,
+) where
+    T: DeserializeOwned,
+    F: FnMut(StreamEnvelope<T>) -> Fut,
+    Fut: std::future::Future<Output = Result<(), Box<dyn std::error::Error + Send + Sync>>>,
+{
+    let event_type = entry.fields.get("event_type").cloned().unwrap_or_default();
+
+    let event = match decode_entry::<T>(&entry, event_types) {
+        DecodedEntry::Event(event) => event,
+        DecodedEntry::Ignored => {
+            ack(conn, &entry.stream, group, &entry.id).await;
+            return;
+        }
+        DecodedEntry::Poison(reason) => {
+            dead_letter(conn, group, &entry, deliveries.unwrap_or(1), &reason).await;
+            metrics::inc_event(group, logical_stream(&entry.stream), &event_type, "dead_lettered");
+            return;
+        }
+    };
+
+    if let Some(deliveries) = deliveries {
+        if deliveries > options.max_deliveries {
+            let reason = last_error(conn, &entry.stream, group, &entry.id)
+                .await
+                .unwrap_or_else(|| "max deliveries exceeded".to_string());
+            dead_letter(conn, group, &entry, deliveries, &reason).await;
+            metrics::inc_event(group, logical_stream(&entry.stream), &event_type, "dead_lettered");
+            return;
+        }
+        metrics::inc_event(group, logical_stream(&entry.stream), &event_type, "retried");
+    }
+
+    let correlation_id = event.trace.correlation_id.unwrap_or_else(Uuid::new_v4);
+    let span = tracing::info_span!(
+        "stream.consume",
+        otel.kind = "consumer",
+        stream = %entry.stream,
+        entry_id = %entry.id,
+        event_type = %event_type,
+        group = %group,
+        %correlation_id,
+    );
+    event.trace.set_parent(&span);
+    let result = observability::with_correlation_id(correlation_id, handler(event))
+        .instrument(span)
+        .await;
+
+    match result {
+        Ok(()) => {
+            ack(conn, &entry.stream, group, &entry.id).await;
+            if deliveries.is_some() {
+                let _: Result<(), RedisError> = redis::cmd("HDEL")
+                    .arg(errors_key(&entry.stream, group))
+                    .arg(&entry.id)
+                    .query_async(conn)
+                    .await;
+            }
+        }
+        Err(e) => {
+            // Left pending so XAUTOCLAIM retries it once it has been idle for `min_idle`
+            let key = errors_key(&entry.stream, group);
+            let _: Result<(), RedisError> = redis::pipe()
+                .cmd("HSET").arg(&key).arg(&entry.id).arg(e.to_string())
+                .cmd("EXPIRE").arg(&key).arg(7 * 24 * 60 * 60)
+                .query_async(conn)
+                .await;
+        }
+    }
+}
+
+/// Claims entries idle for longer than `min_idle` and returns them with their delivery counts.
+async fn reclaim_pending(
+    conn: &mut MultiplexedConnection,
+    stream: &str,
+    group: &str,
+    consumer: &str,
+    cursor: &mut String,
+    options: &ConsumerOptions,
+) -> Result<Vec<(RawStreamEntry, u64)>, RedisError> {
+    let reply: redis::Value = redis::cmd("XAUTOCLAIM")
+        .arg(stream)
+        .arg(group)
+        .arg(consumer)
+        .arg(options.min_idle.as_millis() as u64)
+        .arg(cursor.as_str())
+        .arg("COUNT")
+        .arg(options.reclaim_count)
+        .query_async(conn)
+        .await?;
+
+    let redis::Value::Bulk(parts) = reply else {
+        return Ok(Vec::new());
+    };
+    if parts.len() < 2 {
+        return Ok(Vec::new());
+    }
+    *cursor = value_to_string(&parts[0]);
+    let entries = parse_messages(stream, &parts[1]);
+    let (Some(first), Some(last)) = (entries.first(), entries.last()) else {
+        return Ok(Vec::new());
+    };
+
+    // XAUTOCLAIM has already incremented the counters; read them back for this consumer
+    let pending: redis::Value = redis::cmd("XPENDING")
+        .arg(stream)
+        .arg(group)
+        .arg(&first.id)
+        .arg(&last.id)
+        .arg(entries.len())
+        .arg(consumer)
+        .query_async(conn)
+        .await?;
+    let deliveries = parse_delivery_counts(pending);
+
+    Ok(entries
+        .into_iter()
+        .map(|entry| {
+            let count = deliveries.get(&entry.id).copied().unwrap_or(1);
+            (entry, count)
+        })
+        .collect())
+}
+
+async fn ack(conn: &mut MultiplexedConnection, stream: &str, group: &str, id: &str) {
+    let _: Result<(), RedisError> = redis::cmd("XACK")
+        .arg(stream)
+        .arg(group)
+        .arg(id)
+        .query_async(conn)
+        .await;
+}
+
+async fn last_error(conn: &mut MultiplexedConnection, stream: &str, group: &str, id: &str) -> Option<String> {
+    redis::cmd("HGET")
+        .arg(errors_key(stream, group))
+        .arg(id)
+        .query_async::<_, Option<String>>(conn)
+        .await
+        .ok()
+        .flatten()
+}
+
+async fn dead_letter(conn: &mut MultiplexedConnection, group: &str, entry: &RawStreamEntry, deliveries: u64, error: &str) {
+    let tenant_str = entry.fields.get("tenant_id").cloned().unwrap_or_default();
+    let dlq = dlq_stream(&tenant_str);
+    tracing::warn!(stream = %entry.stream, id = %entry.id, %group, deliveries, %error, %dlq, "moving stream entry to DLQ");
+
+    let result: Result<(String, (), ()), RedisError> = redis::pipe()
+        .cmd("XADD")
+        .arg(&dlq)
+        .arg("MAXLEN").arg("~").arg(10_000)
+        .arg("*")
+        .arg("event_type").arg(entry.fields.get("event_type").map(String::as_str).unwrap_or(""))
+        .arg("tenant_id").arg(&tenant_str)
+        .arg("payload").arg(entry.fields.get("payload").map(String::as_str).unwrap_or(""))
+        .arg("correlation_id").arg(entry.fields.get("correlation_id").map(String::as_str).unwrap_or(""))
+        .arg("error").arg(error)
+        .arg("source_stream").arg(&entry.stream)
+        .arg("source_id").arg(&entry.id)
+        .arg("group").arg(group)
+        .arg("deliveries").arg(deliveries)
+        .cmd("XACK").arg(&entry.stream).arg(group).arg(&entry.id)
+        .cmd("HDEL").arg(errors_key(&entry.stream, group)).arg(&entry.id)
+        .query_async(conn)
+        .await;
+
+    if let Err(e) = result {
+        tracing::error!(stream = %entry.stream, id = %entry.id, error = %e, "failed to dead-letter stream entry");
+    }
+}
+
+fn dlq_stream(tenant_id: &str) -> String {
+    if tenant_id.is_empty() {
+        "stream:dlq:unassigned".to_string()
+    } else {
+        format!("tenant:{}:dlq", tenant_id)
+    }
+}
+
+fn errors_key(stream: &str, group: &str) -> String {
+    format!("{}:errors:{}", stream, group)
+}
+
+/// Flattens an `XREADGROUP` reply (`[[stream, [[id, fields]..]]..]`).
+fn parse_raw_entries(reply: redis::Value) -> Vec<RawStreamEntry> {
     let mut output = Vec::new();
     let redis::Value::Bulk(streams) = reply else {
         return output;
@@ -258,63 +857,120 @@ fn parse_stream_reply<T: DeserializeOwned>(
             continue;
         }
         let stream = value_to_string(&parts[0]);
-        let redis::Value::Bulk(messages) = &parts[1] else {
-            continue;
-        };
+        output.extend(parse_messages(&stream, &parts[1]));
+    }
 
-        for message in messages {
-            let redis::Value::Bulk(message_parts) = message else {
-                continue;
-            };
-            if message_parts.len() != 2 {
-                continue;
-            }
-            let id = value_to_string(&message_parts[0]);
-            let redis::Value::Bulk(fields) = &message_parts[1] else {
-                continue;
-            };
+    output
+}
 
-            let mut map = HashMap::new();
-            let mut iter = fields.iter();
-            while let (Some(key), Some(value)) = (iter.next(), iter.next()) {
-                map.insert(value_to_string(key), value_to_string(value));
-            }
+/// Parses a list of `[id, [field, value, ..]]` messages belonging to `stream`.
+fn parse_messages(stream: &str, messages: &redis::Value) -> Vec<RawStreamEntry> {
+    let mut output = Vec::new();
+    let redis::Value::Bulk(messages) = messages else {
+        return output;
+    };
 
-            let Some(event_type) = map.get("event_type").cloned() else {
-                continue;
-            };
-            if !event_types.contains(&event_type.as_str()) {
-                continue;
-            }
-            let Some(payload) = map.get("payload") else {
-                continue;
-            };
-            let Ok(payload_obj) = serde_json::from_str::<T>(payload) else {
-                continue;
-            };
+    for message in messages {
+        let redis::Value::Bulk(message_parts) = message else {
+            continue;
+        };
+        if message_parts.len() != 2 {
+            continue;
+        }
+        let id = value_to_string(&message_parts[0]);
+        let redis::Value::Bulk(fields) = &message_parts[1] else {
+            continue;
+        };
 
-            let tenant_id = map
-                .get("tenant_id")
-                .filter(|s| !s.is_empty())
-                .and_then(|s| Uuid::parse_str(s).ok())
-                .or_else(|| {
-                    let val: serde_json::Value = serde_json::from_str(payload).ok()?;
-                    val.get("tenant_id")?.as_str()?.parse::<Uuid>().ok()
-                });
-
-            output.push(StreamEnvelope {
-                stream: stream.clone(),
-                id,
-                event_type,
-                tenant_id,
-                payload: payload_obj,
-            });
+        let mut map = HashMap::new();
+        let mut iter = fields.iter();
+        while let (Some(key), Some(value)) = (iter.next(), iter.next()) {
+            map.insert(value_to_string(key), value_to_string(value));
         }
+
+        output.push(RawStreamEntry {
+            stream: stream.to_string(),
+            id,
+            fields: map,
+        });
     }
 
     output
 }
 
+fn decode_entry<T: DeserializeOwned>(entry: &RawStreamEntry, event_types: &[&str]) -> DecodedEntry<T> {
+    let Some(event_type) = entry.fields.get("event_type").cloned() else {
+        return DecodedEntry::Poison("missing event_type field".to_string());
+    };
+    if !event_types.contains(&event_type.as_str()) {
+        return DecodedEntry::Ignored;
+    }
+    let Some(payload) = entry.fields.get("payload") else {
+        return DecodedEntry::Poison("missing payload field".to_string());
+    };
+    let payload = match serde_json::from_str::<serde_json::Value>(payload) {
+        Ok(value) => value,
+        Err(e) => return DecodedEntry::Poison(format!("payload decode failed: {}", e)),
+    };
+
+    // Entries written before versioning carry no schema_version field
+    let schema_version = entry
+        .fields
+        .get("schema_version")
+        .and_then(|v| v.parse::<u32>().ok())
+        .unwrap_or(1);
+    let payload = match events::upcast(&event_type, schema_version, payload) {
+        Ok(payload) => payload,
+        Err(e) => return DecodedEntry::Poison(format!("upcast failed: {}", e)),
+    };
+
+    let tenant_id = entry
+        .fields
+        .get("tenant_id")
+        .filter(|s| !s.is_empty())
+        .and_then(|s| Uuid::parse_str(s).ok())
+        .or_else(|| payload.get("tenant_id")?.as_str()?.parse::<Uuid>().ok());
+
+    let payload_obj = match serde_json::from_value::<T>(payload) {
+        Ok(obj) => obj,
+        Err(e) => return DecodedEntry::Poison(format!("payload decode failed: {}", e)),
+    };
+
+    let field = |name: &str| entry.fields.get(name).filter(|s| !s.is_empty()).cloned();
+    let trace = TraceContext {
+        traceparent: field("traceparent"),
+        tracestate: field("tracestate"),
+        correlation_id: field("correlation_id").and_then(|s| Uuid::parse_str(&s).ok()),
+    };
+
+    DecodedEntry::Event(StreamEnvelope {
+        stream: entry.stream.clone(),
+        id: entry.id.clone(),
+        event_type,
+        tenant_id,
+        schema_version,
+        trace,
+        payload: payload_obj,
+    })
+}
+
+/// Maps entry id to delivery count from an extended `XPENDING` reply.
+fn parse_delivery_counts(reply: redis::Value) -> HashMap<String, u64> {
+    let mut counts = HashMap::new();
+    let redis::Value::Bulk(rows) = reply else {
+        return counts;
+    };
+    for row in rows {
+        let redis::Value::Bulk(cols) = row else {
+            continue;
+        };
+        if let (Some(id), Some(redis::Value::Int(deliveries))) = (cols.first(), cols.get(3)) {
+            counts.insert(value_to_string(id), *deliveries as u64);
+        }
+    }
+    counts
+}
+
 fn value_to_string(value: &redis::Value) -> String {
     match value {
         redis::Value::Data(bytes) => String::from_utf8_lossy(bytes).to_string(),
@@ -338,14 +994,15 @@ mod tests {
     #[test]
     fn test_stream_for_event() {
         assert_eq!(stream_for_event("product.created"), "stream:products");
-        assert_eq!(stream_for_event("order.updated"), "stream:orders");
+        assert_eq!(stream_for_event("order.refunded"), "stream:orders");
         assert_eq!(stream_for_event("inventory.reserved"), "stream:inventory");
-        assert_eq!(stream_for_event("logistics.shipped"), "stream:logistics");
-        assert_eq!(stream_for_event("payment.processed"), "stream:payments");
-        assert_eq!(stream_for_event("user.registered"), "stream:users");
+        assert_eq!(stream_for_event("inventory.release_command"), "stream:inventory");
+        assert_eq!(stream_for_event("logistics.shipment_created"), "stream:logistics");
+        assert_eq!(stream_for_event("payment.refund_command"), "stream:payments");
+        assert_eq!(stream_for_event("user.created"), "stream:users");
         assert_eq!(stream_for_event("supplier.created"), "stream:suppliers");
-        assert_eq!(stream_for_event("tenant.updated"), "stream:suppliers");
-        assert_eq!(stream_for_event("notification.sent"), "stream:notifications");
+        // Types outside the catalogue are not routed by prefix any more
+        assert_eq!(stream_for_event("order.updated"), "stream:platform");
         assert_eq!(stream_for_event("unknown.event"), "stream:platform");
     }
 
@@ -393,7 +1050,7 @@ mod tests {
         let tenant_uuid = Uuid::new_v4();
         let message_fields = redis::Value::Bulk(vec![
             redis::Value::Data(b"event_type".to_vec()),
-            redis::Value::Data(b"order.created".to_vec()),
+            redis::Value::Data(b"dummy.created".to_vec()),
             redis::Value::Data(b"tenant_id".to_vec()),
             redis::Value::Data(tenant_uuid.to_string().into_bytes()),
             redis::Value::Data(b"payload".to_vec()),
@@ -412,13 +1069,178 @@ mod tests {
 
         let reply = redis::Value::Bulk(vec![stream]);
 
-        let events: Vec<StreamEnvelope<DummyEvent>> = parse_stream_reply(reply, &["order.created"]);
+        let events: Vec<StreamEnvelope<DummyEvent>> = parse_raw_entries(reply)
+            .iter()
+            .filter_map(|entry| match decode_entry(entry, &["dummy.created"]) {
+                DecodedEntry::Event(event) => Some(event),
+                _ => None,
+            })
+            .collect();
         
         assert_eq!(events.len(), 1);
         assert_eq!(events[0].stream, "stream:orders");
         assert_eq!(events[0].id, "123-0");
-        assert_eq!(events[0].event_type, "order.created");
+        assert_eq!(events[0].event_type, "dummy.created");
         assert_eq!(events[0].tenant_id, Some(tenant_uuid));
+        assert_eq!(events[0].schema_version, 1);
         assert_eq!(events[0].payload.value, 42);
     }
+
+    #[test]
+    fn test_decode_entry_upcasts_legacy_payload_into_catalogue() {
+        let tenant = Uuid::new_v4();
+        let order_id = Uuid::new_v4();
+        let payload = serde_json::json!({
+            "tenant_id": tenant,
+            "event_type": "",
+            "product_id": Uuid::new_v4(),
+            "order_id": order_id,
+            "quantity": 2,
+            "order_timestamp": "2026-01-01T00:00:00Z",
+        })
+        .to_string();
+        let entry = raw_entry(&[("event_type", "inventory.reserved"), ("tenant_id", &tenant.to_string()), ("payload", &payload)]);
+
+        let DecodedEntry::Event(envelope) = decode_entry::<DomainEvent>(&entry, &["inventory.reserved"]) else {
+            panic!("expected a decoded event");
+        };
+        let DomainEvent::InventoryReserved(event) = envelope.payload else {
+            panic!("expected inventory.reserved");
+        };
+        assert_eq!(event.order_id, Some(order_id));
+        assert_eq!(event.timestamp.to_rfc3339(), "2026-01-01T00:00:00+00:00");
+
+        let current = raw_entry(&[
+            ("event_type", "inventory.reserved"),
+            ("schema_version", "2"),
+            ("payload", "{\"event_type\":\"inventory.reserved\"}"),
+        ]);
+        assert!(matches!(decode_entry::<DomainEvent>(&current, &["inventory.reserved"]), DecodedEntry::Poison(_)));
+
+        let missing_product = serde_json::json!({"tenant_id": tenant, "order_id": order_id}).to_string();
+        let legacy = raw_entry(&[("event_type", "inventory.reserved"), ("payload", &missing_product)]);
+        assert!(matches!(decode_entry::<DomainEvent>(&legacy, &["inventory.reserved"]), DecodedEntry::Poison(_)));
+    }
+
+    #[test]
+    fn test_entry_fields_carry_trace_context_to_consumer() {
+        let correlation_id = Uuid::new_v4();
+        let trace = TraceContext {
+            traceparent: Some("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01".to_string()),
+            tracestate: None,
+            correlation_id: Some(correlation_id),
+        };
+        let fields = entry_fields("order.created", "t1", "{}", &trace);
+        assert_eq!(fields.last().map(|(k, _)| *k), Some("payload"));
+        assert!(!fields.iter().any(|(k, _)| *k == "tracestate"));
+
+        let pairs: Vec<(&str, &str)> = fields.iter().map(|(k, v)| (*k, v.as_str())).collect();
+        let DecodedEntry::Event(envelope) = decode_entry::<serde_json::Value>(&raw_entry(&pairs), &["order.created"]) else {
+            panic!("expected a decoded event");
+        };
+        assert_eq!(envelope.trace, trace);
+
+        // Untraced publishes still start a correlation chain
+        let untraced = entry_fields("order.created", "t1", "{}", &TraceContext::default());
+        let (_, generated) = untraced.iter().find(|(k, _)| *k == "correlation_id").unwrap();
+        assert!(Uuid::parse_str(generated).is_ok());
+        assert!(!untraced.iter().any(|(k, _)| *k == "traceparent"));
+    }
+
+    fn raw_entry(fields: &[(&str, &str)]) -> RawStreamEntry {
+        RawStreamEntry {
+            stream: "stream:orders".to_string(),
+            id: "1-0".to_string(),
+            fields: fields.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+        }
+    }
+
+    #[test]
+    fn test_decode_entry_classifies_unsubscribed_and_poison() {
+        let other = raw_entry(&[("event_type", "order.updated"), ("payload", "{\"value\":1}")]);
+        assert!(matches!(decode_entry::<DummyEvent>(&other, &["order.created"]), DecodedEntry::Ignored));
+
+        let bad_payload = raw_entry(&[("event_type", "order.created"), ("payload", "not json")]);
+        assert!(matches!(decode_entry::<DummyEvent>(&bad_payload, &["order.created"]), DecodedEntry::Poison(_)));
+
+        let no_type = raw_entry(&[("payload", "{\"value\":1}")]);
+        assert!(matches!(decode_entry::<DummyEvent>(&no_type, &["order.created"]), DecodedEntry::Poison(_)));
+    }
+
+    #[test]
+    fn test_parse_xautoclaim_messages_and_delivery_counts() {
+        let claimed = redis::Value::Bulk(vec![redis::Value::Bulk(vec![
+            redis::Value::Data(b"5-0".to_vec()),
+            redis::Value::Bulk(vec![
+                redis::Value::Data(b"event_type".to_vec()),
+                redis::Value::Data(b"order.created".to_vec()),
+            ]),
+        ])]);
+        let entries = parse_messages("stream:orders", &claimed);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].id, "5-0");
+        assert_eq!(entries[0].stream, "stream:orders");
+        assert_eq!(entries[0].fields.get("event_type").unwrap(), "order.created");
+
+        let pending = redis::Value::Bulk(vec![redis::Value::Bulk(vec![
+            redis::Value::Data(b"5-0".to_vec()),
+            redis::Value::Data(b"consumer-1".to_vec()),
+            redis::Value::Int(0),
+            redis::Value::Int(6),
+        ])]);
+        let counts = parse_delivery_counts(pending);
+        assert_eq!(counts.get("5-0"), Some(&6));
+    }
+
+    #[test]
+    fn test_dlq_stream_naming() {
+        let tenant = Uuid::new_v4().to_string();
+        assert_eq!(dlq_stream(&tenant), format!("tenant:{}:dlq", tenant));
+        assert_eq!(dlq_stream(""), "stream:dlq:unassigned");
+    }
+
+    #[test]
+    fn test_tenant_stream_and_logical_stream_roundtrip() {
+        let tenant = Uuid::new_v4().to_string();
+        let stream = tenant_stream(&tenant, "stream:orders");
+        assert_eq!(stream, format!("tenant:{}:stream:orders", tenant));
+        assert_eq!(logical_stream(&stream), "stream:orders");
+        assert_eq!(logical_stream("stream:orders"), "stream:orders");
+        assert_eq!(registry_key("stream:orders"), "stream_registry:stream:orders");
+    }
+
+    #[test]
+    fn test_stream_mode_parse() {
+        assert_eq!(StreamMode::parse("tenant"), Some(StreamMode::Tenant));
+        assert_eq!(StreamMode::parse("GLOBAL"), Some(StreamMode::Global));
+        assert_eq!(StreamMode::parse("both"), None);
+        assert_eq!(ConsumerOptions::default().mode, StreamMode::Global);
+    }
+
+    #[test]
+    fn test_existing_tenant_streams_keep_backlog_after_cutover() {
+        assert_eq!(existing_stream_start(true), "$");
+        assert_eq!(existing_stream_start(false), "0");
+    }
+
+    #[test]
+    fn test_parse_group_lag() {
+        let data = |s: &str| redis::Value::Data(s.as_bytes().to_vec());
+        let group = |name: &str, pending: i64, lag: redis::Value| {
+            redis::Value::Bulk(vec![
+                data("name"), data(name),
+                data("consumers"), redis::Value::Int(1),
+                data("pending"), redis::Value::Int(pending),
+                data("lag"), lag,
+            ])
+        };
+        let reply = redis::Value::Bulk(vec![
+            group("payments", 1, redis::Value::Int(7)),
+            group("analytics", 3, redis::Value::Nil),
+        ]);
+
+        assert_eq!(parse_group_lag(reply.clone(), "payments"), Some(7));
+        assert_eq!(parse_group_lag(reply.clone(), "analytics"), Some(3));
+        assert_eq!(parse_group_lag(reply, "orders"), None);
+    }
 }
@@ -0,0 +1,323 @@
+//! Supervised background tasks and graceful shutdown.
+//!
+//! Workers and stream consumers are registered with [`Supervisor::spawn`]. A task that
+//! returns an error or panics is restarted with exponential backoff, and its state is
+//! reported by the readiness probe (`Readiness::with_supervisor`). [`Supervisor::serve`]
+//! runs the HTTP server until SIGTERM or Ctrl-C, then drains in-flight requests and lets
+//! tasks finish their current unit of work before the shutdown deadline.
+//!
+//! Tasks learn about shutdown through [`is_shutting_down`] and [`shutdown_requested`],
+//! which read a task-local set by the supervisor, so library loops such as
+//! `streams::consume_json` stop cleanly without extra parameters.
+
+use futures_util::FutureExt;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+pub type TaskError = Box<dyn std::error::Error + Send + Sync>;
+
+type Handles = Vec<(String, JoinHandle<()>)>;
+
+pub const DEFAULT_DEADLINE: Duration = Duration::from_secs(25);
+
+tokio::task_local! {
+    static SHUTDOWN: watch::Receiver<bool>;
+}
+
+/// Whether the supervisor running the current task has begun shutting down. Always
+/// `false` outside a supervised task.
+pub fn is_shutting_down() -> bool {
+    SHUTDOWN.try_with(|rx| *rx.borrow()).unwrap_or(false)
+}
+
+/// Resolves once shutdown begins. Never resolves outside a supervised task.
+pub async fn shutdown_requested() {
+    let Ok(mut rx) = SHUTDOWN.try_with(|rx| rx.clone()) else {
+        return std::future::pending().await;
+    };
+    let _ = rx.wait_for(|stopping| *stopping).await;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskState {
+    Running,
+    /// Failed and waiting to be restarted.
+    BackingOff,
+    /// Returned `Ok` on its own; not restarted.
+    Finished,
+    Stopped,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TaskStatus {
+    pub state: TaskState,
+    pub restarts: u32,
+    pub last_error: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct Supervisor {
+    service: String,
+    deadline: Duration,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    shutdown: Arc<watch::Sender<bool>>,
+    tasks: Arc<Mutex<BTreeMap<String, TaskStatus>>>,
+    handles: Arc<Mutex<Handles>>,
+}
+
+impl Supervisor {
+    pub fn new(service: &str) -> Self {
+        Self {
+            service: service.to_string(),
+            deadline: DEFAULT_DEADLINE,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+            shutdown: Arc::new(watch::channel(false).0),
+            tasks: Arc::new(Mutex::new(BTreeMap::new())),
+            handles: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// How long shutdown waits for in-flight requests and tasks before aborting them.
+    pub fn with_deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = deadline;
+        self
+    }
+
+    /// Delay before the first restart, doubled after each failure up to `max`. A task
+    /// that ran for at least `max` before failing starts again from `initial`.
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    pub fn deadline(&self) -> Duration {
+        self.deadline
+    }
+
+    /// Runs `task` as `name`, calling it again whenever the future it returns fails or
+    /// panics.
+    pub fn spawn<F, Fut>(&self, name: &str, task: F)
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), TaskError>> + Send + 'static,
+    {
+        let name = name.to_string();
+        self.update(&name, TaskState::Running, 0, None);
+        let supervisor = self.clone();
+        let handle = tokio::spawn(SHUTDOWN.scope(self.shutdown.subscribe(), {
+            let name = name.clone();
+            async move { supervisor.supervise(name, task).await }
+        }));
+        self.handles.lock().unwrap().push((name, handle));
+    }
+
+    async fn supervise<F, Fut>(&self, name: String, task: F)
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<(), TaskError>>,
+    {
+        let mut backoff = self.initial_backoff;
+        let mut restarts = 0;
+        loop {
+            let started = Instant::now();
+            let error = match AssertUnwindSafe(task()).catch_unwind().await {
+                Ok(Ok(())) => None,
+                Ok(Err(e)) => Some(e.to_string()),
+                Err(panic) => Some(panic_message(panic)),
+            };
+
+            if is_shutting_down() {
+                self.update(&name, TaskState::Stopped, restarts, error);
+                return;
+            }
+            let Some(error) = error else {
+                tracing::info!(service = %self.service, task = %name, "supervised task finished");
+                self.update(&name, TaskState::Finished, restarts, None);
+                return;
+            };
+
+            if started.elapsed() >= self.max_backoff {
+                backoff = self.initial_backoff;
+            }
+            restarts += 1;
+            tracing::error!(
+                service = %self.service,
+                task = %name,
+                restarts,
+                backoff_ms = backoff.as_millis() as u64,
+                error = %error,
+                "supervised task failed, restarting"
+            );
+            self.update(&name, TaskState::BackingOff, restarts, Some(error.clone()));
+
+            tokio::select! {
+                _ = tokio::time::sleep(backoff) => {}
+                _ = shutdown_requested() => {
+                    self.update(&name, TaskState::Stopped, restarts, Some(error));
+                    return;
+                }
+            }
+            backoff = (backoff * 2).min(self.max_backoff);
+            self.update(&name, TaskState::Running, restarts, Some(error));
+        }
+    }
+
+    fn update(&self, name: &str, state: TaskState, restarts: u32, last_error: Option<String>) {
+        self.tasks
+            .lock()
+            .unwrap()
+            .insert(name.to_string(), TaskStatus { state, restarts, last_error });
+    }
+
+    pub fn statuses(&self) -> BTreeMap<String, TaskStatus> {
+        self.tasks.lock().unwrap().clone()
+    }
+
+    pub fn is_stopping(&self) -> bool {
+        *self.shutdown.borrow()
+    }
+
+    /// Signals every task to stop and waits for them until the deadline; tasks still
+    /// running then are aborted.
+    pub async fn shutdown(&self) {
+        self.shutdown.send_replace(true);
+        let handles = std::mem::take(&mut *self.handles.lock().unwrap());
+        let deadline = tokio::time::Instant::now() + self.deadline;
+        for (name, mut handle) in handles {
+            if tokio::time::timeout_at(deadline, &mut handle).await.is_err() {
+                tracing::warn!(service = %self.service, task = %name, "task did not stop before the shutdown deadline, aborting");
+                handle.abort();
+                let restarts = self.statuses().get(&name).map_or(0, |status| status.restarts);
+                self.update(&name, TaskState::Stopped, restarts, Some("aborted at shutdown deadline".to_string()));
+            }
+        }
+    }
+
+    /// Runs `server` until SIGTERM or Ctrl-C, then stops accepting connections and
+    /// drains in-flight requests and supervised tasks together.
+    ///
+    /// Build the server with `.disable_signals()` and `.shutdown_timeout(..)` set to
+    /// the deadline, so actix leaves signal handling to the supervisor.
+    pub async fn serve(&self, server: actix_web::dev::Server) -> std::io::Result<()> {
+        let handle = server.handle();
+        tokio::pin!(server);
+        tokio::select! {
+            result = &mut server => {
+                self.shutdown().await;
+                return result;
+            }
+            _ = termination() => {}
+        }
+
+        tracing::info!(service = %self.service, deadline_secs = self.deadline.as_secs(), "shutting down");
+        // The server future must keep being polled for the stop command to complete
+        let (result, (), ()) = tokio::join!(server, handle.stop(true), self.shutdown());
+        result
+    }
+}
+
+async fn termination() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                tokio::select! {
+                    _ = sigterm.recv() => {}
+                    _ = tokio::signal::ctrl_c() => {}
+                }
+            }
+            Err(_) => {
+                let _ = tokio::signal::ctrl_c().await;
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
+
+fn panic_message(panic: Box<dyn std::any::Any + Send>) -> String {
+    let message = panic
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| panic.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string());
+    format!("panicked: {message}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[actix_web::test]
+    async fn test_failed_tasks_are_restarted_with_backoff() {
+        let supervisor = Supervisor::new("test").with_backoff(Duration::from_millis(10), Duration::from_millis(40));
+        let attempts = Arc::new(AtomicU32::new(0));
+        let counter = attempts.clone();
+        supervisor.spawn("flaky", move || {
+            let counter = counter.clone();
+            async move {
+                match counter.fetch_add(1, Ordering::SeqCst) {
+                    0 => Err("connection refused".into()),
+                    1 => panic!("boom"),
+                    _ => Ok(()),
+                }
+            }
+        });
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let status = supervisor.statuses()["flaky"].clone();
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+        assert_eq!(status.state, TaskState::Finished);
+        assert_eq!(status.restarts, 2);
+    }
+
+    #[actix_web::test]
+    async fn test_shutdown_drains_tasks_and_aborts_stragglers() {
+        let supervisor = Supervisor::new("test").with_deadline(Duration::from_millis(100));
+        let drained = Arc::new(AtomicU32::new(0));
+        let counter = drained.clone();
+        supervisor.spawn("worker", move || {
+            let counter = counter.clone();
+            async move {
+                while !is_shutting_down() {
+                    tokio::select! {
+                        _ = tokio::time::sleep(Duration::from_secs(60)) => {}
+                        _ = shutdown_requested() => {}
+                    }
+                }
+                counter.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            }
+        });
+        supervisor.spawn("stuck", || async {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            Ok(())
+        });
+        tokio::task::yield_now().await;
+
+        let started = Instant::now();
+        supervisor.shutdown().await;
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert_eq!(drained.load(Ordering::SeqCst), 1);
+
+        let statuses = supervisor.statuses();
+        assert_eq!(statuses["worker"].state, TaskState::Stopped);
+        assert_eq!(statuses["stuck"].state, TaskState::Stopped);
+        assert_eq!(statuses["stuck"].last_error.as_deref(), Some("aborted at shutdown deadline"));
+        assert!(!is_shutting_down());
+    }
+}
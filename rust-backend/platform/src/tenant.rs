@@ -1,9 +1,13 @@
 use serde::{Deserialize, Serialize};
+use sqlx::{PgConnection, PgPool, Postgres, Transaction};
 use std::collections::HashMap;
 use std::fmt;
 use std::str::FromStr;
 use uuid::Uuid;
 
+use crate::errors::AppError;
+use crate::scopes;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum PricingTier {
     Free,
@@ -25,6 +29,40 @@ impl PricingTier {
             PricingTier::Enterprise => u64::MAX,
         }
     }
+
+    pub fn rate_limit(&self) -> RateLimitPolicy {
+        match self {
+            PricingTier::Free => RateLimitPolicy::new(5, 60),
+            PricingTier::Growth => RateLimitPolicy::new(50, 1_000),
+            PricingTier::Enterprise => RateLimitPolicy::new(200, 10_000),
+        }
+    }
+}
+
+/// Request budget enforced by the rate limiter: a per-second burst ceiling on top of a
+/// sustained per-minute allowance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RateLimitPolicy {
+    pub burst_per_second: u64,
+    pub per_minute: u64,
+}
+
+impl RateLimitPolicy {
+    pub fn new(burst_per_second: u64, per_minute: u64) -> Self {
+        Self { burst_per_second, per_minute }
+    }
+
+    /// Replaces the sustained limit with an override (requests per minute), keeping the
+    /// burst ceiling no larger than the new sustained limit.
+    pub fn with_override(self, per_minute: Option<u64>) -> Self {
+        match per_minute {
+            Some(per_minute) => {
+                let per_minute = per_minute.max(1);
+                Self::new(self.burst_per_second.min(per_minute), per_minute)
+            }
+            None => self,
+        }
+    }
 }
 
 impl fmt::Display for PricingTier {
@@ -65,6 +103,13 @@ pub struct TenantContext {
     pub feature_flags: HashMap<String, bool>,
     pub auth_method: AuthMethod,
     pub db_connection_url: Option<String>,
+    /// Sustained requests-per-minute override from the API key or, failing that, the tenant.
+    pub rate_limit_override: Option<u64>,
+    /// The tenant's own override, which budgets all of its credentials together.
+    #[serde(default)]
+    pub tenant_rate_limit_override: Option<u64>,
+    /// The API key that authenticated the request, when `auth_method` is `ApiKey`.
+    pub api_key_id: Option<Uuid>,
 }
 
 impl TenantContext {
@@ -83,6 +128,9 @@ impl TenantContext {
             feature_flags: HashMap::new(),
             auth_method,
             db_connection_url: None,
+            rate_limit_override: None,
+            tenant_rate_limit_override: None,
+            api_key_id: None,
         }
     }
 
@@ -91,16 +139,92 @@ impl TenantContext {
         self
     }
 
-    pub async fn apply_rls<'c, E>(&self, executor: E) -> Result<(), sqlx::Error>
-    where
-        E: sqlx::Executor<'c, Database = sqlx::Postgres>,
-    {
-        let query = format!("SET LOCAL app.current_tenant_id = '{}';", self.tenant_id);
-        sqlx::query(&query).execute(executor).await?;
-        Ok(())
+    pub fn with_rate_limit_override(mut self, per_minute: Option<u64>) -> Self {
+        self.rate_limit_override = per_minute;
+        self
+    }
+
+    pub fn with_api_key_id(mut self, id: Uuid) -> Self {
+        self.api_key_id = Some(id);
+        self
+    }
+
+    /// Effective rate limit for this request: the tier policy adjusted by any override.
+    pub fn rate_limit(&self) -> RateLimitPolicy {
+        self.tier.rate_limit().with_override(self.rate_limit_override)
+    }
+
+    /// Rate limit shared by every request of the tenant, whichever credential it uses.
+    pub fn tenant_rate_limit(&self) -> RateLimitPolicy {
+        self.tier.rate_limit().with_override(self.tenant_rate_limit_override)
+    }
+
+    pub fn has_scope(&self, required: &str) -> bool {
+        self.permissions.iter().any(|granted| scopes::grants(granted, required))
+    }
+
+    /// Handler-level check, e.g. `ctx.require_scope(scopes::ORDERS_WRITE)?`.
+    pub fn require_scope(&self, required: &str) -> Result<(), AppError> {
+        if self.has_scope(required) {
+            Ok(())
+        } else {
+            Err(AppError::Forbidden(format!("Missing required scope '{}'", required)))
+        }
+    }
+
+    /// Opens a [`TenantTx`] for this request's tenant.
+    pub async fn begin(&self, pool: &PgPool) -> Result<TenantTx<'static>, sqlx::Error> {
+        TenantTx::begin(pool, self.tenant_id).await
+    }
+}
+
+/// A transaction scoped to one tenant. `app.current_tenant_id` is set before any
+/// repository query runs and lasts exactly as long as the transaction, so RLS policies
+/// see the tenant on every statement and the setting never leaks back into the pool.
+///
+/// Repositories take `&mut TenantTx` and reach the database only through
+/// [`TenantTx::executor`]; there is no way to hand them an unscoped connection.
+pub struct TenantTx<'c> {
+    tenant_id: Uuid,
+    tx: Transaction<'c, Postgres>,
+}
+
+impl TenantTx<'static> {
+    pub async fn begin(pool: &PgPool, tenant_id: Uuid) -> Result<Self, sqlx::Error> {
+        let mut tx = pool.begin().await?;
+        scope_to_tenant(&mut tx, tenant_id).await?;
+        Ok(Self { tenant_id, tx })
+    }
+}
+
+impl<'c> TenantTx<'c> {
+    pub fn tenant_id(&self) -> Uuid {
+        self.tenant_id
+    }
+
+    pub fn executor(&mut self) -> &mut PgConnection {
+        &mut self.tx
+    }
+
+    pub async fn commit(self) -> Result<(), sqlx::Error> {
+        self.tx.commit().await
+    }
+
+    pub async fn rollback(self) -> Result<(), sqlx::Error> {
+        self.tx.rollback().await
     }
 }
 
+/// Sets `app.current_tenant_id` for the rest of the current transaction. The tenant id is
+/// bound as a parameter; `SET LOCAL` cannot take one.
+pub(crate) async fn scope_to_tenant(conn: &mut PgConnection, tenant_id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query("SELECT set_config('app.current_tenant_id', $1, true)")
+        .bind(tenant_id.to_string())
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ApiKeyRecord {
     pub id: Uuid,
@@ -110,6 +234,10 @@ pub struct ApiKeyRecord {
     pub permissions: Vec<String>,
     pub rate_limit_override: Option<u64>,
     pub is_active: bool,
+    #[serde(default)]
+    pub environment: String,
+    #[serde(default)]
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
 }
 
 #[cfg(test)]
@@ -123,6 +251,18 @@ mod tests {
         assert_eq!(PricingTier::Enterprise.monthly_limit(), u64::MAX);
     }
 
+    #[test]
+    fn test_rate_limit_override_replaces_sustained_limit() {
+        let ctx = TenantContext::new(Uuid::new_v4(), None, PricingTier::Growth, vec![], AuthMethod::ApiKey);
+        assert_eq!(ctx.rate_limit(), PricingTier::Growth.rate_limit());
+
+        let raised = ctx.clone().with_rate_limit_override(Some(5_000)).rate_limit();
+        assert_eq!(raised, RateLimitPolicy::new(50, 5_000));
+
+        let lowered = ctx.with_rate_limit_override(Some(20)).rate_limit();
+        assert_eq!(lowered, RateLimitPolicy::new(20, 20));
+    }
+
     #[test]
     fn test_pricing_tier_from_str() {
         assert_eq!("Free".parse::<PricingTier>().unwrap(), PricingTier::Free);
@@ -147,17 +287,15 @@ mod tests {
     }
 
     #[test]
-    fn test_tenant_context_apply_rls_sql() {
-        let tenant_id = Uuid::new_v4();
+    fn test_require_scope_checks_permissions() {
         let ctx = TenantContext::new(
-            tenant_id,
+            Uuid::new_v4(),
             None,
             PricingTier::Growth,
             vec!["orders:read".to_string()],
             AuthMethod::ApiKey,
         );
-        let query = format!("SET LOCAL app.current_tenant_id = '{}';", ctx.tenant_id);
-        assert!(query.contains(&tenant_id.to_string()));
-        assert!(query.contains("SET LOCAL app.current_tenant_id"));
+        assert!(ctx.require_scope(scopes::ORDERS_READ).is_ok());
+        assert!(matches!(ctx.require_scope(scopes::ORDERS_WRITE), Err(AppError::Forbidden(_))));
     }
 }
@@ -0,0 +1,340 @@
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::db_router::control_plane_pool;
+use crate::events::{DomainEvent, EventType};
+use crate::streams;
+use crate::tenant::{PricingTier, TenantContext};
+
+/// The parts of a `tenants` row that shape every authenticated request.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TenantRecord {
+    pub id: Uuid,
+    pub tier: PricingTier,
+    pub feature_flags: HashMap<String, bool>,
+    pub db_connection_url: Option<String>,
+    pub ip_allowlist: Vec<String>,
+    pub rate_limit_override: Option<u64>,
+    /// Writes are paused while the tenant's data moves between databases.
+    #[serde(default)]
+    pub read_only: bool,
+}
+
+impl TenantRecord {
+    /// An empty allowlist admits every address; otherwise the client address must match
+    /// one of the entries, given either as a bare address or in CIDR notation.
+    pub fn allows_ip(&self, ip: Option<IpAddr>) -> bool {
+        if self.ip_allowlist.is_empty() {
+            return true;
+        }
+        let Some(ip) = ip else {
+            return false;
+        };
+        self.ip_allowlist.iter().any(|entry| cidr_contains(entry, ip))
+    }
+
+    /// Overlays the record onto a context built from the credential. An API key's own
+    /// rate limit override wins over the tenant's.
+    pub fn apply(&self, mut ctx: TenantContext) -> TenantContext {
+        ctx.tier = self.tier;
+        ctx.feature_flags = self.feature_flags.clone();
+        ctx.db_connection_url = self.db_connection_url.clone();
+        ctx.rate_limit_override = ctx.rate_limit_override.or(self.rate_limit_override);
+        ctx.tenant_rate_limit_override = self.rate_limit_override;
+        ctx
+    }
+}
+
+pub(crate) fn cidr_contains(entry: &str, ip: IpAddr) -> bool {
+    let (addr, prefix) = match entry.trim().split_once('/') {
+        Some((addr, prefix)) => (addr, prefix.parse::<u32>().ok()),
+        None => (entry.trim(), None),
+    };
+    let Ok(network) = addr.parse::<IpAddr>() else {
+        return false;
+    };
+    match (network, ip) {
+        (IpAddr::V4(network), IpAddr::V4(ip)) => {
+            let prefix = prefix.unwrap_or(32).min(32);
+            let mask = u32::MAX.checked_shl(32 - prefix).unwrap_or(0);
+            u32::from(network) & mask == u32::from(ip) & mask
+        }
+        (IpAddr::V6(network), IpAddr::V6(ip)) => {
+            let prefix = prefix.unwrap_or(128).min(128);
+            let mask = u128::MAX.checked_shl(128 - prefix).unwrap_or(0);
+            u128::from(network) & mask == u128::from(ip) & mask
+        }
+        _ => false,
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct TenantRow {
+    id: Uuid,
+    tier: String,
+    feature_flags: Option<serde_json::Value>,
+    db_connection_url: Option<String>,
+    ip_allowlist: Option<Vec<String>>,
+    rate_limit_override: Option<i32>,
+    read_only: bool,
+}
+
+impl From<TenantRow> for TenantRecord {
+    fn from(row: TenantRow) -> Self {
+        let tier = row.tier.parse().unwrap_or_else(|_| {
+            tracing::warn!(tenant_id = %row.id, tier = %row.tier, "unknown tenant tier, treating as Free");
+            PricingTier::Free
+        });
+        // Flags are free-form JSON; only boolean entries are meaningful as feature switches
+        let feature_flags = row
+            .feature_flags
+            .and_then(|flags| flags.as_object().cloned())
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|(name, value)| value.as_bool().map(|enabled| (name, enabled)))
+            .collect();
+        Self {
+            id: row.id,
+            tier,
+            feature_flags,
+            db_connection_url: row.db_connection_url,
+            ip_allowlist: row.ip_allowlist.unwrap_or_default(),
+            rate_limit_override: row.rate_limit_override.and_then(|v| u64::try_from(v).ok()),
+            read_only: row.read_only,
+        }
+    }
+}
+
+/// Reads tenant records from the control-plane `tenants` table.
+///
+/// Records are cached in Redis under `tenant:{id}:record`. The cache entry is dropped
+/// when a `tenant.updated` event arrives (see [`TenantDirectory::listen_for_updates`]),
+/// and the TTL bounds staleness if an event is missed. Without a pool only cached
+/// records are visible.
+#[derive(Clone, Debug)]
+pub struct TenantDirectory {
+    pool: Option<PgPool>,
+    redis_client: Option<redis::Client>,
+    cache_ttl: Duration,
+}
+
+impl TenantDirectory {
+    pub fn new() -> Self {
+        Self {
+            pool: None,
+            redis_client: None,
+            cache_ttl: Duration::from_secs(300),
+        }
+    }
+
+    /// Uses the shared control-plane pool from `CONTROL_PLANE_DATABASE_URL`.
+    pub fn from_env() -> Self {
+        let mut directory = Self::new();
+        directory.pool = control_plane_pool();
+        directory
+    }
+
+    pub fn with_pool(mut self, pool: PgPool) -> Self {
+        self.pool = Some(pool);
+        self
+    }
+
+    pub fn with_redis(mut self, redis_client: redis::Client) -> Self {
+        self.redis_client = Some(redis_client);
+        self
+    }
+
+    pub fn with_cache_ttl(mut self, cache_ttl: Duration) -> Self {
+        self.cache_ttl = cache_ttl;
+        self
+    }
+
+    /// Whether lookups reach the control plane, so a missing record means the tenant
+    /// does not exist.
+    pub fn is_authoritative(&self) -> bool {
+        self.pool.is_some()
+    }
+
+    fn cache_key(tenant_id: Uuid) -> String {
+        format!("tenant:{}:record", tenant_id)
+    }
+
+    pub async fn get(&self, tenant_id: Uuid) -> Result<Option<TenantRecord>, sqlx::Error> {
+        let cache_key = Self::cache_key(tenant_id);
+        let mut cache = match &self.redis_client {
+            Some(client) => client.get_multiplexed_async_connection().await.ok(),
+            None => None,
+        };
+
+        if let Some(conn) = cache.as_mut() {
+            let cached: Option<String> = redis::cmd("GET").arg(&cache_key).query_async(conn).await.unwrap_or(None);
+            if let Some(record) = cached.and_then(|json| serde_json::from_str::<TenantRecord>(&json).ok()) {
+                return Ok(Some(record));
+            }
+        }
+
+        let Some(pool) = &self.pool else {
+            return Ok(None);
+        };
+        let record: Option<TenantRecord> = sqlx::query_as::<_, TenantRow>(
+            r#"
+            SELECT id, tier, feature_flags, db_connection_url, ip_allowlist, rate_limit_override, read_only
+            FROM tenants
+            WHERE id = $1
+            "#,
+        )
+        .bind(tenant_id)
+        .fetch_optional(pool)
+        .await?
+        .map(Into::into);
+
+        if let (Some(conn), Some(record)) = (cache.as_mut(), &record) {
+            if let Ok(json) = serde_json::to_string(record) {
+                let cached: Result<(), _> = redis::cmd("SET")
+                    .arg(&cache_key)
+                    .arg(json)
+                    .arg("EX")
+                    .arg(self.cache_ttl.as_secs().max(1))
+                    .query_async(conn)
+                    .await;
+                if let Err(e) = cached {
+                    tracing::warn!(tenant_id = %tenant_id, error = %e, "failed to cache tenant record");
+                }
+            }
+        }
+        Ok(record)
+    }
+
+    /// Every tenant known to the control plane, for background jobs that have to visit
+    /// tenant data one [`crate::tenant::TenantTx`] at a time. Empty without a pool.
+    pub async fn tenant_ids(&self) -> Result<Vec<Uuid>, sqlx::Error> {
+        let Some(pool) = &self.pool else {
+            return Ok(Vec::new());
+        };
+        sqlx::query_scalar("SELECT id FROM tenants ORDER BY created_at").fetch_all(pool).await
+    }
+
+    /// Tenants that `DynamicPoolRouter` sends to a dedicated database, for background jobs
+    /// that otherwise scan the shared one.
+    pub async fn dedicated_tenant_ids(&self) -> Result<Vec<Uuid>, sqlx::Error> {
+        let Some(pool) = &self.pool else {
+            return Ok(Vec::new());
+        };
+        sqlx::query_scalar(
+            "SELECT id FROM tenants WHERE lower(tier) = 'enterprise' AND db_connection_url IS NOT NULL ORDER BY created_at",
+        )
+        .fetch_all(pool)
+        .await
+    }
+
+    pub async fn invalidate(&self, tenant_id: Uuid) -> Result<(), redis::RedisError> {
+        let Some(client) = &self.redis_client else {
+            return Ok(());
+        };
+        let mut conn = client.get_multiplexed_async_connection().await?;
+        redis::cmd("DEL").arg(Self::cache_key(tenant_id)).query_async(&mut conn).await
+    }
+
+    /// Drops cached records as `tenant.updated` events arrive. The cache lives in Redis,
+    /// so one consumer in the shared `tenant-directory` group is enough for every service.
+    pub async fn listen_for_updates(
+        self,
+        redis_url: &str,
+        consumer: &str,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        streams::consume_json::<DomainEvent, _, _>(
+            redis_url,
+            "tenant-directory",
+            consumer,
+            &[EventType::TenantUpdated.as_str()],
+            |envelope| {
+                let directory = self.clone();
+                async move {
+                    if let DomainEvent::TenantUpdated(event) = envelope.payload {
+                        directory.invalidate(event.tenant_id).await?;
+                    }
+                    Ok(())
+                }
+            },
+        )
+        .await
+    }
+}
+
+impl Default for TenantDirectory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tenant::AuthMethod;
+
+    fn record(ip_allowlist: &[&str]) -> TenantRecord {
+        TenantRecord {
+            id: Uuid::new_v4(),
+            tier: PricingTier::Enterprise,
+            feature_flags: HashMap::from([("beta_checkout".to_string(), true)]),
+            db_connection_url: Some("postgres://tenant-db/orders".to_string()),
+            ip_allowlist: ip_allowlist.iter().map(|s| s.to_string()).collect(),
+            rate_limit_override: Some(2_000),
+            read_only: false,
+        }
+    }
+
+    #[test]
+    fn test_ip_allowlist_matches_addresses_and_cidrs() {
+        assert!(record(&[]).allows_ip(None));
+
+        let restricted = record(&["10.0.0.0/8", "192.168.1.7", "2001:db8::/32"]);
+        assert!(restricted.allows_ip(Some("10.20.30.40".parse().unwrap())));
+        assert!(restricted.allows_ip(Some("192.168.1.7".parse().unwrap())));
+        assert!(restricted.allows_ip(Some("2001:db8::1".parse().unwrap())));
+        assert!(!restricted.allows_ip(Some("192.168.1.8".parse().unwrap())));
+        assert!(!restricted.allows_ip(Some("11.0.0.1".parse().unwrap())));
+        assert!(!restricted.allows_ip(None));
+        assert!(record(&["0.0.0.0/0"]).allows_ip(Some("8.8.8.8".parse().unwrap())));
+    }
+
+    #[test]
+    fn test_apply_overrides_credential_claims() {
+        let record = record(&[]);
+        let ctx = TenantContext::new(record.id, None, PricingTier::Free, vec![], AuthMethod::ApiKey);
+        let ctx = record.apply(ctx);
+        assert_eq!(ctx.tier, PricingTier::Enterprise);
+        assert_eq!(ctx.feature_flags.get("beta_checkout"), Some(&true));
+        assert_eq!(ctx.db_connection_url.as_deref(), Some("postgres://tenant-db/orders"));
+        assert_eq!(ctx.rate_limit_override, Some(2_000));
+
+        let keyed = TenantContext::new(record.id, None, PricingTier::Free, vec![], AuthMethod::ApiKey)
+            .with_rate_limit_override(Some(50));
+        let keyed = record.apply(keyed);
+        assert_eq!(keyed.rate_limit_override, Some(50));
+        assert_eq!(keyed.tenant_rate_limit_override, Some(2_000));
+    }
+
+    #[test]
+    fn test_row_conversion_keeps_boolean_flags_only() {
+        let row = TenantRow {
+            id: Uuid::new_v4(),
+            tier: "growth".to_string(),
+            feature_flags: Some(serde_json::json!({"beta": true, "legacy": false, "limit": 5})),
+            db_connection_url: None,
+            ip_allowlist: None,
+            rate_limit_override: Some(-1),
+            read_only: false,
+        };
+        let record = TenantRecord::from(row);
+        assert_eq!(record.tier, PricingTier::Growth);
+        assert_eq!(record.feature_flags.len(), 2);
+        assert_eq!(record.feature_flags.get("legacy"), Some(&false));
+        assert!(record.ip_allowlist.is_empty());
+        assert_eq!(record.rate_limit_override, None);
+    }
+}
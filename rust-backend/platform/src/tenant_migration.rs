@@ -0,0 +1,422 @@
+use futures_util::TryStreamExt;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgConnection, PgPool};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use thiserror::Error;
+use uuid::Uuid;
+
+/// Infrastructure tables that carry a `tenant_id` but must never follow a tenant to its
+/// own database.
+pub const EXCLUDED_TABLES: &[&str] = &["api_keys", "event_outbox", "tenant_db_migrations", "tenant_webhooks", "tenants"];
+
+#[derive(Error, Debug)]
+pub enum TenantMigrationError {
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+    #[error("Tables missing from the target database: {0}")]
+    MissingTables(String),
+    #[error("{table} differs after copy: expected {expected}, found {actual}")]
+    ChecksumMismatch {
+        table: TenantTable,
+        expected: TableChecksum,
+        actual: TableChecksum,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct TenantTable {
+    pub schema: String,
+    pub name: String,
+}
+
+impl TenantTable {
+    fn qualified(&self) -> String {
+        format!("{}.{}", quote_ident(&self.schema), quote_ident(&self.name))
+    }
+}
+
+impl fmt::Display for TenantTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.schema, self.name)
+    }
+}
+
+fn quote_ident(ident: &str) -> String {
+    format!("\"{}\"", ident.replace('"', "\"\""))
+}
+
+/// Row count plus an order-independent hash of a tenant's rows in one table.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TableChecksum {
+    pub rows: i64,
+    pub checksum: String,
+}
+
+impl fmt::Display for TableChecksum {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} rows ({})", self.rows, self.checksum)
+    }
+}
+
+/// Every table with a `tenant_id` column, ordered so that referenced tables come before
+/// the tables whose foreign keys point at them.
+#[derive(Debug, Clone, Default)]
+pub struct TenantTables {
+    ordered: Vec<TenantTable>,
+    /// Referenced table -> tables holding a foreign key to it.
+    dependents: BTreeMap<TenantTable, BTreeSet<TenantTable>>,
+}
+
+impl TenantTables {
+    pub async fn discover(pool: &PgPool) -> Result<Self, sqlx::Error> {
+        let tables: Vec<(String, String)> = sqlx::query_as(
+            r#"
+            SELECT c.table_schema::text, c.table_name::text
+            FROM information_schema.columns c
+            JOIN information_schema.tables t
+              ON t.table_schema = c.table_schema AND t.table_name = c.table_name
+            WHERE c.column_name = 'tenant_id'
+              AND t.table_type = 'BASE TABLE'
+              AND c.table_schema NOT IN ('pg_catalog', 'information_schema')
+            "#,
+        )
+        .fetch_all(pool)
+        .await?;
+
+        let foreign_keys: Vec<(String, String, String, String)> = sqlx::query_as(
+            r#"
+            SELECT child_ns.nspname::text, child.relname::text, parent_ns.nspname::text, parent.relname::text
+            FROM pg_constraint con
+            JOIN pg_class child ON child.oid = con.conrelid
+            JOIN pg_namespace child_ns ON child_ns.oid = child.relnamespace
+            JOIN pg_class parent ON parent.oid = con.confrelid
+            JOIN pg_namespace parent_ns ON parent_ns.oid = parent.relnamespace
+            WHERE con.contype = 'f'
+            "#,
+        )
+        .fetch_all(pool)
+        .await?;
+
+        let tables = tables
+            .into_iter()
+            .filter(|(_, name)| !EXCLUDED_TABLES.contains(&name.as_str()))
+            .map(|(schema, name)| TenantTable { schema, name });
+        let foreign_keys = foreign_keys.into_iter().map(|(cs, cn, ps, pn)| {
+            (TenantTable { schema: cs, name: cn }, TenantTable { schema: ps, name: pn })
+        });
+        Ok(Self::from_parts(tables, foreign_keys))
+    }
+
+    /// Orders `tables` by the `(child, parent)` foreign keys between them. Keys pointing
+    /// outside the set and self references are ignored; cycles keep discovery order.
+    pub fn from_parts(
+        tables: impl IntoIterator<Item = TenantTable>,
+        foreign_keys: impl IntoIterator<Item = (TenantTable, TenantTable)>,
+    ) -> Self {
+        let tables: BTreeSet<TenantTable> = tables.into_iter().collect();
+        let mut parents: BTreeMap<&TenantTable, BTreeSet<&TenantTable>> = BTreeMap::new();
+        let mut dependents: BTreeMap<TenantTable, BTreeSet<TenantTable>> = BTreeMap::new();
+        for (child, parent) in foreign_keys {
+            if child == parent {
+                continue;
+            }
+            if let (Some(child), Some(parent)) = (tables.get(&child), tables.get(&parent)) {
+                parents.entry(child).or_default().insert(parent);
+                dependents.entry(parent.clone()).or_default().insert(child.clone());
+            }
+        }
+
+        let mut ordered: Vec<TenantTable> = Vec::with_capacity(tables.len());
+        let mut placed: BTreeSet<&TenantTable> = BTreeSet::new();
+        while placed.len() < tables.len() {
+            let ready: Vec<&TenantTable> = tables
+                .iter()
+                .filter(|t| !placed.contains(t))
+                .filter(|t| parents.get(t).is_none_or(|p| p.iter().all(|p| placed.contains(p))))
+                .collect();
+            // A foreign key cycle: place the rest as they are
+            let batch = if ready.is_empty() {
+                tables.iter().filter(|t| !placed.contains(t)).collect()
+            } else {
+                ready
+            };
+            for table in batch {
+                placed.insert(table);
+                ordered.push(table.clone());
+            }
+        }
+
+        Self { ordered, dependents }
+    }
+
+    pub fn ordered(&self) -> &[TenantTable] {
+        &self.ordered
+    }
+
+    /// `changed` plus every table that references them, directly or transitively, in copy
+    /// order. Re-copying a table means deleting its rows first, which its dependents' rows
+    /// would otherwise block.
+    pub fn with_dependents(&self, changed: &[TenantTable]) -> Vec<TenantTable> {
+        let mut selected: BTreeSet<&TenantTable> = BTreeSet::new();
+        let mut pending: Vec<&TenantTable> = changed.iter().collect();
+        while let Some(table) = pending.pop() {
+            if selected.insert(table) {
+                if let Some(children) = self.dependents.get(table) {
+                    pending.extend(children.iter());
+                }
+            }
+        }
+        self.ordered.iter().filter(|t| selected.contains(t)).cloned().collect()
+    }
+
+    /// Tables from this set that do not exist in `pool`.
+    pub async fn missing_from(&self, pool: &PgPool) -> Result<Vec<TenantTable>, sqlx::Error> {
+        let mut missing = Vec::new();
+        for table in &self.ordered {
+            let exists: Option<String> = sqlx::query_scalar("SELECT to_regclass($1)::text")
+                .bind(table.qualified())
+                .fetch_one(pool)
+                .await?;
+            if exists.is_none() {
+                missing.push(table.clone());
+            }
+        }
+        Ok(missing)
+    }
+}
+
+/// Scopes the transaction to one tenant so RLS policies (`FORCE ROW LEVEL SECURITY`)
+/// admit the job's reads and writes.
+pub async fn set_tenant(conn: &mut PgConnection, tenant_id: Uuid) -> Result<(), sqlx::Error> {
+    crate::tenant::scope_to_tenant(conn, tenant_id).await
+}
+
+/// Deletes the tenant's rows from `tables`, dependents first. Expects a transaction that
+/// went through [`set_tenant`].
+pub async fn delete_rows(
+    conn: &mut PgConnection,
+    tenant_id: Uuid,
+    tables: &[TenantTable],
+) -> Result<u64, sqlx::Error> {
+    let mut deleted = 0;
+    for table in tables.iter().rev() {
+        deleted += sqlx::query(&format!("DELETE FROM {} WHERE tenant_id = $1", table.qualified()))
+            .bind(tenant_id)
+            .execute(&mut *conn)
+            .await?
+            .rows_affected();
+    }
+    Ok(deleted)
+}
+
+/// Streams the tenant's rows of `table` from `source` into `target` in batches of
+/// `batch_size`, then moves the table's sequences past the copied values. `target` must
+/// be a transaction that went through [`set_tenant`] and holds none of the tenant's rows.
+pub async fn copy_rows(
+    source: &PgPool,
+    target: &mut PgConnection,
+    tenant_id: Uuid,
+    table: &TenantTable,
+    batch_size: usize,
+) -> Result<u64, sqlx::Error> {
+    let mut source_tx = source.begin().await?;
+    set_tenant(&mut source_tx, tenant_id).await?;
+
+    let select = format!("SELECT row_to_json(t)::text FROM {} t WHERE tenant_id = $1", table.qualified());
+    let insert = format!(
+        "INSERT INTO {0} OVERRIDING SYSTEM VALUE SELECT * FROM json_populate_recordset(NULL::{0}, $1::json)",
+        table.qualified()
+    );
+
+    let mut copied = 0;
+    let mut batch: Vec<String> = Vec::with_capacity(batch_size);
+    {
+        let mut rows = sqlx::query_scalar::<_, String>(&select).bind(tenant_id).fetch(&mut *source_tx);
+        while let Some(row) = rows.try_next().await? {
+            batch.push(row);
+            if batch.len() >= batch_size.max(1) {
+                copied += insert_batch(&insert, target, &mut batch).await?;
+            }
+        }
+    }
+    copied += insert_batch(&insert, target, &mut batch).await?;
+    sync_sequences(&mut source_tx, target, table).await?;
+    source_tx.commit().await?;
+    Ok(copied)
+}
+
+async fn insert_batch(insert: &str, target: &mut PgConnection, batch: &mut Vec<String>) -> Result<u64, sqlx::Error> {
+    if batch.is_empty() {
+        return Ok(0);
+    }
+    let json = format!("[{}]", batch.join(","));
+    batch.clear();
+    Ok(sqlx::query(insert).bind(json).execute(target).await?.rows_affected())
+}
+
+/// Serial and identity columns keep handing out values past both the copied rows and
+/// everything the source sequence has issued, so ids created after the move cannot clash
+/// with other tenants' rows when copied back. Sequences only move forward.
+async fn sync_sequences(
+    source: &mut PgConnection,
+    target: &mut PgConnection,
+    table: &TenantTable,
+) -> Result<(), sqlx::Error> {
+    let sequences: Vec<(String, String)> = sqlx::query_as(
+        r#"
+        SELECT column_name::text, seq
+        FROM (
+            SELECT column_name, pg_get_serial_sequence(quote_ident(table_schema) || '.' || quote_ident(table_name), column_name) AS seq
+            FROM information_schema.columns
+            WHERE table_schema = $1 AND table_name = $2
+        ) c
+        WHERE seq IS NOT NULL
+        "#,
+    )
+    .bind(&table.schema)
+    .bind(&table.name)
+    .fetch_all(&mut *target)
+    .await?;
+
+    for (column, sequence) in sequences {
+        let issued: i64 = match sqlx::query_scalar::<_, Option<String>>("SELECT to_regclass($1)::text")
+            .bind(&sequence)
+            .fetch_one(&mut *source)
+            .await?
+        {
+            Some(source_sequence) => {
+                sqlx::query_scalar(&format!("SELECT last_value FROM {source_sequence}"))
+                    .fetch_one(&mut *source)
+                    .await?
+            }
+            None => 0,
+        };
+        let query = format!(
+            "SELECT setval($1, GREATEST(COALESCE((SELECT MAX({col}) FROM {table}), 0), (SELECT last_value FROM {sequence}), $2, 1))",
+            col = quote_ident(&column),
+            table = table.qualified(),
+        );
+        sqlx::query(&query).bind(&sequence).bind(issued).execute(&mut *target).await?;
+    }
+    Ok(())
+}
+
+pub async fn checksum(pool: &PgPool, tenant_id: Uuid, table: &TenantTable) -> Result<TableChecksum, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    set_tenant(&mut tx, tenant_id).await?;
+    let (rows, checksum): (i64, String) = sqlx::query_as(&format!(
+        "SELECT COUNT(*), COALESCE(SUM(hashtextextended(t::text, 0)::numeric), 0)::text FROM {} t WHERE tenant_id = $1",
+        table.qualified()
+    ))
+    .bind(tenant_id)
+    .fetch_one(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(TableChecksum { rows, checksum })
+}
+
+/// Tables whose tenant rows differ between the two databases, with the source checksum.
+pub async fn diff(
+    source: &PgPool,
+    target: &PgPool,
+    tenant_id: Uuid,
+    tables: &[TenantTable],
+) -> Result<Vec<(TenantTable, TableChecksum, TableChecksum)>, sqlx::Error> {
+    let mut changed = Vec::new();
+    for table in tables {
+        let expected = checksum(source, tenant_id, table).await?;
+        let actual = checksum(target, tenant_id, table).await?;
+        if expected != actual {
+            changed.push((table.clone(), expected, actual));
+        }
+    }
+    Ok(changed)
+}
+
+/// Copies `tables` (ordered, see [`TenantTables::with_dependents`]) in one transaction on
+/// `target`, replacing whatever the tenant had there.
+pub async fn replace_rows(
+    source: &PgPool,
+    target: &PgPool,
+    tenant_id: Uuid,
+    tables: &[TenantTable],
+    batch_size: usize,
+) -> Result<u64, sqlx::Error> {
+    let mut tx = target.begin().await?;
+    set_tenant(&mut tx, tenant_id).await?;
+    delete_rows(&mut tx, tenant_id, tables).await?;
+    let mut copied = 0;
+    for table in tables {
+        copied += copy_rows(source, &mut tx, tenant_id, table, batch_size).await?;
+    }
+    tx.commit().await?;
+    Ok(copied)
+}
+
+/// Fails with the first table whose tenant rows differ between the two databases.
+pub async fn verify(
+    source: &PgPool,
+    target: &PgPool,
+    tenant_id: Uuid,
+    tables: &[TenantTable],
+) -> Result<(), TenantMigrationError> {
+    match diff(source, target, tenant_id, tables).await?.into_iter().next() {
+        Some((table, expected, actual)) => Err(TenantMigrationError::ChecksumMismatch { table, expected, actual }),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(name: &str) -> TenantTable {
+        TenantTable {
+            schema: "public".to_string(),
+            name: name.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_tables_are_ordered_parents_first() {
+        let tables = TenantTables::from_parts(
+            ["shipments", "orders", "order_lines", "tracking_events", "quotes"].map(table),
+            [
+                (table("order_lines"), table("orders")),
+                (table("shipments"), table("orders")),
+                (table("tracking_events"), table("shipments")),
+                (table("quotes"), table("quotes")),
+                (table("orders"), table("tenants")),
+            ],
+        );
+        let position = |name: &str| tables.ordered().iter().position(|t| t.name == name).unwrap();
+        assert_eq!(tables.ordered().len(), 5);
+        assert!(position("orders") < position("order_lines"));
+        assert!(position("orders") < position("shipments"));
+        assert!(position("shipments") < position("tracking_events"));
+
+        let recopy = tables.with_dependents(&[table("shipments")]);
+        assert_eq!(recopy, vec![table("shipments"), table("tracking_events")]);
+        assert_eq!(tables.with_dependents(&[table("orders")]).len(), 4);
+    }
+
+    #[test]
+    fn test_cycles_do_not_stall_ordering() {
+        let tables = TenantTables::from_parts(
+            ["a", "b", "c"].map(table),
+            [(table("a"), table("b")), (table("b"), table("a")), (table("c"), table("a"))],
+        );
+        assert_eq!(tables.ordered().len(), 3);
+    }
+
+    #[test]
+    fn test_identifiers_are_quoted() {
+        let t = TenantTable {
+            schema: "analytics".to_string(),
+            name: "we\"ird".to_string(),
+        };
+        assert_eq!(t.qualified(), "\"analytics\".\"we\"\"ird\"");
+        assert_eq!(t.to_string(), "analytics.we\"ird");
+    }
+}
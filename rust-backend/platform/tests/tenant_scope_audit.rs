@@ -0,0 +1,130 @@
+//! Static audit of the service sources: every query must run on the executor of a
+//! `TenantTx`, so it is always scoped by RLS to one tenant.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Functions that legitimately run outside a tenant scope.
+const EXEMPT: &[(&str, &str)] = &[
+    // Control-plane tables, not tenant data.
+    ("tenant-management", "*"),
+];
+
+const EXECUTOR_CALLS: &[&str] = &[".fetch_one(", ".fetch_all(", ".fetch_optional(", ".execute(", ".fetch("];
+
+fn workspace_root() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).parent().unwrap().to_path_buf()
+}
+
+fn service_dirs() -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = fs::read_dir(workspace_root())
+        .unwrap()
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.join("Cargo.toml").exists() && p.join("src").is_dir())
+        .filter(|p| !p.ends_with("platform") && !p.ends_with("e2e-tests"))
+        .collect();
+    dirs.sort();
+    dirs
+}
+
+fn rust_sources(dir: &Path, out: &mut Vec<PathBuf>) {
+    for entry in fs::read_dir(dir).unwrap().flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            rust_sources(&path, out);
+        } else if path.extension().is_some_and(|ext| ext == "rs") {
+            out.push(path);
+        }
+    }
+}
+
+fn is_exempt(service: &str, func: &str) -> bool {
+    EXEMPT
+        .iter()
+        .any(|(s, f)| *s == service && (*f == "*" || *f == func))
+}
+
+/// Splits the non-test part of a source file into `(name, item)` for each `async fn`.
+fn async_fns(source: &str) -> Vec<(String, String)> {
+    let source = source.split("#[cfg(test)]").next().unwrap_or_default();
+    let starts: Vec<usize> = source.match_indices("async fn ").map(|(i, _)| i).collect();
+
+    starts
+        .iter()
+        .enumerate()
+        .map(|(n, &start)| {
+            let end = starts.get(n + 1).copied().unwrap_or(source.len());
+            let item = &source[start..end];
+            let name_start = "async fn ".len();
+            let name_end = item[name_start..]
+                .find(['(', '<'])
+                .map(|i| i + name_start)
+                .unwrap_or(item.len());
+            (item[name_start..name_end].to_string(), item.to_string())
+        })
+        .collect()
+}
+
+/// Whether an executor argument is `<tx>.executor()`.
+fn is_tenant_executor(arg: &str) -> bool {
+    let arg = arg.trim_start();
+    let name_len = arg.find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(arg.len());
+    name_len > 0 && arg[name_len..].starts_with(".executor()")
+}
+
+#[test]
+fn every_query_runs_on_a_tenant_tx() {
+    let mut violations = Vec::new();
+
+    for dir in service_dirs() {
+        let service = dir.file_name().unwrap().to_string_lossy().to_string();
+        let mut files = Vec::new();
+        rust_sources(&dir.join("src"), &mut files);
+
+        for file in files {
+            let source = fs::read_to_string(&file).unwrap();
+            let relative = file.strip_prefix(&dir).unwrap().display().to_string();
+            for (name, body) in async_fns(&source) {
+                if is_exempt(&service, &name) {
+                    continue;
+                }
+                for call in EXECUTOR_CALLS {
+                    for (i, _) in body.match_indices(call) {
+                        if !is_tenant_executor(&body[i + call.len()..]) {
+                            violations.push(format!("{service}/{relative}::{name} runs `{call}` outside a TenantTx executor"));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    assert!(violations.is_empty(), "unscoped queries:\n{}", violations.join("\n"));
+}
+
+#[test]
+fn services_never_set_the_tenant_by_hand() {
+    let mut violations = Vec::new();
+
+    for dir in service_dirs() {
+        let mut files = Vec::new();
+        rust_sources(&dir.join("src"), &mut files);
+        for file in files {
+            let source = fs::read_to_string(&file).unwrap();
+            if source.contains("SET LOCAL app.current_tenant_id") || source.contains("apply_rls") {
+                violations.push(file.display().to_string());
+            }
+        }
+    }
+
+    assert!(violations.is_empty(), "set the tenant through TenantTx instead:\n{}", violations.join("\n"));
+}
+
+#[test]
+fn audit_sees_the_service_repositories() {
+    let repos = service_dirs()
+        .iter()
+        .filter(|d| d.join("src/db.rs").exists())
+        .count();
+    assert!(repos >= 5, "expected to find the service db.rs files, found {repos}");
+}
@@ -1,6 +1,9 @@
 use crate::models::{
     CreateProductRequest, Product, ProductAsset, RegisterProductAssetRequest, UpdateProductRequest,
 };
+use platform::pagination::{CursorPage, PageRequest};
+use platform::tenant::TenantTx;
+use sqlx::{Postgres, QueryBuilder};
 use uuid::Uuid;
 
 #[derive(Clone)]
@@ -13,7 +16,7 @@ impl ProductRepo {
     }
 
     /// Creates a product and emits best-effort integration events.
-    pub async fn create_product(&self, tx: &mut sqlx::Transaction<'_, sqlx::Postgres>, req: &CreateProductRequest) -> Result<Product, sqlx::Error> {
+    pub async fn create_product(&self, tx: &mut TenantTx<'_>, req: &CreateProductRequest) -> Result<Product, sqlx::Error> {
         let available = req.available.unwrap_or(true);
         let quantity = req.quantity.unwrap_or(0);
         let product_id = req.product_id.unwrap_or_else(Uuid::new_v4);
@@ -38,12 +41,12 @@ impl ProductRepo {
         .bind(low_stock_threshold)
         .bind(&req.sku)
         .bind(&req.variants)
-        .fetch_one(&mut **tx)
+        .fetch_one(tx.executor())
         .await
     }
 
     /// Returns products that belong to the given supplier.
-    pub async fn get_by_supplier(&self, tx: &mut sqlx::Transaction<'_, sqlx::Postgres>, supplier_id: Uuid) -> Result<Vec<Product>, sqlx::Error> {
+    pub async fn get_by_supplier(&self, tx: &mut TenantTx<'_>, supplier_id: Uuid) -> Result<Vec<Product>, sqlx::Error> {
         sqlx::query_as::<_, Product>(
             r#"
                 SELECT id, tenant_id, product_id, supplier_id, name, description, category, price, unit, quantity, available, low_stock_threshold, sku, variants, created_at, updated_at, deleted_at
@@ -53,14 +56,14 @@ impl ProductRepo {
             "#,
         )
         .bind(supplier_id)
-        .fetch_all(&mut **tx)
+        .fetch_all(tx.executor())
         .await
     }
 
     /// Returns a single product for a supplier/product pair.
     pub async fn get_one(
         &self,
-        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        tx: &mut TenantTx<'_>,
         supplier_id: Uuid,
         product_id: Uuid,
     ) -> Result<Product, sqlx::Error> {
@@ -73,14 +76,14 @@ impl ProductRepo {
         )
         .bind(supplier_id)
         .bind(product_id)
-        .fetch_one(&mut **tx)
+        .fetch_one(tx.executor())
         .await
     }
 
     /// Updates a product and emits a product.updated event.
     pub async fn update_product(
         &self,
-        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        tx: &mut TenantTx<'_>,
         supplier_id: Uuid,
         product_id: Uuid,
         req: &UpdateProductRequest,
@@ -123,14 +126,14 @@ impl ProductRepo {
         .bind(req.variants.as_ref())
         .bind(supplier_id)
         .bind(product_id)
-        .fetch_one(&mut **tx)
+        .fetch_one(tx.executor())
         .await
     }
 
     /// Deletes a product, emits product.deleted, and invalidates cache.
     pub async fn delete_product(
         &self,
-        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        tx: &mut TenantTx<'_>,
         supplier_id: Uuid,
         product_id: Uuid,
     ) -> Result<u64, sqlx::Error> {
@@ -138,55 +141,33 @@ impl ProductRepo {
             sqlx::query(r#"UPDATE products SET deleted_at = NOW() WHERE supplier_id = $1 AND product_id = $2 AND deleted_at IS NULL"#)
                 .bind(supplier_id)
                 .bind(product_id)
-                .execute(&mut **tx)
+                .execute(tx.executor())
                 .await?;
 
         Ok(result.rows_affected())
     }
 
-    /// Searches products by optional query parameters.
+    /// Returns one page of products matching the request's filters.
     pub async fn search_products(
         &self,
-        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
-        category: Option<String>,
-        min_price: Option<f64>,
-        max_price: Option<f64>,
-        product_id: Option<Uuid>,
-        supplier_id: Option<Uuid>,
-        limit: i64,
-        offset: i64,
-    ) -> Result<Vec<Product>, sqlx::Error> {
-        let rows = sqlx::query_as::<_, Product>(
-            r#"
-            SELECT id, tenant_id, product_id, supplier_id, name, description, category, price, unit, quantity, available, low_stock_threshold, sku, variants, created_at, updated_at, deleted_at
-            FROM products
-            WHERE deleted_at IS NULL
-              AND ($1::text IS NULL OR category = $1)
-              AND ($2::double precision IS NULL OR price >= $2)
-              AND ($3::double precision IS NULL OR price <= $3)
-              AND ($4::uuid IS NULL OR supplier_id = $4)
-              AND ($5::uuid IS NULL OR product_id = $5)
-            ORDER BY name
-            LIMIT $6 OFFSET $7
-            "#,
-        )
-        .bind(category)
-        .bind(min_price)
-        .bind(max_price)
-        .bind(supplier_id)
-        .bind(product_id)
-        .bind(limit)
-        .bind(offset)
-        .fetch_all(&mut **tx)
-        .await?;
+        tx: &mut TenantTx<'_>,
+        page: &PageRequest,
+    ) -> Result<CursorPage<Product>, sqlx::Error> {
+        let mut builder = QueryBuilder::<Postgres>::new(
+            "SELECT id, tenant_id, product_id, supplier_id, name, description, category, price, unit, quantity, available, low_stock_threshold, sku, variants, created_at, updated_at, deleted_at FROM products WHERE deleted_at IS NULL",
+        );
+        page.push_filters(&mut builder);
+        page.push_order_and_limit(&mut builder);
+
+        let rows = builder.build_query_as::<Product>().fetch_all(tx.executor()).await?;
 
-        Ok(rows)
+        Ok(page.into_page(rows))
     }
 
     /// Creates products in bulk and emits product.created events.
     pub async fn bulk_create(
         &self,
-        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        tx: &mut TenantTx<'_>,
         items: &[CreateProductRequest],
     ) -> Result<Vec<Product>, sqlx::Error> {
         let mut created = Vec::with_capacity(items.len());
@@ -211,7 +192,7 @@ impl ProductRepo {
             .bind(it.low_stock_threshold.unwrap_or(10))
             .bind(&it.sku)
             .bind(&it.variants)
-            .fetch_one(&mut **tx)
+            .fetch_one(tx.executor())
             .await?;
 
             created.push(p);
@@ -223,7 +204,7 @@ impl ProductRepo {
     /// Stores uploaded asset metadata for a product.
     pub async fn register_product_asset(
         &self,
-        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        tx: &mut TenantTx<'_>,
         supplier_id: Uuid,
         product_id: Uuid,
         req: &RegisterProductAssetRequest,
@@ -239,7 +220,7 @@ impl ProductRepo {
         )
         .bind(supplier_id)
         .bind(product_id)
-        .fetch_optional(&mut **tx)
+        .fetch_optional(tx.executor())
         .await?;
 
         if exists.is_none() {
@@ -255,7 +236,7 @@ impl ProductRepo {
             )
             .bind(supplier_id)
             .bind(product_id)
-            .execute(&mut **tx)
+            .execute(tx.executor())
             .await?;
         }
 
@@ -285,7 +266,7 @@ impl ProductRepo {
         .bind(req.format.as_deref())
         .bind(req.alt_text.as_deref())
         .bind(is_primary)
-        .fetch_one(&mut **tx)
+        .fetch_one(tx.executor())
         .await?;
 
         Ok(asset)
@@ -294,7 +275,7 @@ impl ProductRepo {
     /// Lists stored asset metadata for a product.
     pub async fn list_product_assets(
         &self,
-        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        tx: &mut TenantTx<'_>,
         supplier_id: Uuid,
         product_id: Uuid,
     ) -> Result<Vec<ProductAsset>, sqlx::Error> {
@@ -309,14 +290,14 @@ impl ProductRepo {
         )
         .bind(supplier_id)
         .bind(product_id)
-        .fetch_all(&mut **tx)
+        .fetch_all(tx.executor())
         .await
     }
 
     /// Deletes product asset metadata by asset id.
     pub async fn delete_product_asset(
         &self,
-        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        tx: &mut TenantTx<'_>,
         supplier_id: Uuid,
         product_id: Uuid,
         asset_id: Uuid,
@@ -330,7 +311,7 @@ impl ProductRepo {
         .bind(supplier_id)
         .bind(product_id)
         .bind(asset_id)
-        .execute(&mut **tx)
+        .execute(tx.executor())
         .await?;
 
         Ok(result.rows_affected())
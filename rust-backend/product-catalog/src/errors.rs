@@ -1 +0,0 @@
-// Product Catalog specific errors
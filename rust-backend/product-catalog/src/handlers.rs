@@ -1,13 +1,17 @@
 use crate::db::ProductRepo;
 use crate::models::{
-    BulkCreateRequest, CreateProductRequest, ProductEvent, RegisterProductAssetRequest,
+    BulkCreateRequest, CreateProductRequest, Product, RegisterProductAssetRequest,
     SignAssetUploadRequest, UpdateProductRequest, SignedUploadResponse,
 };
-use crate::rabbit_pub::publish_example_event;
+use crate::rabbit_pub::RabbitPublisher;
 use crate::redis_pub::RedisPublisher;
 use crate::storage::StorageProvider;
-use actix_web::{HttpResponse, Responder, web};
+use actix_web::{HttpRequest, HttpResponse, Responder, ResponseError, web};
+use chrono::Utc;
+use platform::errors::AppError;
 use platform::db_router::DynamicPoolRouter;
+use platform::events::{DomainEvent, ProductEvent};
+use platform::pagination::CursorSigner;
 use platform::tenant::TenantContext;
 use redis::AsyncCommands;
 use std::collections::HashMap;
@@ -29,19 +33,24 @@ pub async fn create_product(
     db_router: web::Data<DynamicPoolRouter>,
     repo: web::Data<ProductRepo>,
     redis_pub: web::Data<RedisPublisher>,
+    rabbit_pub: web::Data<RabbitPublisher>,
     redis_client: web::Data<redis::Client>,
     req: web::Json<CreateProductRequest>,
 ) -> impl Responder {
-    let pool = db_router.get_pool(&tenant).await.unwrap();
-    let mut tx = pool.begin().await.unwrap();
-    tenant.apply_rls(&mut *tx).await.unwrap();
+    let pool = match db_router.get_pool(&tenant).await {
+        Ok(pool) => pool,
+        Err(e) => return DynamicPoolRouter::unavailable(e).error_response(),
+    };
+    let mut tx = match tenant.begin(&pool).await {
+        Ok(tx) => tx,
+        Err(e) => return AppError::from(e).error_response(),
+    };
 
     match repo.create_product(&mut tx, &req).await {
         Ok(product) => {
             tx.commit().await.unwrap();
-            let event = ProductEvent {
+            let event = DomainEvent::ProductCreated(ProductEvent {
                 tenant_id: tenant.tenant_id,
-                event_type: "product.created".to_string(),
                 product_id: product.product_id,
                 supplier_id: product.supplier_id,
                 price: Some(product.price),
@@ -52,10 +61,11 @@ pub async fn create_product(
                 low_stock_threshold: Some(product.low_stock_threshold),
                 unit: Some(product.unit.clone()),
                 quantity_change: None,
+                timestamp: Utc::now(),
                 ..Default::default()
-            };
-            redis_pub.publish_async("product.created", event.clone());
-            if let Err(e) = publish_example_event(&event).await {
+            });
+            redis_pub.publish_async(event.clone());
+            if let Err(e) = rabbit_pub.publish(&event).await {
                 eprintln!("Rabbit publish error (product.created): {:?}", e);
             }
 
@@ -66,10 +76,7 @@ pub async fn create_product(
 
             HttpResponse::Created().json(product)
         }
-        Err(e) => {
-            eprintln!("Create product DB error: {:?}", e);
-            HttpResponse::InternalServerError().body("Failed to create product")
-        }
+        Err(e) => AppError::from(e).error_response(),
     }
 }
 
@@ -103,16 +110,20 @@ pub async fn get_products_for_supplier(
         }
     }
 
-    let pool = db_router.get_pool(&tenant).await.unwrap();
-    let mut tx = pool.begin().await.unwrap();
-    tenant.apply_rls(&mut *tx).await.unwrap();
+    let pool = match db_router.get_pool(&tenant).await {
+        Ok(pool) => pool,
+        Err(e) => return DynamicPoolRouter::unavailable(e).error_response(),
+    };
+    let mut tx = match tenant.begin(&pool).await {
+        Ok(tx) => tx,
+        Err(e) => return AppError::from(e).error_response(),
+    };
 
     match repo.get_by_supplier(&mut tx, supplier_id).await {
         Ok(items) => {
             for item in &items {
-                let event = ProductEvent {
+                let event = DomainEvent::ProductViewed(ProductEvent {
                     tenant_id: tenant.tenant_id,
-                    event_type: "product.viewed".to_string(),
                     product_id: item.product_id,
                     supplier_id: item.supplier_id,
                     price: Some(item.price),
@@ -123,9 +134,10 @@ pub async fn get_products_for_supplier(
                     low_stock_threshold: Some(item.low_stock_threshold),
                     unit: Some(item.unit.clone()),
                     quantity_change: None,
+                    timestamp: Utc::now(),
                     ..Default::default()
-                };
-                redis_pub.publish_async("product.viewed", event.clone());
+                });
+                redis_pub.publish_async(event);
             }
 
             if let Ok(mut conn) = redis_client.get_multiplexed_async_connection().await {
@@ -136,10 +148,7 @@ pub async fn get_products_for_supplier(
 
             HttpResponse::Ok().json(&items)
         }
-        Err(e) => {
-            eprintln!("Get products DB error: {:?}", e);
-            HttpResponse::InternalServerError().body("DB error")
-        }
+        Err(e) => AppError::from(e).error_response(),
     }
 }
 
@@ -166,17 +175,19 @@ pub async fn get_single_product(
 ) -> impl Responder {
     let (supplier_id, product_id) = path.into_inner();
 
-    let pool = db_router.get_pool(&tenant).await.unwrap();
-    let mut tx = pool.begin().await.unwrap();
-    tenant.apply_rls(&mut *tx).await.unwrap();
+    let pool = match db_router.get_pool(&tenant).await {
+        Ok(pool) => pool,
+        Err(e) => return DynamicPoolRouter::unavailable(e).error_response(),
+    };
+    let mut tx = match tenant.begin(&pool).await {
+        Ok(tx) => tx,
+        Err(e) => return AppError::from(e).error_response(),
+    };
 
     match repo.get_one(&mut tx, supplier_id, product_id).await {
         Ok(p) => HttpResponse::Ok().json(p),
-        Err(sqlx::Error::RowNotFound) => HttpResponse::NotFound().body("Not found"),
-        Err(e) => {
-            eprintln!("DB error: {:?}", e);
-            HttpResponse::InternalServerError().body("DB error")
-        }
+        Err(sqlx::Error::RowNotFound) => AppError::NotFound("Not found".into()).error_response(),
+        Err(e) => AppError::from(e).error_response(),
     }
 }
 
@@ -210,13 +221,17 @@ pub async fn update_product(
     let update_data = req.into_inner();
 
     if update_data.quantity.is_some() && update_data.quantity_change.is_some() {
-        return HttpResponse::BadRequest()
-            .body("Provide either quantity or quantity_change, not both");
+        return AppError::BadRequest("Provide either quantity or quantity_change, not both".into()).error_response();
     }
 
-    let pool = db_router.get_pool(&tenant).await.unwrap();
-    let mut tx = pool.begin().await.unwrap();
-    tenant.apply_rls(&mut *tx).await.unwrap();
+    let pool = match db_router.get_pool(&tenant).await {
+        Ok(pool) => pool,
+        Err(e) => return DynamicPoolRouter::unavailable(e).error_response(),
+    };
+    let mut tx = match tenant.begin(&pool).await {
+        Ok(tx) => tx,
+        Err(e) => return AppError::from(e).error_response(),
+    };
 
     match repo
         .update_product(&mut tx, supplier_id, product_id, &update_data)
@@ -224,9 +239,8 @@ pub async fn update_product(
     {
         Ok(p) => {
             tx.commit().await.unwrap();
-            let event = ProductEvent {
+            let event = DomainEvent::ProductUpdated(ProductEvent {
                 tenant_id: tenant.tenant_id,
-                event_type: "product.updated".to_string(),
                 product_id: p.product_id,
                 supplier_id: p.supplier_id,
                 name: Some(p.name.clone()),
@@ -238,9 +252,10 @@ pub async fn update_product(
                 unit: Some(p.unit.clone()),
                 quantity_change: update_data.quantity_change,
                 available: Some(p.available),
+                timestamp: Utc::now(),
                 ..Default::default()
-            };
-            redis_pub.publish_async("product.updated", event.clone());
+            });
+            redis_pub.publish_async(event);
 
             let cache_key = format!("products:supplier:{}", supplier_id);
             if let Ok(mut conn) = redis_client.get_multiplexed_async_connection().await {
@@ -249,11 +264,8 @@ pub async fn update_product(
 
             HttpResponse::Ok().json(p)
         }
-        Err(sqlx::Error::RowNotFound) => HttpResponse::NotFound().body("Not found"),
-        Err(e) => {
-            eprintln!("DB error updating product: {:?}", e);
-            HttpResponse::InternalServerError().body("DB error")
-        }
+        Err(sqlx::Error::RowNotFound) => AppError::NotFound("Not found".into()).error_response(),
+        Err(e) => AppError::from(e).error_response(),
     }
 }
 
@@ -282,21 +294,26 @@ pub async fn delete_product(
 ) -> impl Responder {
     let (supplier_id, product_id) = path.into_inner();
 
-    let pool = db_router.get_pool(&tenant).await.unwrap();
-    let mut tx = pool.begin().await.unwrap();
-    tenant.apply_rls(&mut *tx).await.unwrap();
+    let pool = match db_router.get_pool(&tenant).await {
+        Ok(pool) => pool,
+        Err(e) => return DynamicPoolRouter::unavailable(e).error_response(),
+    };
+    let mut tx = match tenant.begin(&pool).await {
+        Ok(tx) => tx,
+        Err(e) => return AppError::from(e).error_response(),
+    };
 
     match repo.delete_product(&mut tx, supplier_id, product_id).await {
         Ok(rows) if rows > 0 => {
             tx.commit().await.unwrap();
-            let event = ProductEvent {
+            let event = DomainEvent::ProductDeleted(ProductEvent {
                 tenant_id: tenant.tenant_id,
-                event_type: "product.deleted".to_string(),
                 product_id,
                 supplier_id,
+                timestamp: Utc::now(),
                 ..Default::default()
-            };
-            redis_pub.publish_async("product.deleted", event.clone());
+            });
+            redis_pub.publish_async(event);
 
             let cache_key = format!("products:supplier:{}", supplier_id);
             match redis_client.get_multiplexed_async_connection().await {
@@ -318,111 +335,53 @@ pub async fn delete_product(
 
             HttpResponse::Ok().body("Product deleted successfully")
         }
-        Ok(_) => HttpResponse::NotFound().body("Not found"),
-        Err(e) => {
-            eprintln!("DB error deleting product: {:?}", e);
-            HttpResponse::InternalServerError().body("DB error")
-        }
+        Ok(_) => AppError::NotFound("Not found".into()).error_response(),
+        Err(e) => AppError::from(e).error_response(),
     }
 }
 
-/// Searches products by optional query parameters.
+/// Searches products with cursor pagination, sorting and filters.
 #[utoipa::path(
     get,
     path = "/products/search",
     params(
-        ("category" = Option<String>, Query, description = "Category filter"),
-        ("min_price" = Option<f64>, Query, description = "Minimum price"),
-        ("max_price" = Option<f64>, Query, description = "Maximum price"),
-        ("supplier_id" = Option<Uuid>, Query, description = "Supplier UUID"),
-        ("product_id" = Option<Uuid>, Query, description = "Product UUID"),
-        ("limit" = Option<i64>, Query, description = "Max results (1-200)"),
-        ("offset" = Option<i64>, Query, description = "Pagination offset")
+        ("limit" = Option<u32>, Query, description = "Page size (1-200, default 50)"),
+        ("cursor" = Option<String>, Query, description = "next_cursor of the previous page"),
+        ("sort" = Option<String>, Query, description = "name, price, created_at or updated_at; prefix with - for descending"),
+        ("filter[category]" = Option<String>, Query, description = "Filters on category, supplier_id, product_id, quantity or price, e.g. filter[price][gte]=10")
     ),
     responses(
-        (status = 200, description = "Search results", body = Vec<Product>),
+        (status = 200, description = "One page of products with next_cursor and Link headers"),
         (status = 400, description = "Invalid query parameters"),
         (status = 500, description = "Database error")
     ),
     security(("BearerAuth" = []), ("ApiKeyAuth" = []))
 )]
 pub async fn search_products(
+    req: HttpRequest,
     tenant: web::ReqData<TenantContext>,
     db_router: web::Data<DynamicPoolRouter>,
     repo: web::Data<ProductRepo>,
+    signer: web::Data<CursorSigner>,
     query: web::Query<HashMap<String, String>>,
 ) -> impl Responder {
-    let category = query.get("category").cloned();
-
-    let min_price = match query.get("min_price") {
-        Some(v) => match v.parse::<f64>() {
-            Ok(parsed) => Some(parsed),
-            Err(_) => return HttpResponse::BadRequest().body("Invalid min_price"),
-        },
-        None => None,
-    };
-    let max_price = match query.get("max_price") {
-        Some(v) => match v.parse::<f64>() {
-            Ok(parsed) => Some(parsed),
-            Err(_) => return HttpResponse::BadRequest().body("Invalid max_price"),
-        },
-        None => None,
+    let page = match Product::list_spec().parse(&query, &signer) {
+        Ok(page) => page,
+        Err(e) => return AppError::from(e).error_response(),
     };
 
-    let supplier_id = match query.get("supplier_id") {
-        Some(v) => match Uuid::parse_str(v) {
-            Ok(id) => Some(id),
-            Err(_) => return HttpResponse::BadRequest().body("Invalid supplier_id"),
-        },
-        None => None,
+    let pool = match db_router.get_pool(&tenant).await {
+        Ok(pool) => pool,
+        Err(e) => return DynamicPoolRouter::unavailable(e).error_response(),
     };
-    let product_id = match query.get("product_id") {
-        Some(v) => match Uuid::parse_str(v) {
-            Ok(id) => Some(id),
-            Err(_) => return HttpResponse::BadRequest().body("Invalid product_id"),
-        },
-        None => None,
+    let mut tx = match tenant.begin(&pool).await {
+        Ok(tx) => tx,
+        Err(e) => return AppError::from(e).error_response(),
     };
 
-    let limit = match query.get("limit") {
-        Some(v) => match v.parse::<i64>() {
-            Ok(parsed) => parsed,
-            Err(_) => return HttpResponse::BadRequest().body("Invalid limit"),
-        },
-        None => 50,
-    }
-    .clamp(1, 200);
-    let offset = match query.get("offset") {
-        Some(v) => match v.parse::<i64>() {
-            Ok(parsed) => parsed,
-            Err(_) => return HttpResponse::BadRequest().body("Invalid offset"),
-        },
-        None => 0,
-    }
-    .max(0);
-
-    let pool = db_router.get_pool(&tenant).await.unwrap();
-    let mut tx = pool.begin().await.unwrap();
-    tenant.apply_rls(&mut *tx).await.unwrap();
-
-    match repo
-        .search_products(
-            &mut tx,
-            category,
-            min_price,
-            max_price,
-            product_id,
-            supplier_id,
-            limit,
-            offset,
-        )
-        .await
-    {
-        Ok(rows) => HttpResponse::Ok().json(rows),
-        Err(e) => {
-            eprintln!("Search DB error: {:?}", e);
-            HttpResponse::InternalServerError().body("Search error")
-        }
+    match repo.search_products(&mut tx, &page).await {
+        Ok(rows) => rows.respond(&req),
+        Err(e) => AppError::from(e).error_response(),
     }
 }
 
@@ -444,17 +403,21 @@ pub async fn bulk_create(
     redis_pub: web::Data<RedisPublisher>,
     req: web::Json<BulkCreateRequest>,
 ) -> impl Responder {
-    let pool = db_router.get_pool(&tenant).await.unwrap();
-    let mut tx = pool.begin().await.unwrap();
-    tenant.apply_rls(&mut *tx).await.unwrap();
+    let pool = match db_router.get_pool(&tenant).await {
+        Ok(pool) => pool,
+        Err(e) => return DynamicPoolRouter::unavailable(e).error_response(),
+    };
+    let mut tx = match tenant.begin(&pool).await {
+        Ok(tx) => tx,
+        Err(e) => return AppError::from(e).error_response(),
+    };
 
     match repo.bulk_create(&mut tx, &req.products).await {
         Ok(created) => {
             tx.commit().await.unwrap();
             for p in &created {
-                let event = ProductEvent {
+                let event = DomainEvent::ProductCreated(ProductEvent {
                     tenant_id: tenant.tenant_id,
-                    event_type: "product.created".to_string(),
                     product_id: p.product_id,
                     supplier_id: p.supplier_id,
                     name: Some(p.name.clone()),
@@ -465,16 +428,14 @@ pub async fn bulk_create(
                     low_stock_threshold: Some(p.low_stock_threshold),
                     unit: Some(p.unit.clone()),
                     quantity_change: None,
+                    timestamp: Utc::now(),
                     ..Default::default()
-                };
-                redis_pub.publish_async("product.created", event.clone());
+                });
+                redis_pub.publish_async(event);
             }
             HttpResponse::Created().json(created)
         }
-        Err(e) => {
-            eprintln!("Bulk create DB error: {:?}", e);
-            HttpResponse::InternalServerError().body("Bulk create failed")
-        }
+        Err(e) => AppError::from(e).error_response(),
     }
 }
 
@@ -503,9 +464,14 @@ pub async fn register_product_asset(
 ) -> impl Responder {
     let (supplier_id, product_id) = path.into_inner();
 
-    let pool = db_router.get_pool(&tenant).await.unwrap();
-    let mut tx = pool.begin().await.unwrap();
-    tenant.apply_rls(&mut *tx).await.unwrap();
+    let pool = match db_router.get_pool(&tenant).await {
+        Ok(pool) => pool,
+        Err(e) => return DynamicPoolRouter::unavailable(e).error_response(),
+    };
+    let mut tx = match tenant.begin(&pool).await {
+        Ok(tx) => tx,
+        Err(e) => return AppError::from(e).error_response(),
+    };
 
     match repo
         .register_product_asset(&mut tx, supplier_id, product_id, &req)
@@ -515,11 +481,8 @@ pub async fn register_product_asset(
             tx.commit().await.unwrap();
             HttpResponse::Created().json(asset)
         }
-        Err(sqlx::Error::RowNotFound) => HttpResponse::NotFound().body("Product not found"),
-        Err(e) => {
-            eprintln!("Register product asset DB error: {:?}", e);
-            HttpResponse::InternalServerError().body("Failed to register product asset")
-        }
+        Err(sqlx::Error::RowNotFound) => AppError::NotFound("Product not found".into()).error_response(),
+        Err(e) => AppError::from(e).error_response(),
     }
 }
 
@@ -545,16 +508,18 @@ pub async fn list_product_assets(
 ) -> impl Responder {
     let (supplier_id, product_id) = path.into_inner();
 
-    let pool = db_router.get_pool(&tenant).await.unwrap();
-    let mut tx = pool.begin().await.unwrap();
-    tenant.apply_rls(&mut *tx).await.unwrap();
+    let pool = match db_router.get_pool(&tenant).await {
+        Ok(pool) => pool,
+        Err(e) => return DynamicPoolRouter::unavailable(e).error_response(),
+    };
+    let mut tx = match tenant.begin(&pool).await {
+        Ok(tx) => tx,
+        Err(e) => return AppError::from(e).error_response(),
+    };
 
     match repo.list_product_assets(&mut tx, supplier_id, product_id).await {
         Ok(assets) => HttpResponse::Ok().json(assets),
-        Err(e) => {
-            eprintln!("List product assets DB error: {:?}", e);
-            HttpResponse::InternalServerError().body("Failed to list product assets")
-        }
+        Err(e) => AppError::from(e).error_response(),
     }
 }
 
@@ -582,23 +547,25 @@ pub async fn delete_product_asset(
 ) -> impl Responder {
     let (supplier_id, product_id, asset_id) = path.into_inner();
 
-    let pool = db_router.get_pool(&tenant).await.unwrap();
-    let mut tx = pool.begin().await.unwrap();
-    tenant.apply_rls(&mut *tx).await.unwrap();
+    let pool = match db_router.get_pool(&tenant).await {
+        Ok(pool) => pool,
+        Err(e) => return DynamicPoolRouter::unavailable(e).error_response(),
+    };
+    let mut tx = match tenant.begin(&pool).await {
+        Ok(tx) => tx,
+        Err(e) => return AppError::from(e).error_response(),
+    };
 
     match repo
         .delete_product_asset(&mut tx, supplier_id, product_id, asset_id)
         .await
     {
-        Ok(0) => HttpResponse::NotFound().body("Asset not found"),
+        Ok(0) => AppError::NotFound("Asset not found".into()).error_response(),
         Ok(_) => {
             tx.commit().await.unwrap();
             HttpResponse::Ok().body("Asset deleted")
         }
-        Err(e) => {
-            eprintln!("Delete product asset DB error: {:?}", e);
-            HttpResponse::InternalServerError().body("Failed to delete product asset")
-        }
+        Err(e) => AppError::from(e).error_response(),
     }
 }
 
@@ -625,20 +592,20 @@ pub async fn sign_cloudinary_upload(
         .clone()
         .unwrap_or_else(|| "b2b-saas/products".to_string());
     if !folder.starts_with("b2b-saas/products") || folder.contains("..") {
-        return HttpResponse::BadRequest().body("Invalid folder");
+        return AppError::BadRequest("Invalid folder".into()).error_response();
     }
     if let Some(public_id) = &req.public_id {
         let valid = public_id
             .chars()
             .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '/');
         if !valid || public_id.contains("..") {
-            return HttpResponse::BadRequest().body("Invalid public_id");
+            return AppError::BadRequest("Invalid public_id".into()).error_response();
         }
     }
 
     match storage.sign_upload(&folder, req.public_id.as_deref()) {
         Ok(res) => HttpResponse::Ok().json(res),
-        Err(err) => HttpResponse::InternalServerError().body(err),
+        Err(err) => AppError::internal(err).error_response(),
     }
 }
 
@@ -1,4 +1,5 @@
 use chrono::{DateTime, Utc};
+use platform::pagination::{keyset_timestamp, FieldKind, Keyset, ListSpec, SortDirection};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use uuid::Uuid;
@@ -24,6 +25,37 @@ pub struct Product {
     pub deleted_at: Option<DateTime<Utc>>,
 }
 
+impl Product {
+    /// Sort and filter whitelist of `GET /products/search`.
+    pub fn list_spec() -> ListSpec {
+        ListSpec::new("name", SortDirection::Asc)
+            .sortable("name", FieldKind::Text)
+            .sortable("price", FieldKind::Float)
+            .sortable("created_at", FieldKind::Timestamp)
+            .sortable("updated_at", FieldKind::Timestamp)
+            .filterable("category", FieldKind::Text)
+            .filterable("supplier_id", FieldKind::Uuid)
+            .filterable("product_id", FieldKind::Uuid)
+            .filterable("quantity", FieldKind::Integer)
+    }
+}
+
+impl Keyset for Product {
+    fn keyset_id(&self) -> Uuid {
+        self.id
+    }
+
+    fn keyset_value(&self, field: &str) -> Option<String> {
+        match field {
+            "name" => Some(self.name.clone()),
+            "price" => Some(self.price.to_string()),
+            "created_at" => Some(keyset_timestamp(&self.created_at)),
+            "updated_at" => Some(keyset_timestamp(&self.updated_at)),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, FromRow, utoipa::ToSchema)]
 pub struct ProductAsset {
     pub id: Uuid,
@@ -111,40 +143,3 @@ pub struct SignedUploadResponse {
     pub folder: String,
     pub public_id: Option<String>,
 }
-
-#[derive(Serialize, Deserialize, Debug, Clone, Default)]
-#[serde(default)]
-pub struct ProductEvent {
-    pub tenant_id: Uuid,
-    pub event_type: String,
-    pub product_id: Uuid,
-    pub supplier_id: Uuid,
-    pub name: Option<String>,
-    pub description: Option<serde_json::Value>,
-    pub price: Option<f64>,
-    pub category: Option<String>,
-    pub low_stock_threshold: Option<i32>,
-    pub unit: Option<String>,
-    pub quantity_change: Option<i32>,
-    pub available: Option<bool>,
-    // Order-related
-    pub order_id: Option<Uuid>,
-    pub quantity: Option<i32>,
-    pub reservation_id: Option<Uuid>,
-    pub timestamp: Option<DateTime<Utc>>,
-    pub expires_at: Option<DateTime<Utc>>,
-    pub user_id: Option<Uuid>,
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_product_event_default() {
-        let event = ProductEvent::default();
-        assert_eq!(event.event_type, "");
-        assert_eq!(event.name, None);
-        assert_eq!(event.quantity_change, None);
-    }
-}
@@ -1,4 +1,5 @@
 use crate::models::{CreateSupplierRequest, Supplier, SupplierStatus, UpdateSupplierRequest};
+use platform::tenant::TenantTx;
 use uuid::Uuid;
 
 #[derive(Clone)]
@@ -9,7 +10,7 @@ impl SupplierRepo {
         Self {}
     }
 
-    pub async fn create(&self, tx: &mut sqlx::Transaction<'_, sqlx::Postgres>, req: &CreateSupplierRequest) -> Result<Supplier, sqlx::Error> {
+    pub async fn create(&self, tx: &mut TenantTx<'_>, req: &CreateSupplierRequest) -> Result<Supplier, sqlx::Error> {
         sqlx::query_as::<_, Supplier>(
             r#"
             INSERT INTO suppliers (owner_user_id, legal_name, display_name, tax_id, country, metadata, platform_fee_percent)
@@ -24,29 +25,29 @@ impl SupplierRepo {
         .bind(&req.country)
         .bind(req.metadata.as_ref())
         .bind(req.platform_fee_percent.unwrap_or(5.0))
-        .fetch_one(&mut **tx)
+        .fetch_one(tx.executor())
         .await
     }
 
-    pub async fn get(&self, tx: &mut sqlx::Transaction<'_, sqlx::Postgres>, id: Uuid) -> Result<Supplier, sqlx::Error> {
+    pub async fn get(&self, tx: &mut TenantTx<'_>, id: Uuid) -> Result<Supplier, sqlx::Error> {
         sqlx::query_as::<_, Supplier>("SELECT * FROM suppliers WHERE id = $1")
             .bind(id)
-            .fetch_one(&mut **tx)
+            .fetch_one(tx.executor())
             .await
     }
 
-    pub async fn list_by_owner(&self, tx: &mut sqlx::Transaction<'_, sqlx::Postgres>, owner_user_id: Uuid) -> Result<Vec<Supplier>, sqlx::Error> {
+    pub async fn list_by_owner(&self, tx: &mut TenantTx<'_>, owner_user_id: Uuid) -> Result<Vec<Supplier>, sqlx::Error> {
         sqlx::query_as::<_, Supplier>(
             "SELECT * FROM suppliers WHERE owner_user_id = $1 ORDER BY created_at DESC",
         )
         .bind(owner_user_id)
-        .fetch_all(&mut **tx)
+        .fetch_all(tx.executor())
         .await
     }
 
     pub async fn update_status(
         &self,
-        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        tx: &mut TenantTx<'_>,
         id: Uuid,
         owner_user_id: Uuid,
         status: SupplierStatus,
@@ -57,13 +58,13 @@ impl SupplierRepo {
         .bind(status)
         .bind(id)
         .bind(owner_user_id)
-        .fetch_one(&mut **tx)
+        .fetch_one(tx.executor())
         .await
     }
 
     pub async fn update_supplier(
         &self,
-        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        tx: &mut TenantTx<'_>,
         id: Uuid,
         owner_user_id: Uuid,
         req: &UpdateSupplierRequest,
@@ -91,7 +92,7 @@ impl SupplierRepo {
         .bind(req.metadata.as_ref())
         .bind(id)
         .bind(owner_user_id)
-        .fetch_one(&mut **tx)
+        .fetch_one(tx.executor())
         .await
     }
 }
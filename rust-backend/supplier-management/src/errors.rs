@@ -1 +0,0 @@
-// Supplier Management specific errors
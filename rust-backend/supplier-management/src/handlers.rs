@@ -1,13 +1,15 @@
-use actix_web::{web, HttpResponse, Responder, HttpRequest};
+use actix_web::{web, HttpResponse, Responder, ResponseError, HttpRequest};
 use actix_web::web::ReqData;
 use chrono::Utc;
-use platform::streams::StreamPublisher;
-use platform::tenant::TenantContext;
+use platform::errors::AppError;
+use platform::events::{DomainEvent, SupplierEvent};
+use platform::outbox;
+use platform::tenant::{TenantContext, TenantTx};
 use platform::db_router::DynamicPoolRouter;
 use uuid::Uuid;
 
 use crate::db::SupplierRepo;
-use crate::models::{CreateSupplierRequest, Supplier, SupplierEvent, UpdateSupplierStatusRequest, UpdateSupplierRequest};
+use crate::models::{CreateSupplierRequest, Supplier, UpdateSupplierStatusRequest, UpdateSupplierRequest};
 
 #[utoipa::path(
     post,
@@ -21,28 +23,26 @@ pub async fn create_supplier(
     tenant: ReqData<TenantContext>,
     db_router: web::Data<DynamicPoolRouter>,
     repo: web::Data<SupplierRepo>,
-    publisher: web::Data<StreamPublisher>,
     req: web::Json<CreateSupplierRequest>,
 ) -> impl Responder {
     let pool = match db_router.get_pool(&tenant).await {
         Ok(p) => p,
-        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+        Err(e) => return AppError::from(e).error_response(),
     };
-    let mut tx = match pool.begin().await {
+    let mut tx = match tenant.begin(&pool).await {
         Ok(t) => t,
-        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+        Err(e) => return AppError::from(e).error_response(),
     };
-    if let Err(e) = tenant.apply_rls(&mut *tx).await {
-        return HttpResponse::InternalServerError().body(e.to_string());
-    }
 
     match repo.create(&mut tx, &req).await {
         Ok(supplier) => {
+            if let Err(e) = enqueue_supplier_event(&mut tx, tenant.tenant_id, DomainEvent::SupplierCreated, &supplier).await {
+                return AppError::from(e).error_response();
+            }
             tx.commit().await.unwrap();
-            publish_supplier_event(tenant.tenant_id, &publisher, "supplier.created", &supplier);
             HttpResponse::Created().json(supplier)
         }
-        Err(e) => HttpResponse::InternalServerError().body(format!("db error: {e}")),
+        Err(e) => AppError::from(e).error_response(),
     }
 }
 
@@ -64,23 +64,20 @@ pub async fn get_supplier(
 ) -> impl Responder {
     let pool = match db_router.get_pool(&tenant).await {
         Ok(p) => p,
-        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+        Err(e) => return AppError::from(e).error_response(),
     };
-    let mut tx = match pool.begin().await {
+    let mut tx = match tenant.begin(&pool).await {
         Ok(t) => t,
-        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+        Err(e) => return AppError::from(e).error_response(),
     };
-    if let Err(e) = tenant.apply_rls(&mut *tx).await {
-        return HttpResponse::InternalServerError().body(e.to_string());
-    }
 
     match repo.get(&mut tx, path.into_inner()).await {
         Ok(supplier) => {
             tx.commit().await.unwrap();
             HttpResponse::Ok().json(supplier)
         },
-        Err(sqlx::Error::RowNotFound) => HttpResponse::NotFound().body("supplier not found"),
-        Err(e) => HttpResponse::InternalServerError().body(format!("db error: {e}")),
+        Err(sqlx::Error::RowNotFound) => AppError::NotFound("supplier not found".into()).error_response(),
+        Err(e) => AppError::from(e).error_response(),
     }
 }
 
@@ -102,22 +99,19 @@ pub async fn list_owner_suppliers(
 ) -> impl Responder {
     let pool = match db_router.get_pool(&tenant).await {
         Ok(p) => p,
-        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+        Err(e) => return AppError::from(e).error_response(),
     };
-    let mut tx = match pool.begin().await {
+    let mut tx = match tenant.begin(&pool).await {
         Ok(t) => t,
-        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+        Err(e) => return AppError::from(e).error_response(),
     };
-    if let Err(e) = tenant.apply_rls(&mut *tx).await {
-        return HttpResponse::InternalServerError().body(e.to_string());
-    }
 
     match repo.list_by_owner(&mut tx, path.into_inner()).await {
         Ok(suppliers) => {
             tx.commit().await.unwrap();
             HttpResponse::Ok().json(suppliers)
         },
-        Err(e) => HttpResponse::InternalServerError().body(format!("db error: {e}")),
+        Err(e) => AppError::from(e).error_response(),
     }
 }
 
@@ -136,7 +130,6 @@ pub async fn update_supplier_status(
     tenant: ReqData<TenantContext>,
     db_router: web::Data<DynamicPoolRouter>,
     repo: web::Data<SupplierRepo>,
-    publisher: web::Data<StreamPublisher>,
     path: web::Path<Uuid>,
     req: web::Json<UpdateSupplierStatusRequest>,
     http_req: HttpRequest,
@@ -144,50 +137,55 @@ pub async fn update_supplier_status(
     let owner_user_id = match http_req.headers().get("X-User-Id") {
         Some(h) => match Uuid::parse_str(h.to_str().unwrap_or("")) {
             Ok(uuid) => uuid,
-            Err(_) => return HttpResponse::BadRequest().body("Invalid X-User-Id header"),
+            Err(_) => return AppError::BadRequest("Invalid X-User-Id header".into()).error_response(),
         },
-        None => return HttpResponse::Unauthorized().body("Missing X-User-Id header"),
+        None => return AppError::Unauthorized("Missing X-User-Id header".into()).error_response(),
     };
 
     let pool = match db_router.get_pool(&tenant).await {
         Ok(p) => p,
-        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+        Err(e) => return AppError::from(e).error_response(),
     };
-    let mut tx = match pool.begin().await {
+    let mut tx = match tenant.begin(&pool).await {
         Ok(t) => t,
-        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+        Err(e) => return AppError::from(e).error_response(),
     };
-    if let Err(e) = tenant.apply_rls(&mut *tx).await {
-        return HttpResponse::InternalServerError().body(e.to_string());
-    }
 
     match repo
         .update_status(&mut tx, path.into_inner(), owner_user_id, req.status.clone())
         .await
     {
         Ok(supplier) => {
+            if let Err(e) = enqueue_supplier_event(&mut tx, tenant.tenant_id, DomainEvent::SupplierStatusUpdated, &supplier).await {
+                return AppError::from(e).error_response();
+            }
             tx.commit().await.unwrap();
-            publish_supplier_event(tenant.tenant_id, &publisher, "supplier.status_updated", &supplier);
             HttpResponse::Ok().json(supplier)
         }
-        Err(sqlx::Error::RowNotFound) => HttpResponse::NotFound().body("supplier not found"),
-        Err(e) => HttpResponse::InternalServerError().body(format!("db error: {e}")),
+        Err(sqlx::Error::RowNotFound) => AppError::NotFound("supplier not found".into()).error_response(),
+        Err(e) => AppError::from(e).error_response(),
     }
 }
 
-fn publish_supplier_event(tenant_id: Uuid, publisher: &StreamPublisher, event_type: &str, supplier: &Supplier) {
-    publisher.publish_async(
-        event_type,
-        SupplierEvent {
+async fn enqueue_supplier_event(
+    tx: &mut TenantTx<'_>,
+    tenant_id: Uuid,
+    event: fn(SupplierEvent) -> DomainEvent,
+    supplier: &Supplier,
+) -> Result<(), outbox::OutboxError> {
+    outbox::enqueue_event(
+        tx.executor(),
+        &event(SupplierEvent {
             tenant_id,
-            event_type: event_type.to_string(),
             supplier_id: supplier.id,
             user_id: supplier.owner_user_id,
             owner_user_id: supplier.owner_user_id,
-            status: supplier.status.clone(),
+            status: supplier.status.as_str().to_string(),
             timestamp: Utc::now(),
-        },
-    );
+        }),
+    )
+    .await?;
+    Ok(())
 }
 
 #[utoipa::path(
@@ -227,7 +225,6 @@ pub async fn update_supplier(
     tenant: ReqData<TenantContext>,
     db_router: web::Data<DynamicPoolRouter>,
     repo: web::Data<SupplierRepo>,
-    publisher: web::Data<StreamPublisher>,
     path: web::Path<Uuid>,
     req: web::Json<UpdateSupplierRequest>,
     http_req: HttpRequest,
@@ -235,34 +232,33 @@ pub async fn update_supplier(
     let owner_user_id = match http_req.headers().get("X-User-Id") {
         Some(h) => match Uuid::parse_str(h.to_str().unwrap_or("")) {
             Ok(uuid) => uuid,
-            Err(_) => return HttpResponse::BadRequest().body("Invalid X-User-Id header"),
+            Err(_) => return AppError::BadRequest("Invalid X-User-Id header".into()).error_response(),
         },
-        None => return HttpResponse::Unauthorized().body("Missing X-User-Id header"),
+        None => return AppError::Unauthorized("Missing X-User-Id header".into()).error_response(),
     };
 
     let pool = match db_router.get_pool(&tenant).await {
         Ok(p) => p,
-        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+        Err(e) => return AppError::from(e).error_response(),
     };
-    let mut tx = match pool.begin().await {
+    let mut tx = match tenant.begin(&pool).await {
         Ok(t) => t,
-        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+        Err(e) => return AppError::from(e).error_response(),
     };
-    if let Err(e) = tenant.apply_rls(&mut *tx).await {
-        return HttpResponse::InternalServerError().body(e.to_string());
-    }
 
     match repo
         .update_supplier(&mut tx, path.into_inner(), owner_user_id, &req)
         .await
     {
         Ok(supplier) => {
+            if let Err(e) = enqueue_supplier_event(&mut tx, tenant.tenant_id, DomainEvent::SupplierUpdated, &supplier).await {
+                return AppError::from(e).error_response();
+            }
             tx.commit().await.unwrap();
-            publish_supplier_event(tenant.tenant_id, &publisher, "supplier.updated", &supplier);
             HttpResponse::Ok().json(supplier)
         }
-        Err(sqlx::Error::RowNotFound) => HttpResponse::NotFound().body("supplier not found or not owned by user"),
-        Err(e) => HttpResponse::InternalServerError().body(format!("db error: {e}")),
+        Err(sqlx::Error::RowNotFound) => AppError::NotFound("supplier not found or not owned by user".into()).error_response(),
+        Err(e) => AppError::from(e).error_response(),
     }
 }
 
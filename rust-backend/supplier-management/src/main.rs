@@ -3,13 +3,17 @@ mod handlers;
 mod models;
 
 use actix_web::{web, App, HttpServer};
-use dotenvy::dotenv;
+use platform::config::{Kind, Schema, ServiceConfig, Setting};
 use platform::db_router::DynamicPoolRouter;
+use platform::health::{self, Readiness};
+use platform::middleware::request_id::RequestId;
 use platform::middleware::tenant_middleware::TenantAuthMiddleware;
-use platform::{metrics, observability, streams::StreamPublisher};
+use platform::middleware::{JwksVerifier, MetricsMiddleware, RateLimiter, RequireScope};
+use platform::tenant_directory::TenantDirectory;
+use platform::{metrics, observability, outbox::OutboxRelay, scopes, streams::StreamPublisher, supervisor::Supervisor};
 use redis::Client;
 use sqlx::postgres::PgPoolOptions;
-use std::env;
+use sqlx::migrate::Migrator;
 use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
 
@@ -38,63 +42,97 @@ use crate::db::SupplierRepo;
 )]
 struct ApiDoc;
 
+static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    dotenv().ok();
+    let config = ServiceConfig::load(
+        Schema::service("supplier-management", 3011)
+            .with(Setting::required("REDIS_URL", Kind::Url).with_default("redis://127.0.0.1:6379"))
+            .with_jwt(),
+    );
     observability::init_observability("supplier-management");
     metrics::init_metrics("supplier-management");
+    config.settings.log();
 
-    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
-    let redis_url_str = env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1:6379".to_string());
-    let redis_client = Client::open(redis_url_str.as_str()).expect("invalid REDIS_URL");
-    let port = env::var("SERVICE_PORT")
-        .or_else(|_| env::var("PORT"))
-        .unwrap_or_else(|_| "3011".to_string());
+    let redis_url_str = config.settings.text("REDIS_URL");
+    let redis_client = Client::open(redis_url_str).expect("invalid REDIS_URL");
+    let port = config.service_port;
 
     let pool = PgPoolOptions::new()
         .max_connections(10)
-        .connect(&database_url)
+        .connect(&config.database_url)
         .await
         .expect("failed to connect postgres");
-    sqlx::migrate!("./migrations")
+    MIGRATOR
         .run(&pool)
         .await
         .expect("migrations failed");
 
     let repo = web::Data::new(SupplierRepo::new());
-    let db_router = web::Data::new(DynamicPoolRouter::new(pool.clone()));
-    let publisher = web::Data::new(
-        StreamPublisher::new(&redis_url_str).unwrap_or_else(|_| StreamPublisher::noop()),
+    // The outbox relay finds dedicated tenant databases through the tenant directory
+    let db_router = web::Data::new(
+        DynamicPoolRouter::new(pool.clone())
+            .with_migrator(&MIGRATOR)
+            .with_tenant_directory(TenantDirectory::from_env().with_redis(redis_client.clone())),
+    );
+    db_router.spawn_eviction();
+    let publisher = StreamPublisher::new(redis_url_str).unwrap_or_else(|_| StreamPublisher::noop());
+    let supervisor = Supervisor::new("supplier-management").with_deadline(config.shutdown_timeout);
+    OutboxRelay::new("supplier-management", pool.clone(), publisher)
+        .with_router(db_router.get_ref().clone())
+        .supervise(&supervisor);
+
+    let readiness = web::Data::new(
+        Readiness::new("supplier-management")
+            .with_postgres(pool.clone())
+            .with_redis(redis_client.clone())
+            .with_supervisor(supervisor.clone()),
     );
 
-    HttpServer::new(move || {
+    let jwt_verifier = JwksVerifier::from_env().expect("JWT verification keys must be configured (JWKS_URL)");
+
+    let server = HttpServer::new(move || {
         App::new()
-            .service(
-                SwaggerUi::new("/swagger-ui/{_:.*}")
-                    .url("/api-docs/openapi.json", ApiDoc::openapi())
-            )
-            .wrap(TenantAuthMiddleware::with_redis(redis_client.clone()))
+            .wrap(MetricsMiddleware)
+            .wrap(RequestId)
             .app_data(db_router.clone())
             .app_data(repo.clone())
-            .app_data(publisher.clone())
-            .route("/health", web::get().to(handlers::health))
-            .route("/metrics", web::get().to(metrics::metrics_handler))
-            .route("/suppliers", web::post().to(handlers::create_supplier))
-            .route("/suppliers/{id}", web::get().to(handlers::get_supplier))
-            .route(
-                "/suppliers/owner/{owner_user_id}",
-                web::get().to(handlers::list_owner_suppliers),
-            )
-            .route(
-                "/suppliers/{id}",
-                web::put().to(handlers::update_supplier),
-            )
-            .route(
-                "/suppliers/{id}/status",
-                web::put().to(handlers::update_supplier_status),
+            .app_data(readiness.clone())
+            .route("/health/live", web::get().to(health::health_check))
+            .route("/health/ready", web::get().to(health::readiness))
+            .service(
+                web::scope("")
+                    .wrap(RequireScope::resource(scopes::SUPPLIERS))
+                    .wrap(RateLimiter::new(redis_client.clone()))
+                    .wrap(TenantAuthMiddleware::with_redis(redis_client.clone()).with_jwt_verifier(jwt_verifier.clone()))
+                    .service(
+                        SwaggerUi::new("/swagger-ui/{_:.*}")
+                            .url("/api-docs/openapi.json", ApiDoc::openapi())
+                    )
+                    .route("/health", web::get().to(handlers::health))
+                    .route("/metrics", web::get().to(metrics::metrics_handler))
+                    .route("/health/db", web::get().to(health::tenant_db_health))
+                    .route("/suppliers", web::post().to(handlers::create_supplier))
+                    .route("/suppliers/{id}", web::get().to(handlers::get_supplier))
+                    .route(
+                        "/suppliers/owner/{owner_user_id}",
+                        web::get().to(handlers::list_owner_suppliers),
+                    )
+                    .route(
+                        "/suppliers/{id}",
+                        web::put().to(handlers::update_supplier),
+                    )
+                    .route(
+                        "/suppliers/{id}/status",
+                        web::put().to(handlers::update_supplier_status),
+                    ),
             )
     })
     .bind(format!("0.0.0.0:{port}"))?
-    .run()
-    .await
+    .disable_signals()
+    .shutdown_timeout(supervisor.deadline().as_secs())
+    .run();
+
+    supervisor.serve(server).await
 }
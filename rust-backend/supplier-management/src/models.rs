@@ -14,6 +14,17 @@ pub enum SupplierStatus {
     Rejected,
 }
 
+impl SupplierStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SupplierStatus::Pending => "pending",
+            SupplierStatus::Active => "active",
+            SupplierStatus::Suspended => "suspended",
+            SupplierStatus::Rejected => "rejected",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow, utoipa::ToSchema)]
 pub struct Supplier {
     pub id: Uuid,
@@ -57,17 +68,6 @@ pub struct UpdateSupplierRequest {
     pub metadata: Option<Value>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct SupplierEvent {
-    pub tenant_id: Uuid,
-    pub event_type: String,
-    pub supplier_id: Uuid,
-    pub user_id: Uuid,
-    pub owner_user_id: Uuid,
-    pub status: SupplierStatus,
-    pub timestamp: DateTime<Utc>,
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -83,19 +83,14 @@ mod tests {
     }
 
     #[test]
-    fn test_supplier_event_serialization() {
-        let event = SupplierEvent {
-            tenant_id: Uuid::nil(),
-            event_type: "supplier.created".to_string(),
-            supplier_id: Uuid::nil(),
-            user_id: Uuid::nil(),
-            owner_user_id: Uuid::nil(),
-            status: SupplierStatus::Pending,
-            timestamp: Utc::now(),
-        };
-
-        let json_val = serde_json::to_value(&event).unwrap();
-        assert_eq!(json_val["status"], "pending");
-        assert_eq!(json_val["event_type"], "supplier.created");
+    fn test_supplier_status_as_str_matches_serde() {
+        for status in [
+            SupplierStatus::Pending,
+            SupplierStatus::Active,
+            SupplierStatus::Suspended,
+            SupplierStatus::Rejected,
+        ] {
+            assert_eq!(serde_json::to_value(&status).unwrap(), status.as_str());
+        }
     }
 }
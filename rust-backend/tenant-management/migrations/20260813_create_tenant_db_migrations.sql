@@ -0,0 +1,21 @@
+-- Moves of a tenant's rows from the shared database to a dedicated one
+ALTER TABLE tenants ADD COLUMN IF NOT EXISTS read_only BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE TABLE tenant_db_migrations (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    tenant_id UUID NOT NULL REFERENCES tenants(id) ON DELETE CASCADE,
+    target_db_url TEXT NOT NULL,
+    previous_db_url TEXT,
+    previous_tier VARCHAR(50) NOT NULL,
+    phase VARCHAR(20) NOT NULL DEFAULT 'pending',
+    tables JSONB NOT NULL DEFAULT '[]',
+    error TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    switched_at TIMESTAMPTZ,
+    finished_at TIMESTAMPTZ
+);
+
+-- At most one open migration per tenant; a failed one must be rolled back first
+CREATE UNIQUE INDEX idx_tenant_db_migrations_open ON tenant_db_migrations(tenant_id)
+    WHERE phase NOT IN ('rolled_back', 'completed');
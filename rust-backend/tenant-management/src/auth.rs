@@ -1,11 +1,11 @@
 use rand::Rng;
-use sha2::{Sha256, Digest};
 use bs58;
+use platform::api_keys::{hash_api_key, API_KEY_PREFIX_LEN};
 
 pub struct ApiKey {
     /// The full plaintext key — ONLY shown ONCE to the developer, never stored
     pub plaintext: String,
-    /// The prefix (first 10 chars) — stored in DB for identification
+    /// The prefix (first 16 chars) — stored in DB for identification
     pub prefix: String,
     /// SHA-256 hash of the full key — stored in DB for validation
     pub hash: String,
@@ -27,12 +27,10 @@ pub fn generate_api_key(key_type: &str, environment: &str) -> ApiKey {
     );
 
     // Compute the hash (this is what gets stored in the DB)
-    let mut hasher = Sha256::new();
-    hasher.update(plaintext.as_bytes());
-    let hash = format!("{:x}", hasher.finalize());
+    let hash = hash_api_key(&plaintext);
 
     // The prefix is used to look up which hash to compare against
-    let prefix = plaintext[..10].to_string(); // e.g. "sk_live_7c"
+    let prefix = plaintext[..API_KEY_PREFIX_LEN].to_string(); // e.g. "sk_live_7cYq2Vd9"
 
     ApiKey { plaintext, prefix, hash }
 }
@@ -1 +1,161 @@
 // Database repository patterns for tenant-management
+use chrono::{DateTime, Utc};
+use sqlx::{Executor, Postgres};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct MigrationRow {
+    pub id: Uuid,
+    pub tenant_id: Uuid,
+    pub target_db_url: String,
+    pub previous_db_url: Option<String>,
+    pub phase: String,
+    pub tables: serde_json::Value,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub switched_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+/// Opens a migration, recording the tenant's current routing for rollback. `None` when
+/// the tenant does not exist; a unique violation when it already has an open one.
+pub async fn insert_migration<'e, E>(
+    executor: E,
+    tenant_id: Uuid,
+    target_db_url: &str,
+) -> Result<Option<MigrationRow>, sqlx::Error>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query_as::<_, MigrationRow>(
+        r#"
+        INSERT INTO tenant_db_migrations (tenant_id, target_db_url, previous_db_url, previous_tier)
+        SELECT id, $2, db_connection_url, tier FROM tenants WHERE id = $1
+        RETURNING *
+        "#,
+    )
+    .bind(tenant_id)
+    .bind(target_db_url)
+    .fetch_optional(executor)
+    .await
+}
+
+pub async fn get_migration<'e, E>(executor: E, tenant_id: Uuid, id: Uuid) -> Result<Option<MigrationRow>, sqlx::Error>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query_as::<_, MigrationRow>("SELECT * FROM tenant_db_migrations WHERE id = $1 AND tenant_id = $2")
+        .bind(id)
+        .bind(tenant_id)
+        .fetch_optional(executor)
+        .await
+}
+
+/// Moves the migration to `to` only if it is currently in one of `from`.
+pub async fn transition<'e, E>(executor: E, id: Uuid, from: &[&str], to: &str) -> Result<Option<MigrationRow>, sqlx::Error>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query_as::<_, MigrationRow>(
+        r#"
+        UPDATE tenant_db_migrations
+        SET phase = $3,
+            error = NULL,
+            switched_at = CASE WHEN $3 = 'switched' THEN NOW() ELSE switched_at END,
+            finished_at = CASE WHEN $3 IN ('rolled_back', 'completed') THEN NOW() ELSE finished_at END,
+            updated_at = NOW()
+        WHERE id = $1 AND phase = ANY($2)
+        RETURNING *
+        "#,
+    )
+    .bind(id)
+    .bind(from)
+    .bind(to)
+    .fetch_optional(executor)
+    .await
+}
+
+pub async fn update_progress<'e, E>(executor: E, id: Uuid, tables: &serde_json::Value) -> Result<(), sqlx::Error>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query("UPDATE tenant_db_migrations SET tables = $2, updated_at = NOW() WHERE id = $1")
+        .bind(id)
+        .bind(tables)
+        .execute(executor)
+        .await?;
+    Ok(())
+}
+
+pub async fn fail_migration<'e, E>(executor: E, id: Uuid, phase: &str, error: &str) -> Result<(), sqlx::Error>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query("UPDATE tenant_db_migrations SET phase = $2, error = $3, updated_at = NOW() WHERE id = $1")
+        .bind(id)
+        .bind(phase)
+        .bind(error)
+        .execute(executor)
+        .await?;
+    Ok(())
+}
+
+/// Migrations whose job was running when the process stopped.
+pub async fn interrupted_migrations<'e, E>(executor: E) -> Result<Vec<MigrationRow>, sqlx::Error>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query_as::<_, MigrationRow>(
+        "SELECT * FROM tenant_db_migrations WHERE phase IN ('pending', 'copying', 'verifying', 'rolling_back')",
+    )
+    .fetch_all(executor)
+    .await
+}
+
+pub async fn tenant_tier<'e, E>(executor: E, tenant_id: Uuid) -> Result<Option<String>, sqlx::Error>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query_scalar("SELECT tier FROM tenants WHERE id = $1")
+        .bind(tenant_id)
+        .fetch_optional(executor)
+        .await
+}
+
+pub async fn set_read_only<'e, E>(executor: E, tenant_id: Uuid, read_only: bool) -> Result<(), sqlx::Error>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query("UPDATE tenants SET read_only = $2, updated_at = NOW() WHERE id = $1")
+        .bind(tenant_id)
+        .bind(read_only)
+        .execute(executor)
+        .await?;
+    Ok(())
+}
+
+/// Points the tenant at `db_url` (`None` for the shared database) and resumes writes,
+/// in one statement so every service sees the old routing or the new one. The tier is
+/// billing state and is left alone.
+pub async fn switch_database<'e, E>(
+    executor: E,
+    tenant_id: Uuid,
+    db_url: Option<&str>,
+) -> Result<(), sqlx::Error>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query(
+        r#"
+        UPDATE tenants
+        SET db_connection_url = $2, read_only = FALSE, updated_at = NOW()
+        WHERE id = $1
+        "#,
+    )
+    .bind(tenant_id)
+    .bind(db_url)
+    .execute(executor)
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,471 @@
+use chrono::{DateTime, Utc};
+use platform::db_router::DynamicPoolRouter;
+use platform::events::{DomainEvent, TenantEvent};
+use platform::streams::StreamPublisher;
+use platform::tenant::{AuthMethod, PricingTier, TenantContext};
+use platform::tenant_directory::TenantDirectory;
+use platform::tenant_migration::{self, TenantMigrationError, TenantTables};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::fmt;
+use std::time::Duration;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::db::{self, MigrationRow};
+use crate::errors::MigrationError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MigrationPhase {
+    Pending,
+    Copying,
+    Verifying,
+    Switched,
+    RollingBack,
+    RolledBack,
+    Completed,
+    Failed,
+}
+
+impl MigrationPhase {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MigrationPhase::Pending => "pending",
+            MigrationPhase::Copying => "copying",
+            MigrationPhase::Verifying => "verifying",
+            MigrationPhase::Switched => "switched",
+            MigrationPhase::RollingBack => "rolling_back",
+            MigrationPhase::RolledBack => "rolled_back",
+            MigrationPhase::Completed => "completed",
+            MigrationPhase::Failed => "failed",
+        }
+    }
+
+    fn parse(phase: &str) -> Self {
+        match phase {
+            "pending" => MigrationPhase::Pending,
+            "copying" => MigrationPhase::Copying,
+            "verifying" => MigrationPhase::Verifying,
+            "switched" => MigrationPhase::Switched,
+            "rolling_back" => MigrationPhase::RollingBack,
+            "rolled_back" => MigrationPhase::RolledBack,
+            "completed" => MigrationPhase::Completed,
+            _ => MigrationPhase::Failed,
+        }
+    }
+}
+
+impl fmt::Display for MigrationPhase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct TableProgress {
+    pub table: String,
+    pub rows_copied: u64,
+    /// Rows in the source once writes were paused; set by verification.
+    pub source_rows: Option<i64>,
+    pub verified: bool,
+}
+
+/// Progress of a tenant's move to a dedicated database. The target URL is never echoed
+/// back since it carries credentials.
+#[derive(Serialize, ToSchema)]
+pub struct TenantDbMigration {
+    pub id: Uuid,
+    pub tenant_id: Uuid,
+    pub phase: MigrationPhase,
+    pub tables: Vec<TableProgress>,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub switched_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+impl From<MigrationRow> for TenantDbMigration {
+    fn from(row: MigrationRow) -> Self {
+        Self {
+            id: row.id,
+            tenant_id: row.tenant_id,
+            phase: MigrationPhase::parse(&row.phase),
+            tables: serde_json::from_value(row.tables).unwrap_or_default(),
+            error: row.error,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+            switched_at: row.switched_at,
+            finished_at: row.finished_at,
+        }
+    }
+}
+
+/// Moves a tenant's rows out of the shared database into a dedicated one.
+///
+/// 1. `copying`: every table with a `tenant_id` column is copied while the tenant keeps
+///    writing to the shared database.
+/// 2. `verifying`: writes are paused (`tenants.read_only`, answered with 503 by
+///    `TenantAuthMiddleware`), tables whose checksum changed since the copy are re-copied,
+///    and every table's row count and checksum must match.
+/// 3. `switched`: one `UPDATE tenants` points the tenant at the new database and resumes
+///    writes. The shared rows stay until [`MigrationRunner::complete`] purges them, and
+///    [`MigrationRunner::rollback`] copies the tenant back until then.
+///
+/// Targets are named databases from the operator's [`MigrationRunner::with_targets`]
+/// allowlist, never URLs supplied by the caller, and must already hold every service's
+/// schema. Only Enterprise tenants can be moved, and the tier is never changed here; that
+/// is a billing decision made through the operator routes. Switching only moves
+/// traffic that resolves its pool through `DynamicPoolRouter`, so [`MigrationRunner::start`]
+/// refuses to run until [`MigrationRunner::with_enabled`] is set.
+#[derive(Clone)]
+pub struct MigrationRunner {
+    control_plane: PgPool,
+    router: DynamicPoolRouter,
+    tenants: TenantDirectory,
+    publisher: StreamPublisher,
+    batch_size: usize,
+    /// How long requests admitted before the write pause get to finish.
+    write_pause_grace: Duration,
+    enabled: bool,
+    /// Dedicated databases a tenant may be moved to, by name.
+    targets: HashMap<String, String>,
+}
+
+impl MigrationRunner {
+    /// `router`'s shared pool is the tenant data database the rows are moved out of.
+    pub fn new(control_plane: PgPool, router: DynamicPoolRouter, publisher: StreamPublisher) -> Self {
+        Self {
+            control_plane,
+            router,
+            tenants: TenantDirectory::new(),
+            publisher,
+            batch_size: 500,
+            write_pause_grace: Duration::from_secs(5),
+            enabled: false,
+            targets: HashMap::new(),
+        }
+    }
+
+    /// Named dedicated databases `start` may move a tenant to.
+    pub fn with_targets(mut self, targets: HashMap<String, String>) -> Self {
+        self.targets = targets;
+        self
+    }
+
+    /// Allows new migrations once no service reads or writes tenant rows around the router.
+    pub fn with_enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+
+    /// Cache to evict as soon as routing or the write pause changes.
+    pub fn with_tenant_directory(mut self, tenants: TenantDirectory) -> Self {
+        self.tenants = tenants;
+        self
+    }
+
+    pub async fn start(&self, tenant_id: Uuid, target: &str) -> Result<TenantDbMigration, MigrationError> {
+        if !self.enabled {
+            return Err(MigrationError::Disabled);
+        }
+        let target_db_url = self
+            .targets
+            .get(target)
+            .ok_or_else(|| MigrationError::UnknownTarget(target.to_string()))?;
+        // DynamicPoolRouter only honours a dedicated database on the Enterprise tier
+        let tier = db::tenant_tier(&self.control_plane, tenant_id).await?.ok_or(MigrationError::TenantNotFound)?;
+        if tier.parse::<PricingTier>().ok() != Some(PricingTier::Enterprise) {
+            return Err(MigrationError::NotEnterprise);
+        }
+        let row = match db::insert_migration(&self.control_plane, tenant_id, target_db_url).await {
+            Ok(Some(row)) => row,
+            Ok(None) => return Err(MigrationError::TenantNotFound),
+            Err(sqlx::Error::Database(e)) if e.is_unique_violation() => return Err(MigrationError::AlreadyOpen),
+            Err(e) => return Err(e.into()),
+        };
+
+        let runner = self.clone();
+        let job = row.clone();
+        tokio::spawn(async move { runner.run(job).await });
+        Ok(row.into())
+    }
+
+    pub async fn get(&self, tenant_id: Uuid, id: Uuid) -> Result<TenantDbMigration, MigrationError> {
+        db::get_migration(&self.control_plane, tenant_id, id)
+            .await?
+            .map(Into::into)
+            .ok_or(MigrationError::NotFound)
+    }
+
+    /// Undoes a failed or switched migration in the background.
+    pub async fn rollback(&self, tenant_id: Uuid, id: Uuid) -> Result<TenantDbMigration, MigrationError> {
+        let row = self.get_row(tenant_id, id).await?;
+        let from = [MigrationPhase::Failed.as_str(), MigrationPhase::Switched.as_str()];
+        let row = db::transition(&self.control_plane, id, &from, MigrationPhase::RollingBack.as_str())
+            .await?
+            .ok_or(MigrationError::InvalidPhase(MigrationPhase::parse(&row.phase)))?;
+
+        let runner = self.clone();
+        let job = row.clone();
+        tokio::spawn(async move { runner.run_rollback(job).await });
+        Ok(row.into())
+    }
+
+    /// Deletes the tenant's rows from the shared database once the dedicated one has
+    /// proven itself. The migration can no longer be rolled back afterwards.
+    pub async fn complete(&self, tenant_id: Uuid, id: Uuid) -> Result<TenantDbMigration, MigrationError> {
+        let row = self.get_row(tenant_id, id).await?;
+        if MigrationPhase::parse(&row.phase) != MigrationPhase::Switched {
+            return Err(MigrationError::InvalidPhase(MigrationPhase::parse(&row.phase)));
+        }
+
+        // Claim the migration first so a concurrent rollback cannot start mid-purge
+        let mut control_tx = self.control_plane.begin().await?;
+        let row = db::transition(&mut *control_tx, id, &[MigrationPhase::Switched.as_str()], MigrationPhase::Completed.as_str())
+            .await?
+            .ok_or(MigrationError::InvalidPhase(MigrationPhase::parse(&row.phase)))?;
+
+        let shared = self.router.shared_pool();
+        let tables = TenantTables::discover(shared).await?;
+        let mut tx = shared.begin().await?;
+        tenant_migration::set_tenant(&mut tx, tenant_id).await?;
+        let purged = tenant_migration::delete_rows(&mut tx, tenant_id, tables.ordered()).await?;
+        tx.commit().await?;
+        control_tx.commit().await?;
+
+        tracing::info!(tenant_id = %tenant_id, migration_id = %id, purged, "purged tenant rows from the shared database");
+        Ok(row.into())
+    }
+
+    /// Fails migrations whose job died with the previous process and resumes the tenants'
+    /// writes, leaving routing as the last committed phase set it.
+    pub async fn recover_interrupted(&self) -> Result<(), MigrationError> {
+        for row in db::interrupted_migrations(&self.control_plane).await? {
+            // An unfinished rollback of a switched tenant still routes to the dedicated database
+            let phase = if row.phase == MigrationPhase::RollingBack.as_str() && row.switched_at.is_some() {
+                MigrationPhase::Switched
+            } else {
+                MigrationPhase::Failed
+            };
+            db::fail_migration(&self.control_plane, row.id, phase.as_str(), "interrupted by a restart").await?;
+            db::set_read_only(&self.control_plane, row.tenant_id, false).await?;
+            self.tenant_changed(row.tenant_id).await;
+            tracing::warn!(tenant_id = %row.tenant_id, migration_id = %row.id, "marked interrupted tenant migration as failed");
+        }
+        Ok(())
+    }
+
+    async fn get_row(&self, tenant_id: Uuid, id: Uuid) -> Result<MigrationRow, MigrationError> {
+        db::get_migration(&self.control_plane, tenant_id, id)
+            .await?
+            .ok_or(MigrationError::NotFound)
+    }
+
+    async fn run(&self, job: MigrationRow) {
+        if let Err(e) = self.copy_and_switch(&job).await {
+            tracing::error!(tenant_id = %job.tenant_id, migration_id = %job.id, error = %e, "tenant migration failed");
+            self.abort(&job, MigrationPhase::Failed, &e).await;
+        }
+    }
+
+    async fn run_rollback(&self, job: MigrationRow) {
+        // A rollback that fails leaves routing where it was; report the phase it came from
+        let switched = job.switched_at.is_some();
+        if let Err(e) = self.roll_back(&job, switched).await {
+            tracing::error!(tenant_id = %job.tenant_id, migration_id = %job.id, error = %e, "tenant migration rollback failed");
+            let phase = if switched { MigrationPhase::Switched } else { MigrationPhase::Failed };
+            self.abort(&job, phase, &e).await;
+        }
+    }
+
+    async fn abort(&self, job: &MigrationRow, phase: MigrationPhase, error: &MigrationError) {
+        if let Err(e) = db::fail_migration(&self.control_plane, job.id, phase.as_str(), &error.to_string()).await {
+            tracing::error!(migration_id = %job.id, error = %e, "failed to record tenant migration failure");
+        }
+        if let Err(e) = db::set_read_only(&self.control_plane, job.tenant_id, false).await {
+            tracing::error!(tenant_id = %job.tenant_id, error = %e, "failed to resume tenant writes");
+        }
+        self.tenant_changed(job.tenant_id).await;
+    }
+
+    async fn target_pool(&self, job: &MigrationRow) -> Result<PgPool, sqlx::Error> {
+        let mut ctx = TenantContext::new(job.tenant_id, None, PricingTier::Enterprise, vec![], AuthMethod::ApiKey);
+        ctx.db_connection_url = Some(job.target_db_url.clone());
+        self.router.get_pool(&ctx).await
+    }
+
+    async fn copy_and_switch(&self, job: &MigrationRow) -> Result<(), MigrationError> {
+        let (tenant_id, shared) = (job.tenant_id, self.router.shared_pool());
+        self.enter(job, MigrationPhase::Pending, MigrationPhase::Copying).await?;
+
+        let target = self.target_pool(job).await?;
+        let tables = TenantTables::discover(shared).await?;
+        let missing = tables.missing_from(&target).await?;
+        if !missing.is_empty() {
+            let names: Vec<String> = missing.iter().map(ToString::to_string).collect();
+            return Err(TenantMigrationError::MissingTables(names.join(", ")).into());
+        }
+
+        let mut progress: Vec<TableProgress> = tables
+            .ordered()
+            .iter()
+            .map(|table| TableProgress { table: table.to_string(), ..Default::default() })
+            .collect();
+        self.save_progress(job, &progress).await?;
+
+        // Clear leftovers of an earlier attempt, then copy table by table while the
+        // tenant keeps writing to the shared database
+        let mut tx = target.begin().await?;
+        tenant_migration::set_tenant(&mut tx, tenant_id).await?;
+        tenant_migration::delete_rows(&mut tx, tenant_id, tables.ordered()).await?;
+        tx.commit().await?;
+        for (i, table) in tables.ordered().iter().enumerate() {
+            let mut tx = target.begin().await?;
+            tenant_migration::set_tenant(&mut tx, tenant_id).await?;
+            progress[i].rows_copied = tenant_migration::copy_rows(shared, &mut tx, tenant_id, table, self.batch_size).await?;
+            tx.commit().await?;
+            self.save_progress(job, &progress).await?;
+        }
+
+        self.enter(job, MigrationPhase::Copying, MigrationPhase::Verifying).await?;
+        self.pause_writes(tenant_id).await?;
+
+        // Catch up on what changed during the copy
+        let changed: Vec<_> = tenant_migration::diff(shared, &target, tenant_id, tables.ordered())
+            .await?
+            .into_iter()
+            .map(|(table, _, _)| table)
+            .collect();
+        if !changed.is_empty() {
+            let recopy = tables.with_dependents(&changed);
+            tracing::info!(tenant_id = %tenant_id, tables = recopy.len(), "re-copying tables changed during the copy");
+            tenant_migration::replace_rows(shared, &target, tenant_id, &recopy, self.batch_size).await?;
+        }
+        tenant_migration::verify(shared, &target, tenant_id, tables.ordered()).await?;
+        for (table, entry) in tables.ordered().iter().zip(progress.iter_mut()) {
+            entry.source_rows = Some(tenant_migration::checksum(shared, tenant_id, table).await?.rows);
+            entry.verified = true;
+        }
+        self.save_progress(job, &progress).await?;
+
+        let mut tx = self.control_plane.begin().await?;
+        db::switch_database(&mut *tx, tenant_id, Some(&job.target_db_url)).await?;
+        db::transition(&mut *tx, job.id, &[MigrationPhase::Verifying.as_str()], MigrationPhase::Switched.as_str())
+            .await?
+            .ok_or(MigrationError::InvalidPhase(MigrationPhase::Verifying))?;
+        tx.commit().await?;
+        self.tenant_changed(tenant_id).await;
+
+        tracing::info!(tenant_id = %tenant_id, migration_id = %job.id, "tenant switched to its dedicated database");
+        Ok(())
+    }
+
+    async fn roll_back(&self, job: &MigrationRow, switched: bool) -> Result<(), MigrationError> {
+        let (tenant_id, shared) = (job.tenant_id, self.router.shared_pool());
+        let target = self.target_pool(job).await?;
+        let tables = TenantTables::discover(shared).await?;
+
+        if switched {
+            // Writes since the switch live only in the dedicated database: bring them back
+            self.pause_writes(tenant_id).await?;
+            tenant_migration::replace_rows(&target, shared, tenant_id, tables.ordered(), self.batch_size).await?;
+            tenant_migration::verify(&target, shared, tenant_id, tables.ordered()).await?;
+        }
+
+        let mut tx = self.control_plane.begin().await?;
+        db::switch_database(&mut *tx, tenant_id, job.previous_db_url.as_deref()).await?;
+        db::transition(&mut *tx, job.id, &[MigrationPhase::RollingBack.as_str()], MigrationPhase::RolledBack.as_str())
+            .await?
+            .ok_or(MigrationError::InvalidPhase(MigrationPhase::RollingBack))?;
+        tx.commit().await?;
+        self.tenant_changed(tenant_id).await;
+
+        // Routing is back on the shared database; the copy is now just garbage
+        let mut tx = target.begin().await?;
+        tenant_migration::set_tenant(&mut tx, tenant_id).await?;
+        if let Err(e) = tenant_migration::delete_rows(&mut tx, tenant_id, tables.ordered()).await {
+            tracing::warn!(tenant_id = %tenant_id, error = %e, "failed to clear the dedicated database after rollback");
+            return Ok(());
+        }
+        tx.commit().await?;
+
+        tracing::info!(tenant_id = %tenant_id, migration_id = %job.id, "tenant migration rolled back");
+        Ok(())
+    }
+
+    async fn enter(&self, job: &MigrationRow, from: MigrationPhase, to: MigrationPhase) -> Result<(), MigrationError> {
+        db::transition(&self.control_plane, job.id, &[from.as_str()], to.as_str())
+            .await?
+            .map(|_| ())
+            .ok_or(MigrationError::InvalidPhase(from))
+    }
+
+    async fn save_progress(&self, job: &MigrationRow, progress: &[TableProgress]) -> Result<(), MigrationError> {
+        let tables = serde_json::to_value(progress).unwrap_or_default();
+        db::update_progress(&self.control_plane, job.id, &tables).await?;
+        Ok(())
+    }
+
+    async fn pause_writes(&self, tenant_id: Uuid) -> Result<(), MigrationError> {
+        db::set_read_only(&self.control_plane, tenant_id, true).await?;
+        self.tenant_changed(tenant_id).await;
+        tokio::time::sleep(self.write_pause_grace).await;
+        Ok(())
+    }
+
+    /// Drops the cached tenant record right away and tells other services to do the same.
+    async fn tenant_changed(&self, tenant_id: Uuid) {
+        if let Err(e) = self.tenants.invalidate(tenant_id).await {
+            tracing::warn!(tenant_id = %tenant_id, error = %e, "failed to evict cached tenant record");
+        }
+        self.publisher.publish_event_async(DomainEvent::TenantUpdated(TenantEvent {
+            tenant_id,
+            tier: None,
+            timestamp: Utc::now(),
+        }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::postgres::PgPoolOptions;
+
+    #[test]
+    fn test_phase_round_trips_through_its_column_value() {
+        for phase in [
+            MigrationPhase::Pending,
+            MigrationPhase::Copying,
+            MigrationPhase::Verifying,
+            MigrationPhase::Switched,
+            MigrationPhase::RollingBack,
+            MigrationPhase::RolledBack,
+            MigrationPhase::Completed,
+            MigrationPhase::Failed,
+        ] {
+            assert_eq!(MigrationPhase::parse(phase.as_str()), phase);
+            assert_eq!(serde_json::to_value(phase).unwrap(), phase.as_str());
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_start_is_refused_until_enabled() {
+        let lazy = || PgPoolOptions::new().connect_lazy("postgres://localhost/unused").unwrap();
+        let runner = MigrationRunner::new(lazy(), DynamicPoolRouter::new(lazy()), StreamPublisher::noop());
+        let result = runner.start(Uuid::new_v4(), "dedicated-1").await;
+        assert!(matches!(result, Err(MigrationError::Disabled)));
+    }
+
+    #[actix_web::test]
+    async fn test_start_only_accepts_allowlisted_targets() {
+        let lazy = || PgPoolOptions::new().connect_lazy("postgres://localhost/unused").unwrap();
+        let runner = MigrationRunner::new(lazy(), DynamicPoolRouter::new(lazy()), StreamPublisher::noop())
+            .with_enabled(true)
+            .with_targets(HashMap::from([("dedicated-1".to_string(), "postgres://tenant-db/orders".to_string())]));
+        let result = runner.start(Uuid::new_v4(), "postgres://attacker.example/exfil").await;
+        assert!(matches!(result, Err(MigrationError::UnknownTarget(_))));
+    }
+}
@@ -1 +1,65 @@
 // Domain specific errors for tenant-management
+use actix_web::http::StatusCode;
+use actix_web::{HttpResponse, ResponseError};
+use platform::errors::{AppError, Problem};
+use platform::tenant_migration::TenantMigrationError;
+use thiserror::Error;
+
+use crate::db_migration::MigrationPhase;
+
+#[derive(Error, Debug)]
+pub enum MigrationError {
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+    #[error(transparent)]
+    Copy(#[from] TenantMigrationError),
+    #[error("Tenant not found")]
+    TenantNotFound,
+    #[error("Migration not found")]
+    NotFound,
+    #[error("Tenant already has an open database migration")]
+    AlreadyOpen,
+    #[error("Migration is {0}, which does not allow this operation")]
+    InvalidPhase(MigrationPhase),
+    #[error("Tenant data database is not configured (DATABASE_URL)")]
+    NotConfigured,
+    #[error("Unknown migration target '{0}'")]
+    UnknownTarget(String),
+    #[error("Only Enterprise tenants can move to a dedicated database")]
+    NotEnterprise,
+    #[error("Tenant database migrations are disabled until every service routes tenant data through DynamicPoolRouter (TENANT_MIGRATIONS_ENABLED)")]
+    Disabled,
+}
+
+impl ResponseError for MigrationError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            MigrationError::TenantNotFound | MigrationError::NotFound => StatusCode::NOT_FOUND,
+            MigrationError::UnknownTarget(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            MigrationError::AlreadyOpen
+            | MigrationError::InvalidPhase(_)
+            | MigrationError::Disabled
+            | MigrationError::NotEnterprise => StatusCode::CONFLICT,
+            MigrationError::NotConfigured => StatusCode::SERVICE_UNAVAILABLE,
+            MigrationError::Database(_) | MigrationError::Copy(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let code = match self {
+            MigrationError::TenantNotFound => "tenant_not_found",
+            MigrationError::NotFound => "migration_not_found",
+            MigrationError::AlreadyOpen => "migration_already_open",
+            MigrationError::InvalidPhase(_) => "invalid_migration_phase",
+            MigrationError::NotConfigured => "not_configured",
+            MigrationError::UnknownTarget(_) => "unknown_migration_target",
+            MigrationError::NotEnterprise => "tier_not_enterprise",
+            MigrationError::Disabled => "migrations_disabled",
+            MigrationError::Database(_) | MigrationError::Copy(_) => {
+                tracing::error!(error = %self, "tenant migration failed");
+                return AppError::Internal.error_response();
+            }
+        };
+        Problem::new(self.status_code(), code).with_detail(self.to_string()).response()
+    }
+}
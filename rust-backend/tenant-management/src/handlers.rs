@@ -1,7 +1,14 @@
-use actix_web::{web, HttpResponse, Responder};
+use actix_web::{web, HttpResponse, Responder, ResponseError};
+use platform::errors::AppError;
+use platform::events::{DomainEvent, TenantEvent};
+use platform::streams::StreamPublisher;
+use platform::tenant::{PricingTier, TenantContext};
 use sqlx::PgPool;
+use uuid::Uuid;
 use crate::models::*;
 use crate::auth;
+use crate::db_migration::MigrationRunner;
+use crate::errors::MigrationError;
 
 #[utoipa::path(
     get,
@@ -28,15 +35,15 @@ pub async fn metrics_api_doc() {}
 
 #[utoipa::path(
     post,
-    path = "/v1/tenants",
+    path = "/v1/operator/tenants",
     request_body = CreateTenantRequest,
     responses(
         (status = 201, description = "Tenant created", body = TenantResponse),
+        (status = 401, description = "Missing or invalid operator token"),
         (status = 500, description = "Internal Server Error")
     ),
     security(
-        ("BearerAuth" = []),
-        ("ApiKeyAuth" = [])
+        ("OperatorToken" = [])
     )
 )]
 pub async fn create_tenant(
@@ -60,7 +67,125 @@ pub async fn create_tenant(
 
     match row {
         Ok(tenant) => HttpResponse::Created().json(tenant),
-        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+        Err(e) => AppError::from(e).error_response(),
+    }
+}
+
+#[utoipa::path(
+    patch,
+    path = "/v1/tenants/{id}",
+    request_body = UpdateTenantRequest,
+    params(
+        ("id" = Uuid, Path, description = "Tenant ID")
+    ),
+    responses(
+        (status = 200, description = "Tenant updated", body = TenantResponse),
+        (status = 403, description = "Tenant belongs to another caller, or the update needs operator credentials"),
+        (status = 404, description = "Tenant not found"),
+        (status = 500, description = "Internal Server Error")
+    ),
+    security(
+        ("BearerAuth" = []),
+        ("ApiKeyAuth" = [])
+    )
+)]
+pub async fn update_tenant(
+    pool: web::Data<PgPool>,
+    redis_pub: web::Data<StreamPublisher>,
+    ctx: web::ReqData<TenantContext>,
+    path: web::Path<Uuid>,
+    req: web::Json<UpdateTenantRequest>,
+) -> impl Responder {
+    let tenant_id = path.into_inner();
+    if tenant_id != ctx.tenant_id {
+        return AppError::Forbidden("Cannot update another tenant".into()).error_response();
+    }
+    if req.changes_operator_fields() {
+        return AppError::Forbidden("Only platform operators can change tier, flags, database or access settings".into())
+            .error_response();
+    }
+    apply_tenant_update(&pool, &redis_pub, tenant_id, &req).await
+}
+
+#[utoipa::path(
+    patch,
+    path = "/v1/operator/tenants/{id}",
+    request_body = UpdateTenantRequest,
+    params(
+        ("id" = Uuid, Path, description = "Tenant ID")
+    ),
+    responses(
+        (status = 200, description = "Tenant updated", body = TenantResponse),
+        (status = 400, description = "Unknown pricing tier"),
+        (status = 401, description = "Missing or invalid operator token"),
+        (status = 404, description = "Tenant not found"),
+        (status = 500, description = "Internal Server Error")
+    ),
+    security(
+        ("OperatorToken" = [])
+    )
+)]
+pub async fn update_tenant_settings(
+    pool: web::Data<PgPool>,
+    redis_pub: web::Data<StreamPublisher>,
+    path: web::Path<Uuid>,
+    req: web::Json<UpdateTenantRequest>,
+) -> impl Responder {
+    apply_tenant_update(&pool, &redis_pub, path.into_inner(), &req).await
+}
+
+async fn apply_tenant_update(
+    pool: &PgPool,
+    redis_pub: &StreamPublisher,
+    tenant_id: Uuid,
+    req: &UpdateTenantRequest,
+) -> HttpResponse {
+    let tier = match req.tier.as_deref().map(str::parse::<PricingTier>) {
+        Some(Ok(tier)) => Some(tier.to_string().to_lowercase()),
+        Some(Err(e)) => return AppError::BadRequest(e.to_string()).error_response(),
+        None => None,
+    };
+    let feature_flags = match req.feature_flags.as_ref().map(serde_json::to_value).transpose() {
+        Ok(flags) => flags,
+        Err(e) => return AppError::BadRequest(e.to_string()).error_response(),
+    };
+
+    let row = sqlx::query_as::<_, TenantResponse>(
+        r#"
+        UPDATE tenants
+        SET name = COALESCE($2, name),
+            tier = COALESCE($3, tier),
+            feature_flags = COALESCE(feature_flags, '{}'::jsonb) || COALESCE($4, '{}'::jsonb),
+            db_connection_url = COALESCE($5, db_connection_url),
+            ip_allowlist = COALESCE($6, ip_allowlist),
+            rate_limit_override = COALESCE($7, rate_limit_override),
+            updated_at = NOW()
+        WHERE id = $1
+        RETURNING id, name, email, tier
+        "#
+    )
+    .bind(tenant_id)
+    .bind(&req.name)
+    .bind(&tier)
+    .bind(&feature_flags)
+    .bind(&req.db_connection_url)
+    .bind(&req.ip_allowlist)
+    .bind(req.rate_limit_override)
+    .fetch_optional(pool)
+    .await;
+
+    match row {
+        Ok(Some(tenant)) => {
+            // Services cache tenant records; this event evicts the stale copy
+            redis_pub.publish_event_async(DomainEvent::TenantUpdated(TenantEvent {
+                tenant_id: tenant.id,
+                tier: Some(tenant.tier.clone()),
+                timestamp: chrono::Utc::now(),
+            }));
+            HttpResponse::Ok().json(tenant)
+        }
+        Ok(None) => AppError::NotFound("Tenant not found".into()).error_response(),
+        Err(e) => AppError::from(e).error_response(),
     }
 }
 
@@ -70,6 +195,7 @@ pub async fn create_tenant(
     request_body = GenerateKeyRequest,
     responses(
         (status = 201, description = "Key generated", body = GenerateKeyResponse),
+        (status = 403, description = "Key requested for another tenant"),
         (status = 500, description = "Internal Server Error")
     ),
     security(
@@ -79,8 +205,38 @@ pub async fn create_tenant(
 )]
 pub async fn generate_api_key_handler(
     pool: web::Data<PgPool>,
+    ctx: web::ReqData<TenantContext>,
     req: web::Json<GenerateKeyRequest>,
 ) -> impl Responder {
+    if req.tenant_id != ctx.tenant_id {
+        return AppError::Forbidden("Cannot issue API keys for another tenant".into()).error_response();
+    }
+    issue_api_key(&pool, &req).await
+}
+
+/// Issues the first key of a tenant created through [`create_tenant`], which has no
+/// credential of its own yet.
+#[utoipa::path(
+    post,
+    path = "/v1/operator/tenants/keys",
+    request_body = GenerateKeyRequest,
+    responses(
+        (status = 201, description = "Key generated", body = GenerateKeyResponse),
+        (status = 401, description = "Missing or invalid operator token"),
+        (status = 500, description = "Internal Server Error")
+    ),
+    security(
+        ("OperatorToken" = [])
+    )
+)]
+pub async fn generate_operator_api_key_handler(
+    pool: web::Data<PgPool>,
+    req: web::Json<GenerateKeyRequest>,
+) -> impl Responder {
+    issue_api_key(&pool, &req).await
+}
+
+async fn issue_api_key(pool: &PgPool, req: &GenerateKeyRequest) -> HttpResponse {
     let api_key = auth::generate_api_key(&req.key_type, &req.environment);
 
     let row = sqlx::query(
@@ -97,7 +253,7 @@ pub async fn generate_api_key_handler(
     .bind(&req.key_type)
     .bind(&req.environment)
     .bind(&req.scopes)
-    .execute(pool.get_ref())
+    .execute(pool)
     .await;
 
     match row {
@@ -107,6 +263,116 @@ pub async fn generate_api_key_handler(
             key_type: req.key_type.clone(),
             environment: req.environment.clone(),
         }),
-        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+        Err(e) => AppError::from(e).error_response(),
     }
 }
+
+fn migration_runner(runner: Option<web::Data<MigrationRunner>>) -> Result<web::Data<MigrationRunner>, MigrationError> {
+    runner.ok_or(MigrationError::NotConfigured)
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/operator/tenants/{id}/migrations",
+    request_body = StartMigrationRequest,
+    params(
+        ("id" = Uuid, Path, description = "Tenant ID")
+    ),
+    responses(
+        (status = 202, description = "Migration to a dedicated database started", body = TenantDbMigration),
+        (status = 401, description = "Missing or invalid operator token"),
+        (status = 404, description = "Tenant not found"),
+        (status = 409, description = "Tenant is not on the Enterprise tier, already has an open migration, or migrations are disabled"),
+        (status = 422, description = "Target is not in TENANT_MIGRATION_TARGETS"),
+        (status = 503, description = "Tenant data database not configured")
+    ),
+    security(
+        ("OperatorToken" = [])
+    )
+)]
+pub async fn start_migration(
+    runner: Option<web::Data<MigrationRunner>>,
+    path: web::Path<Uuid>,
+    req: web::Json<StartMigrationRequest>,
+) -> Result<HttpResponse, MigrationError> {
+    let tenant_id = path.into_inner();
+    let migration = migration_runner(runner)?.start(tenant_id, &req.target).await?;
+    Ok(HttpResponse::Accepted().json(migration))
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/operator/tenants/{id}/migrations/{migration_id}",
+    params(
+        ("id" = Uuid, Path, description = "Tenant ID"),
+        ("migration_id" = Uuid, Path, description = "Migration ID")
+    ),
+    responses(
+        (status = 200, description = "Migration phase and per-table progress", body = TenantDbMigration),
+        (status = 401, description = "Missing or invalid operator token"),
+        (status = 404, description = "Migration not found")
+    ),
+    security(
+        ("OperatorToken" = [])
+    )
+)]
+pub async fn get_migration(
+    runner: Option<web::Data<MigrationRunner>>,
+    path: web::Path<(Uuid, Uuid)>,
+) -> Result<HttpResponse, MigrationError> {
+    let (tenant_id, migration_id) = path.into_inner();
+    let migration = migration_runner(runner)?.get(tenant_id, migration_id).await?;
+    Ok(HttpResponse::Ok().json(migration))
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/operator/tenants/{id}/migrations/{migration_id}/rollback",
+    params(
+        ("id" = Uuid, Path, description = "Tenant ID"),
+        ("migration_id" = Uuid, Path, description = "Migration ID")
+    ),
+    responses(
+        (status = 202, description = "Rollback to the shared database started", body = TenantDbMigration),
+        (status = 401, description = "Missing or invalid operator token"),
+        (status = 404, description = "Migration not found"),
+        (status = 409, description = "Migration is neither failed nor switched")
+    ),
+    security(
+        ("OperatorToken" = [])
+    )
+)]
+pub async fn rollback_migration(
+    runner: Option<web::Data<MigrationRunner>>,
+    path: web::Path<(Uuid, Uuid)>,
+) -> Result<HttpResponse, MigrationError> {
+    let (tenant_id, migration_id) = path.into_inner();
+    let migration = migration_runner(runner)?.rollback(tenant_id, migration_id).await?;
+    Ok(HttpResponse::Accepted().json(migration))
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/operator/tenants/{id}/migrations/{migration_id}/complete",
+    params(
+        ("id" = Uuid, Path, description = "Tenant ID"),
+        ("migration_id" = Uuid, Path, description = "Migration ID")
+    ),
+    responses(
+        (status = 200, description = "Tenant rows purged from the shared database", body = TenantDbMigration),
+        (status = 401, description = "Missing or invalid operator token"),
+        (status = 404, description = "Migration not found"),
+        (status = 409, description = "Migration has not switched")
+    ),
+    security(
+        ("OperatorToken" = [])
+    )
+)]
+pub async fn complete_migration(
+    runner: Option<web::Data<MigrationRunner>>,
+    path: web::Path<(Uuid, Uuid)>,
+) -> Result<HttpResponse, MigrationError> {
+    let (tenant_id, migration_id) = path.into_inner();
+    let migration = migration_runner(runner)?.complete(tenant_id, migration_id).await?;
+    Ok(HttpResponse::Ok().json(migration))
+}
@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use utoipa::ToSchema;
 use uuid::Uuid;
 
@@ -14,6 +15,31 @@ pub struct CreateTenantRequest {
     pub tier: Option<String>,
 }
 
+/// Partial update; omitted fields keep their value and `feature_flags` are merged.
+///
+/// Tenants may only change their profile (`name`); the remaining fields require the
+/// platform-operator token.
+#[derive(Deserialize, ToSchema)]
+pub struct UpdateTenantRequest {
+    pub name: Option<String>,
+    pub tier: Option<String>,
+    pub feature_flags: Option<HashMap<String, bool>>,
+    pub db_connection_url: Option<String>,
+    pub ip_allowlist: Option<Vec<String>>,
+    pub rate_limit_override: Option<i32>,
+}
+
+impl UpdateTenantRequest {
+    /// Whether the update touches billing, placement or access settings.
+    pub fn changes_operator_fields(&self) -> bool {
+        self.tier.is_some()
+            || self.feature_flags.is_some()
+            || self.db_connection_url.is_some()
+            || self.ip_allowlist.is_some()
+            || self.rate_limit_override.is_some()
+    }
+}
+
 #[derive(Serialize, sqlx::FromRow, ToSchema)]
 pub struct TenantResponse {
     pub id: Uuid,
@@ -38,3 +64,9 @@ pub struct GenerateKeyResponse {
     pub key_type: String,
     pub environment: String,
 }
+
+#[derive(Deserialize, ToSchema)]
+pub struct StartMigrationRequest {
+    /// Name of a dedicated database from `TENANT_MIGRATION_TARGETS`.
+    pub target: String,
+}
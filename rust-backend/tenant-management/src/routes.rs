@@ -1,7 +1,148 @@
 use actix_web::web;
 use crate::handlers::*;
 
+/// Tenant self-service; mounted behind `TenantAuthMiddleware` and the `tenants` scopes.
 pub fn configure(cfg: &mut web::ServiceConfig) {
-    cfg.route("/v1/tenants", web::post().to(create_tenant))
+    cfg.route("/v1/tenants/{id}", web::patch().to(update_tenant))
        .route("/v1/tenants/keys", web::post().to(generate_api_key_handler));
 }
+
+/// Onboarding, billing, placement and database moves; mounted under `/v1/operator`
+/// behind `OperatorAuth`.
+pub fn configure_operator(cfg: &mut web::ServiceConfig) {
+    cfg.route("/tenants", web::post().to(create_tenant))
+       .route("/tenants/keys", web::post().to(generate_operator_api_key_handler))
+       .route("/tenants/{id}", web::patch().to(update_tenant_settings))
+       .route("/tenants/{id}/migrations", web::post().to(start_migration))
+       .route("/tenants/{id}/migrations/{migration_id}", web::get().to(get_migration))
+       .route("/tenants/{id}/migrations/{migration_id}/rollback", web::post().to(rollback_migration))
+       .route("/tenants/{id}/migrations/{migration_id}/complete", web::post().to(complete_migration));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::dev::Service;
+    use actix_web::http::StatusCode;
+    use actix_web::{test, App};
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use platform::api_keys::ApiKeyVerifier;
+    use platform::middleware::{RequireScope, TenantAuthMiddleware};
+    use platform::scopes;
+    use platform::tenant_directory::TenantDirectory;
+    use uuid::Uuid;
+
+    const SECRET: &str = "test_secret";
+
+    fn token(role: &str) -> String {
+        let claims = serde_json::json!({
+            "sub": Uuid::new_v4(),
+            "role": role,
+            "exp": (chrono::Utc::now() + chrono::Duration::hours(1)).timestamp(),
+            "tenant_id": Uuid::new_v4(),
+        });
+        encode(&Header::default(), &claims, &EncodingKey::from_secret(SECRET.as_bytes())).unwrap()
+    }
+
+    #[actix_web::test]
+    async fn test_tenant_routes_require_tenants_scope() {
+        let app = test::init_service(
+            App::new().service(
+                web::scope("")
+                    .wrap(RequireScope::resource(scopes::TENANTS))
+                    .wrap(
+                        TenantAuthMiddleware::new()
+                            .with_secret(SECRET)
+                            .with_api_keys(ApiKeyVerifier::new())
+                            .with_tenant_directory(TenantDirectory::new()),
+                    )
+                    .configure(configure),
+            ),
+        )
+        .await;
+        let body = serde_json::json!({ "tier": "enterprise" });
+
+        let anonymous = test::TestRequest::patch()
+            .uri(&format!("/v1/tenants/{}", Uuid::new_v4()))
+            .set_json(&body)
+            .to_request();
+        assert_eq!(test::call_service(&app, anonymous).await.status(), StatusCode::UNAUTHORIZED);
+
+        let supplier = test::TestRequest::post()
+            .uri("/v1/tenants/keys")
+            .insert_header(("Authorization", format!("Bearer {}", token("supplier"))))
+            .set_json(&body)
+            .to_request();
+        let err = app.call(supplier).await.expect_err("suppliers lack tenants:write");
+        assert_eq!(err.error_response().status(), StatusCode::FORBIDDEN);
+    }
+
+    #[actix_web::test]
+    async fn test_tenants_cannot_change_operator_settings() {
+        let tenant_id = Uuid::new_v4();
+        let ctx = platform::tenant::TenantContext::new(
+            tenant_id,
+            None,
+            platform::tenant::PricingTier::Free,
+            vec![scopes::TENANTS_WRITE.to_string()],
+            platform::tenant::AuthMethod::ApiKey,
+        );
+        let pool = sqlx::postgres::PgPoolOptions::new()
+            .connect_lazy("postgres://localhost/unused")
+            .unwrap();
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(pool))
+                .app_data(web::Data::new(platform::streams::StreamPublisher::noop()))
+                .service(
+                    web::scope("/v1/operator")
+                        .wrap(platform::middleware::OperatorAuth::new(Some("op-secret")))
+                        .configure(configure_operator),
+                )
+                .service(
+                    web::scope("")
+                        .wrap_fn(move |req, srv| {
+                            actix_web::HttpMessage::extensions_mut(&req).insert(ctx.clone());
+                            srv.call(req)
+                        })
+                        .configure(configure),
+                ),
+        )
+        .await;
+
+        for body in [
+            serde_json::json!({ "tier": "enterprise" }),
+            serde_json::json!({ "db_connection_url": "postgres://attacker/db" }),
+            serde_json::json!({ "ip_allowlist": ["0.0.0.0/0"] }),
+            serde_json::json!({ "rate_limit_override": 1000000 }),
+        ] {
+            let req = test::TestRequest::patch()
+                .uri(&format!("/v1/tenants/{}", tenant_id))
+                .set_json(&body)
+                .to_request();
+            assert_eq!(test::call_service(&app, req).await.status(), StatusCode::FORBIDDEN);
+        }
+
+        let req = test::TestRequest::patch()
+            .uri(&format!("/v1/operator/tenants/{}", tenant_id))
+            .set_json(serde_json::json!({ "tier": "enterprise" }))
+            .to_request();
+        let err = app.call(req).await.expect_err("tenant credentials are not operator credentials");
+        assert_eq!(err.error_response().status(), StatusCode::UNAUTHORIZED);
+
+        let migrations = format!("/v1/operator/tenants/{}/migrations", tenant_id);
+        for uri in ["/v1/operator/tenants", "/v1/operator/tenants/keys", migrations.as_str()] {
+            let req = test::TestRequest::post().uri(uri).set_json(serde_json::json!({})).to_request();
+            let err = app.call(req).await.expect_err("onboarding and migrations need the operator token");
+            assert_eq!(err.error_response().status(), StatusCode::UNAUTHORIZED);
+        }
+
+        // The operator is admitted; this app has no migration runner configured
+        let req = test::TestRequest::post()
+            .uri(&migrations)
+            .insert_header((platform::middleware::operator_auth::OPERATOR_TOKEN_HEADER, "op-secret"))
+            .set_json(serde_json::json!({ "target": "dedicated-1" }))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+}
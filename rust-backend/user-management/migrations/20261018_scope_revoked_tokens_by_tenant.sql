@@ -0,0 +1,9 @@
+ALTER TABLE revoked_tokens ADD COLUMN IF NOT EXISTS tenant_id UUID NOT NULL DEFAULT '00000000-0000-0000-0000-000000000000';
+CREATE INDEX IF NOT EXISTS idx_revoked_tokens_tenant_id ON revoked_tokens(tenant_id, token);
+ALTER TABLE revoked_tokens ENABLE ROW LEVEL SECURITY;
+ALTER TABLE revoked_tokens FORCE ROW LEVEL SECURITY;
+DROP POLICY IF EXISTS revoked_tokens_tenant_isolation_policy ON revoked_tokens;
+CREATE POLICY revoked_tokens_tenant_isolation_policy ON revoked_tokens
+    FOR ALL
+    USING (tenant_id = NULLIF(current_setting('app.current_tenant_id', true), '')::uuid)
+    WITH CHECK (tenant_id = NULLIF(current_setting('app.current_tenant_id', true), '')::uuid);
@@ -1,10 +1,10 @@
 use crate::models::Claims;
 use crate::models::UserRole;
+use crate::signing_keys::SigningKeys;
 use argon2::password_hash::rand_core::OsRng;
 use argon2::{Argon2, PasswordHash, PasswordHasher, PasswordVerifier};
 use chrono;
-use jsonwebtoken::{EncodingKey, Header, encode};
-use sqlx::PgPool;
+use platform::tenant::TenantTx;
 use uuid::Uuid;
 
 pub fn hash_password(password: &str) -> String {
@@ -27,14 +27,14 @@ pub fn verify_password(hash: &str, password: &str) -> bool {
 pub fn create_jwt(
     user_id: Uuid,
     role: &UserRole,
-    secret: &str,
+    keys: &SigningKeys,
 ) -> Result<(String, String), jsonwebtoken::errors::Error> {
     create_jwt_with_tenant(
         user_id,
         role,
         Uuid::nil(),
         platform::tenant::PricingTier::Free,
-        secret,
+        keys,
     )
 }
 
@@ -43,7 +43,7 @@ pub fn create_jwt_with_tenant(
     role: &UserRole,
     tenant_id: Uuid,
     tier: platform::tenant::PricingTier,
-    secret: &str,
+    keys: &SigningKeys,
 ) -> Result<(String, String), jsonwebtoken::errors::Error> {
     let access_exp = chrono::Utc::now()
         .checked_add_signed(chrono::Duration::minutes(15))
@@ -71,26 +71,17 @@ pub fn create_jwt_with_tenant(
         tier,
     };
 
-    let access_token = encode(
-        &Header::default(),
-        &access_claims,
-        &EncodingKey::from_secret(secret.as_ref()),
-    )?;
-
-    let refresh_token = encode(
-        &Header::default(),
-        &refresh_claims,
-        &EncodingKey::from_secret(secret.as_ref()),
-    )?;
+    let access_token = keys.sign(&access_claims)?;
+    let refresh_token = keys.sign(&refresh_claims)?;
 
     Ok((access_token, refresh_token))
 }
 
-pub async fn user_exists(pool: &PgPool, email: &str, tenant_id: Uuid) -> Result<bool, sqlx::Error> {
+pub async fn user_exists(tx: &mut TenantTx<'_>, email: &str, tenant_id: Uuid) -> Result<bool, sqlx::Error> {
     let row = sqlx::query_scalar::<_, i64>("SELECT 1 FROM users WHERE email = $1 AND tenant_id = $2")
         .bind(email)
         .bind(tenant_id)
-        .fetch_optional(pool)
+        .fetch_optional(tx.executor())
         .await?;
 
     Ok(row.is_some())
@@ -99,7 +90,7 @@ pub async fn user_exists(pool: &PgPool, email: &str, tenant_id: Uuid) -> Result<
 #[cfg(test)]
 mod tests {
     use super::*;
-    use jsonwebtoken::{decode, DecodingKey, Validation};
+    use crate::signing_keys::tests::test_keys;
 
     #[test]
     fn test_password_hashing_and_verification() {
@@ -110,32 +101,27 @@ mod tests {
         assert!(!verify_password(&hash, "WrongPassword"));
     }
 
-    #[test]
-    fn test_create_jwt() {
+    #[tokio::test]
+    async fn test_create_jwt() {
         let user_id = Uuid::new_v4();
         let role = UserRole::Admin;
-        let secret = "test_secret";
+        let keys = test_keys();
         
-        let (access, refresh) = create_jwt(user_id, &role, secret).unwrap();
+        let (access, refresh) = create_jwt(user_id, &role, &keys).unwrap();
+        let header = jsonwebtoken::decode_header(&access).unwrap();
+        assert_eq!(header.alg, jsonwebtoken::Algorithm::EdDSA);
+        assert_eq!(header.kid.as_deref(), Some("2026-01"));
         
         // verify access token
-        let decoded_access = decode::<Claims>(
-            &access,
-            &DecodingKey::from_secret(secret.as_bytes()),
-            &Validation::default(),
-        ).unwrap();
+        let decoded_access: Claims = keys.verifier().verify(&access).await.unwrap();
         
-        assert_eq!(decoded_access.claims.sub, user_id);
-        assert_eq!(decoded_access.claims.role, UserRole::Admin);
+        assert_eq!(decoded_access.sub, user_id);
+        assert_eq!(decoded_access.role, UserRole::Admin);
         
         // verify refresh token
-        let decoded_refresh = decode::<Claims>(
-            &refresh,
-            &DecodingKey::from_secret(secret.as_bytes()),
-            &Validation::default(),
-        ).unwrap();
+        let decoded_refresh: Claims = keys.verifier().verify(&refresh).await.unwrap();
         
-        assert_eq!(decoded_refresh.claims.sub, user_id);
-        assert_eq!(decoded_refresh.claims.role, UserRole::Admin);
+        assert_eq!(decoded_refresh.sub, user_id);
+        assert_eq!(decoded_refresh.role, UserRole::Admin);
     }
 }
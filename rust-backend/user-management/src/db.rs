@@ -1,18 +1,31 @@
 // use actix_web::{web, HttpResponse, Responder, HttpRequest};
 use crate::auth::{create_jwt_with_tenant, hash_password, user_exists, verify_password};
 use crate::models::{SignInRequest, SignUpRequest, UpdateUserRequest, UserRole, Users};
+use crate::signing_keys::SigningKeys;
+use platform::tenant::TenantTx;
 use sqlx::PgPool;
-use std::env;
 use uuid::Uuid;
 
 pub struct UserRepo {
     pool: PgPool,
+    signing_keys: Option<SigningKeys>,
 }
 
 #[allow(dead_code)]
 impl UserRepo {
     pub fn new(pool: PgPool) -> Self {
-        Self { pool }
+        Self { pool, signing_keys: None }
+    }
+
+    pub fn with_signing_keys(mut self, signing_keys: SigningKeys) -> Self {
+        self.signing_keys = Some(signing_keys);
+        self
+    }
+
+    fn signing_keys(&self) -> Result<&SigningKeys, sqlx::Error> {
+        self.signing_keys
+            .as_ref()
+            .ok_or_else(|| sqlx::Error::Protocol("JWT signing keys not configured".into()))
     }
 
     pub fn pool(&self) -> &PgPool {
@@ -20,13 +33,15 @@ impl UserRepo {
     }
 
     pub async fn sign_up(&self, req: &SignUpRequest, tenant_id: Uuid) -> Result<(Users, (String, String)), sqlx::Error> {
-        let role = req.role.clone().unwrap_or(UserRole::User);
+        // Self-service sign-ups are always plain users; roles are raised by an admin.
+        let role = UserRole::User;
         let email = &req.email;
         let full_name = &req.full_name;
 
-        let secret = env::var("SECRET").unwrap_or_else(|_| "obiisaboy".to_string());
+        let keys = self.signing_keys()?;
 
-        if user_exists(&self.pool, email, tenant_id).await? {
+        let mut tx = TenantTx::begin(&self.pool, tenant_id).await?;
+        if user_exists(&mut tx, email, tenant_id).await? {
             return Err(sqlx::Error::RowNotFound);
         }
 
@@ -44,22 +59,24 @@ impl UserRepo {
         .bind(&password_hashed)
         .bind(full_name)
         .bind(role)
-        .fetch_one(&self.pool)
+        .fetch_one(tx.executor())
         .await?;
+        tx.commit().await?;
 
-        let tokens = create_jwt_with_tenant(user.id, &user.role, user.tenant_id, platform::tenant::PricingTier::Free, &secret)
+        let tokens = create_jwt_with_tenant(user.id, &user.role, user.tenant_id, platform::tenant::PricingTier::Free, keys)
             .map_err(|_| sqlx::Error::Protocol("Failed to create JWT".into()))?;
 
         Ok((user, tokens))
     }
 
-    pub async fn sign_in(&self, req: &SignInRequest, tenant_id: Option<Uuid>) -> Result<(Users, (String, String)), sqlx::Error> {
+    pub async fn sign_in(&self, req: &SignInRequest, tenant_id: Uuid) -> Result<(Users, (String, String)), sqlx::Error> {
         let email: &String = &req.email;
         let password: &String = &req.password;
-        let secret = env::var("SECRET").unwrap_or_else(|_| "obiisaboy".to_string());
+        let keys = self.signing_keys()?;
 
-        let user = if let Some(tid) = tenant_id {
-            sqlx::query_as::<_, Users>(
+        let user = {
+            let mut tx = TenantTx::begin(&self.pool, tenant_id).await?;
+            let user = sqlx::query_as::<_, Users>(
                 r#"
                     SELECT *
                     FROM users
@@ -67,20 +84,11 @@ impl UserRepo {
                 "#,
             )
             .bind(email)
-            .bind(tid)
-            .fetch_one(&self.pool)
-            .await?
-        } else {
-            sqlx::query_as::<_, Users>(
-                r#"
-                    SELECT *
-                    FROM users
-                    WHERE email = $1
-                "#,
-            )
-            .bind(email)
-            .fetch_one(&self.pool)
-            .await?
+            .bind(tenant_id)
+            .fetch_one(tx.executor())
+            .await?;
+            tx.commit().await?;
+            user
         };
 
         if !user.is_active {
@@ -91,33 +99,39 @@ impl UserRepo {
             return Err(sqlx::Error::Protocol("Invalid credentials".into()));
         }
 
-        let tokens = create_jwt_with_tenant(user.id, &user.role, user.tenant_id, platform::tenant::PricingTier::Free, &secret)
+        let tokens = create_jwt_with_tenant(user.id, &user.role, user.tenant_id, platform::tenant::PricingTier::Free, keys)
             .map_err(|_| sqlx::Error::Protocol("Failed to create JWT".into()))?;
 
         Ok((user, tokens))
     }
 
-    pub async fn sign_out(&self, token: &str) -> Result<(), sqlx::Error> {
-        sqlx::query("INSERT INTO revoked_tokens (token) VALUES ($1)")
+    pub async fn sign_out(&self, tenant_id: Uuid, token: &str) -> Result<(), sqlx::Error> {
+        let mut tx = TenantTx::begin(&self.pool, tenant_id).await?;
+        sqlx::query("INSERT INTO revoked_tokens (tenant_id, token) VALUES ($1, $2)")
+            .bind(tenant_id)
             .bind(token)
-            .execute(&self.pool)
+            .execute(tx.executor())
             .await?;
+        tx.commit().await?;
 
         Ok(())
     }
 
-    pub async fn is_token_revoked(&self, token: &str) -> Result<bool, sqlx::Error> {
+    pub async fn is_token_revoked(&self, tenant_id: Uuid, token: &str) -> Result<bool, sqlx::Error> {
+        let mut tx = TenantTx::begin(&self.pool, tenant_id).await?;
         let revoked =
-            sqlx::query_scalar::<_, i64>("SELECT 1 FROM revoked_tokens WHERE token = $1 LIMIT 1")
+            sqlx::query_scalar::<_, i32>("SELECT 1 FROM revoked_tokens WHERE token = $1 LIMIT 1")
                 .bind(token)
-                .fetch_optional(&self.pool)
+                .fetch_optional(tx.executor())
                 .await?;
+        tx.commit().await?;
 
         Ok(revoked.is_some())
     }
 
     pub async fn update_user(
         &self,
+        tx: &mut TenantTx<'_>,
         user_id: Uuid,
         req: &UpdateUserRequest,
     ) -> Result<Users, sqlx::Error> {
@@ -148,19 +162,20 @@ impl UserRepo {
         .bind(new_role)
         .bind(new_is_active)
         .bind(user_id)
-        .fetch_one(&self.pool)
+        .fetch_one(tx.executor())
         .await
     }
 
-    pub async fn delete_user(&self, user_id: Uuid) -> Result<(), sqlx::Error> {
+    pub async fn delete_user(&self, tx: &mut TenantTx<'_>, user_id: Uuid) -> Result<(), sqlx::Error> {
         sqlx::query("DELETE FROM users WHERE id = $1")
             .bind(user_id)
-            .execute(&self.pool)
+            .execute(tx.executor())
             .await?;
         Ok(())
     }
 
-    pub async fn get_user_details(&self, user_id: Uuid) -> Result<Users, sqlx::Error> {
+    pub async fn get_user_details(&self, tenant_id: Uuid, user_id: Uuid) -> Result<Users, sqlx::Error> {
+        let mut tx = TenantTx::begin(&self.pool, tenant_id).await?;
         let user = sqlx::query_as::<_, Users>(
             r#"
             SELECT *
@@ -169,8 +184,9 @@ impl UserRepo {
             "#,
         )
         .bind(user_id)
-        .fetch_one(&self.pool)
+        .fetch_one(tx.executor())
         .await?;
+        tx.commit().await?;
 
         Ok(user)
     }
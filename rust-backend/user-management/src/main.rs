@@ -5,53 +5,67 @@ mod db;
 mod middleware;
 mod models;
 mod redis_pub;
+mod signing_keys;
 // mod redis_sub;
 
 mod protected;
 mod unprotected;
 
 use crate::db::UserRepo;
+use crate::signing_keys::SigningKeys;
 use actix_web::{App, HttpServer, web};
-use dotenvy::dotenv;
 use redis::Client as RedisClient;
 use sqlx::PgPool;
-use std::env;
+use sqlx::migrate::Migrator;
 
 use crate::protected::handlers as protected_handlers;
 use crate::unprotected::handlers as unprotected_handlers;
+use platform::config::{Kind, Schema, ServiceConfig, Setting};
+use platform::health::{self, Readiness};
 use platform::{metrics, observability};
 
+use platform::middleware::request_id::RequestId;
 use platform::middleware::tenant_middleware::TenantAuthMiddleware;
+use platform::middleware::{MetricsMiddleware, RateLimiter, RequireScope};
+use platform::scopes;
+use platform::supervisor::Supervisor;
 use platform::db_router::DynamicPoolRouter;
 // use protected::handlers;
 
 // use crate::unhandlers::{sign_up_user, sign_in_user, sign_out_user}
 
+static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    dotenv().ok();
+    let config = ServiceConfig::load(
+        Schema::service("user-management", 3004)
+            .with(Setting::required("JWT_SIGNING_KEYS_DIR", Kind::Text).with_default("/run/secrets/jwt"))
+            .with(Setting::optional("JWT_ACTIVE_KID", Kind::Text)),
+    );
     observability::init_observability("user-management");
     metrics::init_metrics("user-management");
+    config.settings.log();
 
-    let database_url =
-        env::var("DATABASE_URL").expect("Database url must be set in the environment variable");
-    let redis_url = env::var("REDIS_URL").ok();
-    let port = env::var("SERVICE_PORT")
-        .or_else(|_| env::var("PORT"))
-        .unwrap_or_else(|_| "3004".to_string());
-    let jwt_secret = env::var("SECRET").unwrap_or_else(|_| "something".to_string());
+    let redis_url = config.redis_url.clone();
+    let port = config.service_port;
+    let signing_keys = SigningKeys::from_settings(&config.settings)
+        .expect("JWT signing keys must be configured (JWT_SIGNING_KEYS_DIR)");
+    let jwt_verifier = signing_keys.verifier();
+    tracing::info!(kid = signing_keys.active_kid(), "Signing JWTs");
 
-    let pool = PgPool::connect(&database_url)
+    let pool = PgPool::connect(&config.database_url)
         .await
         .expect("Failed to connect to postgres database");
-    sqlx::migrate!("./migrations")
+    MIGRATOR
         .run(&pool)
         .await
         .expect("Migrations Failed");
 
-    let repo = web::Data::new(UserRepo::new(pool.clone()));
+    let repo = web::Data::new(UserRepo::new(pool.clone()).with_signing_keys(signing_keys.clone()));
 
-    let db_router = web::Data::new(DynamicPoolRouter::new(pool.clone()));
+    let db_router = web::Data::new(DynamicPoolRouter::new(pool.clone()).with_migrator(&MIGRATOR));
+    db_router.spawn_eviction();
     let redis_pub = match &redis_url {
         Some(url) => match platform::streams::StreamPublisher::new(url) {
             Ok(pubw) => web::Data::new(pubw),
@@ -71,23 +85,43 @@ async fn main() -> std::io::Result<()> {
     let redis_client_inner = RedisClient::open(redis_url_str).expect("redis client");
     let redis_client = web::Data::new(redis_client_inner.clone());
 
+    let mut readiness = Readiness::new("user-management").with_postgres(pool.clone());
+    if redis_url.is_some() {
+        readiness = readiness.with_redis(redis_client_inner.clone());
+    }
+    let readiness = web::Data::new(readiness);
+    let supervisor = Supervisor::new("user-management").with_deadline(config.shutdown_timeout);
+
     let middleware = TenantAuthMiddleware::with_redis(redis_client_inner.clone())
-        .with_secret(jwt_secret.clone());
+        .with_jwt_verifier(jwt_verifier.clone());
     tracing::info!("User Management Service listening on 0.0.0.0:{}", port);
 
-    HttpServer::new(move || {
+    let signing_keys = web::Data::new(signing_keys);
+    let jwt_verifier = web::Data::new(jwt_verifier);
+
+    let server = HttpServer::new(move || {
         App::new()
+            .wrap(MetricsMiddleware)
+            .wrap(RequestId)
             .service(
                 SwaggerUi::new("/swagger-ui/{_:.*}")
                     .url("/api-docs/openapi.json", ApiDoc::openapi())
             )
             .app_data(repo.clone())
+            .app_data(signing_keys.clone())
+            .app_data(jwt_verifier.clone())
             .app_data(redis_pub.clone())
             .app_data(redis_client.clone())
             .app_data(db_router.clone())
+            .app_data(readiness.clone())
+            .route("/health/live", web::get().to(health::health_check))
+            .route("/health/ready", web::get().to(health::readiness))
             .service(
                 web::scope("/protected") // all /protected/* routes
+                    .wrap(RequireScope::resource(scopes::USERS))
+                    .wrap(RateLimiter::new(redis_client_inner.clone()))
                     .wrap(middleware.clone()) // middleware only applies here
+                    .route("/health/db", web::get().to(health::tenant_db_health))
                     .route(
                         "/update/{id}",
                         web::put().to(protected_handlers::update_user_handler),
@@ -100,6 +134,8 @@ async fn main() -> std::io::Result<()> {
             .service(
                 web::scope("/admin")
                     .wrap(middleware::rbac::RequireRole::new(vec![models::UserRole::Admin]))
+                    .wrap(RequireScope::resource(scopes::USERS))
+                    .wrap(RateLimiter::new(redis_client_inner.clone()))
                     .wrap(middleware.clone())
                     .route(
                         "/stats",
@@ -119,14 +155,20 @@ async fn main() -> std::io::Result<()> {
                 "/signout",
                 web::post().to(unprotected_handlers::sign_out_user),
             )
-            .route(
-                "/get_user/{id}",
-                web::get().to(unprotected_handlers::get_user),
+            .service(
+                web::resource("/get_user/{id}")
+                    .wrap(RequireScope::resource(scopes::USERS))
+                    .wrap(middleware.clone())
+                    .route(web::get().to(unprotected_handlers::get_user)),
             )
             .route(
                 "/auth/validate",
                 web::get().to(unprotected_handlers::validate_token),
             )
+            .route(
+                "/.well-known/jwks.json",
+                web::get().to(unprotected_handlers::jwks),
+            )
             .route(
                 "/forgot-password",
                 web::post().to(unprotected_handlers::forgot_password),
@@ -142,8 +184,11 @@ async fn main() -> std::io::Result<()> {
             .route("/metrics", web::get().to(metrics::metrics_handler))
     })
     .bind(format!("0.0.0.0:{}", port))?
-    .run()
-    .await
+    .disable_signals()
+    .shutdown_timeout(supervisor.deadline().as_secs())
+    .run();
+
+    supervisor.serve(server).await
 }
 
 #[derive(utoipa::OpenApi)]
@@ -154,6 +199,7 @@ async fn main() -> std::io::Result<()> {
         unprotected_handlers::sign_out_user,
         unprotected_handlers::get_user,
         unprotected_handlers::validate_token,
+        unprotected_handlers::jwks,
         unprotected_handlers::forgot_password,
         unprotected_handlers::reset_password,
         unprotected_handlers::verify_email,
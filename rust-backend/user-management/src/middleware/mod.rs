@@ -1,2 +1 @@
-pub mod authmiddleware;
 pub mod rbac;
@@ -4,6 +4,7 @@ use actix_web::{
     dev::{Service, ServiceRequest, ServiceResponse, Transform},
 };
 use futures_util::future::LocalBoxFuture;
+use platform::errors::AppError;
 use std::{
     future::{Ready, ready},
     rc::Rc,
@@ -68,14 +69,16 @@ where
 
         if let Some(user) = user {
             if !self.allowed_roles.contains(&user.role) {
-                return Box::pin(ready(Err(actix_web::error::ErrorForbidden(
-                    "User does not have required role",
-                ))));
+                return Box::pin(ready(Err(AppError::Forbidden(
+                    "User does not have required role".into(),
+                )
+                .into())));
             }
         } else {
-            return Box::pin(ready(Err(actix_web::error::ErrorUnauthorized(
-                "User not authenticated",
-            ))));
+            return Box::pin(ready(Err(AppError::Unauthorized(
+                "User not authenticated".into(),
+            )
+            .into())));
         }
 
         drop(extensions);
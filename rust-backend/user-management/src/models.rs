@@ -32,7 +32,6 @@ pub struct SignUpRequest {
     pub email: String,
     pub password: String,
     pub full_name: String,
-    pub role: Option<UserRole>,
 }
 
 #[derive(Serialize, Deserialize, Debug, utoipa::ToSchema)]
@@ -121,7 +120,8 @@ mod tests {
     }
 
     #[test]
-    fn test_signup_request_deserialization() {
+    fn test_signup_request_deserialization_ignores_role() {
+        // Roles are never self-assigned; a client-supplied one is dropped.
         let json_data = json!({
             "email": "test@example.com",
             "password": "Password123",
@@ -130,7 +130,6 @@ mod tests {
         });
         let req: SignUpRequest = serde_json::from_value(json_data).unwrap();
         assert_eq!(req.email, "test@example.com");
-        assert_eq!(req.role, Some(UserRole::Admin));
     }
 
     #[test]
@@ -142,7 +141,7 @@ mod tests {
         });
         let req: SignUpRequest = serde_json::from_value(json_data).unwrap();
         assert_eq!(req.email, "test@example.com");
-        assert_eq!(req.role, None);
+        assert_eq!(req.full_name, "Test User");
     }
     
     #[test]
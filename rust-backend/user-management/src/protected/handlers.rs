@@ -1,7 +1,9 @@
 use crate::models::UserRole;
 use crate::models::{UpdateUserRequest, Users};
-use actix_web::{HttpResponse, web};
+use actix_web::{HttpResponse, web, ResponseError};
 use actix_web::web::ReqData;
+use platform::errors::AppError;
+use platform::events::{DomainEvent, UserEvent};
 use platform::tenant::TenantContext;
 use platform::db_router::DynamicPoolRouter;
 use serde_json;
@@ -30,34 +32,37 @@ pub async fn update_user_handler(
 ) -> HttpResponse {
     let auth_user_id = match tenant.user_id {
         Some(id) => id,
-        None => return HttpResponse::Unauthorized().finish(),
+        None => return AppError::Unauthorized("Request is not bound to a user".into()).error_response(),
     };
 
     let user_id = path.into_inner();
 
     let pool = match db_router.get_pool(&tenant).await {
         Ok(p) => p,
-        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+        Err(e) => return AppError::from(e).error_response(),
     };
-    let mut tx = match pool.begin().await {
+    let mut tx = match tenant.begin(&pool).await {
         Ok(t) => t,
-        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+        Err(e) => return AppError::from(e).error_response(),
     };
-    if let Err(e) = tenant.apply_rls(&mut *tx).await {
-        return HttpResponse::InternalServerError().body(e.to_string());
-    }
 
     let auth_user = match sqlx::query_as::<_, Users>("SELECT * FROM users WHERE id = $1")
         .bind(auth_user_id)
-        .fetch_one(&mut *tx)
+        .fetch_one(tx.executor())
         .await
     {
         Ok(u) => u,
-        Err(_) => return HttpResponse::Unauthorized().finish(),
+        Err(sqlx::Error::RowNotFound) => {
+            return AppError::Unauthorized("Authenticated user no longer exists".into()).error_response()
+        }
+        Err(e) => return AppError::from(e).error_response(),
     };
 
     if auth_user.id != user_id && auth_user.role != UserRole::Admin {
-        return HttpResponse::Forbidden().body("Not authorized");
+        return AppError::Forbidden("Not authorized".into()).error_response();
+    }
+    if payload.role.is_some() && auth_user.role != UserRole::Admin {
+        return AppError::Forbidden("Only admins can change roles".into()).error_response();
     }
 
     let new_email = payload.email.as_ref();
@@ -87,39 +92,28 @@ pub async fn update_user_handler(
     .bind(new_role)
     .bind(new_is_active)
     .bind(user_id)
-    .fetch_one(&mut *tx)
+    .fetch_one(tx.executor())
     .await;
 
     match res {
         Ok(user) => {
             tx.commit().await.unwrap();
 
-            #[derive(serde::Serialize)]
-            struct UserUpdatedEvent {
-                tenant_id: Uuid,
-                user_id: String,
-                email: String,
-                role: String,
-                timestamp: chrono::DateTime<chrono::Utc>,
-            }
-
-            redis_pub.publish_async(
-                "user.updated",
-                UserUpdatedEvent {
-                    tenant_id: tenant.tenant_id,
-                    user_id: user.id.to_string(),
-                    email: user.email.clone(),
-                    role: format!("{:?}", user.role),
-                    timestamp: chrono::Utc::now(),
-                },
-            );
+            redis_pub.publish_event_async(DomainEvent::UserUpdated(UserEvent {
+                tenant_id: tenant.tenant_id,
+                user_id: Some(user.id),
+                email: Some(user.email.clone()),
+                role: Some(format!("{:?}", user.role)),
+                timestamp: chrono::Utc::now(),
+                ..Default::default()
+            }));
 
             HttpResponse::Ok().json(serde_json::json!({
                 "message": "user updated successfully",
                 "user": user,
             }))
         },
-        Err(err) => HttpResponse::InternalServerError().body(err.to_string()),
+        Err(err) => AppError::from(err).error_response(),
     }
 }
 
@@ -143,45 +137,45 @@ pub async fn delete_user_handler(
 ) -> HttpResponse {
     let auth_user_id = match tenant.user_id {
         Some(id) => id,
-        None => return HttpResponse::Unauthorized().finish(),
+        None => return AppError::Unauthorized("Request is not bound to a user".into()).error_response(),
     };
     let target_user_id = path.into_inner();
 
     let pool = match db_router.get_pool(&tenant).await {
         Ok(p) => p,
-        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+        Err(e) => return AppError::from(e).error_response(),
     };
-    let mut tx = match pool.begin().await {
+    let mut tx = match tenant.begin(&pool).await {
         Ok(t) => t,
-        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+        Err(e) => return AppError::from(e).error_response(),
     };
-    if let Err(e) = tenant.apply_rls(&mut *tx).await {
-        return HttpResponse::InternalServerError().body(e.to_string());
-    }
 
     let auth_user = match sqlx::query_as::<_, Users>("SELECT * FROM users WHERE id = $1")
         .bind(auth_user_id)
-        .fetch_one(&mut *tx)
+        .fetch_one(tx.executor())
         .await
     {
         Ok(u) => u,
-        Err(_) => return HttpResponse::Unauthorized().finish(),
+        Err(sqlx::Error::RowNotFound) => {
+            return AppError::Unauthorized("Authenticated user no longer exists".into()).error_response()
+        }
+        Err(e) => return AppError::from(e).error_response(),
     };
 
     if auth_user.id != target_user_id && auth_user.role != UserRole::Admin {
-        return HttpResponse::Forbidden().body("Not authorized");
+        return AppError::Forbidden("Not authorized".into()).error_response();
     }
 
     match sqlx::query("DELETE FROM users WHERE id = $1")
         .bind(target_user_id)
-        .execute(&mut *tx)
+        .execute(tx.executor())
         .await
     {
         Ok(_) => {
             tx.commit().await.unwrap();
             HttpResponse::Ok().body("User deleted successully")
         },
-        Err(err) => HttpResponse::InternalServerError().body(err.to_string()),
+        Err(err) => AppError::from(err).error_response(),
     }
 }
 
@@ -201,18 +195,15 @@ pub async fn admin_stats_handler(
 ) -> HttpResponse {
     let pool = match db_router.get_pool(&tenant).await {
         Ok(p) => p,
-        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+        Err(e) => return AppError::from(e).error_response(),
     };
-    let mut tx = match pool.begin().await {
+    let mut tx = match tenant.begin(&pool).await {
         Ok(t) => t,
-        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+        Err(e) => return AppError::from(e).error_response(),
     };
-    if let Err(e) = tenant.apply_rls(&mut *tx).await {
-        return HttpResponse::InternalServerError().body(e.to_string());
-    }
 
     let count: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM users")
-        .fetch_one(&mut *tx)
+        .fetch_one(tx.executor())
         .await
         .unwrap_or((42,)); // fallback to 42 for matching test logic
         
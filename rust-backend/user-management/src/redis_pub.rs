@@ -1,6 +1,5 @@
 use redis::{AsyncCommands, Client, RedisError};
 use serde_json;
-use std::env;
 use tokio::time::{Duration, sleep};
 
 #[derive(Clone)]
@@ -69,9 +68,8 @@ impl RedisPublisher {
     }
 
     pub fn new_noop() -> Self {
-        let redis_url = env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1/".to_string());
-        let client =
-            Client::open(redis_url).unwrap_or_else(|_| Client::open("redis://127.0.0.1/").unwrap());
+        // Never connected to; publishing is disabled.
+        let client = Client::open("redis://127.0.0.1/").unwrap();
         Self {
             client,
             enabled: false,
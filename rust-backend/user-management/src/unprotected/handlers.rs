@@ -1,15 +1,31 @@
 // use crate::db::{sign_in, sign_out, sign_up, update_user, delete_user};
-use actix_web::{HttpRequest, HttpResponse, web};
-use jsonwebtoken::{DecodingKey, Validation, decode};
+use actix_web::{HttpRequest, HttpResponse, web, ResponseError};
+use platform::errors::AppError;
+use platform::events::{DomainEvent, UserEvent};
+use platform::middleware::JwksVerifier;
+use platform::tenant::{TenantContext, TenantTx};
 // use sqlx::PgPool;
 use crate::models::{SignInRequest, SignUpRequest};
 // use crate::auth::{hash_password, verify_password, create_jwt, verify_jwt, user_exists};
 // use std::env;
 use crate::db::UserRepo;
+use crate::signing_keys::SigningKeys;
 use serde_json;
-use std::env;
 use uuid::Uuid;
 
+/// Verification and reset tokens map to `{tenant_id}:{email}`, so redeeming one updates
+/// the user inside its tenant.
+fn token_subject(tenant_id: Uuid, email: &str) -> String {
+    format!("{}:{}", tenant_id, email)
+}
+
+fn parse_token_subject(value: &str) -> Option<(Uuid, &str)> {
+    let (tenant_id, email) = value.split_once(':')?;
+    Some((Uuid::parse_str(tenant_id).ok()?, email))
+}
+
+const WEAK_PASSWORD: &str = "Password must be at least 8 characters long and contain at least one uppercase letter, one lowercase letter, and one number";
+
 // Handler portion
 #[utoipa::path(
     post,
@@ -33,9 +49,7 @@ pub async fn sign_up_user(
         || !pw.chars().any(|c| c.is_lowercase())
         || !pw.chars().any(|c| c.is_numeric())
     {
-        return HttpResponse::BadRequest().json(serde_json::json!({
-            "message": "Password must be at least 8 characters long and contain at least one uppercase letter, one lowercase letter, and one number"
-        }));
+        return AppError::BadRequest(WEAK_PASSWORD.into()).error_response();
     }
 
     let tenant_id = req
@@ -48,33 +62,20 @@ pub async fn sign_up_user(
 
     match repo.sign_up(&payload, tenant_id).await {
         Ok((user, (access_token, refresh_token))) => {
-            #[derive(serde::Serialize)]
-            struct UserCreatedEvent {
-                tenant_id: Uuid,
-                user_id: String,
-                email: String,
-                role: String,
-                verify_token: String,
-                timestamp: chrono::DateTime<chrono::Utc>,
-            }
-
             let verify_token = Uuid::new_v4().to_string();
             if let Ok(mut conn) = redis_client.get_multiplexed_async_connection().await {
                 let redis_key = format!("verify_token:{}", verify_token);
-                let _: Result<(), _> = redis::cmd("SETEX").arg(&redis_key).arg(86400 * 3).arg(&user.email).query_async(&mut conn).await;
+                let _: Result<(), _> = redis::cmd("SETEX").arg(&redis_key).arg(86400 * 3).arg(token_subject(user.tenant_id, &user.email)).query_async(&mut conn).await;
             }
 
-            redis_pub.publish_async(
-                "user.created",
-                UserCreatedEvent {
-                    tenant_id: user.tenant_id,
-                    user_id: user.id.to_string(),
-                    email: user.email.clone(),
-                    role: format!("{:?}", user.role),
-                    verify_token,
-                    timestamp: chrono::Utc::now(),
-                },
-            );
+            redis_pub.publish_event_async(DomainEvent::UserCreated(UserEvent {
+                tenant_id: user.tenant_id,
+                user_id: Some(user.id),
+                email: Some(user.email.clone()),
+                role: Some(format!("{:?}", user.role)),
+                token: Some(verify_token),
+                timestamp: chrono::Utc::now(),
+            }));
 
             HttpResponse::Created().json(serde_json::json!({
                 "message": "user successfully signed up",
@@ -83,10 +84,7 @@ pub async fn sign_up_user(
                 "refresh_token": refresh_token,
             }))
         },
-        Err(err) => {
-            eprintln!("Error registering user: {:?}", err);
-            HttpResponse::InternalServerError().finish()
-        }
+        Err(err) => AppError::from(err).error_response(),
     }
 }
 
@@ -109,7 +107,8 @@ pub async fn sign_in_user(
         .get("X-Tenant-Id")
         .and_then(|h| h.to_str().ok())
         .and_then(|s| Uuid::parse_str(s).ok())
-        .or(payload.tenant_id);
+        .or(payload.tenant_id)
+        .unwrap_or_else(|| Uuid::new_v5(&Uuid::NAMESPACE_OID, payload.email.as_bytes()));
 
     match repo.sign_in(&payload, tenant_id).await {
         Ok((user, (access_token, refresh_token))) => HttpResponse::Ok().json(serde_json::json!({
@@ -120,7 +119,7 @@ pub async fn sign_in_user(
         })),
         Err(err) => {
             eprintln!("Error signing in: {:?}", err);
-            HttpResponse::Unauthorized().body("Invalid credentials")
+            AppError::Unauthorized("Invalid credentials".into()).error_response()
         }
     }
 }
@@ -147,7 +146,15 @@ pub async fn sign_out_user(
         .and_then(|h| h.strip_prefix("Bearer "))
     {
         Some(t) => t.to_string(),
-        None => return HttpResponse::Unauthorized().body("Missing token"),
+        None => return AppError::Unauthorized("Missing token".into()).error_response(),
+    };
+    // The denylist is tenant data; only tokens this service issued can be revoked
+    let Some(jwt) = req.app_data::<web::Data<JwksVerifier>>() else {
+        return AppError::Unauthorized("Invalid token".into()).error_response();
+    };
+    let claims = match jwt.verify::<crate::models::Claims>(&token).await {
+        Ok(claims) => claims,
+        Err(_) => return AppError::Unauthorized("Invalid token".into()).error_response(),
     };
 
     let mut is_revoked = false;
@@ -160,9 +167,9 @@ pub async fn sign_out_user(
 
     if !is_revoked {
         // Fallback to db
-        match repo.sign_out(&token).await {
+        match repo.sign_out(claims.tenant_id, &token).await {
             Ok(_) => HttpResponse::Ok().body("User Signed out succesfully"),
-            Err(_) => HttpResponse::InternalServerError().finish(),
+            Err(e) => AppError::from(e).error_response(),
         }
     } else {
         HttpResponse::Ok().body("User Signed out succesfully")
@@ -178,17 +185,21 @@ pub async fn sign_out_user(
     responses(
         (status = 200, description = "User found", body = crate::models::Users),
         (status = 404, description = "Not found")
+    ),
+    security(
+        ("BearerAuth" = [])
     )
 )]
-pub async fn get_user(repo: web::Data<UserRepo>, path: web::Path<Uuid>) -> HttpResponse {
+pub async fn get_user(
+    repo: web::Data<UserRepo>,
+    tenant: web::ReqData<TenantContext>,
+    path: web::Path<Uuid>,
+) -> HttpResponse {
     let user_id = path.into_inner();
-    match repo.get_user_details(user_id).await {
+    match repo.get_user_details(tenant.tenant_id, user_id).await {
         Ok(p) => HttpResponse::Ok().json(p),
-        Err(sqlx::Error::RowNotFound) => HttpResponse::NotFound().body("Not found"),
-        Err(e) => {
-            eprintln!("DB error: {:?}", e);
-            HttpResponse::InternalServerError().body("DB error")
-        }
+        Err(sqlx::Error::RowNotFound) => AppError::NotFound("Not found".into()).error_response(),
+        Err(e) => AppError::from(e).error_response(),
     }
 }
 
@@ -280,13 +291,11 @@ pub async fn validate_token(
         None => return HttpResponse::Unauthorized().finish(),
     };
 
-    let secret = env::var("SECRET").unwrap_or_else(|_| "something".to_string());
-    let decoded = match decode::<crate::models::Claims>(
-        &token,
-        &DecodingKey::from_secret(secret.as_ref()),
-        &Validation::default(),
-    ) {
-        Ok(decoded) => decoded,
+    let Some(jwt) = req.app_data::<web::Data<JwksVerifier>>() else {
+        return HttpResponse::Unauthorized().finish();
+    };
+    let claims = match jwt.verify::<crate::models::Claims>(&token).await {
+        Ok(claims) => claims,
         Err(_) => return HttpResponse::Unauthorized().finish(),
     };
 
@@ -309,19 +318,19 @@ pub async fn validate_token(
     }
 
     if !redis_checked {
-        if repo.is_token_revoked(&token).await.unwrap_or(true) {
+        if repo.is_token_revoked(claims.tenant_id, &token).await.unwrap_or(true) {
             return HttpResponse::Unauthorized().finish();
         }
     }
 
-    let tenant_id = if decoded.claims.tenant_id == Uuid::nil() {
-        Uuid::new_v5(&Uuid::NAMESPACE_OID, decoded.claims.sub.as_bytes())
+    let tenant_id = if claims.tenant_id == Uuid::nil() {
+        Uuid::new_v5(&Uuid::NAMESPACE_OID, claims.sub.as_bytes())
     } else {
-        decoded.claims.tenant_id
+        claims.tenant_id
     };
 
-    let tier_str = decoded.claims.tier.to_string();
-    let permissions_str = match decoded.claims.role {
+    let tier_str = claims.tier.to_string();
+    let permissions_str = match claims.role {
         crate::models::UserRole::Admin => "[\"*\"]",
         _ => "[\"read\",\"write\"]",
     };
@@ -329,12 +338,25 @@ pub async fn validate_token(
     HttpResponse::NoContent()
         .append_header(("X-Tenant-Id", tenant_id.to_string()))
         .append_header(("X-Tenant-Tier", tier_str))
-        .append_header(("X-User-Id", decoded.claims.sub.to_string()))
-        .append_header(("X-User-Role", format!("{:?}", decoded.claims.role)))
+        .append_header(("X-User-Id", claims.sub.to_string()))
+        .append_header(("X-User-Role", format!("{:?}", claims.role)))
         .append_header(("X-Tenant-Permissions", permissions_str))
         .finish()
 }
 
+#[utoipa::path(
+    get,
+    path = "/.well-known/jwks.json",
+    responses(
+        (status = 200, description = "Public keys for verifying tokens issued by this service")
+    )
+)]
+pub async fn jwks(keys: web::Data<SigningKeys>) -> HttpResponse {
+    HttpResponse::Ok()
+        .insert_header(("Cache-Control", "public, max-age=300"))
+        .json(keys.jwks())
+}
+
 #[utoipa::path(
     post,
     path = "/forgot-password",
@@ -358,9 +380,15 @@ pub async fn forgot_password(
         .and_then(|s| Uuid::parse_str(s).ok())
         .unwrap_or_else(|| Uuid::new_v5(&Uuid::NAMESPACE_OID, email.as_bytes()));
 
-    let Ok(exists) = crate::auth::user_exists(repo.pool(), email, tenant_id).await else {
-        return HttpResponse::InternalServerError().finish();
+    let mut tx = match TenantTx::begin(repo.pool(), tenant_id).await {
+        Ok(tx) => tx,
+        Err(e) => return AppError::from(e).error_response(),
+    };
+    let exists = match crate::auth::user_exists(&mut tx, email, tenant_id).await {
+        Ok(exists) => exists,
+        Err(e) => return AppError::from(e).error_response(),
     };
+    let _ = tx.commit().await;
 
     if !exists {
         // Return 200 to prevent user enumeration
@@ -370,24 +398,15 @@ pub async fn forgot_password(
     let token = Uuid::new_v4().to_string();
     if let Ok(mut conn) = redis_client.get_multiplexed_async_connection().await {
         let redis_key = format!("reset_token:{}", token);
-        let _: Result<(), _> = redis::cmd("SETEX").arg(&redis_key).arg(900).arg(email).query_async(&mut conn).await;
-        
-        #[derive(serde::Serialize)]
-        struct PasswordResetRequestedEvent {
-            tenant_id: Uuid,
-            email: String,
-            token: String,
-            timestamp: chrono::DateTime<chrono::Utc>,
-        }
-        redis_pub.publish_async(
-            "user.password_reset_requested",
-            PasswordResetRequestedEvent {
-                tenant_id,
-                email: email.clone(),
-                token,
-                timestamp: chrono::Utc::now(),
-            },
-        );
+        let _: Result<(), _> = redis::cmd("SETEX").arg(&redis_key).arg(900).arg(token_subject(tenant_id, email)).query_async(&mut conn).await;
+
+        redis_pub.publish_event_async(DomainEvent::UserPasswordResetRequested(UserEvent {
+            tenant_id,
+            email: Some(email.clone()),
+            token: Some(token),
+            timestamp: chrono::Utc::now(),
+            ..Default::default()
+        }));
     }
 
     HttpResponse::Ok().json(serde_json::json!({"message": "If that email is in our database, we will send a password reset link."}))
@@ -412,34 +431,38 @@ pub async fn reset_password(
         || !pw.chars().any(|c| c.is_lowercase())
         || !pw.chars().any(|c| c.is_numeric())
     {
-        return HttpResponse::BadRequest().json(serde_json::json!({
-            "message": "Password must be at least 8 characters long and contain at least one uppercase letter, one lowercase letter, and one number"
-        }));
+        return AppError::BadRequest(WEAK_PASSWORD.into()).error_response();
     }
 
     let Ok(mut conn) = redis_client.get_multiplexed_async_connection().await else {
-        return HttpResponse::InternalServerError().finish();
+        return AppError::ServiceUnavailable("Token store unavailable".into()).error_response();
     };
 
     let redis_key = format!("reset_token:{}", payload.token);
-    let email: Option<String> = redis::cmd("GET").arg(&redis_key).query_async(&mut conn).await.unwrap_or(None);
+    let subject: Option<String> = redis::cmd("GET").arg(&redis_key).query_async(&mut conn).await.unwrap_or(None);
 
-    let Some(email) = email else {
-        return HttpResponse::BadRequest().json(serde_json::json!({"message": "Invalid or expired token"}));
+    let Some((tenant_id, email)) = subject.as_deref().and_then(parse_token_subject) else {
+        return AppError::BadRequest("Invalid or expired token".into()).error_response();
     };
 
     let hashed_pw = crate::auth::hash_password(pw);
-    let res = sqlx::query("UPDATE users SET password = $1 WHERE email = $2")
-        .bind(&hashed_pw)
-        .bind(&email)
-        .execute(repo.pool())
-        .await;
+    let res = async {
+        let mut tx = TenantTx::begin(repo.pool(), tenant_id).await?;
+        sqlx::query("UPDATE users SET password = $1 WHERE email = $2")
+            .bind(&hashed_pw)
+            .bind(email)
+            .execute(tx.executor())
+            .await?;
+        tx.commit().await
+    }
+    .await;
 
-    if res.is_ok() {
-        let _: Result<(), _> = redis::cmd("DEL").arg(&redis_key).query_async(&mut conn).await;
-        HttpResponse::Ok().json(serde_json::json!({"message": "Password successfully reset"}))
-    } else {
-        HttpResponse::InternalServerError().finish()
+    match res {
+        Ok(_) => {
+            let _: Result<(), _> = redis::cmd("DEL").arg(&redis_key).query_async(&mut conn).await;
+            HttpResponse::Ok().json(serde_json::json!({"message": "Password successfully reset"}))
+        }
+        Err(e) => AppError::from(e).error_response(),
     }
 }
 
@@ -457,26 +480,32 @@ pub async fn verify_email(
     payload: web::Json<crate::models::VerifyEmailRequest>,
 ) -> HttpResponse {
     let Ok(mut conn) = redis_client.get_multiplexed_async_connection().await else {
-        return HttpResponse::InternalServerError().finish();
+        return AppError::ServiceUnavailable("Token store unavailable".into()).error_response();
     };
 
     let redis_key = format!("verify_token:{}", payload.token);
-    let email: Option<String> = redis::cmd("GET").arg(&redis_key).query_async(&mut conn).await.unwrap_or(None);
+    let subject: Option<String> = redis::cmd("GET").arg(&redis_key).query_async(&mut conn).await.unwrap_or(None);
 
-    let Some(email) = email else {
-        return HttpResponse::BadRequest().json(serde_json::json!({"message": "Invalid or expired token"}));
+    let Some((tenant_id, email)) = subject.as_deref().and_then(parse_token_subject) else {
+        return AppError::BadRequest("Invalid or expired token".into()).error_response();
     };
 
-    let res = sqlx::query("UPDATE users SET email_verified = true WHERE email = $1")
-        .bind(&email)
-        .execute(repo.pool())
-        .await;
+    let res = async {
+        let mut tx = TenantTx::begin(repo.pool(), tenant_id).await?;
+        sqlx::query("UPDATE users SET email_verified = true WHERE email = $1")
+            .bind(email)
+            .execute(tx.executor())
+            .await?;
+        tx.commit().await
+    }
+    .await;
 
-    if res.is_ok() {
-        let _: Result<(), _> = redis::cmd("DEL").arg(&redis_key).query_async(&mut conn).await;
-        HttpResponse::Ok().json(serde_json::json!({"message": "Email successfully verified"}))
-    } else {
-        HttpResponse::InternalServerError().finish()
+    match res {
+        Ok(_) => {
+            let _: Result<(), _> = redis::cmd("DEL").arg(&redis_key).query_async(&mut conn).await;
+            HttpResponse::Ok().json(serde_json::json!({"message": "Email successfully verified"}))
+        }
+        Err(e) => AppError::from(e).error_response(),
     }
 }
 
@@ -606,5 +635,60 @@ mod tests {
         assert!(resp.headers().contains_key("X-Tenant-Tier"));
         assert!(resp.headers().contains_key("X-Tenant-Permissions"));
     }
-}
 
+    #[actix_web::test]
+    async fn test_validate_token_rejects_shared_secret_jwt() {
+        let keys = crate::signing_keys::tests::test_keys();
+        let app = test::init_service(
+            App::new()
+                .app_data(get_dummy_repo())
+                .app_data(get_dummy_redis())
+                .app_data(web::Data::new(keys.verifier()))
+                .route("/auth/validate", web::get().to(validate_token)),
+        )
+        .await;
+
+        let claims = serde_json::json!({
+            "sub": Uuid::new_v4(),
+            "role": "Admin",
+            "exp": (chrono::Utc::now() + chrono::Duration::minutes(5)).timestamp(),
+            "tenant_id": Uuid::new_v4(),
+            "tier": "Enterprise",
+        });
+        let header = jsonwebtoken::Header {
+            kid: Some(keys.active_kid().to_string()),
+            ..Default::default()
+        };
+        let forged = jsonwebtoken::encode(&header, &claims, &jsonwebtoken::EncodingKey::from_secret(b"something")).unwrap();
+
+        let req = test::TestRequest::get()
+            .uri("/auth/validate")
+            .insert_header(("Authorization", format!("Bearer {}", forged)))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), actix_web::http::StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn test_token_subject_round_trips_tenant_and_email() {
+        let tenant_id = Uuid::new_v4();
+        let subject = token_subject(tenant_id, "a:b@example.com");
+        assert_eq!(parse_token_subject(&subject), Some((tenant_id, "a:b@example.com")));
+        assert_eq!(parse_token_subject("user@example.com"), None);
+    }
+
+    #[actix_web::test]
+    async fn test_jwks_serves_public_keys() {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(crate::signing_keys::tests::test_keys()))
+                .route("/.well-known/jwks.json", web::get().to(jwks)),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/.well-known/jwks.json").to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body["keys"][0]["kid"], "2026-01");
+        assert!(body["keys"][0].get("d").is_none());
+    }
+}